@@ -0,0 +1,50 @@
+//! Inbound message parsing with serde_json and with the `simd-json` feature.
+//!
+//! Run with `cargo bench --features simd-json` to compare both parsers on a small request
+//! and on a large tool result.
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use mcp_ox::protocol::JsonRpcMessage;
+use mcp_ox::transport::decode_message;
+use serde_json::json;
+
+fn messages() -> Vec<(&'static str, Vec<u8>)> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 7,
+        "method": "tools/call",
+        "params": { "name": "search", "arguments": { "query": "rust", "limit": 10 } }
+    });
+    let content: Vec<_> = (0..500)
+        .map(|i| json!({ "type": "text", "text": format!("Result line {} with some text", i) }))
+        .collect();
+    let result = json!({
+        "jsonrpc": "2.0",
+        "id": 7,
+        "result": { "content": content, "isError": false }
+    });
+    vec![
+        ("request", serde_json::to_vec(&request).unwrap()),
+        ("large_result", serde_json::to_vec(&result).unwrap()),
+    ]
+}
+
+fn parse(c: &mut Criterion) {
+    for (name, bytes) in messages() {
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function("serde_json", |b| {
+            b.iter(|| serde_json::from_slice::<JsonRpcMessage>(&bytes).unwrap())
+        });
+        group.bench_function("decode_message", |b| {
+            b.iter_batched_ref(
+                || bytes.clone(),
+                |bytes| decode_message(bytes).unwrap(),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,179 @@
+/// Procedural macros for `mcp-ox`, re-exported by it with the `macros` feature.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{Attribute, Error, Expr, FnArg, ItemFn, Lit, LitStr, Meta, parse_macro_input};
+
+/// Turns an async function into an MCP tool.
+///
+/// ```text
+/// /// Searches the index.
+/// #[tool(title = "Search")]
+/// async fn search(args: SearchArgs, context: RequestContext) -> Result<CallToolResult, McpError> {
+///     ...
+/// }
+///
+/// registry.add(SearchTool);
+/// ```
+///
+/// The function takes its arguments, a type implementing `Deserialize` and `JsonSchema`, and
+/// optionally the `RequestContext`. Next to it, a unit struct named after the function in
+/// PascalCase with a `Tool` suffix implements `ToolDefinition`: the tool's input schema is
+/// generated from the arguments type, and calls deserialize the arguments before running the
+/// function.
+///
+/// Options: `name` (defaults to the function name), `title`, and `description` (defaults to
+/// the function's doc comment).
+#[proc_macro_attribute]
+pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut options = ToolOptions::default();
+    let parser = syn::meta::parser(|meta| options.parse(meta));
+    parse_macro_input!(attr with parser);
+    let function = parse_macro_input!(item as ItemFn);
+    expand_tool(options, function)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ToolOptions {
+    name: Option<LitStr>,
+    title: Option<LitStr>,
+    description: Option<LitStr>,
+}
+
+impl ToolOptions {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        let option = if meta.path.is_ident("name") {
+            &mut self.name
+        } else if meta.path.is_ident("title") {
+            &mut self.title
+        } else if meta.path.is_ident("description") {
+            &mut self.description
+        } else {
+            return Err(meta.error("expected `name`, `title` or `description`"));
+        };
+        *option = Some(meta.value()?.parse()?);
+        Ok(())
+    }
+}
+
+fn expand_tool(options: ToolOptions, function: ItemFn) -> syn::Result<TokenStream2> {
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(Error::new_spanned(
+            signature.fn_token,
+            "#[tool] functions must be async",
+        ));
+    }
+    let mut inputs = Vec::new();
+    for input in &signature.inputs {
+        match input {
+            FnArg::Typed(input) => inputs.push(&input.ty),
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver,
+                    "#[tool] functions cannot take self",
+                ));
+            }
+        }
+    }
+    let (arguments, takes_context) = match inputs.as_slice() {
+        [arguments] => (arguments, false),
+        [arguments, _] => (arguments, true),
+        _ => {
+            return Err(Error::new_spanned(
+                &signature.inputs,
+                "#[tool] functions take their arguments and optionally a RequestContext",
+            ));
+        }
+    };
+
+    let function_name = &signature.ident;
+    let name = options
+        .name
+        .unwrap_or_else(|| LitStr::new(&function_name.to_string(), function_name.span()));
+    let title = options.title.map(|title| quote!(.title(#title)));
+    let description = options
+        .description
+        .or_else(|| doc_comment(&function.attrs))
+        .map(|description| quote!(.description(#description)));
+    let call = if takes_context {
+        quote!(#function_name(arguments, context).await)
+    } else {
+        quote!(#function_name(arguments).await)
+    };
+    let visibility = &function.vis;
+    let tool = format_ident!("{}Tool", pascal_case(&function_name.to_string()));
+    let doc = format!("The `{}` tool, generated by `#[tool]`.", name.value());
+
+    Ok(quote! {
+        #function
+
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, Default)]
+        #visibility struct #tool;
+
+        impl ::mcp_ox::tool::ToolDefinition for #tool {
+            fn definition(&self) -> ::mcp_ox::tool::Tool {
+                ::mcp_ox::tool::Tool::builder()
+                    .name(#name)
+                    #title
+                    #description
+                    .input_schema::<#arguments>()
+                    .build()
+            }
+
+            fn handler(self) -> impl ::mcp_ox::tool::ToolHandler + 'static {
+                |arguments: ::std::option::Option<
+                    ::mcp_ox::__private::serde_json::Map<
+                        ::std::string::String,
+                        ::mcp_ox::__private::serde_json::Value,
+                    >,
+                >,
+                 context: ::mcp_ox::server::RequestContext| async move {
+                    let _ = &context;
+                    let arguments: #arguments = ::mcp_ox::tool::parse_arguments(arguments)?;
+                    #call
+                }
+            }
+        }
+    })
+}
+
+/// Joins the `///` lines of a doc comment, or returns `None` if there are none.
+fn doc_comment(attributes: &[Attribute]) -> Option<LitStr> {
+    let mut lines = Vec::new();
+    let mut span = None;
+    for attribute in attributes {
+        let Meta::NameValue(meta) = &attribute.meta else {
+            continue;
+        };
+        if !meta.path.is_ident("doc") {
+            continue;
+        }
+        if let Expr::Lit(expr) = &meta.value
+            && let Lit::Str(line) = &expr.lit
+        {
+            span.get_or_insert(line.span());
+            let line = line.value();
+            lines.push(line.strip_prefix(' ').unwrap_or(&line).to_string());
+        }
+    }
+    let text = lines.join("\n").trim().to_string();
+    let span = span?;
+    (!text.is_empty()).then(|| LitStr::new(&text, span))
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,163 @@
+/// Serving MCP from an actix-web application.
+///
+/// [`scope`] answers the Streamable HTTP transport at the path it is given. actix-web builds
+/// the application once per worker thread, so the [`SessionManager`] is made once, outside
+/// the application factory, and every worker gets a clone sharing its sessions:
+///
+/// ```text
+/// let sessions = SessionManager::new(server);
+/// HttpServer::new(move || {
+///     App::new()
+///         .route("/health", web::get().to(health))
+///         .service(mcp_ox::actix::scope("/mcp", sessions.clone()))
+/// })
+/// .bind(addr)?
+/// .run()
+/// .await?;
+/// ```
+///
+/// The same manager can back an axum router too, see [`crate::axum::router_with_sessions`]
+/// (with the `axum` feature). Middleware that authenticates requests itself hands the result
+/// to the session by inserting an [`AuthContext`] into the request's extensions.
+use std::convert::Infallible;
+
+use actix_web::http::{Method, StatusCode, header};
+use actix_web::web::{self, Bytes};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Scope};
+use futures::StreamExt;
+
+use crate::auth::AuthContext;
+use crate::streamable::{
+    self, HttpBody, HttpMethod, PROTOCOL_VERSION_HEADER, SESSION_ID_HEADER, SessionManager,
+};
+
+/// A scope answering MCP requests at `path` with the sessions of `sessions`.
+pub fn scope(path: &str, sessions: SessionManager) -> Scope {
+    web::scope(path)
+        .app_data(web::Data::new(sessions))
+        .route("", web::route().to(handle))
+}
+
+/// The handler of the MCP endpoint, for applications that route to it themselves. It needs
+/// the [`SessionManager`] as `web::Data`.
+pub async fn handle(
+    request: HttpRequest,
+    body: Bytes,
+    sessions: web::Data<SessionManager>,
+) -> HttpResponse {
+    let method = match *request.method() {
+        Method::GET => HttpMethod::Get,
+        Method::POST => HttpMethod::Post,
+        Method::DELETE => HttpMethod::Delete,
+        _ => HttpMethod::Other,
+    };
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let incoming = streamable::HttpRequest::builder()
+        .method(method)
+        .maybe_session_id(header(SESSION_ID_HEADER))
+        .maybe_protocol_version(header(PROTOCOL_VERSION_HEADER))
+        .maybe_authorization(header(header::AUTHORIZATION.as_str()))
+        .maybe_accept(header(header::ACCEPT.as_str()))
+        .maybe_auth(request.extensions().get::<AuthContext>().cloned())
+        .body(body.to_vec())
+        .build();
+    into_response(sessions.handle(incoming).await)
+}
+
+fn into_response(response: streamable::HttpResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut builder = HttpResponse::build(status);
+    for header in response.headers {
+        builder.insert_header(header);
+    }
+    match response.body {
+        HttpBody::Empty => builder.finish(),
+        HttpBody::Json(json) => builder.body(json),
+        HttpBody::Events(events) => {
+            builder.streaming(events.map(|event| Ok::<_, Infallible>(Bytes::from(event))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Implementation;
+    use crate::server::Server;
+    use crate::streamable::parse_events;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body};
+    use actix_web::{App, HttpResponse as Response};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_mcp_is_served_in_a_scope() {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        let app = init_service(
+            App::new()
+                .route(
+                    "/health",
+                    web::get().to(|| async { Response::Ok().body("ok") }),
+                )
+                .service(scope("/mcp", SessionManager::new(server))),
+        )
+        .await;
+
+        let health = call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(read_body(health).await, "ok");
+
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            }
+        });
+        let request = TestRequest::post()
+            .uri("/mcp")
+            .insert_header((header::ACCEPT, "application/json, text/event-stream"))
+            .set_payload(initialize.to_string())
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let session_id = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = read_body(response).await;
+        let events = parse_events(std::str::from_utf8(&body).unwrap());
+        assert_eq!(events[0]["result"]["serverInfo"]["name"], "test-server");
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" });
+        let request = TestRequest::post()
+            .uri("/mcp")
+            .insert_header((SESSION_ID_HEADER, session_id.as_str()))
+            .set_payload(ping.to_string())
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body = read_body(response).await;
+        let pong: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(pong["result"], json!({}));
+
+        let request = TestRequest::delete()
+            .uri("/mcp")
+            .insert_header((SESSION_ID_HEADER, session_id.as_str()))
+            .to_request();
+        assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,196 @@
+/// Human-in-the-loop approval on the client.
+///
+/// Hosts are expected to keep a human in the loop for actions with side effects: the tool
+/// calls their model decides on, and the completions servers ask their model for. An
+/// [`Approver`] registered with [`Client::with_approver`] is asked before every outgoing
+/// `tools/call` and every incoming `sampling/createMessage`, and may let the request through,
+/// deny it, or let it through changed:
+///
+/// ```text
+/// struct AskUser;
+///
+/// #[async_trait]
+/// impl Approver for AskUser {
+///     async fn approve_tool_call(&self, params: &CallToolRequestParams) -> Approval<CallToolRequestParams> {
+///         match prompt_user(&params.name).await {
+///             true => Approval::Approve,
+///             false => Approval::Deny("Not now".to_string()),
+///         }
+///     }
+/// }
+///
+/// let client = client.with_approver(AskUser);
+/// ```
+///
+/// A denied tool call is not sent and fails with [`ProtocolError::Denied`]; a denied sampling
+/// request is answered with a [`USER_REJECTED`] error.
+///
+/// [`Client::with_approver`]: crate::client::Client::with_approver
+/// [`ProtocolError::Denied`]: crate::protocol::ProtocolError::Denied
+/// [`USER_REJECTED`]: crate::protocol::USER_REJECTED
+use async_trait::async_trait;
+
+use crate::sampling::CreateMessageRequestParams;
+use crate::tool::CallToolRequestParams;
+
+/// What the user decided about a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Approval<T> {
+    /// Go ahead with the request as it is
+    Approve,
+    /// Do not go ahead, for the given reason
+    Deny(String),
+    /// Go ahead with these parameters instead
+    Modify(T),
+}
+
+impl<T> Approval<T> {
+    /// Applies the decision to `params`, returning the parameters to go ahead with or the
+    /// reason of the denial.
+    pub fn apply(self, params: T) -> Result<T, String> {
+        match self {
+            Approval::Approve => Ok(params),
+            Approval::Deny(reason) => Err(reason),
+            Approval::Modify(params) => Ok(params),
+        }
+    }
+}
+
+/// Decides on the requests a user should approve. Both kinds are approved unless overridden.
+#[async_trait]
+pub trait Approver: Send + Sync {
+    /// Decides on a tool call before it is sent to the server.
+    async fn approve_tool_call(
+        &self,
+        _params: &CallToolRequestParams,
+    ) -> Approval<CallToolRequestParams> {
+        Approval::Approve
+    }
+
+    /// Decides on a sampling request of the server before the client samples.
+    async fn approve_sampling(
+        &self,
+        _params: &CreateMessageRequestParams,
+    ) -> Approval<CreateMessageRequestParams> {
+        Approval::Approve
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::error::McpError;
+    use crate::prompt::{PromptMessageContent, PromptMessageRole, TextContent};
+    use crate::protocol::{ClientCapabilities, Implementation, ProtocolError, SamplingCapability};
+    use crate::sampling::{CreateMessageResult, SamplingMessage};
+    use crate::server::{RequestContext, Server};
+    use crate::tool::{CallToolResult, Tool};
+    use crate::transport::MemoryTransport;
+    use serde_json::{Map, Value, json};
+
+    fn text(content: &PromptMessageContent) -> &str {
+        match content {
+            PromptMessageContent::Text(TextContent { text, .. }) => text,
+            _ => panic!("Expected text content"),
+        }
+    }
+
+    struct Reviewer;
+
+    #[async_trait]
+    impl Approver for Reviewer {
+        async fn approve_tool_call(
+            &self,
+            params: &CallToolRequestParams,
+        ) -> Approval<CallToolRequestParams> {
+            let question = params.arguments.as_ref().and_then(|a| a.get("question"));
+            match (params.name.as_str(), question.and_then(Value::as_str)) {
+                ("drop_tables", _) => Approval::Deny("Too destructive".to_string()),
+                ("ask", Some(question)) => {
+                    let mut params = params.clone();
+                    let arguments = json!({ "question": format!("{question} please") });
+                    params.arguments = arguments.as_object().cloned();
+                    Approval::Modify(params)
+                }
+                _ => Approval::Approve,
+            }
+        }
+
+        async fn approve_sampling(
+            &self,
+            params: &CreateMessageRequestParams,
+        ) -> Approval<CreateMessageRequestParams> {
+            match text(&params.messages[0].content).contains("secret") {
+                true => Approval::Deny("Not sharing secrets".to_string()),
+                false => Approval::Approve,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_wait_for_approval() {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        server.add_tool(
+            Tool::builder().name("ask").build(),
+            |arguments: Option<Map<String, Value>>, context: RequestContext| async move {
+                let question = arguments.unwrap_or_default()["question"].clone();
+                let params = CreateMessageRequestParams::builder()
+                    .messages(vec![SamplingMessage {
+                        role: PromptMessageRole::User,
+                        content: PromptMessageContent::text(question.as_str().unwrap_or("")),
+                    }])
+                    .max_tokens(100)
+                    .build();
+                let result = context.create_message(params).await?;
+                Ok(CallToolResult::text(text(&result.content)))
+            },
+        );
+        server.add_tool(
+            Tool::builder().name("drop_tables").build(),
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("dropped")) },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let capabilities = ClientCapabilities {
+            sampling: Some(SamplingCapability {}),
+            ..Default::default()
+        };
+        let client = Client::connect(
+            client,
+            Implementation::new("test-client", "1.0.0"),
+            capabilities,
+        )
+        .await
+        .unwrap()
+        .with_sampling(|params: CreateMessageRequestParams| async move {
+            let content = params.messages[0].content.clone();
+            Ok::<_, McpError>(
+                CreateMessageResult::builder()
+                    .role(PromptMessageRole::Assistant)
+                    .content(content)
+                    .model("echo")
+                    .build(),
+            )
+        })
+        .with_approver(Reviewer);
+
+        let denied = client.call_tool("drop_tables", ()).await;
+        assert!(
+            matches!(denied, Err(ProtocolError::Denied(reason)) if reason == "Too destructive")
+        );
+
+        let result = client
+            .call_tool("ask", json!({ "question": "Weather?" }))
+            .await
+            .unwrap();
+        assert_eq!(result, CallToolResult::text("Weather? please"));
+
+        let result = client
+            .call_tool("ask", json!({ "question": "The secret?" }))
+            .await
+            .unwrap();
+        assert!(result.is_error());
+        assert_eq!(result, CallToolResult::error("Not sharing secrets"));
+    }
+}
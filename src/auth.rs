@@ -0,0 +1,272 @@
+/// Authentication and authorization of client requests.
+///
+/// A server that serves several users, typically over HTTP, authenticates each connection
+/// and serves it with the resulting [`AuthContext`], see [`Server::serve_with_auth`]. Bearer
+/// tokens are turned into one by a [`TokenValidator`], which checks the token with whatever
+/// the deployment trusts, such as an OAuth introspection endpoint or a JWT library:
+///
+/// ```text
+/// let auth = authenticate(&validator, headers.get("authorization")).await?;
+/// ```
+///
+/// Handlers find the context in their [`RequestContext`], and code they call, such as
+/// resource providers, through [`AuthContext::current`], e.g. to list only the resources of
+/// the session's tenant. An [`Authorizer`] decides about every request before its handler
+/// runs:
+///
+/// ```text
+/// let server = Server::new(info)
+///     .with_tools(tools)
+///     .with_authorizer(|request: AuthorizationRequest| async move {
+///         match request.target.as_deref() {
+///             Some("delete_repository") if !request.auth.has_scope("admin") => {
+///                 Err(Forbidden::new("delete_repository requires the admin scope"))
+///             }
+///             _ => Ok(()),
+///         }
+///     });
+/// server.serve_with_auth(transport, AuthContext::builder().subject(user).build()).await?;
+/// ```
+///
+/// Denied requests are answered with a `FORBIDDEN` error carrying the authorizer's reason.
+/// `initialize`, `ping` and `logging/setLevel` are not authorized.
+///
+/// [`Server::serve_with_auth`]: crate::server::Server::serve_with_auth
+/// [`RequestContext`]: crate::server::RequestContext
+use std::future::Future;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bon::Builder;
+use serde_json::{Map, Value};
+
+use crate::error::McpError;
+
+tokio::task_local! {
+    static CURRENT: Arc<AuthContext>;
+}
+
+/// Who a session acts for, as established by whoever accepted its connection.
+#[derive(Debug, Clone, Default, PartialEq, Builder)]
+pub struct AuthContext {
+    /// The authenticated user or service, `None` for anonymous sessions
+    #[builder(into)]
+    pub subject: Option<String>,
+    /// The tenant the subject belongs to, for servers that keep tenants apart
+    #[builder(into)]
+    pub tenant: Option<String>,
+    /// The scopes granted to the session
+    #[builder(default)]
+    pub scopes: Vec<String>,
+    /// Any other claims about the session, such as those of a bearer token
+    #[builder(default)]
+    pub claims: Map<String, Value>,
+}
+
+impl AuthContext {
+    /// A session nobody authenticated.
+    pub fn anonymous() -> Self {
+        Self::default()
+    }
+
+    /// The context of the session whose request is being handled, if any.
+    pub fn current() -> Option<Arc<Self>> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.subject.is_none()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+}
+
+/// Runs `future` with `auth` as [`AuthContext::current`].
+pub(crate) async fn scope<F: Future>(auth: Arc<AuthContext>, future: F) -> F::Output {
+    CURRENT.scope(auth, future).await
+}
+
+/// Why a connection could not be authenticated.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unauthorized: {0}")]
+pub struct Unauthorized(pub String);
+
+impl Unauthorized {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Unauthorized(reason.into())
+    }
+}
+
+/// Turns a bearer token into the [`AuthContext`] of the session it authenticates.
+#[async_trait]
+pub trait TokenValidator: Send + Sync {
+    async fn validate(&self, token: String) -> Result<AuthContext, Unauthorized>;
+}
+
+#[async_trait]
+impl<F, Fut> TokenValidator for F
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<AuthContext, Unauthorized>> + Send,
+{
+    async fn validate(&self, token: String) -> Result<AuthContext, Unauthorized> {
+        self(token).await
+    }
+}
+
+/// The token of an `Authorization: Bearer <token>` header value.
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+/// Authenticates a connection from the value of its `Authorization` header.
+pub async fn authenticate(
+    validator: &dyn TokenValidator,
+    authorization: Option<&str>,
+) -> Result<AuthContext, Unauthorized> {
+    let token = authorization
+        .and_then(bearer_token)
+        .ok_or_else(|| Unauthorized::new("Missing bearer token"))?;
+    validator.validate(token.to_string()).await
+}
+
+/// A request awaiting authorization.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationRequest {
+    /// Who the session acts for
+    pub auth: Arc<AuthContext>,
+    /// The method called
+    pub method: String,
+    /// What the method acts on: the tool name of `tools/call`, the prompt name of
+    /// `prompts/get`, the URI of `resources/read`, `resources/subscribe` and
+    /// `resources/unsubscribe`, or the prompt name or resource template URI that
+    /// `completion/complete` completes an argument of
+    pub target: Option<String>,
+}
+
+impl AuthorizationRequest {
+    pub(crate) fn new(auth: Arc<AuthContext>, method: &str, params: Option<&Value>) -> Self {
+        AuthorizationRequest {
+            auth,
+            method: method.to_string(),
+            target: target(method, params).map(str::to_string),
+        }
+    }
+}
+
+/// The name or URI of what a request for `method` acts on, see
+/// [`AuthorizationRequest::target`].
+pub(crate) fn target<'a>(method: &str, params: Option<&'a Value>) -> Option<&'a str> {
+    let key = match method {
+        "tools/call" | "prompts/get" => "name",
+        "resources/read" | "resources/subscribe" | "resources/unsubscribe" => "uri",
+        "completion/complete" => {
+            let reference = params?.get("ref")?;
+            let key = match reference.get("type")?.as_str()? {
+                "ref/prompt" => "name",
+                "ref/resource" => "uri",
+                _ => return None,
+            };
+            return reference.get(key)?.as_str();
+        }
+        _ => return None,
+    };
+    params?.get(key)?.as_str()
+}
+
+/// The reason a request was denied.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Forbidden: {0}")]
+pub struct Forbidden(pub String);
+
+impl Forbidden {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Forbidden(reason.into())
+    }
+}
+
+impl From<Forbidden> for McpError {
+    fn from(forbidden: Forbidden) -> Self {
+        McpError::forbidden(forbidden.0)
+    }
+}
+
+/// Decides whether a session may make a request.
+#[async_trait]
+pub trait Authorizer: Send + Sync {
+    async fn authorize(&self, request: AuthorizationRequest) -> Result<(), Forbidden>;
+}
+
+#[async_trait]
+impl<F, Fut> Authorizer for F
+where
+    F: Fn(AuthorizationRequest) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<(), Forbidden>> + Send,
+{
+    async fn authorize(&self, request: AuthorizationRequest) -> Result<(), Forbidden> {
+        self(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_requests_name_their_target() {
+        let auth = Arc::new(
+            AuthContext::builder()
+                .subject("ada")
+                .scopes(vec!["read".to_string()])
+                .build(),
+        );
+        assert!(auth.has_scope("read"));
+        assert!(!AuthContext::anonymous().has_scope("read"));
+
+        let call = json!({ "name": "search", "arguments": {} });
+        let read = json!({ "uri": "file:///notes.md" });
+        for (method, params, target) in [
+            ("tools/call", Some(&call), Some("search")),
+            ("resources/read", Some(&read), Some("file:///notes.md")),
+            ("tools/list", None, None),
+            ("prompts/get", Some(&read), None),
+        ] {
+            let request = AuthorizationRequest::new(auth.clone(), method, params);
+            assert_eq!(request.target.as_deref(), target, "{}", method);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bearer_tokens_are_validated() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("bearer  abc "), Some("abc"));
+        assert_eq!(bearer_token("Basic YWRhOg=="), None);
+        assert_eq!(bearer_token("Bearer "), None);
+
+        let validator = |token: String| async move {
+            match token.as_str() {
+                "t-1" => Ok(AuthContext::builder().subject("ada").tenant("acme").build()),
+                _ => Err(Unauthorized::new("Unknown token")),
+            }
+        };
+        let auth = authenticate(&validator, Some("Bearer t-1")).await.unwrap();
+        assert_eq!(auth.tenant.as_deref(), Some("acme"));
+        assert_eq!(
+            authenticate(&validator, Some("Bearer t-2")).await,
+            Err(Unauthorized::new("Unknown token"))
+        );
+        assert_eq!(
+            authenticate(&validator, None).await,
+            Err(Unauthorized::new("Missing bearer token"))
+        );
+
+        assert!(AuthContext::current().is_none());
+        let current = scope(Arc::new(auth), async { AuthContext::current() }).await;
+        assert_eq!(current.unwrap().subject.as_deref(), Some("ada"));
+    }
+}
@@ -0,0 +1,190 @@
+/// Serving MCP from an axum application.
+///
+/// [`router`] answers the Streamable HTTP transport at the root of the router it returns, so
+/// the MCP endpoint can be nested wherever it belongs among the application's own routes,
+/// behind the same middleware:
+///
+/// ```text
+/// let app = Router::new()
+///     .route("/health", get(health))
+///     .nest("/mcp", mcp_ox::axum::router(server))
+///     .layer(TraceLayer::new_for_http());
+/// axum::serve(listener, app).await?;
+/// ```
+///
+/// Middleware that authenticates requests itself hands the result to the session by
+/// inserting an [`AuthContext`] into the request's extensions. Other requests are
+/// authenticated by the token validator of the [`SessionManager`], if it has one, see
+/// [`router_with_sessions`].
+use std::convert::Infallible;
+
+use ::axum::Router;
+use ::axum::body::{Body, Bytes};
+use ::axum::extract::{Extension, State};
+use ::axum::http::{HeaderMap, Method, StatusCode, header};
+use ::axum::response::Response;
+use ::axum::routing::any;
+use futures::StreamExt;
+
+use crate::auth::AuthContext;
+use crate::server::Server;
+use crate::streamable::{
+    HttpBody, HttpMethod, HttpRequest, HttpResponse, PROTOCOL_VERSION_HEADER, SESSION_ID_HEADER,
+    SessionManager,
+};
+
+/// A router answering MCP requests for `server` at its root.
+pub fn router<S>(server: Server) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router_with_sessions(SessionManager::new(server))
+}
+
+/// A router answering MCP requests at its root with the sessions of `sessions`.
+pub fn router_with_sessions<S>(sessions: SessionManager) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new().route("/", any(handle)).with_state(sessions)
+}
+
+async fn handle(
+    State(sessions): State<SessionManager>,
+    auth: Option<Extension<AuthContext>>,
+    method: Method,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let method = match method {
+        Method::GET => HttpMethod::Get,
+        Method::POST => HttpMethod::Post,
+        Method::DELETE => HttpMethod::Delete,
+        _ => HttpMethod::Other,
+    };
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let request = HttpRequest::builder()
+        .method(method)
+        .maybe_session_id(header(SESSION_ID_HEADER))
+        .maybe_protocol_version(header(PROTOCOL_VERSION_HEADER))
+        .maybe_authorization(header(header::AUTHORIZATION.as_str()))
+        .maybe_accept(header(header::ACCEPT.as_str()))
+        .maybe_auth(auth.map(|Extension(auth)| auth))
+        .body(body.to_vec())
+        .build();
+    into_response(sessions.handle(request).await)
+}
+
+fn into_response(response: HttpResponse) -> Response {
+    let mut builder = Response::builder().status(response.status);
+    for (name, value) in response.headers {
+        builder = builder.header(name, value);
+    }
+    let body = match response.body {
+        HttpBody::Empty => Body::empty(),
+        HttpBody::Json(json) => Body::from(json),
+        HttpBody::Events(events) => Body::from_stream(events.map(Ok::<_, Infallible>)),
+    };
+    builder.body(body).unwrap_or_else(|_| {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        response
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Implementation;
+    use crate::streamable::parse_events;
+    use ::axum::http::Request;
+    use ::axum::routing::get;
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    fn request(method: &str, uri: &str, session_id: Option<&str>, body: Value) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::ACCEPT, "application/json, text/event-stream");
+        if let Some(session_id) = session_id {
+            request = request.header(SESSION_ID_HEADER, session_id);
+        }
+        request.body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn text(response: Response) -> String {
+        let body = ::axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mcp_is_nested_among_other_routes() {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        let app: Router = Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .nest("/mcp", router(server));
+
+        let health = Request::builder()
+            .uri("/health")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(health).await.unwrap();
+        assert_eq!(text(response).await, "ok");
+
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            }
+        });
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/mcp", None, initialize))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let session_id = response.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let events = parse_events(&text(response).await);
+        assert_eq!(events[0]["result"]["serverInfo"]["name"], "test-server");
+
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/mcp", Some(&session_id), initialized))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let ping = json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" });
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/mcp", Some(&session_id), ping))
+            .await
+            .unwrap();
+        assert_eq!(parse_events(&text(response).await)[0]["result"], json!({}));
+
+        let response = app
+            .oneshot(request("PUT", "/mcp", Some(&session_id), json!({})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -0,0 +1,569 @@
+/// Sharing a single MCP server connection between several local client processes.
+///
+/// The broker owns the real connection to a server (for example a stdio child process) and
+/// accepts clients on a Unix socket. Every client gets its own request id space: outgoing
+/// requests are renumbered before they reach the server and responses are mapped back to the
+/// client that sent them. Progress tokens are renumbered the same way, so progress notifications
+/// only reach the client whose request they report on. The server is initialized once; later
+/// clients receive the cached initialize result.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use serde_json::{Value, json};
+use tokio::net::UnixListener;
+use tokio::sync::{Mutex, oneshot};
+
+use crate::ping::ping_response;
+use crate::progress::{PROGRESS_NOTIFICATION, ProgressToken};
+use crate::protocol::{
+    ErrorData, INTERNAL_ERROR, JSONRPC_VERSION, JsonRpcError, JsonRpcMessage, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, ProtocolError, RequestId, RequestIdGenerator,
+};
+use crate::transport::{Transport, UnixSocketTransport};
+
+type ClientId = u64;
+
+/// Where the response to a request forwarded upstream has to go.
+enum Pending {
+    Client {
+        client: ClientId,
+        id: RequestId,
+        /// The progress token the client chose, replaced by the broker's id upstream.
+        progress_token: Option<ProgressToken>,
+    },
+    Initialize(oneshot::Sender<JsonRpcMessage>),
+}
+
+struct BrokerState<T> {
+    upstream: T,
+    upstream_ids: RequestIdGenerator,
+    next_client_id: AtomicU64,
+    clients: Mutex<HashMap<ClientId, Arc<UnixSocketTransport>>>,
+    /// Requests forwarded to the server, keyed by the id the broker assigned.
+    pending: Mutex<HashMap<RequestId, Pending>>,
+    /// Server-initiated requests forwarded to a client, keyed by the id the broker assigned.
+    server_requests: Mutex<HashMap<RequestId, RequestId>>,
+    /// The server's initialize response, shared by every client after the first one.
+    initialize: Mutex<Option<JsonRpcMessage>>,
+    initialized_sent: AtomicBool,
+}
+
+/// Multiplexes many local clients onto one upstream MCP server connection.
+pub struct Broker<T> {
+    state: Arc<BrokerState<T>>,
+}
+
+impl<T: Transport + 'static> Broker<T> {
+    /// Creates a broker that owns the given upstream connection.
+    pub fn new(upstream: T) -> Self {
+        Broker {
+            state: Arc::new(BrokerState {
+                upstream,
+                upstream_ids: RequestIdGenerator::default(),
+                next_client_id: AtomicU64::new(1),
+                clients: Mutex::new(HashMap::new()),
+                pending: Mutex::new(HashMap::new()),
+                server_requests: Mutex::new(HashMap::new()),
+                initialize: Mutex::new(None),
+                initialized_sent: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Accepts clients on `listener` until the upstream connection closes.
+    pub async fn serve(&self, listener: UnixListener) -> Result<(), ProtocolError> {
+        let upstream = tokio::spawn(run_upstream(self.state.clone()));
+        tokio::pin!(upstream);
+
+        loop {
+            tokio::select! {
+                result = &mut upstream => {
+                    return result.map_err(|e| ProtocolError::InternalError(e.to_string()))?;
+                }
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted.map_err(|e| {
+                        ProtocolError::TransportError(format!("Failed to accept client: {}", e))
+                    })?;
+                    let client = Arc::new(UnixSocketTransport::from_stream(stream));
+                    let id = self.state.next_client_id.fetch_add(1, Ordering::Relaxed);
+                    self.state.clients.lock().await.insert(id, client.clone());
+                    tokio::spawn(run_client(self.state.clone(), id, client));
+                }
+            }
+        }
+    }
+}
+
+/// Routes messages from the server to the clients.
+async fn run_upstream<T: Transport>(state: Arc<BrokerState<T>>) -> Result<(), ProtocolError> {
+    while let Some(message) = state.upstream.receive().await? {
+        match message {
+            JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_) => {
+                let Some(upstream_id) = response_id(&message) else {
+                    continue;
+                };
+                let pending = state.pending.lock().await.remove(upstream_id);
+                match pending {
+                    Some(Pending::Client { client, id, .. }) => {
+                        let client = state.clients.lock().await.get(&client).cloned();
+                        if let Some(client) = client {
+                            let _ = client.send(with_id(message, id)).await;
+                        }
+                    }
+                    Some(Pending::Initialize(sender)) => {
+                        let _ = sender.send(message);
+                    }
+                    None => {}
+                }
+            }
+            JsonRpcMessage::Request(request) => {
+                forward_server_request(&state, request).await?;
+            }
+            JsonRpcMessage::Notification(notification)
+                if notification.method == PROGRESS_NOTIFICATION =>
+            {
+                forward_progress(&state, notification).await;
+            }
+            JsonRpcMessage::Notification(_) => {
+                let clients: Vec<_> = state.clients.lock().await.values().cloned().collect();
+                for client in clients {
+                    let _ = client.send(message.clone()).await;
+                }
+            }
+        }
+    }
+
+    for client in state.clients.lock().await.drain().map(|(_, client)| client) {
+        let _ = client.close().await;
+    }
+    Ok(())
+}
+
+/// Sends a progress notification to the client whose request it reports on, with the client's
+/// own token restored. Progress for requests that already completed is dropped.
+async fn forward_progress<T: Transport>(
+    state: &BrokerState<T>,
+    mut notification: JsonRpcNotification,
+) {
+    let Some(Value::Object(params)) = notification.params.as_mut() else {
+        return;
+    };
+    let Some(upstream_id) = params
+        .get("progressToken")
+        .and_then(|token| serde_json::from_value::<RequestId>(token.clone()).ok())
+    else {
+        return;
+    };
+    let owner = match state.pending.lock().await.get(&upstream_id) {
+        Some(Pending::Client {
+            client,
+            progress_token: Some(token),
+            ..
+        }) => Some((*client, token.clone())),
+        _ => None,
+    };
+    let Some((client, token)) = owner else {
+        return;
+    };
+    params.insert("progressToken".to_string(), json!(token));
+    let client = state.clients.lock().await.get(&client).cloned();
+    if let Some(client) = client {
+        let _ = client
+            .send(JsonRpcMessage::Notification(notification))
+            .await;
+    }
+}
+
+/// Answers pings locally and hands any other server request to the oldest connected client.
+async fn forward_server_request<T: Transport>(
+    state: &BrokerState<T>,
+    request: JsonRpcRequest,
+) -> Result<(), ProtocolError> {
+    if let Some(pong) = ping_response(&request) {
+        return state.upstream.send(pong).await;
+    }
+
+    let target = {
+        let clients = state.clients.lock().await;
+        clients
+            .iter()
+            .min_by_key(|(id, _)| **id)
+            .map(|(_, client)| client.clone())
+    };
+    let Some(client) = target else {
+        return state
+            .upstream
+            .send(JsonRpcMessage::Error(JsonRpcError {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: Some(request.id),
+                error: ErrorData {
+                    code: INTERNAL_ERROR,
+                    message: "No client connected to the broker".to_string(),
+                    data: None,
+                },
+            }))
+            .await;
+    };
+
+    let broker_id = state.upstream_ids.next_id();
+    state
+        .server_requests
+        .lock()
+        .await
+        .insert(broker_id.clone(), request.id.clone());
+    let message = with_id(JsonRpcMessage::Request(request), broker_id);
+    let _ = client.send(message).await;
+    Ok(())
+}
+
+/// Routes messages from one client to the server.
+async fn run_client<T: Transport>(
+    state: Arc<BrokerState<T>>,
+    client_id: ClientId,
+    client: Arc<UnixSocketTransport>,
+) {
+    while let Ok(Some(message)) = client.receive().await {
+        let result = match message {
+            JsonRpcMessage::Request(mut request) => {
+                if request.method == "initialize" {
+                    initialize(&state, &client, request).await
+                } else {
+                    let upstream_id = state.upstream_ids.next_id();
+                    // Every client numbers its tokens on its own, so the server sees the
+                    // broker's id for the request instead.
+                    let progress_token = ProgressToken::from_request(&request);
+                    if progress_token.is_some() {
+                        upstream_token(&upstream_id).attach_to(&mut request);
+                    }
+                    state.pending.lock().await.insert(
+                        upstream_id.clone(),
+                        Pending::Client {
+                            client: client_id,
+                            id: request.id.clone(),
+                            progress_token,
+                        },
+                    );
+                    let message = with_id(JsonRpcMessage::Request(request), upstream_id);
+                    state.upstream.send(message).await
+                }
+            }
+            JsonRpcMessage::Notification(JsonRpcNotification { method, params, .. }) => {
+                forward_notification(&state, client_id, method, params).await
+            }
+            JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_) => {
+                let upstream_id = match response_id(&message) {
+                    Some(broker_id) => state.server_requests.lock().await.remove(broker_id),
+                    None => None,
+                };
+                match upstream_id {
+                    Some(upstream_id) => state.upstream.send(with_id(message, upstream_id)).await,
+                    None => Ok(()),
+                }
+            }
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+
+    state.clients.lock().await.remove(&client_id);
+    state.pending.lock().await.retain(
+        |_, pending| !matches!(pending, Pending::Client { client, .. } if *client == client_id),
+    );
+}
+
+/// Initializes the server on behalf of the first client and replays the result to later ones.
+async fn initialize<T: Transport>(
+    state: &BrokerState<T>,
+    client: &UnixSocketTransport,
+    request: JsonRpcRequest,
+) -> Result<(), ProtocolError> {
+    let client_request_id = request.id.clone();
+    let mut cached = state.initialize.lock().await;
+
+    if cached.is_none() {
+        let upstream_id = state.upstream_ids.next_id();
+        let (sender, receiver) = oneshot::channel();
+        state
+            .pending
+            .lock()
+            .await
+            .insert(upstream_id.clone(), Pending::Initialize(sender));
+        state
+            .upstream
+            .send(with_id(JsonRpcMessage::Request(request), upstream_id))
+            .await?;
+
+        let response = receiver.await.map_err(|_| {
+            ProtocolError::TransportError("Server closed during initialization".to_string())
+        })?;
+        if let JsonRpcMessage::Error(_) = response {
+            // Let a later client retry instead of caching the failure.
+            return client.send(with_id(response, client_request_id)).await;
+        }
+        *cached = Some(response);
+    }
+
+    match cached.as_ref() {
+        Some(response) => {
+            client
+                .send(with_id(response.clone(), client_request_id))
+                .await
+        }
+        None => Ok(()),
+    }
+}
+
+async fn forward_notification<T: Transport>(
+    state: &BrokerState<T>,
+    client_id: ClientId,
+    method: String,
+    mut params: Option<Value>,
+) -> Result<(), ProtocolError> {
+    match method.as_str() {
+        // The server only expects one initialized notification.
+        "notifications/initialized" if state.initialized_sent.swap(true, Ordering::SeqCst) => {
+            return Ok(());
+        }
+        // Cancellations refer to the client's own id, which the server never saw.
+        "notifications/cancelled" => {
+            let Some(request_id) = params
+                .as_ref()
+                .and_then(|p| p.get("requestId"))
+                .and_then(|id| serde_json::from_value::<RequestId>(id.clone()).ok())
+            else {
+                return Ok(());
+            };
+            let upstream_id =
+                state
+                    .pending
+                    .lock()
+                    .await
+                    .iter()
+                    .find_map(|(upstream_id, p)| match p {
+                        Pending::Client { client, id, .. }
+                            if *client == client_id && *id == request_id =>
+                        {
+                            Some(upstream_id.clone())
+                        }
+                        _ => None,
+                    });
+            let (Some(upstream_id), Some(Value::Object(params))) = (upstream_id, params.as_mut())
+            else {
+                return Ok(());
+            };
+            params.insert("requestId".to_string(), json!(upstream_id));
+        }
+        _ => {}
+    }
+
+    state
+        .upstream
+        .send(JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method,
+            params,
+        }))
+        .await
+}
+
+/// Returns the id a response or error message answers.
+fn response_id(message: &JsonRpcMessage) -> Option<&RequestId> {
+    match message {
+        JsonRpcMessage::Response(response) => Some(&response.id),
+        JsonRpcMessage::Error(error) => error.id.as_ref(),
+        _ => None,
+    }
+}
+
+/// The progress token that stands for a client's token upstream.
+fn upstream_token(upstream_id: &RequestId) -> ProgressToken {
+    match upstream_id {
+        RequestId::Number(id) => ProgressToken::Number(*id),
+        RequestId::Unsigned(id) => ProgressToken::Unsigned(*id),
+        RequestId::String(id) => ProgressToken::String(id.clone()),
+    }
+}
+
+/// Replaces the id of a request, response or error message.
+fn with_id(message: JsonRpcMessage, id: RequestId) -> JsonRpcMessage {
+    match message {
+        JsonRpcMessage::Request(request) => {
+            JsonRpcMessage::Request(JsonRpcRequest { id, ..request })
+        }
+        JsonRpcMessage::Response(response) => {
+            JsonRpcMessage::Response(JsonRpcResponse { id, ..response })
+        }
+        JsonRpcMessage::Error(error) => JsonRpcMessage::Error(JsonRpcError {
+            id: Some(id),
+            ..error
+        }),
+        message => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MemoryTransport;
+    use std::time::Duration;
+
+    /// A fake server that answers every request with its own method name and params.
+    async fn echo_server(transport: MemoryTransport) {
+        while let Ok(Some(message)) = transport.receive().await {
+            if let JsonRpcMessage::Request(request) = message {
+                let response = JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({ "method": request.method, "params": request.params })),
+                    error: None,
+                });
+                if transport.send(response).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// A fake server that reports progress on every request before answering it.
+    async fn progress_server(transport: MemoryTransport) {
+        while let Ok(Some(message)) = transport.receive().await {
+            let JsonRpcMessage::Request(request) = message else {
+                continue;
+            };
+            let token = ProgressToken::from_request(&request).unwrap();
+            let progress = JsonRpcMessage::Notification(JsonRpcNotification {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                method: PROGRESS_NOTIFICATION.to_string(),
+                params: Some(json!({
+                    "progressToken": token,
+                    "progress": 1,
+                    "message": request.method,
+                })),
+            });
+            let response = JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: request.id,
+                result: Some(json!({ "progressToken": token })),
+                error: None,
+            });
+            if transport.send(progress).await.is_err() || transport.send(response).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    fn request(id: i64, method: &str) -> JsonRpcMessage {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(id),
+            method: method.to_string(),
+            params: Some(json!({ "id": id })),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_clients_keep_independent_id_spaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broker.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let (upstream, server) = MemoryTransport::pair();
+        tokio::spawn(echo_server(server));
+        let broker = Broker::new(upstream);
+        tokio::spawn(async move { broker.serve(listener).await });
+
+        let first = UnixSocketTransport::connect(&path).await.unwrap();
+        let second = UnixSocketTransport::connect(&path).await.unwrap();
+
+        first.send(request(1, "tools/list")).await.unwrap();
+        second.send(request(1, "prompts/list")).await.unwrap();
+
+        let Some(JsonRpcMessage::Response(first_response)) = first.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        let Some(JsonRpcMessage::Response(second_response)) = second.receive().await.unwrap()
+        else {
+            panic!("Expected a response");
+        };
+
+        assert_eq!(first_response.id, RequestId::Number(1));
+        assert_eq!(first_response.result.unwrap()["method"], "tools/list");
+        assert_eq!(second_response.id, RequestId::Number(1));
+        assert_eq!(second_response.result.unwrap()["method"], "prompts/list");
+    }
+
+    #[tokio::test]
+    async fn test_initialize_is_shared() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broker.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let (upstream, server) = MemoryTransport::pair();
+        tokio::spawn(echo_server(server));
+        let broker = Broker::new(upstream);
+        tokio::spawn(async move { broker.serve(listener).await });
+
+        let first = UnixSocketTransport::connect(&path).await.unwrap();
+        first.send(request(7, "initialize")).await.unwrap();
+        let Some(JsonRpcMessage::Response(first_response)) = first.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+
+        let second = UnixSocketTransport::connect(&path).await.unwrap();
+        second.send(request(3, "initialize")).await.unwrap();
+        let Some(JsonRpcMessage::Response(second_response)) = second.receive().await.unwrap()
+        else {
+            panic!("Expected a response");
+        };
+
+        assert_eq!(first_response.id, RequestId::Number(7));
+        assert_eq!(second_response.id, RequestId::Number(3));
+        // The second client sees the result of the first client's handshake.
+        assert_eq!(first_response.result, second_response.result);
+        assert_eq!(second_response.result.unwrap()["params"]["id"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_progress_reaches_only_the_requesting_client() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broker.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let (upstream, server) = MemoryTransport::pair();
+        tokio::spawn(progress_server(server));
+        let broker = Broker::new(upstream);
+        tokio::spawn(async move { broker.serve(listener).await });
+
+        let first = UnixSocketTransport::connect(&path).await.unwrap();
+        let second = UnixSocketTransport::connect(&path).await.unwrap();
+        let mut upstream_tokens = Vec::new();
+        for (client, method) in [(&first, "tools/call"), (&second, "prompts/get")] {
+            // Both clients use the same request id and the same token.
+            let mut message = request(1, method);
+            if let JsonRpcMessage::Request(request) = &mut message {
+                ProgressToken::String("1".to_string()).attach_to(request);
+            }
+            client.send(message).await.unwrap();
+
+            let Some(JsonRpcMessage::Notification(progress)) = client.receive().await.unwrap()
+            else {
+                panic!("Expected a progress notification");
+            };
+            let params = progress.params.unwrap();
+            assert_eq!(params["progressToken"], "1");
+            assert_eq!(params["message"], method);
+            let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+                panic!("Expected a response");
+            };
+            assert_eq!(response.id, RequestId::Number(1));
+            upstream_tokens.push(response.result.unwrap()["progressToken"].clone());
+        }
+
+        assert_ne!(upstream_tokens[0], upstream_tokens[1]);
+        // The first client never saw the progress of the second client's request.
+        let extra = tokio::time::timeout(Duration::from_millis(50), first.receive()).await;
+        assert!(extra.is_err(), "Unexpected message: {:?}", extra);
+    }
+}
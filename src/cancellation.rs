@@ -0,0 +1,222 @@
+/// Cancellation of in-flight requests through `notifications/cancelled`.
+///
+/// The requester sends a cancelled notification naming the request id. The receiver looks the
+/// id up in its [`InFlightRequests`] and trips the [`CancellationToken`] it handed to the
+/// handler, which can stop early. Cancelling an unknown or already finished request is a
+/// no-op, as the spec requires.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio_util::sync::CancellationToken;
+
+use crate::protocol::{JSONRPC_VERSION, JsonRpcNotification, ProtocolError, RequestId};
+
+/// Method name of cancellation notifications.
+pub const CANCELLED_NOTIFICATION: &str = "notifications/cancelled";
+
+/// How long a server lets a cancelled handler wind down before dropping it.
+pub const CANCELLATION_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Parameters of a `notifications/cancelled` notification.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledNotificationParams {
+    /// The id of the request to cancel
+    pub request_id: RequestId,
+    /// An optional reason, which may be logged or presented to the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl CancelledNotificationParams {
+    pub fn new(request_id: RequestId, reason: Option<String>) -> Self {
+        CancelledNotificationParams {
+            request_id,
+            reason,
+            meta: None,
+        }
+    }
+
+    pub fn into_notification(self) -> Result<JsonRpcNotification, ProtocolError> {
+        Ok(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: CANCELLED_NOTIFICATION.to_string(),
+            params: Some(
+                serde_json::to_value(self)
+                    .map_err(|e| ProtocolError::InternalError(e.to_string()))?,
+            ),
+        })
+    }
+}
+
+/// Cancellation tokens of the requests a peer is currently handling.
+#[derive(Debug, Clone, Default)]
+pub struct InFlightRequests {
+    tokens: Arc<Mutex<HashMap<RequestId, CancellationToken>>>,
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a request and returns the guard holding its cancellation token.
+    ///
+    /// The request stays registered until the guard is dropped.
+    pub fn register(&self, id: RequestId) -> InFlightGuard {
+        let token = CancellationToken::new();
+        self.lock().insert(id.clone(), token.clone());
+        InFlightGuard {
+            id,
+            token,
+            requests: self.clone(),
+        }
+    }
+
+    /// Cancels the request with the given id. Returns `false` if it is not in flight.
+    pub fn cancel(&self, id: &RequestId) -> bool {
+        match self.lock().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies a `notifications/cancelled` notification. Returns `false` if the notification
+    /// is not a cancellation or names a request that is not in flight.
+    pub fn handle_notification(&self, notification: &JsonRpcNotification) -> bool {
+        if notification.method != CANCELLED_NOTIFICATION {
+            return false;
+        }
+        let Some(params) = notification
+            .params
+            .clone()
+            .and_then(|params| serde_json::from_value::<CancelledNotificationParams>(params).ok())
+        else {
+            return false;
+        };
+        self.cancel(&params.request_id)
+    }
+
+    /// Cancels every in-flight request, e.g. when the connection closes.
+    pub fn cancel_all(&self) {
+        for token in self.lock().values() {
+            token.cancel();
+        }
+    }
+
+    /// Returns the number of requests currently in flight.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<RequestId, CancellationToken>> {
+        self.tokens.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Keeps a request registered as in flight and exposes its cancellation token.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    id: RequestId,
+    token: CancellationToken,
+    requests: InFlightRequests,
+}
+
+impl InFlightGuard {
+    /// The token tripped when the requester cancels this request.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.requests.lock().remove(&self.id);
+    }
+}
+
+/// Cancels a request the client sent, such as one of
+/// [`Client::request_cancellable`](crate::client::Client::request_cancellable).
+///
+/// Cancelling resolves the request's future with [`ProtocolError::Cancelled`] and sends the
+/// server a `notifications/cancelled` for it. Cancelling a finished request does nothing.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    token: CancellationToken,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the request.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// The token tripped by [`CancelHandle::cancel`].
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_notification_cancels_handler() {
+        let requests = InFlightRequests::new();
+        let guard = requests.register(RequestId::Number(4));
+        let token = guard.token().clone();
+
+        let handler = tokio::spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => "cancelled",
+                _ = tokio::time::sleep(Duration::from_secs(10)) => "finished",
+            }
+        });
+
+        let notification =
+            CancelledNotificationParams::new(RequestId::Number(4), Some("user abort".to_string()))
+                .into_notification()
+                .unwrap();
+        assert!(requests.handle_notification(&notification));
+        assert_eq!(handler.await.unwrap(), "cancelled");
+
+        drop(guard);
+        assert!(requests.is_empty());
+        // Cancelling a finished request is ignored.
+        assert!(!requests.handle_notification(&notification));
+    }
+
+    #[test]
+    fn test_notification_serialization() {
+        let notification = CancelledNotificationParams::new(RequestId::from("abc"), None)
+            .into_notification()
+            .unwrap();
+        assert_eq!(notification.method, "notifications/cancelled");
+        assert_eq!(
+            notification.params,
+            Some(serde_json::json!({ "requestId": "abc" }))
+        );
+    }
+}
@@ -0,0 +1,169 @@
+/// Canonical JSON output.
+///
+/// The same message can be serialized in many ways: members may come in any order and
+/// numbers have several spellings. The canonical form follows the JSON Canonicalization
+/// Scheme (RFC 8785), so equal messages always produce identical bytes, as needed for
+/// signatures, golden tests and content hashes:
+///
+/// - no whitespace between tokens
+/// - object members sorted by their UTF-16 code units
+/// - numbers in the shortest form that round-trips, spelled as in ECMAScript, e.g. `1` for
+///   `1.0` and `1e+21` for `1.0e21`
+/// - strings with only the escapes JSON requires
+use std::fmt::Write;
+
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+use crate::protocol::{JsonRpcMessage, ProtocolError};
+
+/// Serializes `value` to canonical JSON.
+pub fn to_canonical_string<T: Serialize + ?Sized>(value: &T) -> Result<String, ProtocolError> {
+    let value =
+        serde_json::to_value(value).map_err(|e| ProtocolError::InternalError(e.to_string()))?;
+    let mut output = String::new();
+    write_value(&mut output, &value);
+    Ok(output)
+}
+
+/// Serializes `value` to canonical JSON bytes.
+pub fn to_canonical_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+    to_canonical_string(value).map(String::into_bytes)
+}
+
+impl JsonRpcMessage {
+    /// Serializes this message to canonical JSON.
+    pub fn to_canonical_string(&self) -> Result<String, ProtocolError> {
+        to_canonical_string(self)
+    }
+}
+
+fn write_value(output: &mut String, value: &Value) {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+        Value::Number(number) => write_number(output, number),
+        Value::String(string) => write_string(output, string),
+        Value::Array(items) => {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_value(output, item);
+            }
+            output.push(']');
+        }
+        Value::Object(object) => {
+            let mut members: Vec<_> = object.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            output.push('{');
+            for (index, (key, value)) in members.into_iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_string(output, key);
+                output.push(':');
+                write_value(output, value);
+            }
+            output.push('}');
+        }
+    }
+}
+
+fn write_string(output: &mut String, string: &str) {
+    // serde_json escapes exactly what RFC 8785 requires.
+    output.push_str(&Value::from(string).to_string());
+}
+
+fn write_number(output: &mut String, number: &Number) {
+    if number.is_i64() || number.is_u64() {
+        output.push_str(&number.to_string());
+        return;
+    }
+    // JSON numbers are always finite.
+    let value = number.as_f64().unwrap_or_default();
+    if value == 0.0 {
+        output.push('0');
+        return;
+    }
+    if value < 0.0 {
+        output.push('-');
+    }
+
+    // `{:e}` yields the shortest digits that round-trip, e.g. `1.2345e-7`.
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    if k <= n && n <= 21 {
+        output.push_str(&digits);
+        output.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        let (integer, fraction) = digits.split_at(n as usize);
+        let _ = write!(output, "{}.{}", integer, fraction);
+    } else if -6 < n && n <= 0 {
+        output.push_str("0.");
+        output.extend(std::iter::repeat_n('0', (-n) as usize));
+        output.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        output.push_str(first);
+        if !rest.is_empty() {
+            let _ = write!(output, ".{}", rest);
+        }
+        let _ = write!(
+            output,
+            "e{}{}",
+            if n > 0 { "+" } else { "-" },
+            (n - 1).abs()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_members_are_sorted_and_whitespace_removed() {
+        let value = json!({
+            "params": { "z": [3, { "b": true, "a": null }], "a": "é\n" },
+            "method": "tools/call",
+            "id": 1,
+            "jsonrpc": "2.0"
+        });
+        assert_eq!(
+            to_canonical_string(&value).unwrap(),
+            r#"{"id":1,"jsonrpc":"2.0","method":"tools/call","params":{"a":"é\n","z":[3,{"a":null,"b":true}]}}"#
+        );
+
+        let message: JsonRpcMessage = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(
+            message.to_canonical_string().unwrap(),
+            to_canonical_string(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_numbers_use_ecmascript_spelling() {
+        for (number, expected) in [
+            (json!(1.0), "1"),
+            (json!(-0.0), "0"),
+            (json!(0.5), "0.5"),
+            (json!(-12.25), "-12.25"),
+            (json!(1e21), "1e+21"),
+            (json!(1e20), "100000000000000000000"),
+            (json!(1.5e-7), "1.5e-7"),
+            (json!(0.000001), "0.000001"),
+            (json!(u64::MAX), "18446744073709551615"),
+            (json!(-7), "-7"),
+        ] {
+            assert_eq!(to_canonical_string(&number).unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+/// Checks against the capabilities negotiated during initialization.
+///
+/// Both sides of a session must only use features the peer advertised. The
+/// [`require_capability!`](crate::require_capability) macro performs that check and returns
+/// [`ProtocolError::CapabilityNotSupported`] from the enclosing function when the capability is
+/// missing, so handlers do not have to repeat the lookup:
+///
+/// ```text
+/// require_capability!(session, resources.subscribe);
+/// ```
+use serde_json::Value;
+
+use crate::client::{Client, SessionInfo};
+use crate::protocol::{ClientCapabilities, ProtocolError, ServerCapabilities};
+use crate::transport::Transport;
+
+/// Anything that knows which capabilities a peer advertised.
+pub trait Capabilities {
+    /// Returns `true` if the capability at `path` was advertised.
+    ///
+    /// Path segments are written in snake case (`list_changed`) and matched against the
+    /// camel case names used on the wire (`listChanged`). A capability counts as advertised
+    /// when every segment is present and not `null`, and the last one is not `false`.
+    fn has_capability(&self, path: &[&str]) -> bool;
+
+    /// Like [`Capabilities::has_capability`], but returns an error naming the missing
+    /// capability.
+    fn require_capability(&self, path: &[&str]) -> Result<(), ProtocolError> {
+        if self.has_capability(path) {
+            Ok(())
+        } else {
+            Err(ProtocolError::CapabilityNotSupported {
+                capability: path.join("."),
+                method: None,
+            })
+        }
+    }
+}
+
+impl Capabilities for Value {
+    fn has_capability(&self, path: &[&str]) -> bool {
+        let mut current = self;
+        for segment in path {
+            match current.get(camel_case(segment)) {
+                Some(next) if !next.is_null() => current = next,
+                _ => return false,
+            }
+        }
+        current != &Value::Bool(false)
+    }
+}
+
+impl Capabilities for ServerCapabilities {
+    fn has_capability(&self, path: &[&str]) -> bool {
+        serde_json::to_value(self).is_ok_and(|value| value.has_capability(path))
+    }
+}
+
+impl Capabilities for ClientCapabilities {
+    fn has_capability(&self, path: &[&str]) -> bool {
+        serde_json::to_value(self).is_ok_and(|value| value.has_capability(path))
+    }
+}
+
+impl Capabilities for SessionInfo {
+    /// Checks the server capabilities as sent, so capabilities this crate does not model
+    /// yet can be required too.
+    fn has_capability(&self, path: &[&str]) -> bool {
+        self.raw_capabilities.has_capability(path)
+    }
+}
+
+impl<T: Transport + 'static> Capabilities for Client<T> {
+    fn has_capability(&self, path: &[&str]) -> bool {
+        self.session_info().has_capability(path)
+    }
+}
+
+impl<C: Capabilities + ?Sized> Capabilities for &C {
+    fn has_capability(&self, path: &[&str]) -> bool {
+        (**self).has_capability(path)
+    }
+}
+
+/// Returns the path of the server capability that client requests of `method` require, or
+/// `None` for methods any server answers, such as `ping`.
+pub fn required_server_capability(method: &str) -> Option<&'static [&'static str]> {
+    match method {
+        "tools/list" | "tools/call" => Some(&["tools"]),
+        "resources/list" | "resources/templates/list" | "resources/read" => Some(&["resources"]),
+        "resources/subscribe" | "resources/unsubscribe" => Some(&["resources", "subscribe"]),
+        "prompts/list" | "prompts/get" => Some(&["prompts"]),
+        "completion/complete" => Some(&["completions"]),
+        "logging/setLevel" => Some(&["logging"]),
+        _ => None,
+    }
+}
+
+fn camel_case(segment: &str) -> String {
+    let mut result = String::with_capacity(segment.len());
+    let mut upper = false;
+    for c in segment.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            result.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Returns early with [`ProtocolError::CapabilityNotSupported`] unless the capability at the
+/// given path was negotiated.
+///
+/// The first argument is anything implementing [`Capabilities`]. The error is converted with
+/// `Into`, so the macro works in any function whose error type implements
+/// `From<ProtocolError>`.
+#[macro_export]
+macro_rules! require_capability {
+    ($capabilities:expr, $($segment:ident).+) => {
+        if let Err(error) = $crate::capability::Capabilities::require_capability(
+            &$capabilities,
+            &[$(stringify!($segment)),+],
+        ) {
+            return Err(error.into());
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{CAPABILITY_NOT_SUPPORTED, ErrorData, ResourcesCapability};
+    use serde_json::json;
+
+    fn subscribe(capabilities: &impl Capabilities) -> Result<&'static str, ErrorData> {
+        require_capability!(capabilities, resources.subscribe);
+        Ok("subscribed")
+    }
+
+    #[test]
+    fn test_require_capability_on_raw_capabilities() {
+        let capabilities = json!({
+            "resources": { "subscribe": true, "listChanged": false },
+            "logging": {},
+            "sampling": null
+        });
+        assert!(capabilities.has_capability(&["logging"]));
+        assert!(capabilities.has_capability(&["resources", "subscribe"]));
+        assert!(!capabilities.has_capability(&["resources", "list_changed"]));
+        assert!(!capabilities.has_capability(&["sampling"]));
+        assert!(!capabilities.has_capability(&["roots", "list_changed"]));
+        assert_eq!(subscribe(&capabilities), Ok("subscribed"));
+
+        let error = subscribe(&json!({ "resources": {} })).unwrap_err();
+        assert_eq!(error.code, CAPABILITY_NOT_SUPPORTED);
+        assert_eq!(
+            error.message,
+            "Capability not negotiated: resources.subscribe"
+        );
+    }
+
+    #[test]
+    fn test_require_capability_on_typed_capabilities() {
+        let mut capabilities = ServerCapabilities {
+            resources: Some(ResourcesCapability {
+                subscribe: Some(true),
+                list_changed: None,
+            }),
+            ..Default::default()
+        };
+        assert!(capabilities.has_capability(&["resources"]));
+        assert!(!capabilities.has_capability(&["tools"]));
+        assert!(subscribe(&capabilities).is_ok());
+
+        capabilities.resources = None;
+        assert!(matches!(
+            capabilities.require_capability(&["resources", "subscribe"]),
+            Err(ProtocolError::CapabilityNotSupported { capability, method: None })
+                if capability == "resources.subscribe"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_requests_fail_fast_without_the_capability() {
+        use crate::error::McpError;
+        use crate::protocol::{ClientRequest, Implementation, PaginatedRequestParams};
+        use crate::resource::SubscribeRequestParams;
+        use crate::server::Server;
+        use crate::tool::{CallToolResult, ListToolsResult, Tool};
+        use crate::transport::MemoryTransport;
+        use serde_json::Map;
+
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        server.add_tool(
+            Tool::builder().name("echo").build(),
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("")) },
+        );
+        let (transport, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let client = Client::connect(
+            transport,
+            Implementation::new("test-client", "1.0.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let params = SubscribeRequestParams {
+            uri: "file:///notes.txt".to_string(),
+            meta: None,
+        };
+        let error = client
+            .request::<Value>(ClientRequest::Subscribe(params))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Capability not negotiated: resources.subscribe, which resources/subscribe requires"
+        );
+        // Callers can tell the missing capability apart from a method the server lacks.
+        let error = McpError::from(error);
+        assert_eq!(error.code(), CAPABILITY_NOT_SUPPORTED);
+        assert_eq!(
+            error.data(),
+            Some(&json!({ "capability": "resources.subscribe", "method": "resources/subscribe" }))
+        );
+        let tools: ListToolsResult = client
+            .request(ClientRequest::ListTools(PaginatedRequestParams::default()))
+            .await
+            .unwrap();
+        assert_eq!(tools.tools.len(), 1);
+        assert!(client.ping().await.is_ok());
+    }
+}
@@ -0,0 +1,2401 @@
+/// MCP clients that connect to servers over a transport.
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, TryStreamExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::approval::Approver;
+use crate::cancellation::{CancelHandle, CancelledNotificationParams};
+use crate::capability::{Capabilities, required_server_capability};
+use crate::completion::{
+    CompleteRequestParams, CompleteResult, Completion, CompletionArgument, CompletionReference,
+};
+use crate::elicitation::{ElicitRequestParams, ElicitationHandler};
+use crate::error::McpError;
+use crate::extension::ExtensionRequest;
+use crate::handshake::{InitializeRequest, client_handshake};
+use crate::logging::{LoggingLevel, LoggingMessageNotificationParams, SetLevelRequestParams};
+use crate::pagination::Paginated;
+use crate::partial::{self, PARTIAL_RESULT_NOTIFICATION, PartialResultNotificationParams};
+use crate::pending::PendingRequests;
+use crate::ping::ping_response;
+use crate::progress::{PROGRESS_NOTIFICATION, ProgressNotificationParams, ProgressToken};
+use crate::prompt::{
+    GetPromptRequestParams, GetPromptResult, ListPromptsResult, Prompt, PromptMessageContent,
+};
+use crate::protocol::{
+    CONNECTION_CLOSED, ClientCapabilities, ClientNotification, ClientRequest, Implementation,
+    InitializeRequestParams, JSONRPC_VERSION, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse, PaginatedRequestParams, ProtocolError, ProtocolVersion, RequestId,
+    RequestIdGenerator, ServerCapabilities, ServerNotification, ServerRequest,
+};
+use crate::reconnect::SessionRestored;
+use crate::require_capability;
+use crate::resource::{
+    ListResourceTemplatesResult, ListResourcesResult, ReadResourceRequestParams,
+    ReadResourceResult, Resource, ResourceTemplate, ResourceUpdatedNotificationParams,
+    SubscribeRequestParams, UnsubscribeRequestParams,
+};
+use crate::retry::RetryPolicy;
+use crate::roots::{ListRootsResult, Root};
+use crate::sampling::{CreateMessageRequestParams, SamplingHandler};
+use crate::timeout::RequestTracker;
+use crate::tool::{CallToolRequestParams, CallToolResult, ListToolsResult, Tool};
+use crate::trace::{RequestScope, TraceContext};
+use crate::transport::Transport;
+
+/// What was negotiated with the server during the initialize handshake.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    /// The protocol version the server answered with, used for the rest of the session
+    pub protocol_version: ProtocolVersion,
+    /// The session id assigned by the server, for transports that have one (e.g. HTTP)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub server_info: Implementation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    pub capabilities: ServerCapabilities,
+    /// The capabilities object exactly as the server sent it, including unknown members
+    pub raw_capabilities: Value,
+    /// When the initialize request was sent
+    pub handshake_started_at: DateTime<Utc>,
+    /// Time from sending initialize to receiving its result
+    pub handshake_duration: Duration,
+}
+
+/// A connection to an MCP server.
+///
+/// Every message the server sends is read by a background task as soon as it arrives:
+/// responses are handed to the requests waiting for them, so requests can be sent
+/// concurrently, notifications are routed to the registered handlers, and requests of the
+/// server are answered.
+pub struct Client<T> {
+    transport: Arc<T>,
+    ids: RequestIdGenerator,
+    session: SessionInfo,
+    tracker: RequestTracker,
+    method_timeouts: HashMap<String, Duration>,
+    retry: Option<RetryPolicy>,
+    client_info: Implementation,
+    capabilities: ClientCapabilities,
+    handlers: Arc<RwLock<Handlers>>,
+    /// The requests waiting for their response
+    pending: PendingRequests,
+    /// Where the notifications about requests in progress go, by progress token
+    listeners: Listeners,
+    reader: Reader,
+}
+
+type Handler<P> = Arc<dyn Fn(P) + Send + Sync>;
+
+/// The handlers server notifications and requests are routed to, one per kind.
+///
+/// Clones share the roots and subscriptions.
+#[derive(Default, Clone)]
+struct Handlers {
+    tools_list_changed: Option<Handler<()>>,
+    resource_updated: Option<Handler<ResourceUpdatedNotificationParams>>,
+    log_message: Option<Handler<LoggingMessageNotificationParams>>,
+    progress: Option<Handler<ProgressNotificationParams>>,
+    sampling: Option<Arc<dyn SamplingHandler>>,
+    elicitation: Option<Arc<dyn ElicitationHandler>>,
+    approver: Option<Arc<dyn Approver>>,
+    /// The roots listed to the server, if the client declared the roots capability
+    roots: Option<Arc<Mutex<Vec<Root>>>>,
+    /// The callbacks of the resources the client subscribed to, by URI
+    subscriptions: Arc<Mutex<HashMap<String, Subscription>>>,
+}
+
+type Listeners = Arc<Mutex<HashMap<ProgressToken, mpsc::UnboundedSender<JsonRpcNotification>>>>;
+
+type Subscription = Arc<dyn Fn(ResourceUpdatedNotificationParams) + Send + Sync>;
+
+impl Handlers {
+    /// Answers a request of the server. Requests the client has no handler for are answered
+    /// with a method not found error.
+    async fn answer(&self, request: JsonRpcRequest) -> JsonRpcMessage {
+        if let Some(pong) = ping_response(&request) {
+            return pong;
+        }
+        let id = request.id.clone();
+        let result = match ServerRequest::try_from(request) {
+            Ok(ServerRequest::CreateMessage(params)) => self.create_message(*params).await,
+            Ok(ServerRequest::Elicit(params)) => self.elicit(params).await,
+            Ok(ServerRequest::ListRoots) if self.roots.is_some() => {
+                let roots = ListRootsResult {
+                    roots: self.roots(),
+                    meta: None,
+                };
+                serde_json::to_value(roots).map_err(|e| McpError::internal_error(e.to_string()))
+            }
+            Ok(request) => Err(McpError::method_not_found(request.method())),
+            Err(error) => Err(error.into()),
+        };
+        match result {
+            Ok(result) => JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id,
+                result: Some(result),
+                error: None,
+            }),
+            Err(error) => error.into_response(id),
+        }
+    }
+
+    fn subscribed(&self) -> Vec<String> {
+        self.lock_subscriptions().keys().cloned().collect()
+    }
+
+    fn lock_subscriptions(&self) -> MutexGuard<'_, HashMap<String, Subscription>> {
+        self.subscriptions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn roots(&self) -> Vec<Root> {
+        self.roots
+            .iter()
+            .flat_map(|roots| roots.lock().unwrap_or_else(|e| e.into_inner()).clone())
+            .collect()
+    }
+
+    async fn create_message(&self, params: CreateMessageRequestParams) -> Result<Value, McpError> {
+        let Some(sampling) = &self.sampling else {
+            return Err(McpError::method_not_found("sampling/createMessage"));
+        };
+        let params = match &self.approver {
+            Some(approver) => approver
+                .approve_sampling(&params)
+                .await
+                .apply(params)
+                .map_err(McpError::user_rejected)?,
+            None => params,
+        };
+        let result = sampling.create_message(params).await?;
+        serde_json::to_value(result).map_err(|e| McpError::internal_error(e.to_string()))
+    }
+
+    async fn elicit(&self, params: ElicitRequestParams) -> Result<Value, McpError> {
+        let Some(elicitation) = &self.elicitation else {
+            return Err(McpError::method_not_found("elicitation/create"));
+        };
+        let result = elicitation.elicit(params).await?;
+        serde_json::to_value(result).map_err(|e| McpError::internal_error(e.to_string()))
+    }
+
+    /// Passes `notification` to the handler of its kind. Notifications without a handler,
+    /// and ones that do not parse, are dropped.
+    fn route(&self, notification: JsonRpcNotification) {
+        let Ok(notification) =
+            ServerNotification::from_parts(&notification.method, notification.params)
+        else {
+            return;
+        };
+        match notification {
+            ServerNotification::ToolListChanged => {
+                if let Some(handler) = &self.tools_list_changed {
+                    handler(());
+                }
+            }
+            ServerNotification::ResourceUpdated(params) => {
+                let subscription = self.lock_subscriptions().get(&params.uri).cloned();
+                if let Some(subscription) = subscription {
+                    subscription(params.clone());
+                }
+                if let Some(handler) = &self.resource_updated {
+                    handler(params);
+                }
+            }
+            ServerNotification::LoggingMessage(params) => {
+                if let Some(handler) = &self.log_message {
+                    handler(params);
+                }
+            }
+            ServerNotification::Progress(params) => {
+                if let Some(handler) = &self.progress {
+                    handler(params);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<T: Transport + 'static> Client<T> {
+    /// Performs the initialize handshake over `transport`, declaring `capabilities`, and
+    /// returns the connected client once the server was sent `notifications/initialized`.
+    pub async fn connect(
+        transport: T,
+        client_info: Implementation,
+        capabilities: ClientCapabilities,
+    ) -> Result<Self, ProtocolError> {
+        let ids = RequestIdGenerator::default();
+        let session = initialize(&transport, &ids, &client_info, &capabilities).await?;
+        let transport = Arc::new(transport);
+        let handlers = Arc::new(RwLock::new(Handlers {
+            roots: capabilities.roots.is_some().then(Arc::default),
+            ..Handlers::default()
+        }));
+        let pending = PendingRequests::new();
+        let listeners = Listeners::default();
+        let reader = Reader::spawn(
+            transport.clone(),
+            handlers.clone(),
+            pending.clone(),
+            listeners.clone(),
+        );
+        Ok(Client {
+            transport,
+            ids,
+            session,
+            tracker: RequestTracker::default(),
+            method_timeouts: HashMap::new(),
+            retry: None,
+            handlers,
+            pending,
+            listeners,
+            reader,
+            client_info,
+            capabilities,
+        })
+    }
+
+    /// Connects again over `transport`, e.g. after the previous connection dropped.
+    ///
+    /// The handshake is repeated with the same client info and capabilities, and the client
+    /// keeps its handlers, roots and settings. Resources it was subscribed to are subscribed
+    /// to again on the new connection.
+    pub async fn reconnect(mut self, transport: T) -> Result<Self, ProtocolError> {
+        self.restore(transport).await?;
+        Ok(self)
+    }
+
+    /// Restores the session over `transport`, as [`Client::reconnect`] does, in place.
+    ///
+    /// Servers that were told about the roots before are told that they changed, so they
+    /// ask for them again.
+    pub async fn restore(&mut self, transport: T) -> Result<SessionRestored, ProtocolError> {
+        let previous_session_id = self.session.session_id.clone();
+        self.session =
+            initialize(&transport, &self.ids, &self.client_info, &self.capabilities).await?;
+        self.transport = Arc::new(transport);
+        self.reader = Reader::spawn(
+            self.transport.clone(),
+            self.handlers.clone(),
+            self.pending.clone(),
+            self.listeners.clone(),
+        );
+        let subscriptions = self.handlers().subscribed();
+        for uri in &subscriptions {
+            let params = SubscribeRequestParams {
+                uri: uri.clone(),
+                meta: None,
+            };
+            let _: Value = self.request(ClientRequest::Subscribe(params)).await?;
+        }
+        if self.capabilities.has_capability(&["roots", "list_changed"]) {
+            let notification = ClientNotification::RootsListChanged.into_notification()?;
+            self.transport
+                .send(JsonRpcMessage::Notification(notification))
+                .await?;
+        }
+        Ok(SessionRestored {
+            previous_session_id,
+            session: self.session.clone(),
+            subscriptions,
+        })
+    }
+
+    /// Sets how long requests may wait for a result before they are cancelled.
+    ///
+    /// Defaults to [`DEFAULT_REQUEST_TIMEOUT`](crate::timeout::DEFAULT_REQUEST_TIMEOUT).
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.tracker = RequestTracker::new(timeout);
+        self
+    }
+
+    /// Gives requests of `method` at most `timeout` for their result, instead of the timeout
+    /// set with [`Client::with_request_timeout`]. Timeouts given to
+    /// [`Client::request_with_timeout`] take precedence.
+    pub fn with_method_timeout(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.method_timeouts.insert(method.into(), timeout);
+        self
+    }
+
+    /// Retries idempotent requests that fail for a transient reason, see [`crate::retry`].
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Calls `handler` whenever the server announces that its list of tools changed.
+    ///
+    /// Like the other notification handlers, it replaces the handler registered before and
+    /// runs as soon as the notification arrives, whether or not a request is waiting for
+    /// its result.
+    pub fn on_tools_list_changed(self, handler: impl Fn() + Send + Sync + 'static) -> Self {
+        self.handlers_mut().tools_list_changed = Some(Arc::new(move |()| handler()));
+        self
+    }
+
+    /// Calls `handler` whenever a resource the client subscribed to was updated.
+    pub fn on_resource_updated(
+        self,
+        handler: impl Fn(ResourceUpdatedNotificationParams) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers_mut().resource_updated = Some(Arc::new(handler));
+        self
+    }
+
+    /// Calls `handler` with every log message the server sends.
+    pub fn on_log_message(
+        self,
+        handler: impl Fn(LoggingMessageNotificationParams) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers_mut().log_message = Some(Arc::new(handler));
+        self
+    }
+
+    /// Calls `handler` with the progress notifications that are not taken by the callback of
+    /// [`Client::request_with_progress`].
+    pub fn on_progress(
+        self,
+        handler: impl Fn(ProgressNotificationParams) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers_mut().progress = Some(Arc::new(handler));
+        self
+    }
+
+    /// Answers the `sampling/createMessage` requests of the server with `handler`. The
+    /// sampling capability must be declared on [`Client::connect`] for servers to send them.
+    pub fn with_sampling(self, handler: impl SamplingHandler + 'static) -> Self {
+        self.handlers_mut().sampling = Some(Arc::new(handler));
+        self
+    }
+
+    /// Answers the `elicitation/create` requests of the server with `handler`. The
+    /// elicitation capability must be declared on [`Client::connect`], see
+    /// [`ClientCapabilities::with_elicitation`], for servers to send them.
+    pub fn with_elicitation(self, handler: impl ElicitationHandler + 'static) -> Self {
+        self.handlers_mut().elicitation = Some(Arc::new(handler));
+        self
+    }
+
+    /// Asks `approver` before every tool call and every sampling request, see
+    /// [`crate::approval`].
+    pub fn with_approver(self, approver: impl Approver + 'static) -> Self {
+        self.handlers_mut().approver = Some(Arc::new(approver));
+        self
+    }
+
+    /// Waits until the connection closes, returning the error it failed with, if any.
+    ///
+    /// The notifications and requests of the server are handled in the background whether or
+    /// not the client listens, and requests may be sent meanwhile. For clients that wait on
+    /// the server between requests, e.g. in a `tokio::select!`.
+    pub async fn listen(&self) -> Result<(), ProtocolError> {
+        self.reader.ended().await
+    }
+
+    /// Returns the details negotiated with the server.
+    pub fn session_info(&self) -> &SessionInfo {
+        &self.session
+    }
+
+    /// Returns the protocol version negotiated with the server.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.session.protocol_version
+    }
+
+    /// Returns the name and version of the server.
+    pub fn server_info(&self) -> &Implementation {
+        &self.session.server_info
+    }
+
+    /// Returns the capabilities the server advertised.
+    pub fn server_capabilities(&self) -> &ServerCapabilities {
+        &self.session.capabilities
+    }
+
+    /// Returns the server's instructions on how to use it, if it gave any.
+    pub fn instructions(&self) -> Option<&str> {
+        self.session.instructions.as_deref()
+    }
+
+    /// Sends a request and waits for its result.
+    pub async fn request<R: DeserializeOwned>(
+        &self,
+        request: ClientRequest,
+    ) -> Result<R, ProtocolError> {
+        self.send_tracked(request, None, None, None).await
+    }
+
+    /// Sends a request and waits at most `timeout` for its result.
+    ///
+    /// When the timeout elapses, the server is sent a `notifications/cancelled` for the request
+    /// and [`ProtocolError::Timeout`] is returned.
+    pub async fn request_with_timeout<R: DeserializeOwned>(
+        &self,
+        request: ClientRequest,
+        timeout: Duration,
+    ) -> Result<R, ProtocolError> {
+        self.send_tracked(request, Some(timeout), None, None).await
+    }
+
+    /// Sends a non-standard request and waits for its result.
+    pub async fn request_extension<E: ExtensionRequest>(
+        &self,
+        params: E,
+    ) -> Result<E::Result, ProtocolError> {
+        let id = self.ids.next_id();
+        let response = self.exchange(params.into_request(id.clone())?, None);
+        self.tracker
+            .track(&*self.transport, id, None, response)
+            .await
+    }
+
+    /// Requests every page of a list, following `nextCursor` until the last page, and returns
+    /// the pages in order.
+    ///
+    /// `request` builds the list request for a page, e.g. `ClientRequest::ListTools`.
+    pub async fn request_all_pages<R: Paginated + DeserializeOwned>(
+        &self,
+        request: impl Fn(PaginatedRequestParams) -> ClientRequest,
+    ) -> Result<Vec<R>, ProtocolError> {
+        let mut pages = Vec::new();
+        let mut seen = HashSet::new();
+        let mut params = PaginatedRequestParams::default();
+        loop {
+            let page: R = self.request(request(params.clone())).await?;
+            let next_cursor = page.next_cursor().cloned();
+            pages.push(page);
+            match next_cursor {
+                Some(cursor) if !seen.insert(cursor.clone()) => {
+                    return Err(ProtocolError::ProtocolError(format!(
+                        "Server returned cursor {} twice",
+                        cursor.0
+                    )));
+                }
+                Some(cursor) => params.cursor = Some(cursor),
+                None => return Ok(pages),
+            }
+        }
+    }
+
+    /// Streams the items of a list, requesting pages with `request` and taking the items of
+    /// each with `items`. Fails unless the server advertised `capability`.
+    fn list_stream<R, I>(
+        &self,
+        capability: &'static str,
+        request: fn(PaginatedRequestParams) -> ClientRequest,
+        items: fn(R) -> Vec<I>,
+    ) -> impl Stream<Item = Result<I, ProtocolError>> + '_
+    where
+        R: Paginated + DeserializeOwned + 'static,
+        I: 'static,
+    {
+        let first = Some((PaginatedRequestParams::default(), HashSet::new()));
+        stream::try_unfold(first, move |state| async move {
+            let Some((mut params, mut seen)) = state else {
+                return Ok(None);
+            };
+            self.require_capability(&[capability])?;
+            let page: R = self.request(request(params.clone())).await?;
+            let next = match page.next_cursor().cloned() {
+                Some(cursor) if !seen.insert(cursor.clone()) => {
+                    return Err(ProtocolError::ProtocolError(format!(
+                        "Server returned cursor {} twice",
+                        cursor.0
+                    )));
+                }
+                Some(cursor) => {
+                    params.cursor = Some(cursor);
+                    Some((params, seen))
+                }
+                None => None,
+            };
+            Ok(Some((stream::iter(items(page).into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+    }
+
+    /// Pings the server and returns the round-trip time.
+    pub async fn ping(&self) -> Result<Duration, ProtocolError> {
+        let started = Instant::now();
+        let _: Value = self.request(ClientRequest::Ping).await?;
+        Ok(started.elapsed())
+    }
+
+    /// Sends a request that can be abandoned through `cancel`.
+    ///
+    /// When `cancel` is tripped before the result arrives, the server is sent a
+    /// `notifications/cancelled` for the request and [`ProtocolError::Cancelled`] is returned.
+    /// Timeouts and retries apply as they do to [`Client::request`].
+    pub async fn request_with_cancellation<R: DeserializeOwned>(
+        &self,
+        request: ClientRequest,
+        cancel: &CancellationToken,
+    ) -> Result<R, ProtocolError> {
+        self.send_tracked(request, None, Some(cancel), None).await
+    }
+
+    /// Sends a request and returns the future resolving with its result, together with a
+    /// handle that cancels it.
+    ///
+    /// The request is sent when the future is first polled. Cancelling through the handle
+    /// before the result arrives resolves the future with [`ProtocolError::Cancelled`], and
+    /// the server is sent a `notifications/cancelled` for the request.
+    pub fn request_cancellable<R: DeserializeOwned>(
+        &self,
+        request: ClientRequest,
+    ) -> (
+        impl Future<Output = Result<R, ProtocolError>> + '_,
+        CancelHandle,
+    ) {
+        let handle = CancelHandle::new();
+        let token = handle.token().clone();
+        let response = async move { self.request_with_cancellation(request, &token).await };
+        (response, handle)
+    }
+
+    /// Calls the tool named `name` with `arguments` like [`Client::call_tool`], returning the
+    /// call together with a handle that cancels it, see [`Client::request_cancellable`].
+    pub fn call_tool_cancellable(
+        &self,
+        name: impl Into<String>,
+        arguments: impl Serialize,
+    ) -> (
+        impl Future<Output = Result<CallToolResult, ProtocolError>> + '_,
+        CancelHandle,
+    ) {
+        let params = call_tool_params(name.into(), arguments);
+        let handle = CancelHandle::new();
+        let token = handle.token().clone();
+        let response = async move {
+            require_capability!(self, tools);
+            let request = ClientRequest::CallTool(params?);
+            self.request_with_cancellation(request, &token).await
+        };
+        (response, handle)
+    }
+
+    /// Tells the server to stop processing the request with the given id.
+    pub async fn cancel(&self, id: RequestId, reason: Option<String>) -> Result<(), ProtocolError> {
+        let notification = CancelledNotificationParams::new(id, reason).into_notification()?;
+        self.transport
+            .send(JsonRpcMessage::Notification(notification))
+            .await
+    }
+
+    /// Sends a request that asks for progress updates and waits for its result.
+    ///
+    /// `on_progress` is called for every progress notification the server sends for this
+    /// request before the result arrives.
+    pub async fn request_with_progress<R: DeserializeOwned>(
+        &self,
+        request: ClientRequest,
+        mut on_progress: impl FnMut(ProgressNotificationParams) + Send,
+    ) -> Result<R, ProtocolError> {
+        let mut on_notification = |notification: JsonRpcNotification| {
+            if notification.method != PROGRESS_NOTIFICATION {
+                return;
+            }
+            if let Some(params) = notification
+                .params
+                .and_then(|params| serde_json::from_value(params).ok())
+            {
+                on_progress(params);
+            }
+        };
+        self.send_tracked(request, None, None, Some(&mut on_notification))
+            .await
+    }
+
+    /// Calls a tool, opting into its partial results, see [`crate::partial`].
+    ///
+    /// `on_partial` is called with the content of every chunk the tool emits before its
+    /// result arrives. Servers that do not offer the extension only send the result.
+    pub async fn call_tool_with_partial_results(
+        &self,
+        mut params: CallToolRequestParams,
+        mut on_partial: impl FnMut(Vec<PromptMessageContent>) + Send,
+    ) -> Result<CallToolResult, ProtocolError> {
+        partial::request_in(&mut params.meta);
+        let mut on_notification = |notification: JsonRpcNotification| {
+            if notification.method != PARTIAL_RESULT_NOTIFICATION {
+                return;
+            }
+            if let Some(params) = notification.params.and_then(|params| {
+                serde_json::from_value::<PartialResultNotificationParams>(params).ok()
+            }) {
+                on_partial(params.content);
+            }
+        };
+        self.send_tracked(
+            ClientRequest::CallTool(params),
+            None,
+            None,
+            Some(&mut on_notification),
+        )
+        .await
+    }
+
+    /// Lists every tool the server offers, following pages until the last one.
+    pub async fn list_tools(&self) -> Result<Vec<Tool>, ProtocolError> {
+        self.list_tools_stream().try_collect().await
+    }
+
+    /// Streams the tools the server offers, requesting the next page once the tools of the
+    /// previous one were consumed.
+    pub fn list_tools_stream(&self) -> impl Stream<Item = Result<Tool, ProtocolError>> + '_ {
+        self.list_stream(
+            "tools",
+            ClientRequest::ListTools,
+            |page: ListToolsResult| page.tools,
+        )
+    }
+
+    /// Calls the tool named `name` with `arguments`.
+    ///
+    /// `arguments` must serialize to a JSON object, or to `null` for a call without arguments.
+    /// A tool that fails answers with a result that has `is_error` set, not with an error.
+    pub async fn call_tool(
+        &self,
+        name: impl Into<String>,
+        arguments: impl Serialize,
+    ) -> Result<CallToolResult, ProtocolError> {
+        require_capability!(self, tools);
+        let params = call_tool_params(name.into(), arguments)?;
+        self.request(ClientRequest::CallTool(params)).await
+    }
+
+    /// Calls the tool named `name` with `arguments` like [`Client::call_tool`], and
+    /// deserializes its result into `Out`.
+    ///
+    /// The structured content of the result is deserialized, or, for tools that declare no
+    /// output schema, the JSON of its only text content. Results of tools that failed, and
+    /// ones that do not match `Out`, are errors that name the tool and the expected type.
+    pub async fn call_tool_typed<Args: Serialize, Out: DeserializeOwned>(
+        &self,
+        name: impl Into<String>,
+        arguments: Args,
+    ) -> Result<Out, ProtocolError> {
+        let name = name.into();
+        let result = self.call_tool(name.clone(), arguments).await?;
+        typed_result(&name, result)
+    }
+
+    /// Calls the tool named `name` with `arguments` like [`Client::call_tool`], asking for
+    /// progress updates.
+    ///
+    /// `on_progress` is called for every progress notification the server sends for the call
+    /// before its result arrives. The progress token of the call is only valid until then;
+    /// notifications the server sends for it later go to the handler registered with
+    /// [`Client::on_progress`], if any.
+    pub async fn call_tool_with_progress(
+        &self,
+        name: impl Into<String>,
+        arguments: impl Serialize,
+        on_progress: impl FnMut(ProgressNotificationParams) + Send,
+    ) -> Result<CallToolResult, ProtocolError> {
+        require_capability!(self, tools);
+        let params = call_tool_params(name.into(), arguments)?;
+        self.request_with_progress(ClientRequest::CallTool(params), on_progress)
+            .await
+    }
+
+    /// Lists every resource the server offers, following pages until the last one.
+    pub async fn list_resources(&self) -> Result<Vec<Resource>, ProtocolError> {
+        self.list_resources_stream().try_collect().await
+    }
+
+    /// Streams the resources the server offers, page by page like
+    /// [`Client::list_tools_stream`].
+    pub fn list_resources_stream(
+        &self,
+    ) -> impl Stream<Item = Result<Resource, ProtocolError>> + '_ {
+        self.list_stream(
+            "resources",
+            ClientRequest::ListResources,
+            |page: ListResourcesResult| page.resources,
+        )
+    }
+
+    /// Lists every resource template the server offers, following pages until the last one.
+    pub async fn list_resource_templates(&self) -> Result<Vec<ResourceTemplate>, ProtocolError> {
+        self.list_resource_templates_stream().try_collect().await
+    }
+
+    /// Streams the resource templates the server offers, page by page like
+    /// [`Client::list_tools_stream`].
+    pub fn list_resource_templates_stream(
+        &self,
+    ) -> impl Stream<Item = Result<ResourceTemplate, ProtocolError>> + '_ {
+        self.list_stream(
+            "resources",
+            ClientRequest::ListResourceTemplates,
+            |page: ListResourceTemplatesResult| page.resource_templates,
+        )
+    }
+
+    /// Lists every prompt the server offers, following pages until the last one.
+    pub async fn list_prompts(&self) -> Result<Vec<Prompt>, ProtocolError> {
+        self.list_prompts_stream().try_collect().await
+    }
+
+    /// Streams the prompts the server offers, page by page like
+    /// [`Client::list_tools_stream`].
+    pub fn list_prompts_stream(&self) -> impl Stream<Item = Result<Prompt, ProtocolError>> + '_ {
+        self.list_stream(
+            "prompts",
+            ClientRequest::ListPrompts,
+            |page: ListPromptsResult| page.prompts,
+        )
+    }
+
+    /// Reads the resource at `uri`.
+    pub async fn read_resource(
+        &self,
+        uri: impl Into<String>,
+    ) -> Result<ReadResourceResult, ProtocolError> {
+        require_capability!(self, resources);
+        self.request(ClientRequest::ReadResource(ReadResourceRequestParams {
+            uri: uri.into(),
+            meta: None,
+        }))
+        .await
+    }
+
+    /// Subscribes to updates of the resource at `uri`, calling `on_update` for each
+    /// `notifications/resources/updated` the server sends for it.
+    ///
+    /// Subscribing again to the same URI replaces the callback. Subscriptions are renewed by
+    /// [`Client::reconnect`].
+    pub async fn subscribe(
+        &self,
+        uri: impl Into<String>,
+        on_update: impl Fn(ResourceUpdatedNotificationParams) + Send + Sync + 'static,
+    ) -> Result<(), ProtocolError> {
+        require_capability!(self, resources.subscribe);
+        let uri = uri.into();
+        let params = SubscribeRequestParams {
+            uri: uri.clone(),
+            meta: None,
+        };
+        let _: Value = self.request(ClientRequest::Subscribe(params)).await?;
+        self.handlers()
+            .lock_subscriptions()
+            .insert(uri, Arc::new(on_update));
+        Ok(())
+    }
+
+    /// Unsubscribes from updates of the resource at `uri`.
+    pub async fn unsubscribe(&self, uri: &str) -> Result<(), ProtocolError> {
+        require_capability!(self, resources.subscribe);
+        self.handlers().lock_subscriptions().remove(uri);
+        let params = UnsubscribeRequestParams {
+            uri: uri.to_string(),
+            meta: None,
+        };
+        let _: Value = self.request(ClientRequest::Unsubscribe(params)).await?;
+        Ok(())
+    }
+
+    /// Returns the URIs of the resources the client is subscribed to.
+    pub fn subscriptions(&self) -> Vec<String> {
+        self.handlers().subscribed()
+    }
+
+    /// Renders the prompt named `name` with `arguments`.
+    pub async fn get_prompt<K: Into<String>, V: Into<String>>(
+        &self,
+        name: impl Into<String>,
+        arguments: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<GetPromptResult, ProtocolError> {
+        require_capability!(self, prompts);
+        let arguments: HashMap<String, String> = arguments
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        self.request(ClientRequest::GetPrompt(GetPromptRequestParams {
+            name: name.into(),
+            arguments: (!arguments.is_empty()).then_some(arguments),
+            meta: None,
+        }))
+        .await
+    }
+
+    /// Asks for completions of the argument `argument` of the prompt or resource template
+    /// `reference`, given what was typed so far as `value`.
+    pub async fn complete(
+        &self,
+        reference: CompletionReference,
+        argument: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<Completion, ProtocolError> {
+        require_capability!(self, completions);
+        let result: CompleteResult = self
+            .request(ClientRequest::Complete(CompleteRequestParams {
+                reference,
+                argument: CompletionArgument {
+                    name: argument.into(),
+                    value: value.into(),
+                },
+                context: None,
+                meta: None,
+            }))
+            .await?;
+        Ok(result.completion)
+    }
+
+    /// Asks the server to send log messages of `level` and above.
+    pub async fn set_log_level(&self, level: LoggingLevel) -> Result<(), ProtocolError> {
+        require_capability!(self, logging);
+        let _: Value = self
+            .request(ClientRequest::SetLevel(SetLevelRequestParams {
+                level,
+                meta: None,
+            }))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the capabilities the client declared.
+    pub fn capabilities(&self) -> &ClientCapabilities {
+        &self.capabilities
+    }
+
+    /// Returns the roots listed to the server.
+    pub fn roots(&self) -> Vec<Root> {
+        self.handlers().roots()
+    }
+
+    /// Replaces the roots listed to the server.
+    ///
+    /// Like the other root mutations, this fails unless the roots capability was declared on
+    /// [`Client::connect`], e.g. with [`ClientCapabilities::with_roots`], and sends
+    /// `notifications/roots/list_changed` if `listChanged` was declared with it.
+    pub async fn set_roots(&self, roots: Vec<Root>) -> Result<(), ProtocolError> {
+        self.update_roots(|current| *current = roots).await
+    }
+
+    /// Adds `root`, replacing the root with the same URI if there is one.
+    pub async fn add_root(&self, root: Root) -> Result<(), ProtocolError> {
+        self.update_roots(|roots| match roots.iter_mut().find(|r| r.uri == root.uri) {
+            Some(existing) => *existing = root,
+            None => roots.push(root),
+        })
+        .await
+    }
+
+    /// Removes the root with the URI `uri`, returning `true` if there was one.
+    pub async fn remove_root(&self, uri: &str) -> Result<bool, ProtocolError> {
+        let mut removed = false;
+        self.update_roots(|roots| {
+            let count = roots.len();
+            roots.retain(|root| root.uri != uri);
+            removed = roots.len() < count;
+        })
+        .await?;
+        Ok(removed)
+    }
+
+    async fn update_roots(&self, update: impl FnOnce(&mut Vec<Root>)) -> Result<(), ProtocolError> {
+        require_capability!(self.capabilities, roots);
+        if let Some(roots) = &self.handlers().roots {
+            update(&mut roots.lock().unwrap_or_else(|e| e.into_inner()));
+        }
+        if !self.capabilities.has_capability(&["roots", "list_changed"]) {
+            return Ok(());
+        }
+        let notification = ClientNotification::RootsListChanged.into_notification()?;
+        self.transport
+            .send(JsonRpcMessage::Notification(notification))
+            .await
+    }
+
+    /// Closes the connection to the server.
+    pub async fn close(&self) -> Result<(), ProtocolError> {
+        self.transport.close().await
+    }
+
+    /// Sends `request` with a deadline, cancelling it when `cancel` is tripped, and retries
+    /// it as the retry policy allows.
+    async fn send_tracked<R: DeserializeOwned>(
+        &self,
+        request: ClientRequest,
+        timeout: Option<Duration>,
+        cancel: Option<&CancellationToken>,
+        mut on_notification: Option<&mut (dyn FnMut(JsonRpcNotification) + Send)>,
+    ) -> Result<R, ProtocolError> {
+        self.preflight(&request)?;
+        let request = self.approve(request).await?;
+        let timeout = timeout.or_else(|| self.method_timeouts.get(request.method()).copied());
+        let mut attempt = 1;
+        loop {
+            let id = self.ids.next_id();
+            let response = self.exchange(
+                request.clone().into_request(id.clone())?,
+                on_notification
+                    .as_mut()
+                    .map(|f| &mut **f as &mut (dyn FnMut(JsonRpcNotification) + Send)),
+            );
+            let response = self
+                .tracker
+                .track(&*self.transport, id.clone(), timeout, response);
+            let result = match cancel {
+                Some(cancel) => tokio::select! {
+                    result = response => result,
+                    _ = cancel.cancelled() => {
+                        self.cancel(id.clone(), Some("Cancelled by client".to_string())).await?;
+                        return Err(ProtocolError::Cancelled(format!(
+                            "Request {} was cancelled",
+                            id
+                        )));
+                    }
+                },
+                None => response.await,
+            };
+            let error = match result {
+                Err(error) => error,
+                result => return result,
+            };
+            match &self.retry {
+                Some(policy) if policy.should_retry(request.method(), attempt, &error) => {
+                    tokio::time::sleep(policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                _ => return Err(error),
+            }
+        }
+    }
+
+    /// Sends `request` and waits for the reader to hand over its response.
+    ///
+    /// When `on_notification` is given, the request carries a progress token derived from its
+    /// id, and the notifications carrying the same token, such as progress, are passed to it.
+    /// When a trace context is current, the request carries a child of it.
+    async fn exchange<R: DeserializeOwned>(
+        &self,
+        mut request: JsonRpcRequest,
+        mut on_notification: Option<&mut (dyn FnMut(JsonRpcNotification) + Send)>,
+    ) -> Result<R, ProtocolError> {
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let _listening = on_notification.is_some().then(|| {
+            let token = ProgressToken::String(request.id.to_string());
+            token.clone().attach_to(&mut request);
+            Listening::new(&self.listeners, token, sender)
+        });
+        if let Some(context) = TraceContext::current() {
+            context.child().attach_to(&mut request);
+        }
+        let mut response = self.pending.register(request.id.clone());
+        if self.reader.has_ended() {
+            return Err(ProtocolError::TransportError(
+                CLOSED_BEFORE_RESPONSE.to_string(),
+            ));
+        }
+        let scope = RequestScope::new(&request);
+        let result = scope
+            .run(async {
+                self.transport
+                    .send(JsonRpcMessage::Request(request))
+                    .await?;
+                loop {
+                    tokio::select! {
+                        biased;
+                        Some(notification) = notifications.recv() => {
+                            if let Some(on_notification) = on_notification.as_mut() {
+                                on_notification(notification);
+                            }
+                        }
+                        result = &mut response => break Ok(result),
+                    }
+                }
+            })
+            .await?;
+        // Notifications the reader passed on just before the response are still queued.
+        while let Ok(notification) = notifications.try_recv() {
+            if let Some(on_notification) = on_notification.as_mut() {
+                on_notification(notification);
+            }
+        }
+        let result = result.map_err(|error| match error.code() {
+            CONNECTION_CLOSED => ProtocolError::TransportError(error.message().to_string()),
+            _ => ProtocolError::ErrorResponse(error.into()),
+        })?;
+        serde_json::from_value(result).map_err(|e| ProtocolError::ParseError(e.to_string()))
+    }
+
+    fn handlers(&self) -> RwLockReadGuard<'_, Handlers> {
+        self.handlers.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn handlers_mut(&self) -> RwLockWriteGuard<'_, Handlers> {
+        self.handlers.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Fails with [`ProtocolError::CapabilityNotSupported`] if the server did not advertise
+    /// the capability `request` requires, instead of sending a request the server would
+    /// answer with method not found.
+    fn preflight(&self, request: &ClientRequest) -> Result<(), ProtocolError> {
+        let method = request.method();
+        match required_server_capability(method) {
+            Some(path) if !self.has_capability(path) => {
+                Err(ProtocolError::CapabilityNotSupported {
+                    capability: path.join("."),
+                    method: Some(method.to_string()),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Asks the approver, if any, whether a tool call may be sent.
+    async fn approve(&self, request: ClientRequest) -> Result<ClientRequest, ProtocolError> {
+        let approver = self.handlers().approver.clone();
+        let (ClientRequest::CallTool(params), Some(approver)) = (&request, approver) else {
+            return Ok(request);
+        };
+        let params = approver
+            .approve_tool_call(params)
+            .await
+            .apply(params.clone())
+            .map_err(ProtocolError::Denied)?;
+        Ok(ClientRequest::CallTool(params))
+    }
+}
+
+/// Builds the parameters of a call of the tool `name`, whose `arguments` must serialize to a
+/// JSON object or to `null`.
+fn call_tool_params(
+    name: String,
+    arguments: impl Serialize,
+) -> Result<CallToolRequestParams, ProtocolError> {
+    let arguments = match serde_json::to_value(arguments) {
+        Ok(Value::Object(arguments)) => Some(arguments),
+        Ok(Value::Null) => None,
+        Ok(other) => {
+            return Err(ProtocolError::InvalidParams(format!(
+                "Tool arguments must be an object, not {}",
+                other
+            )));
+        }
+        Err(e) => return Err(ProtocolError::InvalidParams(e.to_string())),
+    };
+    Ok(CallToolRequestParams {
+        name,
+        arguments,
+        meta: None,
+    })
+}
+
+/// Deserializes the result of the tool `name` into `Out`, see [`Client::call_tool_typed`].
+fn typed_result<Out: DeserializeOwned>(
+    name: &str,
+    result: CallToolResult,
+) -> Result<Out, ProtocolError> {
+    let texts: Vec<&str> = result
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            PromptMessageContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect();
+    if result.is_error() {
+        return Err(ProtocolError::InternalError(format!(
+            "Tool {} failed: {}",
+            name,
+            texts.join("\n")
+        )));
+    }
+    let value = match (result.structured_content, &texts[..]) {
+        (Some(value), _) => value,
+        (None, [text]) if result.content.len() == 1 => serde_json::from_str(text).map_err(|e| {
+            ProtocolError::ParseError(format!(
+                "Tool {} returned no structured content, and its text is not JSON: {}",
+                name, e
+            ))
+        })?,
+        (None, _) => {
+            return Err(ProtocolError::ParseError(format!(
+                "Tool {} returned no structured content, and {} content items instead of a \
+                 single text",
+                name,
+                result.content.len()
+            )));
+        }
+    };
+    serde_json::from_value(value.clone()).map_err(|e| {
+        ProtocolError::ParseError(format!(
+            "Result of tool {} is not a {}: {} in {}",
+            name,
+            std::any::type_name::<Out>(),
+            e,
+            value
+        ))
+    })
+}
+
+/// Performs the initialize handshake over `transport` and returns what was negotiated.
+async fn initialize<T: Transport>(
+    transport: &T,
+    ids: &RequestIdGenerator,
+    client_info: &Implementation,
+    capabilities: &ClientCapabilities,
+) -> Result<SessionInfo, ProtocolError> {
+    let params = InitializeRequestParams {
+        protocol_version: ProtocolVersion::LATEST.to_string(),
+        capabilities: capabilities.clone(),
+        client_info: client_info.clone(),
+        meta: None,
+    };
+
+    let handshake_started_at = Utc::now();
+    let started = Instant::now();
+    let handshake = client_handshake(
+        transport,
+        InitializeRequest {
+            id: ids.next_id(),
+            params,
+        },
+    )
+    .await?;
+    let handshake_duration = started.elapsed();
+
+    Ok(SessionInfo {
+        protocol_version: handshake.protocol_version,
+        session_id: transport.session_id(),
+        server_info: handshake.peer_info,
+        instructions: handshake.instructions,
+        capabilities: handshake.peer_capabilities,
+        raw_capabilities: handshake.raw_capabilities,
+        handshake_started_at,
+        handshake_duration,
+    })
+}
+
+/// Why requests fail that were waiting for a response when the connection closed.
+const CLOSED_BEFORE_RESPONSE: &str = "Connection closed before a response was received";
+
+/// The task reading the messages of the server, see [`read`]. Dropping it stops reading.
+struct Reader {
+    task: JoinHandle<()>,
+    /// How reading ended, once it did
+    ended: watch::Receiver<Option<Result<(), ProtocolError>>>,
+}
+
+impl Reader {
+    fn spawn<T: Transport + 'static>(
+        transport: Arc<T>,
+        handlers: Arc<RwLock<Handlers>>,
+        pending: PendingRequests,
+        listeners: Listeners,
+    ) -> Self {
+        let (ended, receiver) = watch::channel(None);
+        let task = tokio::spawn(async move {
+            let result = read(transport, handlers, pending.clone(), listeners).await;
+            let reason = match &result {
+                Ok(()) => CLOSED_BEFORE_RESPONSE.to_string(),
+                Err(error) => error.to_string(),
+            };
+            // Requests registered from now on see that reading ended, the others are failed.
+            ended.send_replace(Some(result));
+            pending.close(&reason);
+        });
+        Reader {
+            task,
+            ended: receiver,
+        }
+    }
+
+    fn has_ended(&self) -> bool {
+        self.ended.borrow().is_some()
+    }
+
+    /// Waits until reading ended, returning the error it ended with, if any.
+    async fn ended(&self) -> Result<(), ProtocolError> {
+        let mut ended = self.ended.clone();
+        match ended.wait_for(Option::is_some).await {
+            Ok(result) => result.clone().unwrap_or(Ok(())),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Reads the messages of the server until the connection closes. Responses go to the
+/// requests waiting for them, notifications about a request in progress to its listener and
+/// other notifications to the handlers, and requests of the server are answered. Messages the
+/// transport fails to parse are skipped.
+async fn read<T: Transport + 'static>(
+    transport: Arc<T>,
+    handlers: Arc<RwLock<Handlers>>,
+    pending: PendingRequests,
+    listeners: Listeners,
+) -> Result<(), ProtocolError> {
+    let handlers = || handlers.read().unwrap_or_else(|e| e.into_inner()).clone();
+    loop {
+        let message = match transport.receive().await {
+            Ok(message) => message,
+            Err(error @ ProtocolError::TransportError(_)) => return Err(error),
+            // One message that cannot be read does not end the connection.
+            Err(_) => continue,
+        };
+        match message {
+            Some(JsonRpcMessage::Notification(notification)) => {
+                let listener = notification
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.get("progressToken"))
+                    .and_then(|token| serde_json::from_value::<ProgressToken>(token.clone()).ok())
+                    .and_then(|token| {
+                        listeners
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .get(&token)
+                            .cloned()
+                    });
+                let unclaimed = match listener {
+                    Some(listener) => listener.send(notification).err().map(|error| error.0),
+                    None => Some(notification),
+                };
+                if let Some(notification) = unclaimed {
+                    handlers().route(notification);
+                }
+            }
+            // The server may check on us, or ask us to sample, while it works on a request.
+            Some(JsonRpcMessage::Request(request)) => {
+                let handlers = handlers();
+                let transport = transport.clone();
+                tokio::spawn(async move {
+                    let answer = handlers.answer(request).await;
+                    let _ = transport.send(answer).await;
+                });
+            }
+            Some(message) => {
+                pending.resolve(message);
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Passes the notifications about a request in progress to it, until dropped.
+struct Listening<'a> {
+    listeners: &'a Listeners,
+    token: ProgressToken,
+}
+
+impl<'a> Listening<'a> {
+    fn new(
+        listeners: &'a Listeners,
+        token: ProgressToken,
+        sender: mpsc::UnboundedSender<JsonRpcNotification>,
+    ) -> Self {
+        listeners
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(token.clone(), sender);
+        Listening { listeners, token }
+    }
+}
+
+impl Drop for Listening<'_> {
+    fn drop(&mut self) {
+        self.listeners
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{
+        JSONRPC_VERSION, JsonRpcRequest, JsonRpcResponse, PROTOCOL_VERSION, RootsCapability,
+    };
+    use crate::transport::MemoryTransport;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_session_info_after_connect() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            assert_eq!(request.method, "initialize");
+            assert_eq!(
+                request.params.as_ref().unwrap()["capabilities"],
+                json!({ "roots": { "listChanged": true } })
+            );
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": { "tools": { "listChanged": true }, "x-custom": {} },
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" },
+                        "instructions": "Be nice"
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+
+            let Some(JsonRpcMessage::Notification(notification)) = server.receive().await.unwrap()
+            else {
+                panic!("Expected initialized notification");
+            };
+            assert_eq!(notification.method, "notifications/initialized");
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities {
+                roots: Some(RootsCapability {
+                    list_changed: Some(true),
+                }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        server_task.await.unwrap();
+        assert_eq!(client.protocol_version(), ProtocolVersion::V2025_03_26);
+        assert_eq!(client.server_info().name, "test-server");
+        assert_eq!(client.instructions(), Some("Be nice"));
+        assert!(client.server_capabilities().tools.is_some());
+
+        let info = client.session_info();
+        assert_eq!(info.protocol_version, ProtocolVersion::V2025_03_26);
+        assert_eq!(info.session_id, None);
+        assert_eq!(info.server_info.name, "test-server");
+        assert_eq!(info.instructions.as_deref(), Some("Be nice"));
+        assert_eq!(
+            info.capabilities.tools.as_ref().unwrap().list_changed,
+            Some(true)
+        );
+        assert!(info.raw_capabilities.get("x-custom").is_some());
+        assert!(info.handshake_started_at <= Utc::now());
+    }
+
+    #[tokio::test]
+    async fn test_request_with_progress() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            // Initialize handshake.
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected tools/call request");
+            };
+            let token = ProgressToken::from_request(&request).expect("Missing progress token");
+            for (progress, token) in [
+                (1.0, token.clone()),
+                (1.0, ProgressToken::Number(-1)),
+                (2.0, token),
+            ] {
+                let notification = ProgressNotificationParams {
+                    progress_token: token,
+                    progress,
+                    total: Some(2.0),
+                    message: None,
+                    meta: None,
+                }
+                .into_notification()
+                .unwrap();
+                server
+                    .send(JsonRpcMessage::Notification(notification))
+                    .await
+                    .unwrap();
+            }
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({ "content": [] })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut updates = Vec::new();
+        let result: Value = client
+            .request_with_progress(
+                ClientRequest::CallTool(crate::tool::CallToolRequestParams {
+                    name: "slow".to_string(),
+                    arguments: None,
+                    meta: None,
+                }),
+                |params| updates.push(params.progress),
+            )
+            .await
+            .unwrap();
+        server_task.await.unwrap();
+
+        assert_eq!(result, json!({ "content": [] }));
+        assert_eq!(updates, vec![1.0, 2.0]);
+    }
+
+    #[tokio::test]
+    async fn test_notifications_are_routed_to_handlers() {
+        use crate::logging::LoggingLevel;
+        use std::sync::{Arc, Mutex};
+
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": {},
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected ping request");
+            };
+            let notifications = [
+                ServerNotification::ToolListChanged,
+                ServerNotification::LoggingMessage(LoggingMessageNotificationParams {
+                    level: LoggingLevel::Warning,
+                    logger: None,
+                    data: json!("Disk almost full"),
+                    meta: None,
+                }),
+                ServerNotification::Progress(ProgressNotificationParams {
+                    progress_token: ProgressToken::Number(7),
+                    progress: 0.5,
+                    total: None,
+                    message: None,
+                    meta: None,
+                }),
+            ];
+            for notification in notifications {
+                let notification = notification.into_notification().unwrap();
+                server
+                    .send(JsonRpcMessage::Notification(notification))
+                    .await
+                    .unwrap();
+            }
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({})),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+
+            // Between requests, notifications reach a listening client.
+            let notification =
+                ServerNotification::ResourceUpdated(ResourceUpdatedNotificationParams {
+                    uri: "file:///notes.txt".to_string(),
+                    meta: None,
+                })
+                .into_notification()
+                .unwrap();
+            server
+                .send(JsonRpcMessage::Notification(notification))
+                .await
+                .unwrap();
+            server.close().await.unwrap();
+        });
+
+        let heard = Arc::new(Mutex::new(Vec::new()));
+        let record = |heard: &Arc<Mutex<Vec<String>>>| {
+            let heard = heard.clone();
+            move |event: String| heard.lock().unwrap().push(event)
+        };
+        let (tools, log, progress, updated) = (
+            record(&heard),
+            record(&heard),
+            record(&heard),
+            record(&heard),
+        );
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap()
+        .on_tools_list_changed(move || tools("tools".to_string()))
+        .on_log_message(move |params| log(format!("log {}", params.data)))
+        .on_progress(move |params| progress(format!("progress {}", params.progress)))
+        .on_resource_updated(move |params| updated(format!("updated {}", params.uri)));
+
+        client.ping().await.unwrap();
+        client.listen().await.unwrap();
+        server_task.await.unwrap();
+
+        assert_eq!(
+            *heard.lock().unwrap(),
+            [
+                "tools",
+                "log \"Disk almost full\"",
+                "progress 0.5",
+                "updated file:///notes.txt"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_roots_are_listed_and_announced() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            assert_eq!(
+                request.params.as_ref().unwrap()["capabilities"],
+                json!({ "roots": { "listChanged": true } })
+            );
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": {},
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            for _ in 0..3 {
+                let Some(JsonRpcMessage::Notification(notification)) =
+                    server.receive().await.unwrap()
+                else {
+                    panic!("Expected a notification");
+                };
+                assert_eq!(notification.method, "notifications/roots/list_changed");
+            }
+            server
+                .send(JsonRpcMessage::Request(JsonRpcRequest {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: RequestId::Number(1),
+                    method: "roots/list".to_string(),
+                    params: None,
+                }))
+                .await
+                .unwrap();
+            let Some(JsonRpcMessage::Response(response)) = server.receive().await.unwrap() else {
+                panic!("Expected the roots");
+            };
+            server.close().await.unwrap();
+            response.result.unwrap()
+        });
+
+        let root = |uri: &str| Root {
+            uri: uri.to_string(),
+            name: None,
+            meta: None,
+        };
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default().with_roots(),
+        )
+        .await
+        .unwrap();
+        client
+            .set_roots(vec![root("file:///src"), root("file:///docs")])
+            .await
+            .unwrap();
+        client.add_root(root("file:///tests")).await.unwrap();
+        assert!(client.remove_root("file:///docs").await.unwrap());
+        client.listen().await.unwrap();
+
+        assert_eq!(
+            server_task.await.unwrap(),
+            json!({ "roots": [{ "uri": "file:///src" }, { "uri": "file:///tests" }] })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_survive_reconnection() {
+        use crate::memory::InMemoryResourceProvider;
+        use crate::server::Server;
+        use std::sync::{Arc, Mutex};
+
+        let store = InMemoryResourceProvider::new();
+        let log = store.insert("build.log", "Compiling...").await;
+        let server =
+            Server::new(Implementation::new("test-server", "1.0.0")).with_resources(store.clone());
+        let (client_transport, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.clone().serve(server_side));
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let updated = updates.clone();
+        client
+            .subscribe(&log, move |params| updated.lock().unwrap().push(params.uri))
+            .await
+            .unwrap();
+        assert_eq!(client.subscriptions(), vec![log.clone()]);
+        store.update(&log, "Compiling... done").await.unwrap();
+        client.ping().await.unwrap();
+        assert_eq!(*updates.lock().unwrap(), vec![log.clone()]);
+
+        // The new session hears of updates without subscribing again by hand.
+        let (client_transport, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let client = client.reconnect(client_transport).await.unwrap();
+        store.update(&log, "Testing...").await.unwrap();
+        client.ping().await.unwrap();
+        assert_eq!(updates.lock().unwrap().len(), 2);
+
+        client.unsubscribe(&log).await.unwrap();
+        assert!(client.subscriptions().is_empty());
+        store.update(&log, "Testing... done").await.unwrap();
+        client.ping().await.unwrap();
+        assert_eq!(updates.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_reads_are_retried() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            // The first attempt goes unanswered and is cancelled by the client.
+            let Some(JsonRpcMessage::Request(first)) = server.receive().await.unwrap() else {
+                panic!("Expected tools/list request");
+            };
+            let Some(JsonRpcMessage::Notification(cancelled)) = server.receive().await.unwrap()
+            else {
+                panic!("Expected a cancelled notification");
+            };
+            assert_eq!(cancelled.method, "notifications/cancelled");
+            let Some(JsonRpcMessage::Request(second)) = server.receive().await.unwrap() else {
+                panic!("Expected tools/list request");
+            };
+            assert_eq!(second.method, "tools/list");
+            assert_ne!(second.id, first.id);
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: second.id,
+                    result: Some(json!({ "tools": [{ "name": "search", "inputSchema": {} }] })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap()
+        .with_method_timeout("tools/list", Duration::from_millis(50))
+        .with_retry_policy(
+            RetryPolicy::builder()
+                .initial_backoff(Duration::from_millis(10))
+                .build(),
+        );
+
+        let tools = client.list_tools().await.unwrap();
+        server_task.await.unwrap();
+        assert_eq!(tools[0].name, "search");
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_with_progress() {
+        use crate::server::{RequestContext, Server};
+        use crate::tool::Tool;
+        use serde_json::Map;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        server.add_tool(
+            Tool::builder().name("index").build(),
+            |_: Option<Map<String, Value>>, context: RequestContext| async move {
+                for step in [1.0, 2.0] {
+                    context.progress().report(step, Some(2.0), None).await?;
+                }
+                Ok(CallToolResult::text("indexed"))
+            },
+        );
+        let (client_transport, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let unclaimed = Arc::new(AtomicUsize::new(0));
+        let counted = unclaimed.clone();
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap()
+        .on_progress(move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut steps = Vec::new();
+        let result = client
+            .call_tool_with_progress("index", (), |params| steps.push(params.progress))
+            .await
+            .unwrap();
+        client.ping().await.unwrap();
+
+        assert_eq!(result, CallToolResult::text("indexed"));
+        assert_eq!(steps, [1.0, 2.0]);
+        assert_eq!(unclaimed.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_request_with_cancellation() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            // Never answer the call; expect a cancellation for it instead.
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected tools/call request");
+            };
+            let Some(JsonRpcMessage::Notification(notification)) = server.receive().await.unwrap()
+            else {
+                panic!("Expected cancelled notification");
+            };
+            assert_eq!(notification.method, "notifications/cancelled");
+            assert_eq!(notification.params.unwrap()["requestId"], json!(request.id));
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            trigger.cancel();
+        });
+        let result: Result<Value, _> = client
+            .request_with_cancellation(
+                ClientRequest::CallTool(crate::tool::CallToolRequestParams {
+                    name: "slow".to_string(),
+                    arguments: None,
+                    meta: None,
+                }),
+                &cancel,
+            )
+            .await;
+        server_task.await.unwrap();
+
+        assert!(matches!(result, Err(ProtocolError::Cancelled(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_handle() {
+        use crate::server::{RequestContext, Server};
+        use crate::tool::Tool;
+        use serde_json::Map;
+
+        let (cancelled, mut heard) = tokio::sync::mpsc::unbounded_channel();
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        server.add_tool(
+            Tool::builder().name("slow").build(),
+            move |_: Option<Map<String, Value>>, context: RequestContext| {
+                let cancelled = cancelled.clone();
+                async move {
+                    context.cancellation().cancelled().await;
+                    let _ = cancelled.send(context.id().clone());
+                    Ok(CallToolResult::text("stopped"))
+                }
+            },
+        );
+        let (client_transport, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let (call, handle) = client.call_tool_cancellable("slow", ());
+        let canceller = handle.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+        let result = call.await;
+        assert!(matches!(result, Err(ProtocolError::Cancelled(_))));
+        assert!(handle.is_cancelled());
+        assert!(heard.recv().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_protocol_version_is_rejected() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            assert_eq!(request.params.unwrap()["protocolVersion"], PROTOCOL_VERSION);
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2023-01-01",
+                        "capabilities": {},
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+        });
+
+        let result = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(ProtocolError::ProtocolError(_))));
+    }
+
+    /// Fails to parse every `garbled` notification it receives.
+    struct Garbling(MemoryTransport);
+
+    #[async_trait::async_trait]
+    impl Transport for Garbling {
+        async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+            self.0.send(message).await
+        }
+
+        async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+            match self.0.receive().await? {
+                Some(JsonRpcMessage::Notification(notification))
+                    if notification.method == "garbled" =>
+                {
+                    Err(ProtocolError::ParseError("expected value".to_string()))
+                }
+                message => Ok(message),
+            }
+        }
+
+        async fn close(&self) -> Result<(), ProtocolError> {
+            self.0.close().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_messages_are_skipped() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": {},
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            let Some(JsonRpcMessage::Request(ping)) = server.receive().await.unwrap() else {
+                panic!("Expected ping request");
+            };
+            let garbled = JsonRpcNotification {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                method: "garbled".to_string(),
+                params: None,
+            };
+            server
+                .send(JsonRpcMessage::Notification(garbled))
+                .await
+                .unwrap();
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: ping.id,
+                    result: Some(json!({})),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+        });
+
+        let client = Client::connect(
+            Garbling(client_transport),
+            Implementation::new("test-client", "1.0.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        client.ping().await.unwrap();
+        server_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_correlated() {
+        use crate::protocol::{ErrorData, INVALID_PARAMS, JsonRpcError};
+
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": { "tools": { "listChanged": true } },
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            let mut calls = Vec::new();
+            for _ in 0..2 {
+                let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                    panic!("Expected tools/call request");
+                };
+                calls.push(request);
+            }
+            // Answer the second call first, and the first with an error.
+            for call in calls.into_iter().rev() {
+                let message = match call.params.as_ref().unwrap()["name"].as_str() {
+                    Some("second") => JsonRpcMessage::Response(JsonRpcResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        id: call.id,
+                        result: Some(json!({ "content": [{ "type": "text", "text": "two" }] })),
+                        error: None,
+                    }),
+                    _ => JsonRpcMessage::Error(JsonRpcError {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        id: Some(call.id),
+                        error: ErrorData {
+                            code: INVALID_PARAMS,
+                            message: "Missing path".to_string(),
+                            data: Some(json!({ "field": "path" })),
+                        },
+                    }),
+                };
+                server.send(message).await.unwrap();
+            }
+
+            // No request is waiting anymore, yet the notification reaches its handler.
+            let notification = ServerNotification::ToolListChanged
+                .into_notification()
+                .unwrap();
+            server
+                .send(JsonRpcMessage::Notification(notification))
+                .await
+                .unwrap();
+        });
+
+        let announced = Arc::new(tokio::sync::Notify::new());
+        let notify = announced.clone();
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap()
+        .on_tools_list_changed(move || notify.notify_one());
+
+        let (first, second) = tokio::join!(
+            client.call_tool("first", ()),
+            client.call_tool("second", ())
+        );
+        server_task.await.unwrap();
+
+        assert_eq!(second.unwrap(), CallToolResult::text("two"));
+        let Err(ProtocolError::ErrorResponse(error)) = first else {
+            panic!("Expected an error response, got {:?}", first);
+        };
+        assert_eq!(error.code, INVALID_PARAMS);
+        assert_eq!(error.data, Some(json!({ "field": "path" })));
+        tokio::time::timeout(Duration::from_secs(1), announced.notified())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancellable_requests_have_method_timeouts() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            // Never answer; keep the connection open.
+            while server.receive().await.unwrap().is_some() {}
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap()
+        .with_method_timeout("tools/call", Duration::from_millis(20));
+
+        let (call, _handle) = client.call_tool_cancellable("slow", ());
+        assert!(matches!(call.await, Err(ProtocolError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_ping_both_directions() {
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": {},
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            // Answer the client's ping, but ping the client before doing so.
+            let Some(JsonRpcMessage::Request(ping)) = server.receive().await.unwrap() else {
+                panic!("Expected ping request");
+            };
+            assert_eq!(ping.method, "ping");
+            server
+                .send(JsonRpcMessage::Request(JsonRpcRequest {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: RequestId::from("server-ping"),
+                    method: "ping".to_string(),
+                    params: None,
+                }))
+                .await
+                .unwrap();
+            let Some(JsonRpcMessage::Response(pong)) = server.receive().await.unwrap() else {
+                panic!("Expected the client to answer the ping");
+            };
+            assert_eq!(pong.id, RequestId::from("server-ping"));
+            assert_eq!(pong.result, Some(json!({})));
+
+            server.send(ping_response(&ping).unwrap()).await.unwrap();
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        client.ping().await.unwrap();
+        server_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_requests_continue_the_current_trace() {
+        let (client_transport, server) = MemoryTransport::pair();
+        let parent = TraceContext::new_root();
+        let trace_id = parent.trace_id_hex();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": {},
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            let mut contexts = Vec::new();
+            for _ in 0..2 {
+                let Some(JsonRpcMessage::Request(ping)) = server.receive().await.unwrap() else {
+                    panic!("Expected ping request");
+                };
+                contexts.push(TraceContext::extract(&ping));
+                server.send(ping_response(&ping).unwrap()).await.unwrap();
+            }
+            contexts
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        parent.clone().scope(client.ping()).await.unwrap();
+        client.ping().await.unwrap();
+
+        let contexts = server_task.await.unwrap();
+        let child = contexts[0].as_ref().unwrap();
+        assert_eq!(child.trace_id_hex(), trace_id);
+        assert_ne!(child.span_id, parent.span_id);
+        assert_eq!(contexts[1], None);
+    }
+
+    #[tokio::test]
+    async fn test_request_all_pages() {
+        use crate::pagination::paginate;
+        use crate::tool::{ListToolsResult, Tool};
+
+        let (client_transport, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                panic!("Expected initialize request");
+            };
+            server
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "test-server", "version": "1.0.0" }
+                    })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let _ = server.receive().await.unwrap();
+
+            let tools: Vec<Tool> = ["a", "b", "c"]
+                .into_iter()
+                .map(|name| Tool::builder().name(name).build())
+                .collect();
+            for _ in 0..2 {
+                let Some(JsonRpcMessage::Request(request)) = server.receive().await.unwrap() else {
+                    panic!("Expected tools/list request");
+                };
+                let id = request.id.clone();
+                let ClientRequest::ListTools(params) = ClientRequest::try_from(request).unwrap()
+                else {
+                    panic!("Expected tools/list request");
+                };
+                let (tools, next_cursor) = paginate(&tools, &params, 2).unwrap();
+                let result = ListToolsResult {
+                    tools,
+                    next_cursor,
+                    meta: None,
+                };
+                server
+                    .send(JsonRpcMessage::Response(JsonRpcResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        id,
+                        result: Some(serde_json::to_value(result).unwrap()),
+                        error: None,
+                    }))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let client = Client::connect(
+            client_transport,
+            Implementation::new("test-client", "0.1.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let pages: Vec<ListToolsResult> = client
+            .request_all_pages(ClientRequest::ListTools)
+            .await
+            .unwrap();
+        server_task.await.unwrap();
+
+        let names: Vec<_> = pages
+            .iter()
+            .flat_map(|page| &page.tools)
+            .map(|tool| tool.name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_typed_results() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Sum {
+            total: i64,
+        }
+
+        let structured = CallToolResult::structured(json!({ "total": 3 })).unwrap();
+        assert_eq!(
+            typed_result::<Sum>("add", structured).unwrap(),
+            Sum { total: 3 }
+        );
+        let text = CallToolResult::text(r#"{"total": 4}"#);
+        assert_eq!(typed_result::<Sum>("add", text).unwrap(), Sum { total: 4 });
+
+        let error = typed_result::<Sum>("add", CallToolResult::text(r#"{"sum": 4}"#))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Result of tool add is not a"), "{error}");
+        assert!(error.contains("Sum: missing field `total`"), "{error}");
+        let error = typed_result::<Sum>("add", CallToolResult::error("Overflow")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Internal error: Tool add failed: Overflow"
+        );
+    }
+}
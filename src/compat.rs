@@ -0,0 +1,262 @@
+/// Serialization for peers that speak an older protocol revision.
+///
+/// Types in this crate follow the latest revision. Some of their fields did not exist in
+/// earlier revisions, and strict peers reject messages that carry them. A
+/// [`SerializationContext`] created from the negotiated [`ProtocolVersion`] adapts serialized
+/// results and parameters to what that revision defines:
+///
+/// - 2025-03-26 introduced tool `annotations`, audio content and the `completions` server
+///   capability
+/// - 2025-06-18 introduced `title` on tools, prompts, prompt arguments, resources, resource
+///   templates and implementations, `outputSchema` and `structuredContent` for tools, resource
+///   link content, the `elicitation` client capability and the completion `context`
+///
+/// Fields unknown to the revision are omitted. Content of a type the revision does not know is
+/// replaced by text content describing it, so messages keep their shape.
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::protocol::{ProtocolError, ProtocolVersion};
+
+/// Adapts serialized messages to one protocol revision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializationContext {
+    version: ProtocolVersion,
+}
+
+impl SerializationContext {
+    pub fn new(version: ProtocolVersion) -> Self {
+        SerializationContext { version }
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Serializes the result of a `method` request.
+    pub fn serialize_result<T: Serialize>(
+        &self,
+        method: &str,
+        result: &T,
+    ) -> Result<Value, ProtocolError> {
+        let mut value = serde_json::to_value(result)
+            .map_err(|e| ProtocolError::InternalError(e.to_string()))?;
+        self.adapt_result(method, &mut value);
+        Ok(value)
+    }
+
+    /// Serializes the parameters of a `method` request or notification.
+    pub fn serialize_params<T: Serialize>(
+        &self,
+        method: &str,
+        params: &T,
+    ) -> Result<Value, ProtocolError> {
+        let mut value = serde_json::to_value(params)
+            .map_err(|e| ProtocolError::InternalError(e.to_string()))?;
+        self.adapt_params(method, &mut value);
+        Ok(value)
+    }
+
+    /// Removes what this revision does not define from the result of a `method` request.
+    pub fn adapt_result(&self, method: &str, result: &mut Value) {
+        match method {
+            "initialize" => {
+                self.strip_title(result.get_mut("serverInfo"));
+                if self.before(ProtocolVersion::V2025_03_26) {
+                    remove(result.get_mut("capabilities"), "completions");
+                }
+            }
+            "tools/list" => {
+                for tool in items(result, "tools") {
+                    self.strip_title(Some(&mut *tool));
+                    if self.before(ProtocolVersion::V2025_06_18) {
+                        remove(Some(&mut *tool), "outputSchema");
+                    }
+                    if self.before(ProtocolVersion::V2025_03_26) {
+                        remove(Some(tool), "annotations");
+                    }
+                }
+            }
+            "tools/call" => {
+                if self.before(ProtocolVersion::V2025_06_18) {
+                    remove(Some(&mut *result), "structuredContent");
+                }
+                for content in items(result, "content") {
+                    self.adapt_content(content);
+                }
+            }
+            "prompts/list" => {
+                for prompt in items(result, "prompts") {
+                    self.strip_title(Some(&mut *prompt));
+                    for argument in items(prompt, "arguments") {
+                        self.strip_title(Some(argument));
+                    }
+                }
+            }
+            "prompts/get" => {
+                for message in items(result, "messages") {
+                    if let Some(content) = message.get_mut("content") {
+                        self.adapt_content(content);
+                    }
+                }
+            }
+            "resources/list" => {
+                for resource in items(result, "resources") {
+                    self.strip_title(Some(resource));
+                }
+            }
+            "resources/templates/list" => {
+                for template in items(result, "resourceTemplates") {
+                    self.strip_title(Some(template));
+                }
+            }
+            "sampling/createMessage" => {
+                if let Some(content) = result.get_mut("content") {
+                    self.adapt_content(content);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Removes what this revision does not define from the parameters of `method`.
+    pub fn adapt_params(&self, method: &str, params: &mut Value) {
+        match method {
+            "initialize" => {
+                self.strip_title(params.get_mut("clientInfo"));
+                if self.before(ProtocolVersion::V2025_06_18) {
+                    remove(params.get_mut("capabilities"), "elicitation");
+                }
+            }
+            "completion/complete" if self.before(ProtocolVersion::V2025_06_18) => {
+                remove(Some(params), "context");
+            }
+            "sampling/createMessage" => {
+                for message in items(params, "messages") {
+                    if let Some(content) = message.get_mut("content") {
+                        self.adapt_content(content);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn before(&self, version: ProtocolVersion) -> bool {
+        self.version < version
+    }
+
+    fn strip_title(&self, object: Option<&mut Value>) {
+        if self.before(ProtocolVersion::V2025_06_18) {
+            remove(object, "title");
+        }
+    }
+
+    /// Replaces content of a type this revision does not know with a text description.
+    fn adapt_content(&self, content: &mut Value) {
+        let text = match content.get("type").and_then(Value::as_str) {
+            Some("audio") if self.before(ProtocolVersion::V2025_03_26) => {
+                let mime_type = content.get("mimeType").and_then(Value::as_str);
+                format!("[audio content: {}]", mime_type.unwrap_or("unknown type"))
+            }
+            Some("resource_link") if self.before(ProtocolVersion::V2025_06_18) => {
+                let uri = content.get("uri").and_then(Value::as_str);
+                format!("[resource: {}]", uri.unwrap_or_default())
+            }
+            _ => return,
+        };
+        *content = json!({ "type": "text", "text": text });
+    }
+}
+
+fn items<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+    value
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+fn remove(object: Option<&mut Value>, key: &str) {
+    if let Some(Value::Object(object)) = object {
+        object.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_are_omitted_for_older_revisions() {
+        let result = json!({
+            "tools": [{
+                "name": "search",
+                "title": "Search",
+                "inputSchema": { "type": "object", "properties": { "title": {} } },
+                "outputSchema": { "type": "object" },
+                "annotations": { "readOnlyHint": true }
+            }]
+        });
+
+        let mut latest = result.clone();
+        SerializationContext::new(ProtocolVersion::LATEST).adapt_result("tools/list", &mut latest);
+        assert_eq!(latest, result);
+
+        let mut older = result.clone();
+        SerializationContext::new(ProtocolVersion::V2025_03_26)
+            .adapt_result("tools/list", &mut older);
+        assert_eq!(
+            older,
+            json!({
+                "tools": [{
+                    "name": "search",
+                    "inputSchema": { "type": "object", "properties": { "title": {} } },
+                    "annotations": { "readOnlyHint": true }
+                }]
+            })
+        );
+
+        let context = SerializationContext::new(ProtocolVersion::V2024_11_05);
+        let params = context
+            .serialize_params(
+                "initialize",
+                &json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": { "elicitation": {}, "sampling": {} },
+                    "clientInfo": { "name": "client", "title": "Client", "version": "1.0.0" }
+                }),
+            )
+            .unwrap();
+        assert_eq!(params["capabilities"], json!({ "sampling": {} }));
+        assert_eq!(params["clientInfo"].get("title"), None);
+    }
+
+    #[test]
+    fn test_unknown_content_becomes_text() {
+        let context = SerializationContext::new(ProtocolVersion::V2024_11_05);
+        let result = context
+            .serialize_result(
+                "tools/call",
+                &json!({
+                    "content": [
+                        { "type": "text", "text": "done" },
+                        { "type": "audio", "data": "AAAA", "mimeType": "audio/wav" },
+                        { "type": "resource_link", "uri": "file:///a.txt", "name": "a" }
+                    ],
+                    "structuredContent": { "ok": true }
+                }),
+            )
+            .unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "content": [
+                    { "type": "text", "text": "done" },
+                    { "type": "text", "text": "[audio content: audio/wav]" },
+                    { "type": "text", "text": "[resource: file:///a.txt]" }
+                ]
+            })
+        );
+    }
+}
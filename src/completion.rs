@@ -0,0 +1,312 @@
+/// Argument autocompletion offered by servers
+///
+/// A [`CompletionRegistry`] holds a [`CompletionProvider`] for each prompt argument and
+/// resource template variable that can be completed, and answers `completion/complete`
+/// with the values of the matching provider:
+///
+/// ```text
+/// let completions = CompletionRegistry::new()
+///     .with_prompt_argument("review", "language", |value: String, _| async move {
+///         Ok(LANGUAGES.iter().filter(|l| l.starts_with(&value)).map(|l| l.to_string()).collect())
+///     });
+/// let server = Server::new(info).with_completions(completions);
+/// ```
+///
+/// Providers may return any number of values; results are cut to the 100 the protocol allows,
+/// with `total` and `hasMore` telling the client there are more.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::McpError;
+
+/// The most values a completion result may hold.
+pub const MAX_COMPLETION_VALUES: usize = 100;
+
+/// Identifies a prompt
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PromptReference {
+    /// The name of the prompt or prompt template
+    pub name: String,
+}
+
+/// Identifies a resource template
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResourceTemplateReference {
+    /// The URI or URI template of the resource
+    pub uri: String,
+}
+
+/// Identifies what a completion request is for
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CompletionReference {
+    /// A reference to a prompt by name
+    #[serde(rename = "ref/prompt")]
+    Prompt(PromptReference),
+
+    /// A reference to a resource or resource template by URI
+    #[serde(rename = "ref/resource")]
+    Resource(ResourceTemplateReference),
+}
+
+/// The argument being completed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionArgument {
+    /// The name of the argument
+    pub name: String,
+
+    /// The value of the argument to use for completion matching
+    pub value: String,
+}
+
+/// Additional context for a completion request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionContext {
+    /// Previously-resolved variables in a URI template or prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<HashMap<String, String>>,
+}
+
+/// Parameters of a completion/complete request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteRequestParams {
+    /// The prompt or resource the argument belongs to
+    #[serde(rename = "ref")]
+    pub reference: CompletionReference,
+
+    /// The argument's information
+    pub argument: CompletionArgument,
+
+    /// Additional, optional context for completions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<CompletionContext>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The completion values offered for an argument
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    /// An array of completion values. Must not exceed 100 items
+    pub values: Vec<String>,
+
+    /// The total number of completion options available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+
+    /// Whether there are additional completion options beyond those provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+impl Completion {
+    /// A completion offering `values`, cut to [`MAX_COMPLETION_VALUES`]. If values were cut,
+    /// `total` holds how many there were and `hasMore` is set.
+    pub fn from_values(mut values: Vec<String>) -> Self {
+        if values.len() <= MAX_COMPLETION_VALUES {
+            return Completion {
+                values,
+                total: None,
+                has_more: None,
+            };
+        }
+        let total = values.len() as u64;
+        values.truncate(MAX_COMPLETION_VALUES);
+        Completion {
+            values,
+            total: Some(total),
+            has_more: Some(true),
+        }
+    }
+}
+
+/// The server's response to a completion/complete request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteResult {
+    pub completion: Completion,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Completes the values of one prompt argument or resource template variable.
+#[async_trait]
+pub trait CompletionProvider: Send + Sync {
+    /// The values that complete `value`, given the arguments or variables the client has
+    /// already resolved.
+    async fn complete(
+        &self,
+        value: String,
+        arguments: HashMap<String, String>,
+    ) -> Result<Vec<String>, McpError>;
+}
+
+#[async_trait]
+impl<F, Fut> CompletionProvider for F
+where
+    F: Fn(String, HashMap<String, String>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Vec<String>, McpError>> + Send,
+{
+    async fn complete(
+        &self,
+        value: String,
+        arguments: HashMap<String, String>,
+    ) -> Result<Vec<String>, McpError> {
+        self(value, arguments).await
+    }
+}
+
+/// The completion providers of a server, by reference and argument name.
+#[derive(Clone, Default)]
+pub struct CompletionRegistry {
+    providers: HashMap<(CompletionReference, String), Arc<dyn CompletionProvider>>,
+}
+
+impl std::fmt::Debug for CompletionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompletionRegistry")
+            .field("arguments", &self.providers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl CompletionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Completes the argument `argument` of the prompt named `prompt` with `provider`.
+    pub fn with_prompt_argument(
+        self,
+        prompt: impl Into<String>,
+        argument: impl Into<String>,
+        provider: impl CompletionProvider + 'static,
+    ) -> Self {
+        let reference = CompletionReference::Prompt(PromptReference {
+            name: prompt.into(),
+        });
+        self.with_provider(reference, argument.into(), provider)
+    }
+
+    /// Completes the variable `variable` of the resource template `uri_template` with
+    /// `provider`.
+    pub fn with_template_variable(
+        self,
+        uri_template: impl Into<String>,
+        variable: impl Into<String>,
+        provider: impl CompletionProvider + 'static,
+    ) -> Self {
+        let reference = CompletionReference::Resource(ResourceTemplateReference {
+            uri: uri_template.into(),
+        });
+        self.with_provider(reference, variable.into(), provider)
+    }
+
+    fn with_provider(
+        mut self,
+        reference: CompletionReference,
+        argument: String,
+        provider: impl CompletionProvider + 'static,
+    ) -> Self {
+        self.providers
+            .insert((reference, argument), Arc::new(provider));
+        self
+    }
+
+    /// Answers a `completion/complete` request. Arguments without a provider complete to no
+    /// values.
+    pub async fn complete(
+        &self,
+        params: CompleteRequestParams,
+    ) -> Result<CompleteResult, McpError> {
+        let key = (params.reference, params.argument.name);
+        let Some(provider) = self.providers.get(&key) else {
+            return Ok(CompleteResult::default());
+        };
+        let arguments = params
+            .context
+            .and_then(|context| context.arguments)
+            .unwrap_or_default();
+        let values = provider.complete(params.argument.value, arguments).await?;
+        Ok(CompleteResult {
+            completion: Completion::from_values(values),
+            meta: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(params: Value) -> CompleteRequestParams {
+        serde_json::from_value(params).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_arguments_are_completed_by_their_provider() {
+        let completions = CompletionRegistry::new()
+            .with_prompt_argument("review", "language", |value: String, _| async move {
+                Ok(["rust", "ruby", "python"]
+                    .into_iter()
+                    .filter(|language| language.starts_with(&value))
+                    .map(str::to_string)
+                    .collect())
+            })
+            .with_template_variable(
+                "file:///{dir}/{name}",
+                "name",
+                |value: String, arguments: HashMap<String, String>| async move {
+                    let dir = arguments.get("dir").cloned().unwrap_or_default();
+                    Ok((0..150).map(|n| format!("{dir}/{value}{n}")).collect())
+                },
+            );
+
+        let result = completions
+            .complete(request(json!({
+                "ref": { "type": "ref/prompt", "name": "review" },
+                "argument": { "name": "language", "value": "ru" }
+            })))
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            json!({ "completion": { "values": ["rust", "ruby"] } })
+        );
+
+        let result = completions
+            .complete(request(json!({
+                "ref": { "type": "ref/resource", "uri": "file:///{dir}/{name}" },
+                "argument": { "name": "name", "value": "a" },
+                "context": { "arguments": { "dir": "src" } }
+            })))
+            .await
+            .unwrap();
+        assert_eq!(result.completion.values.len(), MAX_COMPLETION_VALUES);
+        assert_eq!(result.completion.values[0], "src/a0");
+        assert_eq!(result.completion.total, Some(150));
+        assert_eq!(result.completion.has_more, Some(true));
+
+        let result = completions
+            .complete(request(json!({
+                "ref": { "type": "ref/prompt", "name": "review" },
+                "argument": { "name": "tone", "value": "" }
+            })))
+            .await
+            .unwrap();
+        assert!(result.completion.values.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+/// Resource providers combined into one, by URI scheme.
+///
+/// A server takes a single [`ResourceProvider`]. [`CompositeResourceProvider`] lets it serve
+/// several, each for the URI schemes it handles:
+///
+/// ```text
+/// let resources = CompositeResourceProvider::new()
+///     .route("file", FileSystemResourceProvider::new("docs"))
+///     .route("str", InMemoryResourceProvider::new());
+/// ```
+///
+/// Reads and subscriptions go to the provider for the URI's scheme; URIs with a scheme no
+/// provider handles are not found. Listing pages through the providers in the order they
+/// were added, so a client that follows `nextCursor` sees every provider's resources. The
+/// composite supports subscriptions and list changes if any of its providers does.
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::protocol::{Cursor, ProtocolError};
+use crate::resource::{
+    ListResourceTemplatesResult, ListResourcesResult, ResourceContent, ResourceError,
+    ResourceProvider,
+};
+use crate::server::SubscriptionHandle;
+
+/// Routes resource requests to providers by URI scheme.
+#[derive(Clone, Default)]
+pub struct CompositeResourceProvider {
+    routes: Vec<(String, Arc<dyn ResourceProvider>)>,
+}
+
+impl std::fmt::Debug for CompositeResourceProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompositeResourceProvider")
+            .field("schemes", &self.schemes().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Where a listing continues: the provider, and its own cursor.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Position {
+    pub(crate) provider: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) cursor: Option<Cursor>,
+}
+
+impl Position {
+    /// The provider a listing over `providers` providers continues with, and its own cursor;
+    /// the first provider if there is no `cursor`.
+    pub(crate) fn from_cursor(
+        cursor: Option<Cursor>,
+        providers: usize,
+    ) -> Result<Position, ProtocolError> {
+        let Some(cursor) = cursor else {
+            return Ok(Position {
+                provider: 0,
+                cursor: None,
+            });
+        };
+        decode_position(&cursor)
+            .filter(|position| position.provider < providers)
+            .ok_or_else(|| ProtocolError::InvalidParams(format!("Invalid cursor: {}", cursor.0)))
+    }
+
+    /// The cursor of the page after a page of the provider at `provider`, which may point at
+    /// the next of `providers` providers.
+    pub(crate) fn next_cursor(
+        provider: usize,
+        next_cursor: Option<Cursor>,
+        providers: usize,
+    ) -> Option<Cursor> {
+        let position = match next_cursor {
+            Some(cursor) => Position {
+                provider,
+                cursor: Some(cursor),
+            },
+            None if provider + 1 < providers => Position {
+                provider: provider + 1,
+                cursor: None,
+            },
+            None => return None,
+        };
+        Some(encode_position(&position))
+    }
+}
+
+impl CompositeResourceProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles URIs with `scheme`, e.g. `file` or `file://`, with `provider`, replacing any
+    /// provider added for it before.
+    pub fn route(mut self, scheme: &str, provider: impl ResourceProvider + 'static) -> Self {
+        let scheme = scheme.trim_end_matches("://").to_ascii_lowercase();
+        let provider: Arc<dyn ResourceProvider> = Arc::new(provider);
+        match self.routes.iter_mut().find(|(routed, _)| *routed == scheme) {
+            Some(route) => route.1 = provider,
+            None => self.routes.push((scheme, provider)),
+        }
+        self
+    }
+
+    /// The schemes routed to a provider, in the order they were added.
+    pub fn schemes(&self) -> impl Iterator<Item = &str> {
+        self.routes.iter().map(|(scheme, _)| scheme.as_str())
+    }
+
+    fn provider(&self, uri: &str) -> Result<&Arc<dyn ResourceProvider>, ResourceError> {
+        let uri = Url::parse(uri)?;
+        self.routes
+            .iter()
+            .find(|(scheme, _)| scheme == uri.scheme())
+            .map(|(_, provider)| provider)
+            .ok_or(ResourceError::NotFound)
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for CompositeResourceProvider {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+        let position = Position::from_cursor(cursor, self.routes.len())?;
+        let Some((_, provider)) = self.routes.get(position.provider) else {
+            return Ok(ListResourcesResult::default());
+        };
+        let mut page = provider.list(position.cursor).await?;
+        page.next_cursor =
+            Position::next_cursor(position.provider, page.next_cursor, self.routes.len());
+        Ok(page)
+    }
+
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+        self.provider(uri)?.read(uri).await
+    }
+
+    async fn list_templates(
+        &self,
+        cursor: Option<Cursor>,
+    ) -> Result<ListResourceTemplatesResult, ResourceError> {
+        let position = Position::from_cursor(cursor, self.routes.len())?;
+        let Some((_, provider)) = self.routes.get(position.provider) else {
+            return Ok(ListResourceTemplatesResult::default());
+        };
+        let mut page = provider.list_templates(position.cursor).await?;
+        page.next_cursor =
+            Position::next_cursor(position.provider, page.next_cursor, self.routes.len());
+        Ok(page)
+    }
+
+    fn supports_subscriptions(&self) -> bool {
+        self.routes
+            .iter()
+            .any(|(_, provider)| provider.supports_subscriptions())
+    }
+
+    fn supports_list_changed(&self) -> bool {
+        self.routes
+            .iter()
+            .any(|(_, provider)| provider.supports_list_changed())
+    }
+
+    fn attach(&self, subscriptions: SubscriptionHandle) {
+        for (_, provider) in &self.routes {
+            if provider.supports_subscriptions() || provider.supports_list_changed() {
+                provider.attach(subscriptions.clone());
+            }
+        }
+    }
+
+    async fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        let provider = self.provider(uri)?;
+        if !provider.supports_subscriptions() {
+            return Err(ProtocolError::InvalidParams(format!(
+                "Resources at {} cannot be subscribed to",
+                uri
+            ))
+            .into());
+        }
+        provider.subscribe(uri).await
+    }
+}
+
+pub(crate) fn encode_position(position: &Position) -> Cursor {
+    let json = serde_json::to_vec(position).unwrap_or_default();
+    Cursor(BASE64_URL_SAFE_NO_PAD.encode(json))
+}
+
+pub(crate) fn decode_position(cursor: &Cursor) -> Option<Position> {
+    let json = BASE64_URL_SAFE_NO_PAD.decode(&cursor.0).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FileSystemResourceProvider;
+    use crate::memory::InMemoryResourceProvider;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_requests_are_routed_by_scheme() {
+        let root = TempDir::new().unwrap();
+        std::fs::write(root.path().join("todo.txt"), "Water the plants").unwrap();
+        let store = InMemoryResourceProvider::new();
+        let log = store.insert("build.log", "Compiling...").await;
+        let resources = CompositeResourceProvider::new()
+            .route("str://", store)
+            .route("file", FileSystemResourceProvider::new(root.path()));
+        assert_eq!(resources.schemes().collect::<Vec<_>>(), ["str", "file"]);
+        assert!(resources.supports_subscriptions());
+
+        let mut names = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = resources.list(cursor).await.unwrap();
+            names.extend(page.resources.into_iter().map(|resource| resource.name));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(names, ["build.log", "todo.txt"]);
+
+        let contents = resources.read(&log).await.unwrap();
+        let [ResourceContent::TextResourceContents { text, .. }] = contents.as_slice() else {
+            panic!("Expected text");
+        };
+        assert_eq!(text, "Compiling...");
+        resources.subscribe(&log).await.unwrap();
+        let todo = Url::from_file_path(root.path().join("todo.txt")).unwrap();
+        assert!(matches!(
+            resources.subscribe(todo.as_str()).await,
+            Err(ResourceError::Protocol(ProtocolError::InvalidParams(_)))
+        ));
+        assert!(matches!(
+            resources.read("https://example.com/").await,
+            Err(ResourceError::NotFound)
+        ));
+        assert!(matches!(
+            resources.list(Some(Cursor::from("not-a-cursor"))).await,
+            Err(ResourceError::Protocol(ProtocolError::InvalidParams(_)))
+        ));
+    }
+}
@@ -0,0 +1,359 @@
+/// Conformance of the message types with the official MCP JSON schema.
+///
+/// For every revision in [`SCHEMAS`], each schema definition is turned into two instances: a
+/// minimal one with only the required members and a full one with every member the schema
+/// names. Both are deserialized into the corresponding mcp-ox type and serialized again. The
+/// output must equal the input, which catches renamed members, missing fields and nullability
+/// drift between the types and the specification.
+///
+/// Definitions without a counterpart are listed in [`SKIPPED`], and known differences in
+/// [`KNOWN_DRIFT`], each with the reason. Both lists are checked exactly, so a schema that adds
+/// a definition or a fix that removes a difference shows up as a test failure.
+///
+/// To check another revision, add its `schema.json` from the specification repository under
+/// `schema/` and list it in [`SCHEMAS`].
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value, json};
+
+use Instance::{Full, Minimal};
+
+use crate::completion::{CompleteResult, CompletionReference};
+use crate::logging::LoggingLevel;
+use crate::progress::ProgressToken;
+use crate::prompt::{
+    GetPromptResult, ListPromptsResult, Prompt, PromptArgument, PromptMessage,
+    PromptMessageContent, PromptMessageRole, ResourceContents, TextResourceContents,
+};
+use crate::protocol::{
+    ClientCapabilities, ClientNotification, ClientRequest, Cursor, EmptyResult, Implementation,
+    InitializeResult, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse, RequestId, ServerCapabilities, ServerNotification, ServerRequest,
+};
+use crate::resource::{
+    ListResourceTemplatesResult, ListResourcesResult, ReadResourceResult, Resource,
+    ResourceContent, ResourceTemplate,
+};
+use crate::roots::{ListRootsResult, Root};
+use crate::sampling::{CreateMessageResult, ModelHint, ModelPreferences, SamplingMessage};
+use crate::tool::{CallToolResult, ListToolsResult, Tool};
+
+/// The schema of each checked revision.
+const SCHEMAS: &[(&str, &str)] = &[("2024-11-05", include_str!("../schema/2024-11-05.json"))];
+
+/// Definitions that are not checked.
+const SKIPPED: &[(&str, &str)] = &[
+    ("Annotated", "abstract base of the content types"),
+    ("Notification", "abstract base of the notifications"),
+    ("PaginatedRequest", "abstract base of the list requests"),
+    ("PaginatedResult", "abstract base of the list results"),
+    ("Request", "abstract base of the requests"),
+    ("Result", "abstract base of the results"),
+    (
+        "ClientResult",
+        "untagged union of results, checked per result",
+    ),
+    (
+        "ServerResult",
+        "untagged union of results, checked per result",
+    ),
+];
+
+/// Differences between the types and the schema that are known, by revision, then by
+/// definition and instance.
+const KNOWN_DRIFT: &[(&str, &[Drift])] = &[(
+    "2024-11-05",
+    &[
+        ("CallToolResult", Full, ANNOTATIONS),
+        ("CreateMessageRequest", Full, ANNOTATIONS),
+        ("CreateMessageResult", Full, ANNOTATIONS),
+        ("GetPromptResult", Full, ANNOTATIONS),
+        ("ImageContent", Full, ANNOTATIONS),
+        ("ListResourceTemplatesResult", Full, ANNOTATIONS),
+        ("ListResourcesResult", Full, ANNOTATIONS),
+        ("PromptMessage", Full, ANNOTATIONS),
+        ("Resource", Full, ANNOTATIONS),
+        ("ResourceTemplate", Full, ANNOTATIONS),
+        ("SamplingMessage", Full, ANNOTATIONS),
+        ("TextContent", Full, ANNOTATIONS),
+        ("EmbeddedResource", Minimal, NESTED_RESOURCE),
+        ("EmbeddedResource", Full, NESTED_RESOURCE),
+        ("EmptyResult", Full, "`_meta` is not kept"),
+        ("InitializedNotification", Full, PARAMS_META),
+        ("ListRootsRequest", Full, PARAMS_META),
+        ("PingRequest", Full, PARAMS_META),
+        ("PromptListChangedNotification", Full, PARAMS_META),
+        ("ResourceListChangedNotification", Full, PARAMS_META),
+        ("RootsListChangedNotification", Full, PARAMS_META),
+        ("ServerRequest", Full, PARAMS_META),
+        ("ToolListChangedNotification", Full, PARAMS_META),
+        ("ListResourcesResult", Minimal, REQUIRED_MIME_TYPE),
+        ("Resource", Minimal, REQUIRED_MIME_TYPE),
+    ],
+)];
+
+const ANNOTATIONS: &str = "`annotations` are not modelled";
+const NESTED_RESOURCE: &str = "embedded resource content nests `resource` twice";
+const PARAMS_META: &str = "messages without parameters drop `params._meta`";
+const REQUIRED_MIME_TYPE: &str = "`Resource::mime_type` is required";
+
+/// A definition, the instance that does not round-trip and the reason.
+type Drift = (&'static str, Instance, &'static str);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Instance {
+    Minimal,
+    Full,
+}
+
+type Check = fn(&Value) -> Result<(), String>;
+
+/// The check for each schema definition.
+fn check_for(definition: &str) -> Option<Check> {
+    Some(match definition {
+        "BlobResourceContents" => round_trip::<ResourceContent>,
+        "CallToolResult" => round_trip::<CallToolResult>,
+        "ClientCapabilities" => round_trip::<ClientCapabilities>,
+        "CompleteResult" => round_trip::<CompleteResult>,
+        "CreateMessageResult" => round_trip::<CreateMessageResult>,
+        "Cursor" => round_trip::<Cursor>,
+        "EmptyResult" => round_trip::<EmptyResult>,
+        "GetPromptResult" => round_trip::<GetPromptResult>,
+        "Implementation" => round_trip::<Implementation>,
+        "InitializeResult" => round_trip::<InitializeResult>,
+        "JSONRPCError" => round_trip::<JsonRpcError>,
+        "JSONRPCMessage" => round_trip::<JsonRpcMessage>,
+        "JSONRPCNotification" => round_trip::<JsonRpcNotification>,
+        "JSONRPCRequest" => round_trip::<JsonRpcRequest>,
+        "JSONRPCResponse" => round_trip::<JsonRpcResponse>,
+        "ListPromptsResult" => round_trip::<ListPromptsResult>,
+        "ListResourceTemplatesResult" => round_trip::<ListResourceTemplatesResult>,
+        "ListResourcesResult" => round_trip::<ListResourcesResult>,
+        "ListRootsResult" => round_trip::<ListRootsResult>,
+        "ListToolsResult" => round_trip::<ListToolsResult>,
+        "LoggingLevel" => round_trip::<LoggingLevel>,
+        "ModelHint" => round_trip::<ModelHint>,
+        "ModelPreferences" => round_trip::<ModelPreferences>,
+        "ProgressToken" => round_trip::<ProgressToken>,
+        "Prompt" => round_trip::<Prompt>,
+        "PromptArgument" => round_trip::<PromptArgument>,
+        "PromptMessage" => round_trip::<PromptMessage>,
+        "PromptReference" | "ResourceReference" => round_trip::<CompletionReference>,
+        "ReadResourceResult" => round_trip::<ReadResourceResult>,
+        "RequestId" => round_trip::<RequestId>,
+        "Resource" => round_trip::<Resource>,
+        "ResourceContents" => round_trip::<ResourceContents>,
+        "ResourceTemplate" => round_trip::<ResourceTemplate>,
+        "Role" => round_trip::<PromptMessageRole>,
+        "Root" => round_trip::<Root>,
+        "SamplingMessage" => round_trip::<SamplingMessage>,
+        "ServerCapabilities" => round_trip::<ServerCapabilities>,
+        "TextResourceContents" => round_trip::<TextResourceContents>,
+        "Tool" => round_trip::<Tool>,
+        "TextContent" | "ImageContent" | "EmbeddedResource" => round_trip::<PromptMessageContent>,
+
+        "ClientRequest"
+        | "CallToolRequest"
+        | "CompleteRequest"
+        | "GetPromptRequest"
+        | "InitializeRequest"
+        | "ListPromptsRequest"
+        | "ListResourceTemplatesRequest"
+        | "ListResourcesRequest"
+        | "ListToolsRequest"
+        | "ReadResourceRequest"
+        | "SetLevelRequest"
+        | "SubscribeRequest"
+        | "UnsubscribeRequest" => {
+            |value| parts(value, ClientRequest::from_parts, ClientRequest::params)
+        }
+        "ServerRequest" | "CreateMessageRequest" | "ListRootsRequest" => {
+            |value| parts(value, ServerRequest::from_parts, ServerRequest::params)
+        }
+        "PingRequest" => |value| {
+            parts(value, ClientRequest::from_parts, ClientRequest::params)?;
+            parts(value, ServerRequest::from_parts, ServerRequest::params)
+        },
+        "ClientNotification" | "InitializedNotification" | "RootsListChangedNotification" => {
+            |value| {
+                parts(
+                    value,
+                    ClientNotification::from_parts,
+                    ClientNotification::params,
+                )
+            }
+        }
+        "ServerNotification"
+        | "LoggingMessageNotification"
+        | "PromptListChangedNotification"
+        | "ResourceListChangedNotification"
+        | "ResourceUpdatedNotification"
+        | "ToolListChangedNotification" => |value| {
+            parts(
+                value,
+                ServerNotification::from_parts,
+                ServerNotification::params,
+            )
+        },
+        "CancelledNotification" | "ProgressNotification" => |value| {
+            parts(
+                value,
+                ClientNotification::from_parts,
+                ClientNotification::params,
+            )?;
+            parts(
+                value,
+                ServerNotification::from_parts,
+                ServerNotification::params,
+            )
+        },
+        _ => return None,
+    })
+}
+
+fn round_trip<T: Serialize + DeserializeOwned>(value: &Value) -> Result<(), String> {
+    let typed: T = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+    let output = serde_json::to_value(typed).map_err(|e| e.to_string())?;
+    compare("", value, &output)
+}
+
+/// Checks a request or notification through its method and parameters. Missing parameters
+/// are equivalent to empty ones.
+fn parts<T, E: ToString>(
+    value: &Value,
+    from_parts: fn(&str, Option<Value>) -> Result<T, E>,
+    params: fn(&T) -> Result<Option<Value>, E>,
+) -> Result<(), String> {
+    let method = value["method"].as_str().ok_or("Missing method")?;
+    let typed = from_parts(method, value.get("params").cloned()).map_err(|e| e.to_string())?;
+    let output = params(&typed).map_err(|e| e.to_string())?;
+    let empty = |params: Option<&Value>| params.is_none_or(|params| params == &json!({}));
+    if empty(value.get("params")) && empty(output.as_ref()) {
+        return Ok(());
+    }
+    compare(
+        "params",
+        value.get("params").unwrap_or(&Value::Null),
+        output.as_ref().unwrap_or(&Value::Null),
+    )
+}
+
+/// Compares two values, treating numbers as equal when their values are.
+fn compare(path: &str, expected: &Value, actual: &Value) -> Result<(), String> {
+    match (expected, actual) {
+        (Value::Number(a), Value::Number(b)) if a.as_f64() == b.as_f64() => Ok(()),
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<_> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let path = format!("{}/{}", path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => compare(&path, a, b)?,
+                    (Some(_), None) => return Err(format!("{} was dropped", path)),
+                    (None, _) => return Err(format!("{} was added", path)),
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .try_for_each(|(index, (a, b))| compare(&format!("{}/{}", path, index), a, b)),
+        (a, b) if a == b => Ok(()),
+        (a, b) => Err(format!("{} changed from {} to {}", path, a, b)),
+    }
+}
+
+/// Builds an instance of `schema`, resolving references against `definitions`.
+fn instance(schema: &Value, definitions: &Map<String, Value>, kind: Instance) -> Value {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        return instance(&definitions[name], definitions, kind);
+    }
+    if let Some(value) = schema.get("const") {
+        return value.clone();
+    }
+    if let Some(value) = schema.get("enum").and_then(|values| values.get(0)) {
+        return value.clone();
+    }
+    if let Some(variant) = schema.get("anyOf").and_then(|variants| variants.get(0)) {
+        return instance(variant, definitions, kind);
+    }
+
+    let kind_of = match schema.get("type") {
+        Some(Value::Array(types)) => types.first().and_then(Value::as_str),
+        Some(kind) => kind.as_str(),
+        None if schema.get("properties").is_some() => Some("object"),
+        None => None,
+    };
+    match kind_of {
+        Some("object") => {
+            let required: BTreeSet<_> = schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let object: Map<_, _> = properties
+                .into_iter()
+                .flatten()
+                .filter(|(name, _)| kind == Full || required.contains(name.as_str()))
+                .map(|(name, property)| (name.clone(), instance(property, definitions, kind)))
+                .collect();
+            Value::Object(object)
+        }
+        Some("array") => match schema.get("items") {
+            Some(items) => json!([instance(items, definitions, kind)]),
+            None => json!([]),
+        },
+        Some("string") => json!("x"),
+        Some("integer") => json!(1),
+        Some("number") => json!(0.5),
+        Some("boolean") => json!(true),
+        _ => json!({}),
+    }
+}
+
+#[test]
+fn test_types_conform_to_schema() {
+    let skipped: BTreeMap<_, _> = SKIPPED.iter().copied().collect();
+    let mut drift = BTreeSet::new();
+    let mut report = Vec::new();
+
+    for (revision, text) in SCHEMAS {
+        let schema: Value = serde_json::from_str(text).unwrap();
+        let definitions = schema["definitions"].as_object().unwrap();
+        for (name, definition) in definitions {
+            let Some(check) = check_for(name) else {
+                assert!(
+                    skipped.contains_key(name.as_str()),
+                    "{} defines {}, which has no check and is not skipped",
+                    revision,
+                    name
+                );
+                continue;
+            };
+            for kind in [Minimal, Full] {
+                let value = instance(definition, definitions, kind);
+                if let Err(error) = check(&value) {
+                    drift.insert((*revision, name.clone(), kind));
+                    report.push(format!("{} {} {:?}: {}", revision, name, kind, error));
+                }
+            }
+        }
+    }
+
+    let known: BTreeSet<_> = KNOWN_DRIFT
+        .iter()
+        .flat_map(|(revision, drift)| {
+            drift
+                .iter()
+                .map(|(name, kind, _)| (*revision, name.to_string(), *kind))
+        })
+        .collect();
+    assert_eq!(drift, known, "\n{}", report.join("\n"));
+}
@@ -0,0 +1,257 @@
+/// Capturing panics in handlers and reporting them as crash incidents.
+///
+/// A panic inside a handler is caught, recorded with its backtrace under a unique incident id,
+/// handed to every configured [`CrashSink`], logged with the `tracing` feature, and turned into
+/// an `INTERNAL_ERROR` response so the rest of the session keeps running.
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Once};
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::protocol::{ErrorData, INTERNAL_ERROR};
+
+/// A record of a panic caught while running a handler.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    /// Unique id of this incident, also sent to the peer in the error response
+    pub incident_id: String,
+    /// What was running when the panic happened, e.g. the method or tool name
+    pub context: String,
+    /// The panic message
+    pub message: String,
+    /// The backtrace captured at the panic site
+    pub backtrace: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<CrashReport> for ErrorData {
+    fn from(report: CrashReport) -> Self {
+        ErrorData {
+            code: INTERNAL_ERROR,
+            message: format!("Internal error (incident {})", report.incident_id),
+            data: Some(json!({ "incidentId": report.incident_id })),
+        }
+    }
+}
+
+/// Destination for crash reports.
+pub trait CrashSink: Send + Sync {
+    fn report(&self, report: &CrashReport);
+}
+
+/// Appends crash reports to a file, one JSON object per line.
+pub struct FileCrashSink {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileCrashSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileCrashSink {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+impl CrashSink for FileCrashSink {
+    fn report(&self, report: &CrashReport) {
+        let Ok(mut line) = serde_json::to_string(report) else {
+            return;
+        };
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+        {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+}
+
+/// Forwards crash reports to Sentry through the current hub.
+#[cfg(feature = "sentry")]
+#[derive(Debug, Default)]
+pub struct SentryCrashSink;
+
+#[cfg(feature = "sentry")]
+impl CrashSink for SentryCrashSink {
+    fn report(&self, report: &CrashReport) {
+        let mut event = sentry_core::protocol::Event {
+            message: Some(report.message.clone()),
+            level: sentry_core::Level::Fatal,
+            ..Default::default()
+        };
+        event
+            .tags
+            .insert("incident_id".to_string(), report.incident_id.clone());
+        event
+            .extra
+            .insert("context".to_string(), report.context.clone().into());
+        event
+            .extra
+            .insert("backtrace".to_string(), report.backtrace.clone().into());
+        sentry_core::capture_event(event);
+    }
+}
+
+/// Runs handler futures with panic isolation and fans crash reports out to its sinks.
+#[derive(Clone, Default)]
+pub struct CrashReporter {
+    sinks: Vec<Arc<dyn CrashSink>>,
+}
+
+impl CrashReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sink that receives every crash report.
+    pub fn with_sink(mut self, sink: impl CrashSink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Polls `future` to completion, turning a panic into a reported [`CrashReport`].
+    pub async fn catch<F: Future>(
+        &self,
+        context: impl Into<String>,
+        future: F,
+    ) -> Result<F::Output, CrashReport> {
+        install_backtrace_hook();
+
+        match AssertUnwindSafe(future).catch_unwind().await {
+            Ok(output) => Ok(output),
+            Err(payload) => {
+                let report = CrashReport {
+                    incident_id: Uuid::new_v4().to_string(),
+                    context: context.into(),
+                    message: panic_message(payload.as_ref()),
+                    backtrace: LAST_BACKTRACE
+                        .with(|b| b.borrow_mut().take())
+                        .unwrap_or_default(),
+                    timestamp: Utc::now(),
+                };
+                #[cfg(feature = "tracing")]
+                tracing::error!(
+                    incident_id = %report.incident_id,
+                    context = %report.context,
+                    "Handler panicked: {}",
+                    report.message
+                );
+                for sink in &self.sinks {
+                    sink.report(&report);
+                }
+                Err(report)
+            }
+        }
+    }
+}
+
+thread_local! {
+    static LAST_BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static BACKTRACE_HOOK: Once = Once::new();
+
+/// The backtrace is only available while the panic hook runs, so a hook records it for the
+/// thread that is about to unwind into [`CrashReporter::catch`]. The previous hook still runs.
+fn install_backtrace_hook() {
+    BACKTRACE_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let backtrace = Backtrace::force_capture().to_string();
+            LAST_BACKTRACE.with(|b| *b.borrow_mut() = Some(backtrace));
+            previous(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Handler panicked".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct CollectingSink(Mutex<Vec<CrashReport>>);
+
+    impl CrashSink for Arc<CollectingSink> {
+        fn report(&self, report: &CrashReport) {
+            self.0.lock().unwrap().push(report.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panic_is_reported() {
+        let sink = Arc::new(CollectingSink::default());
+        let reporter = CrashReporter::new().with_sink(sink.clone());
+
+        let result = reporter.catch("tools/call", async { panic!("boom") }).await;
+        let report: CrashReport = match result {
+            Err(report) => report,
+            Ok(()) => panic!("Expected a crash report"),
+        };
+
+        assert_eq!(report.context, "tools/call");
+        assert_eq!(report.message, "boom");
+        assert!(!report.incident_id.is_empty());
+        assert!(!report.backtrace.is_empty());
+        assert_eq!(sink.0.lock().unwrap().len(), 1);
+
+        let error = ErrorData::from(report.clone());
+        assert_eq!(error.code, INTERNAL_ERROR);
+        assert_eq!(
+            error.data,
+            Some(json!({ "incidentId": report.incident_id }))
+        );
+
+        // The reporter keeps working after a panic.
+        assert_eq!(reporter.catch("ping", async { 42 }).await, Ok(42));
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_appends_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crashes.jsonl");
+        let reporter = CrashReporter::new().with_sink(FileCrashSink::new(&path));
+
+        for _ in 0..2 {
+            let _ = reporter
+                .catch("resources/read", async { panic!("{}", "formatted") })
+                .await;
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let reports: Vec<CrashReport> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].message, "formatted");
+        assert_ne!(reports[0].incident_id, reports[1].incident_id);
+    }
+}
@@ -0,0 +1,144 @@
+/// Static credentials that outgoing HTTP requests carry in a header.
+///
+/// For remote endpoints that only need a token, not an OAuth flow, [`Credentials`] describe
+/// the headers to add to every request: a bearer token, an API key in a header of the
+/// endpoint's choosing, or any other set of headers. They are shared through a
+/// [`CredentialsHandle`], so a token can be rotated while requests are being sent:
+///
+/// ```text
+/// let docs = HttpResourceProvider::new().with_credentials(Credentials::bearer(token));
+/// let credentials = docs.credentials().unwrap();
+/// // later, once the token was refreshed
+/// credentials.rotate(Credentials::bearer(new_token));
+/// ```
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// The headers that authenticate requests to a remote endpoint.
+#[derive(Clone, PartialEq)]
+pub enum Credentials {
+    /// Sent as `Authorization: Bearer <token>`
+    Bearer(String),
+    /// Sent as `<header>: <value>`, e.g. `X-Api-Key: <key>`
+    ApiKey { header: String, value: String },
+    /// Sent as they are
+    Headers(Vec<(String, String)>),
+}
+
+impl Credentials {
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Credentials::Bearer(token.into())
+    }
+
+    pub fn api_key(header: impl Into<String>, value: impl Into<String>) -> Self {
+        Credentials::ApiKey {
+            header: header.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn headers<K: Into<String>, V: Into<String>>(
+        headers: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        Credentials::Headers(
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        )
+    }
+
+    /// Returns the headers to add to a request, as names and values.
+    pub fn to_headers(&self) -> Vec<(String, String)> {
+        match self {
+            Credentials::Bearer(token) => {
+                vec![("Authorization".to_string(), format!("Bearer {}", token))]
+            }
+            Credentials::ApiKey { header, value } => vec![(header.clone(), value.clone())],
+            Credentials::Headers(headers) => headers.clone(),
+        }
+    }
+}
+
+/// Leaves the secrets out, so credentials can be part of logged configuration.
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Bearer(_) => f.write_str("Bearer(***)"),
+            Credentials::ApiKey { header, .. } => write!(f, "ApiKey({}: ***)", header),
+            Credentials::Headers(headers) => f
+                .debug_list()
+                .entries(headers.iter().map(|(name, _)| format!("{}: ***", name)))
+                .finish(),
+        }
+    }
+}
+
+/// Shared credentials, read for every request and replaceable at any time.
+#[derive(Debug, Clone)]
+pub struct CredentialsHandle {
+    current: Arc<RwLock<Credentials>>,
+}
+
+impl CredentialsHandle {
+    pub fn new(credentials: Credentials) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(credentials)),
+        }
+    }
+
+    /// Replaces the credentials. Requests sent from now on carry `credentials`.
+    pub fn rotate(&self, credentials: Credentials) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = credentials;
+    }
+
+    /// Returns the current credentials.
+    pub fn current(&self) -> Credentials {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Returns the headers of the current credentials, see [`Credentials::to_headers`].
+    pub fn headers(&self) -> Vec<(String, String)> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .to_headers()
+    }
+}
+
+impl From<Credentials> for CredentialsHandle {
+    fn from(credentials: Credentials) -> Self {
+        Self::new(credentials)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credentials_become_headers_and_rotate() {
+        let handle = CredentialsHandle::new(Credentials::bearer("first"));
+        let shared = handle.clone();
+        assert_eq!(
+            shared.headers(),
+            vec![("Authorization".to_string(), "Bearer first".to_string())]
+        );
+        handle.rotate(Credentials::api_key("X-Api-Key", "second"));
+        assert_eq!(
+            shared.headers(),
+            vec![("X-Api-Key".to_string(), "second".to_string())]
+        );
+
+        let headers = Credentials::headers([("X-Tenant", "acme"), ("X-Token", "secret")]);
+        assert_eq!(headers.to_headers().len(), 2);
+        assert_eq!(
+            format!("{:?}", headers),
+            r#"["X-Tenant: ***", "X-Token: ***"]"#
+        );
+        assert_eq!(format!("{:?}", handle.current()), "ApiKey(X-Api-Key: ***)");
+    }
+}
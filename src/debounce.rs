@@ -0,0 +1,169 @@
+/// Coalescing bursts of `list_changed` notifications.
+///
+/// Clients usually relist whenever they hear that a list changed, so registering a hundred
+/// tools in a loop, or a watcher reporting a thousand changed files, has them relist a hundred
+/// or a thousand times. With a list-changed window, each session holds such notifications
+/// back: the first change opens the window, further changes while it is open are folded into
+/// it, and when it closes the session is sent one notification for every list that changed.
+///
+/// ```text
+/// let server = Server::new(info).with_list_changed_window(Duration::from_millis(200));
+/// ```
+///
+/// Without a window, every change is announced right away. Held-back notifications are sent
+/// after the messages queued while they were held back, such as log messages.
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::outbound::{LaneKey, Outbound};
+use crate::protocol::{JsonRpcMessage, JsonRpcNotification};
+
+/// The `list_changed` notifications of one session.
+pub(crate) struct ListChanges {
+    outbound: Arc<Outbound>,
+    window: Duration,
+    /// The notifications held back until the window closes, one per method
+    pending: Arc<Mutex<Vec<JsonRpcNotification>>>,
+}
+
+impl ListChanges {
+    pub(crate) fn new(outbound: Arc<Outbound>, window: Duration) -> Self {
+        ListChanges {
+            outbound,
+            window,
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Sends `notification`, or holds it back until the window closes. The returned future
+    /// resolves once it was handed to the transport, or right away if it was held back.
+    pub(crate) fn notify(
+        &self,
+        notification: JsonRpcNotification,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let delivery = match self.window.is_zero() {
+            true => Some(
+                self.outbound
+                    .send_ordered(LaneKey::Session, JsonRpcMessage::Notification(notification)),
+            ),
+            false => {
+                self.hold(notification);
+                None
+            }
+        };
+        async move {
+            if let Some(delivery) = delivery {
+                let _ = delivery.await;
+            }
+        }
+    }
+
+    fn hold(&self, notification: JsonRpcNotification) {
+        let mut pending = lock(&self.pending);
+        if pending
+            .iter()
+            .any(|held| held.method == notification.method)
+        {
+            return;
+        }
+        pending.push(notification);
+        if pending.len() > 1 {
+            return;
+        }
+        // The first change opens the window.
+        let outbound = self.outbound.clone();
+        let held = self.pending.clone();
+        let window = self.window;
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let notifications = std::mem::take(&mut *lock(&held));
+            for notification in notifications {
+                let message = JsonRpcMessage::Notification(notification);
+                let _ = outbound.send_ordered(LaneKey::Session, message).await;
+            }
+        });
+    }
+}
+
+fn lock(
+    pending: &Mutex<Vec<JsonRpcNotification>>,
+) -> std::sync::MutexGuard<'_, Vec<JsonRpcNotification>> {
+    pending.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::handshake::{InitializeRequest, client_handshake};
+    use crate::protocol::{
+        ClientCapabilities, Implementation, InitializeRequestParams, JSONRPC_VERSION,
+        JsonRpcMessage, JsonRpcRequest, RequestId,
+    };
+    use crate::server::Server;
+    use crate::tool::{CallToolResult, Tool};
+    use crate::transport::{MemoryTransport, Transport};
+    use serde_json::{Map, Value};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_bursts_of_changes_are_announced_once() {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"))
+            .with_list_changed_window(Duration::from_millis(100));
+        let handler =
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("ok")) };
+        server.add_tool(Tool::builder().name("search").build(), handler);
+        let handle = server.handle();
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.clone().serve(server_side));
+        client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation::new("test-client", "1.0.0"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        // Once the ping is answered, the session is attached and hears of changes.
+        client
+            .send(JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(1),
+                method: "ping".to_string(),
+                params: None,
+            }))
+            .await
+            .unwrap();
+        client.receive().await.unwrap();
+
+        for name in ["fetch", "summarize", "translate"] {
+            server.add_tool(Tool::builder().name(name).build(), handler);
+            handle.notify_prompts_list_changed().await;
+            tokio::task::yield_now().await;
+        }
+
+        let mut notified = Vec::new();
+        for _ in 0..2 {
+            let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+            else {
+                panic!("Expected a notification");
+            };
+            notified.push(notification.method);
+        }
+        notified.sort();
+        assert_eq!(
+            notified,
+            [
+                "notifications/prompts/list_changed",
+                "notifications/tools/list_changed"
+            ]
+        );
+        let next = tokio::time::timeout(Duration::from_millis(200), client.receive()).await;
+        assert!(next.is_err());
+    }
+}
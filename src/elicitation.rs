@@ -0,0 +1,182 @@
+/// Structured input that servers ask users for through clients
+use std::future::Future;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::McpError;
+
+/// Parameters of an elicitation/create request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitRequestParams {
+    /// The message to present to the user
+    pub message: String,
+
+    /// A JSON Schema of the object the user is asked for. The protocol restricts it to a
+    /// flat object of string, number, integer, boolean and enum properties
+    pub requested_schema: Value,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// How the user responded to an elicitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitAction {
+    /// The user submitted the requested input
+    Accept,
+    /// The user explicitly declined to provide it
+    Decline,
+    /// The user dismissed the request without choosing
+    Cancel,
+}
+
+/// The client's response to an elicitation/create request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitResult {
+    /// The user's response
+    pub action: ElicitAction,
+
+    /// The submitted input, matching the requested schema. Only present when accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<Map<String, Value>>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl ElicitResult {
+    /// The user submitted `content`.
+    pub fn accept(content: Map<String, Value>) -> Self {
+        Self {
+            action: ElicitAction::Accept,
+            content: Some(content),
+            meta: None,
+        }
+    }
+
+    /// The user declined to provide the input.
+    pub fn decline() -> Self {
+        Self {
+            action: ElicitAction::Decline,
+            content: None,
+            meta: None,
+        }
+    }
+
+    /// The user dismissed the request.
+    pub fn cancel() -> Self {
+        Self {
+            action: ElicitAction::Cancel,
+            content: None,
+            meta: None,
+        }
+    }
+}
+
+/// Answers the `elicitation/create` requests of servers on the client, by presenting the
+/// form described by [`ElicitRequestParams::requested_schema`] to the user.
+#[async_trait]
+pub trait ElicitationHandler: Send + Sync {
+    async fn elicit(&self, params: ElicitRequestParams) -> Result<ElicitResult, McpError>;
+}
+
+#[async_trait]
+impl<F, Fut> ElicitationHandler for F
+where
+    F: Fn(ElicitRequestParams) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<ElicitResult, McpError>> + Send,
+{
+    async fn elicit(&self, params: ElicitRequestParams) -> Result<ElicitResult, McpError> {
+        self(params).await
+    }
+}
+
+/// The outcome of an elicitation, with the accepted input deserialized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Elicitation<T> {
+    Accepted(T),
+    Declined,
+    Cancelled,
+}
+
+impl<T> Elicitation<T> {
+    /// The accepted input, if the user accepted.
+    pub fn accepted(self) -> Option<T> {
+        match self {
+            Elicitation::Accepted(input) => Some(input),
+            Elicitation::Declined | Elicitation::Cancelled => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::protocol::{ClientCapabilities, Implementation};
+    use crate::server::{RequestContext, Server};
+    use crate::tool::{CallToolResult, Tool};
+    use crate::transport::MemoryTransport;
+    use serde_json::json;
+
+    #[test]
+    fn test_results_round_trip() {
+        let accepted = json!({ "action": "accept", "content": { "name": "Ada", "age": 36 } });
+        let result: ElicitResult = serde_json::from_value(accepted.clone()).unwrap();
+        assert_eq!(result.action, ElicitAction::Accept);
+        assert_eq!(serde_json::to_value(&result).unwrap(), accepted);
+
+        let declined: ElicitResult =
+            serde_json::from_value(json!({ "action": "decline" })).unwrap();
+        assert_eq!(declined.action, ElicitAction::Decline);
+        assert!(declined.content.is_none());
+    }
+
+    #[derive(Deserialize, schemars::JsonSchema)]
+    struct Contact {
+        email: String,
+    }
+
+    #[tokio::test]
+    async fn test_client_answers_elicitations() {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        server.add_tool(
+            Tool::builder().name("invite").build(),
+            |_: Option<Map<String, Value>>, context: RequestContext| async move {
+                let invited = match context.elicit::<Contact>("Who should be invited?").await? {
+                    Elicitation::Accepted(contact) => format!("Invited {}", contact.email),
+                    Elicitation::Declined | Elicitation::Cancelled => "Nobody invited".to_string(),
+                };
+                Ok(CallToolResult::text(invited))
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let client = Client::connect(
+            client,
+            Implementation::new("test-client", "1.0.0"),
+            ClientCapabilities::default().with_elicitation(),
+        )
+        .await
+        .unwrap()
+        .with_elicitation(|params: ElicitRequestParams| async move {
+            assert_eq!(params.message, "Who should be invited?");
+            assert_eq!(
+                params.requested_schema["properties"]["email"]["type"],
+                "string"
+            );
+            let content = json!({ "email": "ada@example.com" });
+            Ok(ElicitResult::accept(content.as_object().unwrap().clone()))
+        });
+
+        let result = client.call_tool("invite", ()).await.unwrap();
+        assert_eq!(result, CallToolResult::text("Invited ada@example.com"));
+    }
+}
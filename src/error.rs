@@ -0,0 +1,232 @@
+/// A single error type for handler code.
+///
+/// Handlers deal with several error types: [`ProtocolError`] from parsing and transports,
+/// [`ResourceError`] and [`PromptError`] from the resource and prompt helpers, and raw
+/// [`ErrorData`] from peers. [`McpError`] converts from all of them, so a handler can use `?`
+/// throughout and return one type that maps directly onto a JSON-RPC error response.
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::crash::CrashReport;
+use crate::prompt::PromptError;
+use crate::protocol::{
+    CONNECTION_CLOSED, ErrorData, FORBIDDEN, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST,
+    JSONRPC_VERSION, JsonRpcError, JsonRpcMessage, METHOD_NOT_FOUND, PARSE_ERROR, ProtocolError,
+    REQUEST_TIMEOUT, RESOURCE_NOT_FOUND, RequestId, SERVER_BUSY, USER_REJECTED,
+};
+use crate::resource::ResourceError;
+
+/// An error that is reported to the peer as a JSON-RPC error response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpError {
+    error: ErrorData,
+}
+
+impl McpError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        McpError {
+            error: ErrorData {
+                code,
+                message: message.into(),
+                data: None,
+            },
+        }
+    }
+
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self::new(PARSE_ERROR, message)
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(INVALID_REQUEST, message)
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+
+    pub fn internal_error(message: impl Into<String>) -> Self {
+        Self::new(INTERNAL_ERROR, message)
+    }
+
+    pub fn connection_closed(message: impl Into<String>) -> Self {
+        Self::new(CONNECTION_CLOSED, message)
+    }
+
+    pub fn request_timeout(message: impl Into<String>) -> Self {
+        Self::new(REQUEST_TIMEOUT, message)
+    }
+
+    /// A handler ran out of time after `elapsed`, which is included in the error data.
+    pub fn timed_out(elapsed: Duration) -> Self {
+        Self::new(
+            REQUEST_TIMEOUT,
+            format!("Request timed out after {:?}", elapsed),
+        )
+        .with_data(json!({ "elapsedMs": elapsed.as_millis() }))
+    }
+
+    /// The session is not allowed to make the request.
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(FORBIDDEN, message)
+    }
+
+    /// The user declined the request.
+    pub fn user_rejected(message: impl Into<String>) -> Self {
+        Self::new(USER_REJECTED, message)
+    }
+
+    /// The server is handling as many requests as its limits allow.
+    pub fn server_busy(message: impl Into<String>) -> Self {
+        Self::new(SERVER_BUSY, message)
+    }
+
+    /// The request needs a capability the peer did not advertise. The capability, e.g.
+    /// `resources.subscribe`, and the method are included in the error data.
+    pub fn capability_not_supported(capability: &str, method: &str) -> Self {
+        ProtocolError::CapabilityNotSupported {
+            capability: capability.to_string(),
+            method: Some(method.to_string()),
+        }
+        .into()
+    }
+
+    /// A resource that does not exist was requested. The URI is included in the error data.
+    pub fn resource_not_found(uri: &str) -> Self {
+        Self::new(RESOURCE_NOT_FOUND, "Resource not found").with_data(json!({ "uri": uri }))
+    }
+
+    /// Attaches structured data, replacing any data set before.
+    ///
+    /// Data that fails to serialize is dropped.
+    pub fn with_data(mut self, data: impl Serialize) -> Self {
+        self.error.data = serde_json::to_value(data).ok();
+        self
+    }
+
+    pub fn code(&self) -> i32 {
+        self.error.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.error.message
+    }
+
+    pub fn data(&self) -> Option<&serde_json::Value> {
+        self.error.data.as_ref()
+    }
+
+    /// Builds the error response to the request with the given id.
+    pub fn into_response(self, id: RequestId) -> JsonRpcMessage {
+        JsonRpcMessage::Error(JsonRpcError {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(id),
+            error: self.error,
+        })
+    }
+}
+
+impl fmt::Display for McpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.error.message, self.error.code)
+    }
+}
+
+impl std::error::Error for McpError {}
+
+impl From<ErrorData> for McpError {
+    fn from(error: ErrorData) -> Self {
+        McpError { error }
+    }
+}
+
+impl From<McpError> for ErrorData {
+    fn from(error: McpError) -> Self {
+        error.error
+    }
+}
+
+impl From<ProtocolError> for McpError {
+    fn from(error: ProtocolError) -> Self {
+        ErrorData::from(error).into()
+    }
+}
+
+impl From<ResourceError> for McpError {
+    fn from(error: ResourceError) -> Self {
+        match error {
+            ResourceError::NotFound => Self::new(RESOURCE_NOT_FOUND, error.to_string()),
+            ResourceError::InvalidUri(_) | ResourceError::InvalidFilePath => {
+                Self::invalid_params(error.to_string())
+            }
+            ResourceError::Protocol(error) => error.into(),
+            ResourceError::Other(message) => Self::internal_error(message),
+        }
+    }
+}
+
+impl From<PromptError> for McpError {
+    fn from(error: PromptError) -> Self {
+        match error {
+            PromptError::InvalidParameters(message) => Self::invalid_params(message),
+            PromptError::NotFound(_) => Self::invalid_params(error.to_string()),
+            PromptError::Protocol(error) => error.into(),
+            PromptError::Other(message) => Self::internal_error(message),
+        }
+    }
+}
+
+impl From<CrashReport> for McpError {
+    fn from(report: CrashReport) -> Self {
+        ErrorData::from(report).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(uri: &str) -> Result<String, McpError> {
+        if uri.is_empty() {
+            Err(ProtocolError::InvalidParams("Missing uri".to_string()))?;
+        }
+        if uri.starts_with("file://") {
+            Err(ResourceError::NotFound)?;
+        }
+        Err(McpError::resource_not_found(uri))
+    }
+
+    #[test]
+    fn test_conversions_and_codes() {
+        assert_eq!(read("").unwrap_err().code(), INVALID_PARAMS);
+        assert_eq!(read("file:///a").unwrap_err().code(), RESOURCE_NOT_FOUND);
+
+        let error = read("str:///b").unwrap_err();
+        assert_eq!(error.data(), Some(&json!({ "uri": "str:///b" })));
+        assert_eq!(error.to_string(), "Resource not found (code -32002)");
+
+        let prompt_error: McpError = PromptError::Other("boom".to_string()).into();
+        assert_eq!(prompt_error.code(), INTERNAL_ERROR);
+
+        let JsonRpcMessage::Error(response) = McpError::method_not_found("foo/bar")
+            .with_data(json!({ "hint": "check spelling" }))
+            .into_response(RequestId::Number(5))
+        else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(response.id, Some(RequestId::Number(5)));
+        assert_eq!(response.error.code, METHOD_NOT_FOUND);
+        assert_eq!(response.error.message, "Method not found: foo/bar");
+        assert_eq!(
+            response.error.data,
+            Some(json!({ "hint": "check spelling" }))
+        );
+    }
+}
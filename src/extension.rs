@@ -0,0 +1,226 @@
+/// Non-standard methods that extend the protocol.
+///
+/// An extension method is described once by a type implementing [`ExtensionRequest`]: the
+/// type is the request's parameters and names the method and its result. Servers register a
+/// handler for it in an [`ExtensionRegistry`], which answers matching requests and advertises
+/// the extension under the `experimental` server capabilities. Clients send it with
+/// [`Client::request_extension`](crate::client::Client::request_extension).
+///
+/// ```text
+/// x-myorg/flush   {"target": "cache"}   ->   {"flushed": 12}
+/// ```
+///
+/// Extension method names should be namespaced, e.g. `x-myorg/flush`, so they cannot clash
+/// with methods added to the specification later.
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::error::McpError;
+use crate::protocol::{
+    JSONRPC_VERSION, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse, ProtocolError, RequestId,
+    ServerCapabilities, from_params,
+};
+
+/// A non-standard request, identified by its method name.
+pub trait ExtensionRequest: Serialize + DeserializeOwned + Send + 'static {
+    /// The JSON-RPC method name, e.g. `x-myorg/flush`
+    const METHOD: &'static str;
+
+    /// The name advertised under the `experimental` capabilities when a handler is
+    /// registered, or `None` to not advertise the method.
+    const CAPABILITY: Option<&'static str> = None;
+
+    /// The result the peer answers with.
+    type Result: Serialize + DeserializeOwned + Send + 'static;
+
+    /// Wraps these parameters into a JSON-RPC request with the given id.
+    fn into_request(self, id: RequestId) -> Result<JsonRpcRequest, ProtocolError> {
+        Ok(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method: Self::METHOD.to_string(),
+            params: Some(
+                serde_json::to_value(self)
+                    .map_err(|e| ProtocolError::InternalError(e.to_string()))?,
+            ),
+        })
+    }
+}
+
+type Handler =
+    Arc<dyn Fn(Option<Value>) -> BoxFuture<'static, Result<Value, McpError>> + Send + Sync>;
+
+/// Handlers for extension methods and the capabilities they advertise.
+#[derive(Clone, Default)]
+pub struct ExtensionRegistry {
+    handlers: HashMap<String, Handler>,
+    capabilities: HashMap<String, Value>,
+}
+
+impl fmt::Debug for ExtensionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtensionRegistry")
+            .field("methods", &self.handlers.keys().collect::<Vec<_>>())
+            .field("capabilities", &self.capabilities)
+            .finish()
+    }
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler for extension `E`, replacing any handler registered before.
+    ///
+    /// If `E` names a capability, it is advertised with an empty object unless it was
+    /// advertised explicitly.
+    pub fn register<E, F, Fut>(&mut self, handler: F) -> &mut Self
+    where
+        E: ExtensionRequest,
+        F: Fn(E) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<E::Result, McpError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let erased: Handler = Arc::new(move |params| {
+            let handler = handler.clone();
+            async move {
+                let params: E = from_params(params)?;
+                let result = handler(params).await?;
+                serde_json::to_value(result).map_err(|e| McpError::internal_error(e.to_string()))
+            }
+            .boxed()
+        });
+        self.handlers.insert(E::METHOD.to_string(), erased);
+        if let Some(name) = E::CAPABILITY {
+            self.capabilities
+                .entry(name.to_string())
+                .or_insert_with(|| json!({}));
+        }
+        self
+    }
+
+    /// Advertises `capability` under `name` in the `experimental` server capabilities.
+    pub fn advertise(&mut self, name: impl Into<String>, capability: Value) -> &mut Self {
+        self.capabilities.insert(name.into(), capability);
+        self
+    }
+
+    /// Returns `true` if a handler is registered for `method`.
+    pub fn handles(&self, method: &str) -> bool {
+        self.handlers.contains_key(method)
+    }
+
+    /// The capabilities to advertise under `experimental`.
+    pub fn capabilities(&self) -> &HashMap<String, Value> {
+        &self.capabilities
+    }
+
+    /// Adds the advertised capabilities to `capabilities.experimental`.
+    pub fn advertise_in(&self, capabilities: &mut ServerCapabilities) {
+        if self.capabilities.is_empty() {
+            return;
+        }
+        capabilities
+            .experimental
+            .get_or_insert_with(HashMap::new)
+            .extend(self.capabilities.clone());
+    }
+
+    /// Runs the handler registered for `request` and returns the response to send back, or
+    /// `None` if no handler is registered for its method.
+    pub async fn handle(&self, request: JsonRpcRequest) -> Option<JsonRpcMessage> {
+        let handler = self.handlers.get(&request.method)?.clone();
+        Some(match handler(request.params).await {
+            Ok(result) => JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: request.id,
+                result: Some(result),
+                error: None,
+            }),
+            Err(error) => error.into_response(request.id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::INVALID_PARAMS;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Flush {
+        target: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Flushed {
+        flushed: u32,
+    }
+
+    impl ExtensionRequest for Flush {
+        const METHOD: &'static str = "x-myorg/flush";
+        const CAPABILITY: Option<&'static str> = Some("x-myorg/flush");
+        type Result = Flushed;
+    }
+
+    #[tokio::test]
+    async fn test_registered_extension_is_handled_and_advertised() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(|params: Flush| async move {
+            match params.target.as_str() {
+                "cache" => Ok(Flushed { flushed: 12 }),
+                other => Err(McpError::invalid_params(format!(
+                    "Unknown target: {}",
+                    other
+                ))),
+            }
+        });
+        assert!(registry.handles("x-myorg/flush"));
+
+        let request = Flush {
+            target: "cache".to_string(),
+        }
+        .into_request(RequestId::Number(1))
+        .unwrap();
+        let Some(JsonRpcMessage::Response(response)) = registry.handle(request).await else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.result, Some(json!({ "flushed": 12 })));
+
+        let request = JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(2),
+            method: "x-myorg/flush".to_string(),
+            params: Some(json!({ "target": 1 })),
+        };
+        let Some(JsonRpcMessage::Error(error)) = registry.handle(request).await else {
+            panic!("Expected an error");
+        };
+        assert_eq!(error.error.code, INVALID_PARAMS);
+
+        let request = JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(3),
+            method: "x-myorg/other".to_string(),
+            params: None,
+        };
+        assert!(registry.handle(request).await.is_none());
+
+        let mut capabilities = ServerCapabilities::default();
+        registry.advertise_in(&mut capabilities);
+        assert_eq!(
+            capabilities.experimental.unwrap()["x-myorg/flush"],
+            json!({})
+        );
+    }
+}
@@ -0,0 +1,418 @@
+/// Resources backed by the files in a directory.
+///
+/// [`FileSystemResourceProvider`] offers every file under its root as a `file://` resource.
+/// Files that are valid UTF-8 are read as text, all others as base64 blobs. URIs that point
+/// outside of the root, or at files that do not exist, are not found.
+///
+/// With the `watch` feature, [`FileSystemResourceProvider::watch`] makes the provider watch
+/// its root with the `notify` crate: clients can subscribe to files, and are sent
+/// `notifications/resources/updated` when a file they subscribed to changes and
+/// `notifications/resources/list_changed` when files are created, removed or renamed.
+/// Changes are collected over a short window and announced once, so bulk writes do not flood
+/// clients with notifications.
+use std::path::{Path, PathBuf};
+#[cfg(feature = "watch")]
+use std::sync::Mutex;
+#[cfg(feature = "watch")]
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+#[cfg(feature = "watch")]
+use tokio::sync::mpsc;
+use url::Url;
+
+use crate::pagination::{DEFAULT_PAGE_SIZE, paginate};
+use crate::protocol::{Cursor, PaginatedRequestParams};
+use crate::resource::{
+    ListResourcesResult, Resource, ResourceContent, ResourceError, ResourceProvider,
+};
+#[cfg(feature = "watch")]
+use crate::server::SubscriptionHandle;
+
+/// How long changes are collected before they are announced, unless chosen with
+/// [`FileSystemResourceProvider::watch`].
+#[cfg(feature = "watch")]
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Offers the files under a directory as resources.
+pub struct FileSystemResourceProvider {
+    root: PathBuf,
+    #[cfg(feature = "watch")]
+    watching: Option<Watching>,
+}
+
+/// A watch on the root, and the changes it reports until a server takes them.
+#[cfg(feature = "watch")]
+struct Watching {
+    debounce: Duration,
+    events: Mutex<Option<mpsc::UnboundedReceiver<notify::Event>>>,
+    // Stops watching when the provider is dropped.
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl std::fmt::Debug for FileSystemResourceProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("FileSystemResourceProvider");
+        debug.field("root", &self.root);
+        #[cfg(feature = "watch")]
+        debug.field(
+            "debounce",
+            &self.watching.as_ref().map(|watching| watching.debounce),
+        );
+        debug.finish()
+    }
+}
+
+impl FileSystemResourceProvider {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FileSystemResourceProvider {
+            root: root.into(),
+            #[cfg(feature = "watch")]
+            watching: None,
+        }
+    }
+
+    /// Starts watching the root for changes, which are announced once a server takes the
+    /// provider, after collecting them for `debounce`, e.g. [`DEFAULT_DEBOUNCE`].
+    ///
+    /// Fails if the root cannot be watched, e.g. because it does not exist.
+    #[cfg(feature = "watch")]
+    pub fn watch(mut self, debounce: Duration) -> Result<Self, ResourceError> {
+        use notify::Watcher;
+
+        let (sender, events) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    let _ = sender.send(event);
+                }
+            })
+            .map_err(|e| ResourceError::Other(e.to_string()))?;
+        watcher
+            .watch(&self.root(), notify::RecursiveMode::Recursive)
+            .map_err(|e| ResourceError::Other(e.to_string()))?;
+        self.watching = Some(Watching {
+            debounce,
+            events: Mutex::new(Some(events)),
+            _watcher: watcher,
+        });
+        Ok(self)
+    }
+
+    /// The root with symlinks resolved, so paths under it compare equal to the paths the
+    /// file system reports.
+    fn root(&self) -> PathBuf {
+        std::fs::canonicalize(&self.root).unwrap_or_else(|_| self.root.clone())
+    }
+
+    /// Resolves `uri` to a file under the root.
+    fn path(&self, uri: &str) -> Result<PathBuf, ResourceError> {
+        let path = Url::parse(uri)?
+            .to_file_path()
+            .map_err(|_| ResourceError::InvalidFilePath)?;
+        let path = std::fs::canonicalize(path).map_err(|_| ResourceError::NotFound)?;
+        if path.starts_with(self.root()) && path.is_file() {
+            Ok(path)
+        } else {
+            Err(ResourceError::NotFound)
+        }
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for FileSystemResourceProvider {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+        let root = self.root();
+        let files = tokio::task::spawn_blocking(move || files(&root))
+            .await
+            .map_err(|e| ResourceError::Other(e.to_string()))?
+            .map_err(|e| ResourceError::Other(e.to_string()))?;
+        let request = PaginatedRequestParams { cursor, meta: None };
+        let (page, next_cursor) = paginate(&files, &request, DEFAULT_PAGE_SIZE)?;
+        let resources = page
+            .iter()
+            .filter_map(|path| Url::from_file_path(path).ok())
+            .map(|uri| {
+                Resource::builder()
+                    .name_from_uri(uri.clone())
+                    .uri(uri)
+                    .build()
+            })
+            .collect();
+        Ok(ListResourcesResult {
+            resources,
+            next_cursor,
+            meta: None,
+        })
+    }
+
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+        let path = self.path(uri)?;
+        let bytes = tokio::task::spawn_blocking(move || std::fs::read(path))
+            .await
+            .map_err(|e| ResourceError::Other(e.to_string()))?
+            .map_err(|_| ResourceError::NotFound)?;
+        let content = match String::from_utf8(bytes) {
+            Ok(text) => ResourceContent::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(mime::TEXT_PLAIN.to_string()),
+                text,
+                meta: None,
+            },
+            Err(error) => ResourceContent::BlobResourceContent {
+                uri: uri.to_string(),
+                mime_type: Some(mime::APPLICATION_OCTET_STREAM.to_string()),
+                blob: BASE64_STANDARD.encode(error.into_bytes()),
+                meta: None,
+            },
+        };
+        Ok(vec![content])
+    }
+
+    #[cfg(feature = "watch")]
+    fn supports_subscriptions(&self) -> bool {
+        self.watching.is_some()
+    }
+
+    #[cfg(feature = "watch")]
+    fn supports_list_changed(&self) -> bool {
+        self.watching.is_some()
+    }
+
+    #[cfg(feature = "watch")]
+    fn attach(&self, subscriptions: SubscriptionHandle) {
+        let Some(watching) = &self.watching else {
+            return;
+        };
+        let events = watching
+            .events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(events) = events {
+            tokio::spawn(announce_changes(events, watching.debounce, subscriptions));
+        }
+    }
+
+    async fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        self.path(uri).map(|_| ())
+    }
+}
+
+/// Every file under `root`, in a stable order.
+fn files(root: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut directories = vec![root.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                directories.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Announces the changes collected over each `debounce` window, starting with the first
+/// change after the last window, until the watcher is dropped.
+#[cfg(feature = "watch")]
+async fn announce_changes(
+    mut events: mpsc::UnboundedReceiver<notify::Event>,
+    debounce: Duration,
+    subscriptions: SubscriptionHandle,
+) {
+    while let Some(event) = events.recv().await {
+        let mut changes = Changes::default();
+        changes.add(event);
+        let window = tokio::time::sleep(debounce);
+        tokio::pin!(window);
+        loop {
+            tokio::select! {
+                _ = &mut window => break,
+                event = events.recv() => match event {
+                    Some(event) => changes.add(event),
+                    None => break,
+                },
+            }
+        }
+        changes.announce(&subscriptions).await;
+    }
+}
+
+/// The changes collected over one debounce window.
+#[cfg(feature = "watch")]
+#[derive(Default)]
+struct Changes {
+    updated: std::collections::BTreeSet<PathBuf>,
+    list_changed: bool,
+}
+
+#[cfg(feature = "watch")]
+impl Changes {
+    fn add(&mut self, event: notify::Event) {
+        use notify::EventKind;
+        use notify::event::ModifyKind;
+
+        match event.kind {
+            EventKind::Create(_)
+            | EventKind::Remove(_)
+            | EventKind::Modify(ModifyKind::Name(_)) => {
+                self.list_changed = true;
+            }
+            EventKind::Modify(_) | EventKind::Any => {}
+            EventKind::Access(_) | EventKind::Other => return,
+        }
+        self.updated.extend(event.paths);
+    }
+
+    async fn announce(self, subscriptions: &SubscriptionHandle) {
+        if self.list_changed {
+            subscriptions.notify_list_changed().await;
+        }
+        for path in self.updated {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if subscriptions.is_subscribed(uri.as_str()) {
+                subscriptions.notify_updated(uri).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn uri(path: &Path) -> String {
+        Url::from_file_path(std::fs::canonicalize(path).unwrap())
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_files_are_listed_and_read() {
+        let root = TempDir::new().unwrap();
+        std::fs::create_dir(root.path().join("notes")).unwrap();
+        std::fs::write(root.path().join("notes/todo.txt"), "Water the plants").unwrap();
+        std::fs::write(root.path().join("logo.png"), [0x89, 0x50, 0xff]).unwrap();
+        let provider = FileSystemResourceProvider::new(root.path());
+
+        let listed = provider.list(None).await.unwrap();
+        let names: Vec<_> = listed.resources.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["logo.png", "todo.txt"]);
+
+        let todo = uri(&root.path().join("notes/todo.txt"));
+        let contents = provider.read(&todo).await.unwrap();
+        let [ResourceContent::TextResourceContents { text, .. }] = contents.as_slice() else {
+            panic!("Expected text");
+        };
+        assert_eq!(text, "Water the plants");
+        let logo = uri(&root.path().join("logo.png"));
+        let contents = provider.read(&logo).await.unwrap();
+        let [ResourceContent::BlobResourceContent { blob, .. }] = contents.as_slice() else {
+            panic!("Expected a blob");
+        };
+        assert_eq!(blob, "iVD/");
+
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "hidden").unwrap();
+        for uri in [
+            uri(&outside.path().join("secret.txt")),
+            format!("{}/missing.txt", uri(root.path())),
+        ] {
+            assert!(matches!(
+                provider.read(&uri).await,
+                Err(ResourceError::NotFound)
+            ));
+        }
+    }
+
+    #[cfg(feature = "watch")]
+    #[tokio::test]
+    async fn test_watched_changes_are_announced() {
+        use crate::protocol::{
+            ClientCapabilities, Implementation, InitializeRequestParams, JSONRPC_VERSION,
+            JsonRpcMessage, JsonRpcRequest, RequestId,
+        };
+        use crate::server::Server;
+        use crate::transport::{MemoryTransport, Transport};
+        use serde_json::json;
+
+        let root = TempDir::new().unwrap();
+        let todo = root.path().join("todo.txt");
+        std::fs::write(&todo, "Water the plants").unwrap();
+        let server = Server::new(Implementation::new("test-server", "1.0.0")).with_resources(
+            FileSystemResourceProvider::new(root.path())
+                .watch(Duration::from_millis(100))
+                .unwrap(),
+        );
+        let capabilities = server.capabilities().resources.unwrap();
+        assert_eq!(
+            (capabilities.subscribe, capabilities.list_changed),
+            (Some(true), Some(true))
+        );
+
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        crate::handshake::client_handshake(
+            &client,
+            crate::handshake::InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation::new("test-client", "1.0.0"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        let request = |id, method: &str, params| {
+            JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(id),
+                method: method.to_string(),
+                params,
+            })
+        };
+        let todo_uri = uri(&todo);
+        client
+            .send(request(
+                1,
+                "resources/subscribe",
+                Some(json!({ "uri": todo_uri })),
+            ))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(_)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+
+        for line in ["Water", "the", "plants"] {
+            std::fs::write(&todo, line).unwrap();
+        }
+        let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+        else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "notifications/resources/updated");
+        assert_eq!(notification.params, Some(json!({ "uri": todo_uri })));
+
+        std::fs::write(root.path().join("done.txt"), "Nothing yet").unwrap();
+        let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+        else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "notifications/resources/list_changed");
+    }
+}
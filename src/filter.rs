@@ -0,0 +1,254 @@
+/// Hiding parts of a server from some of its clients.
+///
+/// A [`SurfaceFilter`] decides which tools, prompts and resources a session can see. Tools
+/// and prompts are matched by name, resources by URI, and resource templates by URI
+/// template, against glob patterns where `*` matches any run of characters and `?` any one:
+///
+/// ```text
+/// let server = Server::new(info)
+///     .with_tools(tools)
+///     .with_session_filter(|auth: &AuthContext| {
+///         if auth.has_scope("admin") {
+///             SurfaceFilter::new()
+///         } else {
+///             SurfaceFilter::new().deny_tools(["admin_*"]).allow_resources(["file:///public/*"])
+///         }
+///     });
+/// ```
+///
+/// Filtered items are left out of `tools/list`, `prompts/list`, `resources/list` and
+/// `resources/templates/list`, so pages may hold fewer items than the page size. Requests for
+/// them, and for completions of their arguments, are answered as if they did not exist.
+use serde_json::Value;
+
+use crate::auth::target;
+use crate::error::McpError;
+
+/// Allowed and denied glob patterns for one kind of item.
+///
+/// A name passes if it matches one of the allowed patterns, or there are none, and none of
+/// the denied ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameFilter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl NameFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Passes only names matching one of `patterns`, or of the patterns allowed before.
+    pub fn allow<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.allow.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Stops names matching one of `patterns`.
+    pub fn deny<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.deny.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn passes(&self, name: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|pattern| glob_match(pattern, name)))
+            && !self.deny.iter().any(|pattern| glob_match(pattern, name))
+    }
+}
+
+/// What a session can see of a server. The default passes everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SurfaceFilter {
+    pub tools: NameFilter,
+    pub prompts: NameFilter,
+    /// Matched against the URIs of resources and the URI templates of resource templates
+    pub resources: NameFilter,
+}
+
+impl SurfaceFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow_tools<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.tools = self.tools.allow(patterns);
+        self
+    }
+
+    pub fn deny_tools<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.tools = self.tools.deny(patterns);
+        self
+    }
+
+    pub fn allow_prompts<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.prompts = self.prompts.allow(patterns);
+        self
+    }
+
+    pub fn deny_prompts<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.prompts = self.prompts.deny(patterns);
+        self
+    }
+
+    pub fn allow_resources<S: Into<String>>(
+        mut self,
+        patterns: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.resources = self.resources.allow(patterns);
+        self
+    }
+
+    pub fn deny_resources<S: Into<String>>(
+        mut self,
+        patterns: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.resources = self.resources.deny(patterns);
+        self
+    }
+
+    /// The error answering a request for a filtered item, or `None` if the request passes.
+    pub(crate) fn check(&self, method: &str, params: Option<&Value>) -> Option<McpError> {
+        let name = target(method, params)?;
+        let kind = match method {
+            "completion/complete" => params?.get("ref")?.get("type")?.as_str()?,
+            method => method,
+        };
+        match kind {
+            "tools/call" if !self.tools.passes(name) => {
+                Some(McpError::invalid_params(format!("Unknown tool: {}", name)))
+            }
+            "prompts/get" | "ref/prompt" if !self.prompts.passes(name) => Some(
+                McpError::invalid_params(format!("Prompt not found: {}", name)),
+            ),
+            "resources/read" | "resources/subscribe" | "resources/unsubscribe" | "ref/resource"
+                if !self.resources.passes(name) =>
+            {
+                Some(McpError::resource_not_found(name))
+            }
+            _ => None,
+        }
+    }
+
+    /// Removes filtered items from the result of a list request.
+    pub(crate) fn hide(&self, method: &str, result: &mut Value) {
+        let (items, key, filter) = match method {
+            "tools/list" => ("tools", "name", &self.tools),
+            "prompts/list" => ("prompts", "name", &self.prompts),
+            "resources/list" => ("resources", "uri", &self.resources),
+            "resources/templates/list" => ("resourceTemplates", "uriTemplate", &self.resources),
+            _ => return,
+        };
+        if let Some(Value::Array(items)) = result.get_mut(items) {
+            items.retain(|item| {
+                item.get(key)
+                    .and_then(Value::as_str)
+                    .is_none_or(|name| filter.passes(name))
+            });
+        }
+    }
+}
+
+/// Matches `name` against `pattern`, where `*` matches any run of characters and `?` any one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and the part of the name it matched up to.
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("search", "search"));
+        assert!(glob_match("admin_*", "admin_drop"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match(
+            "file:///*/notes.md",
+            "file:///home/ada/notes.md"
+        ));
+        assert!(glob_match("v?", "v2"));
+        assert!(!glob_match("v?", "v10"));
+        assert!(!glob_match("admin_*", "search"));
+        assert!(!glob_match("*.md", "notes.txt"));
+    }
+
+    #[test]
+    fn test_filtered_items_are_hidden_and_blocked() {
+        let filter = SurfaceFilter::new()
+            .deny_tools(["admin_*"])
+            .allow_resources(["file:///public/*"]);
+
+        let mut tools = json!({ "tools": [{ "name": "search" }, { "name": "admin_drop" }] });
+        filter.hide("tools/list", &mut tools);
+        assert_eq!(tools, json!({ "tools": [{ "name": "search" }] }));
+        let mut resources = json!({
+            "resources": [
+                { "uri": "file:///public/readme.md", "name": "readme" },
+                { "uri": "file:///private/keys", "name": "keys" }
+            ]
+        });
+        filter.hide("resources/list", &mut resources);
+        assert_eq!(resources["resources"].as_array().unwrap().len(), 1);
+
+        let call = json!({ "name": "admin_drop" });
+        assert_eq!(
+            filter.check("tools/call", Some(&call)).unwrap().message(),
+            "Unknown tool: admin_drop"
+        );
+        assert!(
+            filter
+                .check("tools/call", Some(&json!({ "name": "search" })))
+                .is_none()
+        );
+        let read = json!({ "uri": "file:///private/keys" });
+        assert!(filter.check("resources/read", Some(&read)).is_some());
+    }
+
+    #[test]
+    fn test_completions_of_filtered_items_are_blocked() {
+        let filter = SurfaceFilter::new()
+            .deny_prompts(["admin_*"])
+            .allow_resources(["file:///public/*"]);
+        let complete = |reference: Value| {
+            let params = json!({ "ref": reference, "argument": { "name": "a", "value": "" } });
+            filter.check("completion/complete", Some(&params))
+        };
+
+        let hidden = complete(json!({ "type": "ref/prompt", "name": "admin_reset" }));
+        assert_eq!(hidden.unwrap().message(), "Prompt not found: admin_reset");
+        assert!(complete(json!({ "type": "ref/prompt", "name": "greet" })).is_none());
+        assert!(
+            complete(json!({ "type": "ref/resource", "uri": "file:///private/{name}" })).is_some()
+        );
+        assert!(
+            complete(json!({ "type": "ref/resource", "uri": "file:///public/{name}" })).is_none()
+        );
+    }
+}
@@ -0,0 +1,284 @@
+/// Tunnelling MCP over a bidirectional gRPC stream, for deployments behind gRPC-only ingress.
+///
+/// [`McpGrpcService`] is a tonic service with a single streaming method, `mcp.Mcp/Connect`.
+/// Every frame of the stream carries one JSON-RPC message encoded as JSON, so no protobuf
+/// definitions are involved. Each call becomes a [`GrpcTransport`] handed out by the
+/// [`GrpcListener`], and is served like any other [`Transport`]:
+///
+/// ```text
+/// let (service, mut listener) = McpGrpcService::new();
+/// tokio::spawn(tonic::transport::Server::builder().add_service(service).serve(addr));
+/// while let Some(transport) = listener.accept().await {
+///     tokio::spawn(serve_session(transport));
+/// }
+/// ```
+use std::convert::Infallible;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use bytes::{Buf, BufMut};
+use tokio::sync::{Mutex, mpsc};
+use tonic::body::Body;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
+use tonic::codegen::{BoxFuture, Service, StdError, http};
+use tonic::server::{Grpc, NamedService};
+use tonic::{Status, Streaming};
+
+use crate::protocol::{JsonRpcMessage, ProtocolError};
+use crate::transport::Transport;
+
+/// Fully qualified name of the gRPC service.
+pub const GRPC_SERVICE_NAME: &str = "mcp.Mcp";
+
+/// Path of the bidirectional streaming method carrying the session.
+pub const GRPC_CONNECT_PATH: &str = "/mcp.Mcp/Connect";
+
+/// Number of outgoing messages buffered per stream before `send` waits for the peer.
+const OUTBOUND_BUFFER: usize = 32;
+
+/// Encodes every gRPC frame as one JSON-RPC message in JSON.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonRpcCodec;
+
+impl Codec for JsonRpcCodec {
+    type Encode = JsonRpcMessage;
+    type Decode = JsonRpcMessage;
+    type Encoder = JsonRpcCodec;
+    type Decoder = JsonRpcCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        JsonRpcCodec
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        JsonRpcCodec
+    }
+}
+
+impl Encoder for JsonRpcCodec {
+    type Item = JsonRpcMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        serde_json::to_writer(dst.writer(), &item)
+            .map_err(|e| Status::internal(format!("Failed to encode message: {}", e)))
+    }
+}
+
+impl Decoder for JsonRpcCodec {
+    type Item = JsonRpcMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        serde_json::from_reader(src.reader())
+            .map(Some)
+            .map_err(|e| Status::invalid_argument(format!("Invalid JSON-RPC message: {}", e)))
+    }
+}
+
+/// One MCP session carried by a gRPC stream.
+pub struct GrpcTransport {
+    inbound: Mutex<Streaming<JsonRpcMessage>>,
+    outbound: Mutex<Option<mpsc::Sender<Result<JsonRpcMessage, Status>>>>,
+}
+
+#[async_trait]
+impl Transport for GrpcTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        let sender =
+            self.outbound.lock().await.clone().ok_or_else(|| {
+                ProtocolError::TransportError("gRPC stream is closed".to_string())
+            })?;
+        sender
+            .send(Ok(message))
+            .await
+            .map_err(|_| ProtocolError::TransportError("gRPC peer closed the stream".to_string()))
+    }
+
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+        self.inbound.lock().await.message().await.map_err(|status| {
+            ProtocolError::TransportError(format!(
+                "Failed to receive message: {}",
+                status.message()
+            ))
+        })
+    }
+
+    /// Ends the response stream, which finishes the call with an OK status.
+    async fn close(&self) -> Result<(), ProtocolError> {
+        self.outbound.lock().await.take();
+        Ok(())
+    }
+}
+
+/// Hands out the sessions opened against an [`McpGrpcService`].
+pub struct GrpcListener {
+    connections: mpsc::UnboundedReceiver<GrpcTransport>,
+}
+
+impl GrpcListener {
+    /// Waits for the next session. Returns `None` once every clone of the service is dropped.
+    pub async fn accept(&mut self) -> Option<GrpcTransport> {
+        self.connections.recv().await
+    }
+}
+
+/// A tonic service exposing MCP sessions as bidirectional gRPC streams.
+#[derive(Clone)]
+pub struct McpGrpcService {
+    connections: mpsc::UnboundedSender<GrpcTransport>,
+}
+
+impl McpGrpcService {
+    /// Creates the service and the listener receiving its sessions.
+    pub fn new() -> (Self, GrpcListener) {
+        let (connections, receiver) = mpsc::unbounded_channel();
+        (
+            McpGrpcService { connections },
+            GrpcListener {
+                connections: receiver,
+            },
+        )
+    }
+}
+
+impl NamedService for McpGrpcService {
+    const NAME: &'static str = GRPC_SERVICE_NAME;
+}
+
+impl<B> Service<http::Request<B>> for McpGrpcService
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        if request.uri().path() != GRPC_CONNECT_PATH {
+            return Box::pin(async { Ok(Status::unimplemented("Unknown method").into_http()) });
+        }
+
+        let connect = Connect {
+            connections: self.connections.clone(),
+        };
+        Box::pin(async move { Ok(Grpc::new(JsonRpcCodec).streaming(connect, request).await) })
+    }
+}
+
+/// Turns one incoming stream into a [`GrpcTransport`] for the listener.
+struct Connect {
+    connections: mpsc::UnboundedSender<GrpcTransport>,
+}
+
+impl Service<tonic::Request<Streaming<JsonRpcMessage>>> for Connect {
+    type Response = tonic::Response<ReceiverStream<Result<JsonRpcMessage, Status>>>;
+    type Error = Status;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: tonic::Request<Streaming<JsonRpcMessage>>) -> Self::Future {
+        let (sender, receiver) = mpsc::channel(OUTBOUND_BUFFER);
+        let transport = GrpcTransport {
+            inbound: Mutex::new(request.into_inner()),
+            outbound: Mutex::new(Some(sender)),
+        };
+        let accepted = self.connections.send(transport).is_ok();
+        Box::pin(async move {
+            if accepted {
+                Ok(tonic::Response::new(ReceiverStream::new(receiver)))
+            } else {
+                Err(Status::unavailable("MCP server is not accepting sessions"))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{JSONRPC_VERSION, JsonRpcRequest, JsonRpcResponse, RequestId};
+    use serde_json::json;
+    use tokio::net::TcpListener;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::codegen::tokio_stream::StreamExt;
+    use tonic::codegen::tokio_stream::wrappers::TcpListenerStream;
+
+    #[tokio::test]
+    async fn test_session_over_grpc_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (service, mut sessions) = McpGrpcService::new();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        // Echo server: answers every request with its own params.
+        tokio::spawn(async move {
+            let transport = sessions.accept().await.unwrap();
+            while let Some(JsonRpcMessage::Request(request)) = transport.receive().await.unwrap() {
+                transport
+                    .send(JsonRpcMessage::Response(JsonRpcResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        id: request.id,
+                        result: request.params,
+                        error: None,
+                    }))
+                    .await
+                    .unwrap();
+            }
+            transport.close().await.unwrap();
+        });
+
+        let channel = tonic::transport::Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = tonic::client::Grpc::new(channel);
+        client.ready().await.unwrap();
+
+        let requests = (1..=3).map(|id| {
+            JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(id),
+                method: "echo".to_string(),
+                params: Some(json!({ "n": id })),
+            })
+        });
+        let mut responses = client
+            .streaming(
+                tonic::Request::new(tonic::codegen::tokio_stream::iter(requests)),
+                PathAndQuery::from_static(GRPC_CONNECT_PATH),
+                JsonRpcCodec,
+            )
+            .await
+            .unwrap()
+            .into_inner();
+
+        let mut results = Vec::new();
+        while let Some(message) = responses.next().await {
+            let JsonRpcMessage::Response(response) = message.unwrap() else {
+                panic!("Expected a response");
+            };
+            results.push((response.id, response.result.unwrap()));
+        }
+        assert_eq!(
+            results,
+            (1..=3)
+                .map(|id| (RequestId::Number(id), json!({ "n": id })))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,363 @@
+/// The initialize handshake that opens every MCP session.
+///
+/// The client sends an `initialize` request, the server answers with the protocol version it
+/// picked together with its own capabilities, and the client confirms with a
+/// `notifications/initialized` notification. [`client_handshake`] and [`server_handshake`] run
+/// that exchange on either side of a transport and return what was learned about the peer.
+/// Only pings may be exchanged before the handshake completes.
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::ping::ping_response;
+use crate::protocol::{
+    ClientCapabilities, ErrorData, INVALID_REQUEST, Implementation, InitializeRequestParams,
+    InitializeResult, JSONRPC_VERSION, JsonRpcError, JsonRpcMessage, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, ProtocolError, ProtocolVersion, RequestId, ServerCapabilities,
+    from_params,
+};
+use crate::transport::Transport;
+
+/// Method name of the initialize request.
+pub const INITIALIZE_METHOD: &str = "initialize";
+
+/// Method name of the notification that completes the handshake.
+pub const INITIALIZED_NOTIFICATION: &str = "notifications/initialized";
+
+/// The request a client sends to start a session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitializeRequest {
+    pub id: RequestId,
+    pub params: InitializeRequestParams,
+}
+
+impl InitializeRequest {
+    pub fn into_request(self) -> Result<JsonRpcRequest, ProtocolError> {
+        Ok(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: self.id,
+            method: INITIALIZE_METHOD.to_string(),
+            params: Some(
+                serde_json::to_value(self.params)
+                    .map_err(|e| ProtocolError::InternalError(e.to_string()))?,
+            ),
+        })
+    }
+}
+
+impl TryFrom<JsonRpcRequest> for InitializeRequest {
+    type Error = ProtocolError;
+
+    fn try_from(request: JsonRpcRequest) -> Result<Self, Self::Error> {
+        if request.method != INITIALIZE_METHOD {
+            return Err(ProtocolError::ProtocolError(format!(
+                "Expected {}, got {}",
+                INITIALIZE_METHOD, request.method
+            )));
+        }
+        Ok(InitializeRequest {
+            id: request.id,
+            params: from_params(request.params)?,
+        })
+    }
+}
+
+/// Sent by the client once it has processed the initialize result.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct InitializedNotification {
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl InitializedNotification {
+    pub fn into_notification(self) -> JsonRpcNotification {
+        JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: INITIALIZED_NOTIFICATION.to_string(),
+            params: self.meta.map(|meta| json!({ "_meta": meta })),
+        }
+    }
+}
+
+/// What the handshake established about the peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handshake<C> {
+    /// The protocol version both sides use for the rest of the session
+    pub protocol_version: ProtocolVersion,
+    /// Name and version of the peer's implementation
+    pub peer_info: Implementation,
+    pub peer_capabilities: C,
+    /// The peer's capabilities object exactly as sent, including unknown members
+    pub raw_capabilities: Value,
+    /// Instructions sent by the server. Always `None` on the server side
+    pub instructions: Option<String>,
+}
+
+/// Runs the client side of the handshake: sends `request`, validates the server's answer and
+/// confirms with `notifications/initialized`.
+///
+/// Fails if the server picked a protocol version this crate does not support.
+pub async fn client_handshake<T: Transport + ?Sized>(
+    transport: &T,
+    request: InitializeRequest,
+) -> Result<Handshake<ServerCapabilities>, ProtocolError> {
+    let id = request.id.clone();
+    transport
+        .send(JsonRpcMessage::Request(request.into_request()?))
+        .await?;
+
+    let result = loop {
+        match receive(transport).await? {
+            JsonRpcMessage::Response(response) if response.id == id => {
+                break response.result.unwrap_or(Value::Null);
+            }
+            JsonRpcMessage::Error(error) if error.id.as_ref() == Some(&id) => {
+                return Err(ProtocolError::ErrorResponse(error.error));
+            }
+            JsonRpcMessage::Request(request) => {
+                answer_before_initialized(transport, request).await?
+            }
+            _ => continue,
+        }
+    };
+
+    let raw_capabilities = result.get("capabilities").cloned().unwrap_or(json!({}));
+    let result: InitializeResult = serde_json::from_value(result)
+        .map_err(|e| ProtocolError::ParseError(format!("Invalid initialize result: {}", e)))?;
+    let protocol_version: ProtocolVersion = match result.protocol_version.parse() {
+        Ok(version) => version,
+        Err(error) => {
+            let _ = transport.close().await;
+            return Err(error);
+        }
+    };
+
+    transport
+        .send(JsonRpcMessage::Notification(
+            InitializedNotification::default().into_notification(),
+        ))
+        .await?;
+
+    Ok(Handshake {
+        protocol_version,
+        peer_info: result.server_info,
+        peer_capabilities: result.capabilities,
+        raw_capabilities,
+        instructions: result.instructions,
+    })
+}
+
+/// Runs the server side of the handshake: waits for the client's initialize request, answers
+/// with the negotiated protocol version and `result`, and waits for `notifications/initialized`.
+///
+/// The protocol version in `result` is replaced by the negotiated one.
+pub async fn server_handshake<T: Transport + ?Sized>(
+    transport: &T,
+    mut result: InitializeResult,
+) -> Result<Handshake<ClientCapabilities>, ProtocolError> {
+    let request = loop {
+        match receive(transport).await? {
+            JsonRpcMessage::Request(request) if request.method == INITIALIZE_METHOD => {
+                let id = request.id.clone();
+                let raw_capabilities = request
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.get("capabilities").cloned())
+                    .unwrap_or(json!({}));
+                match InitializeRequest::try_from(request) {
+                    Ok(request) => break (request, raw_capabilities),
+                    Err(error) => {
+                        let error = ErrorData::from(error);
+                        send_error(transport, id, error.clone()).await?;
+                        return Err(ProtocolError::InvalidParams(error.message));
+                    }
+                }
+            }
+            JsonRpcMessage::Request(request) => {
+                answer_before_initialized(transport, request).await?
+            }
+            _ => continue,
+        }
+    };
+    let (InitializeRequest { id, params }, raw_capabilities) = request;
+
+    let protocol_version = ProtocolVersion::negotiate(&params.protocol_version);
+    result.protocol_version = protocol_version.to_string();
+    let result =
+        serde_json::to_value(result).map_err(|e| ProtocolError::InternalError(e.to_string()))?;
+    transport
+        .send(JsonRpcMessage::Response(JsonRpcResponse {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }))
+        .await?;
+
+    loop {
+        match receive(transport).await? {
+            JsonRpcMessage::Notification(notification)
+                if notification.method == INITIALIZED_NOTIFICATION =>
+            {
+                break;
+            }
+            JsonRpcMessage::Request(request) => {
+                answer_before_initialized(transport, request).await?
+            }
+            _ => continue,
+        }
+    }
+
+    Ok(Handshake {
+        protocol_version,
+        peer_info: params.client_info,
+        peer_capabilities: params.capabilities,
+        raw_capabilities,
+        instructions: None,
+    })
+}
+
+async fn receive<T: Transport + ?Sized>(transport: &T) -> Result<JsonRpcMessage, ProtocolError> {
+    transport.receive().await?.ok_or_else(|| {
+        ProtocolError::TransportError("Connection closed during initialization".to_string())
+    })
+}
+
+/// Answers pings and rejects every other request received before the session is initialized.
+async fn answer_before_initialized<T: Transport + ?Sized>(
+    transport: &T,
+    request: JsonRpcRequest,
+) -> Result<(), ProtocolError> {
+    if let Some(pong) = ping_response(&request) {
+        return transport.send(pong).await;
+    }
+    send_error(
+        transport,
+        request.id,
+        ErrorData {
+            code: INVALID_REQUEST,
+            message: format!("Received {} before initialization", request.method),
+            data: None,
+        },
+    )
+    .await
+}
+
+async fn send_error<T: Transport + ?Sized>(
+    transport: &T,
+    id: RequestId,
+    error: ErrorData,
+) -> Result<(), ProtocolError> {
+    transport
+        .send(JsonRpcMessage::Error(JsonRpcError {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(id),
+            error,
+        }))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ToolsCapability;
+    use crate::transport::MemoryTransport;
+
+    #[tokio::test]
+    async fn test_handshake_on_both_sides() {
+        let (client, server) = MemoryTransport::pair();
+
+        let server_task = tokio::spawn(async move {
+            let handshake = server_handshake(
+                &server,
+                InitializeResult {
+                    protocol_version: String::new(),
+                    capabilities: ServerCapabilities::builder()
+                        .tools(ToolsCapability {
+                            list_changed: Some(true),
+                        })
+                        .build(),
+                    server_info: Implementation::new("test-server", "1.0.0"),
+                    instructions: Some("Be nice".to_string()),
+                    meta: None,
+                },
+            )
+            .await
+            .unwrap();
+            (server, handshake)
+        });
+
+        // A ping before initialization is answered; anything else is refused.
+        client
+            .send(JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(0),
+                method: "ping".to_string(),
+                params: None,
+            }))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(pong)) = client.receive().await.unwrap() else {
+            panic!("Expected a ping response");
+        };
+        assert_eq!(pong.id, RequestId::Number(0));
+
+        let handshake = client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(1),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-03-26".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation::new("test-client", "0.1.0"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        let (_server, server_side) = server_task.await.unwrap();
+
+        assert_eq!(handshake.protocol_version, ProtocolVersion::V2025_03_26);
+        assert_eq!(handshake.peer_info.name, "test-server");
+        assert_eq!(handshake.instructions.as_deref(), Some("Be nice"));
+        assert!(handshake.peer_capabilities.tools.is_some());
+
+        assert_eq!(server_side.protocol_version, ProtocolVersion::V2025_03_26);
+        assert_eq!(server_side.peer_info.name, "test-client");
+        assert_eq!(server_side.peer_capabilities, ClientCapabilities::default());
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_requests_before_initialize() {
+        let (client, server) = MemoryTransport::pair();
+        tokio::spawn(async move {
+            let _ = server_handshake(
+                &server,
+                InitializeResult {
+                    protocol_version: String::new(),
+                    capabilities: ServerCapabilities::default(),
+                    server_info: Implementation::new("test-server", "1.0.0"),
+                    instructions: None,
+                    meta: None,
+                },
+            )
+            .await;
+        });
+
+        client
+            .send(JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(7),
+                method: "tools/list".to_string(),
+                params: None,
+            }))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(error.id, Some(RequestId::Number(7)));
+        assert_eq!(error.error.code, INVALID_REQUEST);
+    }
+}
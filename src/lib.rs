@@ -1,7 +1,84 @@
 pub mod protocol;
+pub mod error;
 pub mod transport;
 pub mod resource;
+pub mod filesystem;
+pub mod memory;
+pub mod composite;
+#[cfg(feature = "http")]
+pub mod remote;
+pub mod uri_template;
 pub mod prompt;
+pub mod tool;
+pub mod completion;
+pub mod logging;
+#[cfg(feature = "tracing")]
+pub mod log_layer;
+pub mod sampling;
+pub mod roots;
+pub mod elicitation;
+pub mod crash;
+pub mod outbound;
+pub mod debounce;
+pub mod client;
+pub mod approval;
+pub mod pool;
+pub mod reconnect;
+pub mod server;
+pub mod state;
+pub mod limit;
+pub mod auth;
+pub mod credentials;
+pub mod filter;
+pub mod mount;
+pub mod reload;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod progress;
+pub mod partial;
+pub mod cancellation;
+pub mod pagination;
+pub mod schema;
+pub mod timeout;
+pub mod retry;
+pub mod pending;
+pub mod capability;
+pub mod handshake;
+pub mod ping;
+pub mod validation;
+pub mod compat;
+pub mod extension;
+pub mod canonical;
+pub mod raw;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod trace;
+pub mod lifecycle;
+#[cfg(test)]
+mod conformance;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(unix)]
+pub mod broker;
+#[cfg(feature = "streamable-http")]
+pub mod streamable;
+#[cfg(feature = "streamable-http")]
+pub mod stateless;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "actix")]
+pub mod actix;
+
+#[cfg(feature = "macros")]
+pub use mcp_ox_macros::tool;
+
+// Lets code generated by the macros refer to this crate as `::mcp_ox` from within it.
+extern crate self as mcp_ox;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -0,0 +1,287 @@
+/// Enforcement of the session lifecycle ordering.
+///
+/// An MCP session starts uninitialized. The client's `initialize` request moves it to
+/// initializing, and the client's `notifications/initialized` completes initialization. Until
+/// then only the handshake itself and pings may be exchanged:
+///
+/// - a server rejects every other request with an `INVALID_REQUEST` error, as well as a second
+///   `initialize` once the session is initialized
+/// - a client refuses to send anything but `initialize`, pings and responses before it sent
+///   `notifications/initialized`
+/// - a server refuses to send requests other than pings before initialization completes
+///
+/// [`LifecycleTransport`] applies these rules to every message passing through a transport.
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::handshake::{INITIALIZE_METHOD, INITIALIZED_NOTIFICATION};
+use crate::ping::PING_METHOD;
+use crate::protocol::{
+    ErrorData, INVALID_REQUEST, JSONRPC_VERSION, JsonRpcError, JsonRpcMessage, JsonRpcRequest,
+    ProtocolError,
+};
+use crate::transport::Transport;
+
+/// Which side of the session this end is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// Where a session is in its lifecycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LifecycleState {
+    /// No initialize request has been exchanged yet
+    #[default]
+    Uninitialized,
+    /// The initialize request was exchanged, `notifications/initialized` was not
+    Initializing,
+    /// The handshake is complete and any message may be exchanged
+    Initialized,
+}
+
+/// The lifecycle state machine of one end of a session.
+#[derive(Debug)]
+pub struct Lifecycle {
+    role: Role,
+    state: Mutex<LifecycleState>,
+}
+
+impl Lifecycle {
+    pub fn new(role: Role) -> Self {
+        Lifecycle {
+            role,
+            state: Mutex::new(LifecycleState::default()),
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn state(&self) -> LifecycleState {
+        *self.lock()
+    }
+
+    /// Checks a message about to be sent and advances the state.
+    pub fn check_outgoing(&self, message: &JsonRpcMessage) -> Result<(), ProtocolError> {
+        let mut state = self.lock();
+        if *state == LifecycleState::Initialized {
+            return Ok(());
+        }
+        let method = match message {
+            JsonRpcMessage::Request(request) => &request.method,
+            JsonRpcMessage::Notification(notification) => &notification.method,
+            _ => return Ok(()),
+        };
+
+        let allowed = match (self.role, message) {
+            (_, JsonRpcMessage::Request(_)) if method == PING_METHOD => true,
+            (Role::Client, JsonRpcMessage::Request(_)) => {
+                *state == LifecycleState::Uninitialized && method == INITIALIZE_METHOD
+            }
+            (Role::Client, _) => {
+                *state == LifecycleState::Initializing && method == INITIALIZED_NOTIFICATION
+            }
+            (Role::Server, JsonRpcMessage::Request(_)) => false,
+            (Role::Server, _) => true,
+        };
+        if !allowed {
+            return Err(ProtocolError::ProtocolError(format!(
+                "Cannot send {} before the session is initialized",
+                method
+            )));
+        }
+
+        if self.role == Role::Client {
+            if method == INITIALIZE_METHOD {
+                *state = LifecycleState::Initializing;
+            } else if method == INITIALIZED_NOTIFICATION {
+                *state = LifecycleState::Initialized;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a received message and advances the state.
+    ///
+    /// A rejected request yields the error response to send back to the peer.
+    pub fn check_incoming(&self, message: &JsonRpcMessage) -> Result<(), JsonRpcError> {
+        if self.role == Role::Client {
+            return Ok(());
+        }
+        let mut state = self.lock();
+        match message {
+            JsonRpcMessage::Request(request) if request.method == PING_METHOD => Ok(()),
+            JsonRpcMessage::Request(request) if request.method == INITIALIZE_METHOD => {
+                if *state != LifecycleState::Uninitialized {
+                    return Err(rejection(
+                        request,
+                        "The session is already initialized".to_string(),
+                    ));
+                }
+                *state = LifecycleState::Initializing;
+                Ok(())
+            }
+            JsonRpcMessage::Request(request) if *state != LifecycleState::Initialized => {
+                Err(rejection(
+                    request,
+                    format!("Received {} before initialization", request.method),
+                ))
+            }
+            JsonRpcMessage::Notification(notification)
+                if notification.method == INITIALIZED_NOTIFICATION =>
+            {
+                if *state == LifecycleState::Initializing {
+                    *state = LifecycleState::Initialized;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LifecycleState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn rejection(request: &JsonRpcRequest, message: String) -> JsonRpcError {
+    JsonRpcError {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        id: Some(request.id.clone()),
+        error: ErrorData {
+            code: INVALID_REQUEST,
+            message,
+            data: None,
+        },
+    }
+}
+
+/// A transport that enforces the session lifecycle on every message it carries.
+///
+/// Requests received out of order are answered with an error and skipped; messages sent out
+/// of order fail with [`ProtocolError::ProtocolError`] without reaching the peer.
+pub struct LifecycleTransport<T> {
+    inner: T,
+    lifecycle: Lifecycle,
+}
+
+impl<T: Transport> LifecycleTransport<T> {
+    pub fn new(inner: T, role: Role) -> Self {
+        LifecycleTransport {
+            inner,
+            lifecycle: Lifecycle::new(role),
+        }
+    }
+
+    pub fn state(&self) -> LifecycleState {
+        self.lifecycle.state()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for LifecycleTransport<T> {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        self.lifecycle.check_outgoing(&message)?;
+        self.inner.send(message).await
+    }
+
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+        loop {
+            let Some(message) = self.inner.receive().await? else {
+                return Ok(None);
+            };
+            match self.lifecycle.check_incoming(&message) {
+                Ok(()) => return Ok(Some(message)),
+                Err(error) => self.inner.send(JsonRpcMessage::Error(error)).await?,
+            }
+        }
+    }
+
+    async fn close(&self) -> Result<(), ProtocolError> {
+        self.inner.close().await
+    }
+
+    fn session_id(&self) -> Option<String> {
+        self.inner.session_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{JsonRpcNotification, RequestId};
+    use crate::transport::MemoryTransport;
+
+    fn request(id: i64, method: &str) -> JsonRpcMessage {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(id),
+            method: method.to_string(),
+            params: None,
+        })
+    }
+
+    fn initialized() -> JsonRpcMessage {
+        JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: INITIALIZED_NOTIFICATION.to_string(),
+            params: None,
+        })
+    }
+
+    #[test]
+    fn test_client_cannot_send_before_initialized() {
+        let lifecycle = Lifecycle::new(Role::Client);
+        assert!(lifecycle.check_outgoing(&request(1, "tools/list")).is_err());
+        assert!(lifecycle.check_outgoing(&initialized()).is_err());
+        assert!(lifecycle.check_outgoing(&request(2, PING_METHOD)).is_ok());
+
+        lifecycle
+            .check_outgoing(&request(3, INITIALIZE_METHOD))
+            .unwrap();
+        assert_eq!(lifecycle.state(), LifecycleState::Initializing);
+        assert!(lifecycle.check_outgoing(&request(4, "tools/list")).is_err());
+
+        lifecycle.check_outgoing(&initialized()).unwrap();
+        assert_eq!(lifecycle.state(), LifecycleState::Initialized);
+        assert!(lifecycle.check_outgoing(&request(5, "tools/list")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_requests_before_initialized() {
+        let (client, server) = MemoryTransport::pair();
+        let server = LifecycleTransport::new(server, Role::Server);
+
+        client.send(request(1, "tools/list")).await.unwrap();
+        client.send(request(2, INITIALIZE_METHOD)).await.unwrap();
+        let Some(JsonRpcMessage::Request(received)) = server.receive().await.unwrap() else {
+            panic!("Expected the initialize request");
+        };
+        assert_eq!(received.method, INITIALIZE_METHOD);
+        let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(error.id, Some(RequestId::Number(1)));
+        assert_eq!(error.error.code, INVALID_REQUEST);
+
+        // Servers may only ping before the client confirmed initialization.
+        assert!(server.send(request(1, "roots/list")).await.is_err());
+
+        client.send(initialized()).await.unwrap();
+        client.send(request(3, "tools/list")).await.unwrap();
+        assert!(server.receive().await.unwrap().is_some());
+        let Some(JsonRpcMessage::Request(received)) = server.receive().await.unwrap() else {
+            panic!("Expected the tools/list request");
+        };
+        assert_eq!(received.method, "tools/list");
+        assert_eq!(server.state(), LifecycleState::Initialized);
+    }
+}
@@ -0,0 +1,211 @@
+/// Limits on how many requests a server handles at once.
+///
+/// Without limits every request a client sends starts its handler right away, so a client
+/// flooding the server with tool calls can exhaust the host. [`ConcurrencyLimits`] caps the
+/// handlers running for one session and across all sessions of a server:
+///
+/// ```text
+/// let server = Server::new(info)
+///     .with_tools(tools)
+///     .with_concurrency_limits(
+///         ConcurrencyLimits::builder()
+///             .per_session(4)
+///             .global(64)
+///             .overflow(Overflow::Queue { max_waiting: 16 })
+///             .build(),
+///     );
+/// ```
+///
+/// A request over a limit either waits for a running one to finish or is rejected with a
+/// `SERVER_BUSY` error, as [`Overflow`] says. Requests cancelled while waiting are dropped
+/// without a response. `ping` and `logging/setLevel` are answered outside the limits.
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bon::Builder;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::McpError;
+
+/// How many handlers may run at once.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Builder)]
+pub struct ConcurrencyLimits {
+    /// Handlers running for one session
+    pub per_session: Option<usize>,
+    /// Handlers running across all sessions of the server and its clones
+    pub global: Option<usize>,
+    /// What happens to requests over a limit
+    #[builder(default)]
+    pub overflow: Overflow,
+}
+
+/// What happens to a request arriving while a limit is reached.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// The request waits for a running one to finish. Once `max_waiting` requests wait for
+    /// the same limit, further ones are rejected.
+    Queue { max_waiting: usize },
+    /// The request is rejected with a `SERVER_BUSY` error.
+    #[default]
+    Reject,
+}
+
+/// The limits of a server, shared by its clones.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Limiter {
+    per_session: Option<usize>,
+    global: Option<Limit>,
+    overflow: Overflow,
+}
+
+impl Limiter {
+    pub(crate) fn new(limits: ConcurrencyLimits) -> Self {
+        Limiter {
+            per_session: limits.per_session,
+            global: limits.global.map(Limit::new),
+            overflow: limits.overflow,
+        }
+    }
+
+    /// The limits for a new session.
+    pub(crate) fn for_session(&self) -> SessionLimiter {
+        SessionLimiter {
+            session: self.per_session.map(Limit::new),
+            global: self.global.clone(),
+            overflow: self.overflow,
+        }
+    }
+}
+
+/// The limits that apply to the requests of one session.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SessionLimiter {
+    session: Option<Limit>,
+    global: Option<Limit>,
+    overflow: Overflow,
+}
+
+/// Permission to run a handler, given back when dropped.
+#[derive(Debug)]
+pub(crate) struct Permits {
+    _session: Option<OwnedSemaphorePermit>,
+    _global: Option<OwnedSemaphorePermit>,
+}
+
+impl SessionLimiter {
+    /// Waits until a handler may run, or fails with `SERVER_BUSY` if the request is rejected.
+    ///
+    /// The session's limit is taken first, so a session waiting on its own requests does not
+    /// hold back the others.
+    pub(crate) async fn acquire(&self) -> Result<Permits, McpError> {
+        let session = match &self.session {
+            Some(limit) => Some(
+                limit
+                    .acquire(self.overflow)
+                    .await
+                    .map_err(|_| McpError::server_busy("Too many requests from this session"))?,
+            ),
+            None => None,
+        };
+        let global = match &self.global {
+            Some(limit) => Some(
+                limit
+                    .acquire(self.overflow)
+                    .await
+                    .map_err(|_| McpError::server_busy("Too many requests on the server"))?,
+            ),
+            None => None,
+        };
+        Ok(Permits {
+            _session: session,
+            _global: global,
+        })
+    }
+}
+
+/// One limit and the requests waiting for it.
+#[derive(Debug, Clone)]
+struct Limit {
+    semaphore: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+}
+
+/// A request that was turned away.
+struct Rejected;
+
+impl Limit {
+    fn new(permits: usize) -> Self {
+        Limit {
+            semaphore: Arc::new(Semaphore::new(permits)),
+            waiting: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    async fn acquire(&self, overflow: Overflow) -> Result<OwnedSemaphorePermit, Rejected> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        let Overflow::Queue { max_waiting } = overflow else {
+            return Err(Rejected);
+        };
+        self.waiting
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |waiting| {
+                (waiting < max_waiting).then_some(waiting + 1)
+            })
+            .map_err(|_| Rejected)?;
+        let _waiting = Waiting(&self.waiting);
+        // The semaphore is never closed.
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Rejected)
+    }
+}
+
+/// A place in the queue of a limit, left when dropped.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SERVER_BUSY;
+    use futures::FutureExt;
+
+    #[tokio::test]
+    async fn test_requests_over_the_limit_are_rejected() {
+        let limiter = Limiter::new(ConcurrencyLimits::builder().per_session(1).build());
+        let session = limiter.for_session();
+        let running = session.acquire().await.unwrap();
+        assert_eq!(session.acquire().await.unwrap_err().code(), SERVER_BUSY);
+        // Other sessions have limits of their own.
+        assert!(limiter.for_session().acquire().await.is_ok());
+        drop(running);
+        assert!(session.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_requests_over_the_limit_queue() {
+        let limiter = Limiter::new(
+            ConcurrencyLimits::builder()
+                .global(1)
+                .overflow(Overflow::Queue { max_waiting: 1 })
+                .build(),
+        );
+        let (first, second) = (limiter.for_session(), limiter.for_session());
+        let running = first.acquire().await.unwrap();
+        let mut waiting = Box::pin(second.acquire());
+        assert!((&mut waiting).now_or_never().is_none());
+        assert_eq!(first.acquire().await.unwrap_err().code(), SERVER_BUSY);
+
+        drop(running);
+        assert!(waiting.await.is_ok());
+        assert!(first.acquire().await.is_ok());
+    }
+}
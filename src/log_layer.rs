@@ -0,0 +1,198 @@
+/// `tracing` events forwarded to clients as MCP log messages.
+///
+/// A server embedded in a larger program usually logs through `tracing` already.
+/// [`ClientLogLayer`] sends those events to the connected clients as
+/// `notifications/message`, alongside whatever else the program's subscriber does with them:
+///
+/// ```text
+/// let server = Server::new(info);
+/// tracing_subscriber::registry()
+///     .with(tracing_subscriber::fmt::layer())
+///     .with(ClientLogLayer::new(server.handle()))
+///     .init();
+/// ```
+///
+/// Events map to the nearest MCP level (`TRACE` and `DEBUG` to `debug`, `WARN` to
+/// `warning`), their target becomes the logger, and their fields the data: the message alone
+/// if that is all there is, an object of every field otherwise. Each client only gets the
+/// events at or above the level it chose with `logging/setLevel`.
+use serde_json::{Map, Value};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::logging::LoggingLevel;
+use crate::server::ServerHandle;
+
+/// A `tracing` layer that forwards events to the clients of a server.
+#[derive(Debug, Clone)]
+pub struct ClientLogLayer {
+    records: mpsc::UnboundedSender<Record>,
+}
+
+/// An event on its way to the clients.
+#[derive(Debug)]
+struct Record {
+    level: LoggingLevel,
+    logger: String,
+    data: Value,
+}
+
+impl ClientLogLayer {
+    /// Forwards events to the clients `handle` notifies.
+    ///
+    /// Events are delivered in order by a task spawned here, so this must be called from
+    /// within a Tokio runtime. Events are dropped once the runtime shuts down.
+    pub fn new(handle: ServerHandle) -> Self {
+        let (records, mut pending) = mpsc::unbounded_channel::<Record>();
+        tokio::spawn(async move {
+            while let Some(record) = pending.recv().await {
+                handle
+                    .log_from(record.level, Some(record.logger), record.data)
+                    .await;
+            }
+        });
+        ClientLogLayer { records }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ClientLogLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let _ = self.records.send(Record {
+            level: logging_level(*metadata.level()),
+            logger: metadata.target().to_string(),
+            data: fields.into_data(),
+        });
+    }
+}
+
+fn logging_level(level: Level) -> LoggingLevel {
+    match level {
+        Level::ERROR => LoggingLevel::Error,
+        Level::WARN => LoggingLevel::Warning,
+        Level::INFO => LoggingLevel::Info,
+        _ => LoggingLevel::Debug,
+    }
+}
+
+/// The fields of an event, as JSON.
+#[derive(Default)]
+struct Fields(Map<String, Value>);
+
+impl Fields {
+    fn into_data(mut self) -> Value {
+        match self.0.remove("message") {
+            Some(message) if self.0.is_empty() => message,
+            Some(message) => {
+                self.0.insert("message".to_string(), message);
+                Value::Object(self.0)
+            }
+            None => Value::Object(self.0),
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::{InitializeRequest, client_handshake};
+    use crate::protocol::{
+        ClientCapabilities, Implementation, InitializeRequestParams, JSONRPC_VERSION,
+        JsonRpcMessage, JsonRpcRequest, RequestId,
+    };
+    use crate::server::Server;
+    use crate::transport::{MemoryTransport, Transport};
+    use serde_json::json;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test]
+    async fn test_events_reach_clients_at_their_level() {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        let subscriber = tracing_subscriber::registry().with(ClientLogLayer::new(server.handle()));
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation::new("test-client", "1.0.0"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        client
+            .send(JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(1),
+                method: "logging/setLevel".to_string(),
+                params: Some(json!({ "level": "info" })),
+            }))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(_)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "indexer", "scanning");
+            tracing::info!(target: "indexer", "indexed");
+            tracing::warn!(target: "indexer", files = 3, slow = true, "fell behind");
+        });
+        let mut messages = Vec::new();
+        for _ in 0..2 {
+            let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+            else {
+                panic!("Expected a notification");
+            };
+            assert_eq!(notification.method, "notifications/message");
+            messages.push(notification.params.unwrap());
+        }
+        assert_eq!(
+            messages,
+            [
+                json!({ "level": "info", "logger": "indexer", "data": "indexed" }),
+                json!({
+                    "level": "warning",
+                    "logger": "indexer",
+                    "data": { "message": "fell behind", "files": 3, "slow": true }
+                }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+/// Structured log messages sent from servers to clients
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// The severity of a log message, as defined by RFC 5424
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggingLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+/// Parameters of a logging/setLevel request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLevelRequestParams {
+    /// The level of logging the client wants to receive from the server
+    pub level: LoggingLevel,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Parameters of a notifications/message notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingMessageNotificationParams {
+    /// The severity of this log message
+    pub level: LoggingLevel,
+    /// An optional name of the logger issuing this message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logger: Option<String>,
+    /// The data to be logged, such as a string message or an object
+    pub data: Value,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
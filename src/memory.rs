@@ -0,0 +1,341 @@
+/// Resources kept in memory, for servers that produce their resources themselves.
+///
+/// [`InMemoryResourceProvider`] stores text and binary resources under `str:///` URIs it
+/// mints on insertion. It is cheap to clone and every clone shares the same resources, so a
+/// server can serve one clone while the rest of the program keeps changing another:
+///
+/// ```text
+/// let store = InMemoryResourceProvider::new();
+/// let server = Server::new(info).with_resources(store.clone());
+///
+/// let uri = store.insert("build.log", "Compiling...").await;
+/// store.update(&uri, "Compiling... done").await?;
+/// ```
+///
+/// Inserting and removing resources announces `notifications/resources/list_changed`, and
+/// updating one sends `notifications/resources/updated` to the sessions subscribed to it.
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use url::Url;
+use uuid::Uuid;
+
+use crate::pagination::{DEFAULT_PAGE_SIZE, paginate};
+use crate::protocol::{Cursor, PaginatedRequestParams};
+use crate::resource::{
+    ListResourcesResult, Resource, ResourceContent, ResourceError, ResourceProvider,
+};
+use crate::server::SubscriptionHandle;
+
+/// The contents of a resource held in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryContent {
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl MemoryContent {
+    fn mime_type(&self) -> mime::Mime {
+        match self {
+            MemoryContent::Text(_) => mime::TEXT_PLAIN,
+            MemoryContent::Blob(_) => mime::APPLICATION_OCTET_STREAM,
+        }
+    }
+
+    fn to_resource_content(&self, uri: &str) -> ResourceContent {
+        let mime_type = Some(self.mime_type().to_string());
+        match self {
+            MemoryContent::Text(text) => ResourceContent::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type,
+                text: text.clone(),
+                meta: None,
+            },
+            MemoryContent::Blob(bytes) => ResourceContent::BlobResourceContent {
+                uri: uri.to_string(),
+                mime_type,
+                blob: BASE64_STANDARD.encode(bytes),
+                meta: None,
+            },
+        }
+    }
+}
+
+impl From<String> for MemoryContent {
+    fn from(text: String) -> Self {
+        MemoryContent::Text(text)
+    }
+}
+
+impl From<&str> for MemoryContent {
+    fn from(text: &str) -> Self {
+        MemoryContent::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for MemoryContent {
+    fn from(bytes: Vec<u8>) -> Self {
+        MemoryContent::Blob(bytes)
+    }
+}
+
+impl From<&[u8]> for MemoryContent {
+    fn from(bytes: &[u8]) -> Self {
+        MemoryContent::Blob(bytes.to_vec())
+    }
+}
+
+/// Serves resources held in memory.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryResourceProvider {
+    store: Arc<Mutex<Store>>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    /// The resources in the order they were inserted, so pages follow a stable order
+    resources: Vec<(Resource, MemoryContent)>,
+    subscriptions: Option<SubscriptionHandle>,
+}
+
+impl InMemoryResourceProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a resource named `name` and returns the URI minted for it.
+    pub async fn insert(
+        &self,
+        name: impl Into<String>,
+        content: impl Into<MemoryContent>,
+    ) -> String {
+        let content = content.into();
+        let uri = Url::parse(&format!("str:///{}", Uuid::new_v4().simple()))
+            .expect("minted URIs are valid");
+        let resource = Resource::builder()
+            .uri(uri)
+            .name(name)
+            .mime_type(content.mime_type())
+            .build();
+        let uri = resource.uri.clone();
+        let subscriptions = {
+            let mut store = self.lock();
+            store.resources.push((resource, content));
+            store.subscriptions.clone()
+        };
+        if let Some(subscriptions) = subscriptions {
+            subscriptions.notify_list_changed().await;
+        }
+        uri
+    }
+
+    /// Replaces the contents of the resource at `uri`.
+    pub async fn update(
+        &self,
+        uri: &str,
+        content: impl Into<MemoryContent>,
+    ) -> Result<(), ResourceError> {
+        let content = content.into();
+        let subscriptions = {
+            let mut store = self.lock();
+            let (resource, stored) = store
+                .resources
+                .iter_mut()
+                .find(|(resource, _)| resource.uri == uri)
+                .ok_or(ResourceError::NotFound)?;
+            resource.mime_type = content.mime_type().to_string();
+            *stored = content;
+            store.subscriptions.clone()
+        };
+        if let Some(subscriptions) = subscriptions {
+            subscriptions.notify_updated(uri).await;
+        }
+        Ok(())
+    }
+
+    /// Removes the resource at `uri`. Returns `false` if there is none.
+    pub async fn remove(&self, uri: &str) -> bool {
+        let subscriptions = {
+            let mut store = self.lock();
+            let before = store.resources.len();
+            store.resources.retain(|(resource, _)| resource.uri != uri);
+            if store.resources.len() == before {
+                return false;
+            }
+            store.subscriptions.clone()
+        };
+        if let Some(subscriptions) = subscriptions {
+            subscriptions.notify_list_changed().await;
+        }
+        true
+    }
+
+    /// The contents of the resource at `uri`, if there is one.
+    pub fn get(&self, uri: &str) -> Option<MemoryContent> {
+        self.lock()
+            .resources
+            .iter()
+            .find(|(resource, _)| resource.uri == uri)
+            .map(|(_, content)| content.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().resources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().resources.is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Store> {
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for InMemoryResourceProvider {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+        let resources: Vec<Resource> = self
+            .lock()
+            .resources
+            .iter()
+            .map(|(resource, _)| resource.clone())
+            .collect();
+        let request = PaginatedRequestParams { cursor, meta: None };
+        let (resources, next_cursor) = paginate(&resources, &request, DEFAULT_PAGE_SIZE)?;
+        Ok(ListResourcesResult {
+            resources,
+            next_cursor,
+            meta: None,
+        })
+    }
+
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+        let content = self.get(uri).ok_or(ResourceError::NotFound)?;
+        Ok(vec![content.to_resource_content(uri)])
+    }
+
+    fn supports_subscriptions(&self) -> bool {
+        true
+    }
+
+    fn supports_list_changed(&self) -> bool {
+        true
+    }
+
+    fn attach(&self, subscriptions: SubscriptionHandle) {
+        self.lock().subscriptions = Some(subscriptions);
+    }
+
+    async fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        self.get(uri).map(|_| ()).ok_or(ResourceError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::{InitializeRequest, client_handshake};
+    use crate::protocol::{
+        ClientCapabilities, Implementation, InitializeRequestParams, JSONRPC_VERSION,
+        JsonRpcMessage, JsonRpcRequest, RequestId,
+    };
+    use crate::server::Server;
+    use crate::transport::{MemoryTransport, Transport};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_resources_are_inserted_updated_and_removed() {
+        let store = InMemoryResourceProvider::new();
+        let log = store.insert("build.log", "Compiling...").await;
+        let image = store.insert("chart.png", vec![0x89, 0x50, 0xff]).await;
+        assert!(log.starts_with("str:///"));
+        assert_ne!(log, image);
+
+        let listed = store.list(None).await.unwrap();
+        let names: Vec<_> = listed.resources.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["build.log", "chart.png"]);
+        assert_eq!(listed.resources[1].mime_type, "application/octet-stream");
+        let contents = store.read(&image).await.unwrap();
+        let [ResourceContent::BlobResourceContent { blob, .. }] = contents.as_slice() else {
+            panic!("Expected a blob");
+        };
+        assert_eq!(blob, "iVD/");
+
+        store.update(&log, "Compiling... done").await.unwrap();
+        assert_eq!(
+            store.get(&log),
+            Some(MemoryContent::Text("Compiling... done".to_string()))
+        );
+        assert!(store.remove(&log).await);
+        assert!(!store.remove(&log).await);
+        assert!(matches!(
+            store.update(&log, "again").await,
+            Err(ResourceError::NotFound)
+        ));
+        assert!(matches!(
+            store.read(&log).await,
+            Err(ResourceError::NotFound)
+        ));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_announced() {
+        let store = InMemoryResourceProvider::new();
+        let log = store.insert("build.log", "Compiling...").await;
+        let server =
+            Server::new(Implementation::new("test-server", "1.0.0")).with_resources(store.clone());
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation::new("test-client", "1.0.0"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        client
+            .send(JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(1),
+                method: "resources/subscribe".to_string(),
+                params: Some(json!({ "uri": log })),
+            }))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(_)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+
+        let notified = async || {
+            let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+            else {
+                panic!("Expected a notification");
+            };
+            (notification.method, notification.params)
+        };
+        store.update(&log, "Compiling... done").await.unwrap();
+        assert_eq!(
+            notified().await,
+            (
+                "notifications/resources/updated".to_string(),
+                Some(json!({ "uri": log }))
+            )
+        );
+        store.insert("report.md", "# Report").await;
+        assert_eq!(
+            notified().await,
+            ("notifications/resources/list_changed".to_string(), None)
+        );
+    }
+}
@@ -0,0 +1,254 @@
+/// Servers built from other servers.
+///
+/// [`Server::mount`] adds the tools, prompts and resources of one server to another under a
+/// prefix, so reusable modules can be written as servers of their own and combined:
+///
+/// ```text
+/// let server = Server::new(info)
+///     .with_tools(tools)
+///     .mount("fs", filesystem_server)
+///     .mount("git", git_server);
+/// ```
+///
+/// Tools and prompts are offered as `<prefix>__<name>`, e.g. `fs__read_file`, and calls for
+/// them are routed to the mounted server under their original name. Resources keep their
+/// URIs; only their names are prefixed, and reads go to the first server that has the
+/// resource. Listing pages through the server's own items and then those of each mounted
+/// server, in the order they were mounted.
+///
+/// [`Server::mount`]: crate::server::Server::mount
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::composite::Position;
+use crate::prompt::{GetPromptResult, ListPromptsResult, PromptError, PromptProvider};
+use crate::protocol::Cursor;
+use crate::resource::{
+    ListResourceTemplatesResult, ListResourcesResult, ResourceContent, ResourceError,
+    ResourceProvider,
+};
+use crate::server::SubscriptionHandle;
+
+/// What separates the prefix of a mounted server from the names of its items.
+pub const MOUNT_SEPARATOR: &str = "__";
+
+/// `name` as offered by a server mounted under `prefix`.
+pub fn prefixed(prefix: &str, name: &str) -> String {
+    format!("{}{}{}", prefix, MOUNT_SEPARATOR, name)
+}
+
+/// `name` with the prefix of a server mounted under `prefix` removed, if it has it.
+pub(crate) fn unprefixed<'a>(prefix: &str, name: &'a str) -> Option<&'a str> {
+    name.strip_prefix(prefix)?.strip_prefix(MOUNT_SEPARATOR)
+}
+
+/// Providers listed one after another; the server's own under no prefix.
+struct Mounts<P: ?Sized> {
+    providers: Vec<(Option<String>, Arc<P>)>,
+}
+
+impl<P: ?Sized> Mounts<P> {
+    fn new(own: Option<Arc<P>>) -> Self {
+        Mounts {
+            providers: own.into_iter().map(|provider| (None, provider)).collect(),
+        }
+    }
+
+    fn mount(&mut self, prefix: &str, provider: Arc<P>) {
+        self.providers.push((Some(prefix.to_string()), provider));
+    }
+}
+
+/// The prompts of a server and of the servers mounted on it.
+pub(crate) struct MountedPrompts(Mounts<dyn PromptProvider>);
+
+impl MountedPrompts {
+    pub(crate) fn new(own: Option<Arc<dyn PromptProvider>>) -> Self {
+        MountedPrompts(Mounts::new(own))
+    }
+
+    pub(crate) fn mount(mut self, prefix: &str, provider: Arc<dyn PromptProvider>) -> Self {
+        self.0.mount(prefix, provider);
+        self
+    }
+}
+
+#[async_trait]
+impl PromptProvider for MountedPrompts {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListPromptsResult, PromptError> {
+        let position = Position::from_cursor(cursor, self.0.providers.len())?;
+        let Some((prefix, provider)) = self.0.providers.get(position.provider) else {
+            return Ok(ListPromptsResult::default());
+        };
+        let mut page = provider.list(position.cursor).await?;
+        if let Some(prefix) = prefix {
+            for prompt in &mut page.prompts {
+                prompt.name = prefixed(prefix, &prompt.name);
+            }
+        }
+        page.next_cursor =
+            Position::next_cursor(position.provider, page.next_cursor, self.0.providers.len());
+        Ok(page)
+    }
+
+    async fn get(
+        &self,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> Result<GetPromptResult, PromptError> {
+        let mounted =
+            self.0.providers.iter().find_map(|(prefix, provider)| {
+                Some((unprefixed(prefix.as_deref()?, name)?, provider))
+            });
+        let (own_name, provider) = match mounted {
+            Some(mounted) => mounted,
+            None => match self.0.providers.first() {
+                Some((None, provider)) => (name, provider),
+                _ => return Err(PromptError::NotFound(name.to_string())),
+            },
+        };
+        provider
+            .get(own_name, arguments)
+            .await
+            .map_err(|error| match error {
+                PromptError::NotFound(_) => PromptError::NotFound(name.to_string()),
+                error => error,
+            })
+    }
+}
+
+/// The resources of a server and of the servers mounted on it.
+pub(crate) struct MountedResources(Mounts<dyn ResourceProvider>);
+
+impl MountedResources {
+    pub(crate) fn new(own: Option<Arc<dyn ResourceProvider>>) -> Self {
+        MountedResources(Mounts::new(own))
+    }
+
+    pub(crate) fn mount(mut self, prefix: &str, provider: Arc<dyn ResourceProvider>) -> Self {
+        self.0.mount(prefix, provider);
+        self
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for MountedResources {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+        let position = Position::from_cursor(cursor, self.0.providers.len())?;
+        let Some((prefix, provider)) = self.0.providers.get(position.provider) else {
+            return Ok(ListResourcesResult::default());
+        };
+        let mut page = provider.list(position.cursor).await?;
+        if let Some(prefix) = prefix {
+            for resource in &mut page.resources {
+                resource.name = prefixed(prefix, &resource.name);
+            }
+        }
+        page.next_cursor =
+            Position::next_cursor(position.provider, page.next_cursor, self.0.providers.len());
+        Ok(page)
+    }
+
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+        for (_, provider) in &self.0.providers {
+            match provider.read(uri).await {
+                Err(ResourceError::NotFound) => continue,
+                result => return result,
+            }
+        }
+        Err(ResourceError::NotFound)
+    }
+
+    async fn list_templates(
+        &self,
+        cursor: Option<Cursor>,
+    ) -> Result<ListResourceTemplatesResult, ResourceError> {
+        let position = Position::from_cursor(cursor, self.0.providers.len())?;
+        let Some((prefix, provider)) = self.0.providers.get(position.provider) else {
+            return Ok(ListResourceTemplatesResult::default());
+        };
+        let mut page = provider.list_templates(position.cursor).await?;
+        if let Some(prefix) = prefix {
+            for template in &mut page.resource_templates {
+                template.name = prefixed(prefix, &template.name);
+            }
+        }
+        page.next_cursor =
+            Position::next_cursor(position.provider, page.next_cursor, self.0.providers.len());
+        Ok(page)
+    }
+
+    fn supports_subscriptions(&self) -> bool {
+        self.0
+            .providers
+            .iter()
+            .any(|(_, provider)| provider.supports_subscriptions())
+    }
+
+    fn supports_list_changed(&self) -> bool {
+        self.0
+            .providers
+            .iter()
+            .any(|(_, provider)| provider.supports_list_changed())
+    }
+
+    fn attach(&self, subscriptions: SubscriptionHandle) {
+        for (_, provider) in &self.0.providers {
+            if provider.supports_subscriptions() || provider.supports_list_changed() {
+                provider.attach(subscriptions.clone());
+            }
+        }
+    }
+
+    /// Subscribes with the first provider that supports subscriptions and has the resource.
+    async fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        for (_, provider) in &self.0.providers {
+            if !provider.supports_subscriptions() {
+                continue;
+            }
+            match provider.subscribe(uri).await {
+                Err(ResourceError::NotFound) => continue,
+                result => return result,
+            }
+        }
+        Err(ResourceError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::InMemoryResourceProvider;
+
+    #[test]
+    fn test_names_are_prefixed() {
+        assert_eq!(prefixed("fs", "read_file"), "fs__read_file");
+        assert_eq!(unprefixed("fs", "fs__read_file"), Some("read_file"));
+        assert_eq!(unprefixed("fs", "fsx__read_file"), None);
+        assert_eq!(unprefixed("fs", "read_file"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resources_of_mounted_servers_are_listed_and_read() {
+        let own = InMemoryResourceProvider::new();
+        let notes = own.insert("notes", "Water the plants").await;
+        let mounted = InMemoryResourceProvider::new();
+        let readme = mounted.insert("readme", "Hello").await;
+        let resources = MountedResources::new(Some(Arc::new(own))).mount("fs", Arc::new(mounted));
+
+        let first = resources.list(None).await.unwrap();
+        assert_eq!(first.resources[0].name, "notes");
+        let second = resources.list(first.next_cursor).await.unwrap();
+        assert_eq!(second.resources[0].name, "fs__readme");
+        assert_eq!(second.next_cursor, None);
+
+        assert!(resources.read(&notes).await.is_ok());
+        assert!(resources.read(&readme).await.is_ok());
+        assert!(matches!(
+            resources.read("str:///missing").await,
+            Err(ResourceError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+/// Outbound message delivery with per-key ordering guarantees.
+///
+/// MCP peers handle requests concurrently, so the order in which messages leave a connection
+/// is only guaranteed where it matters:
+///
+/// - Responses may interleave freely. A response is sent as soon as its handler finishes,
+///   regardless of the order in which the requests arrived.
+/// - Messages queued on the same lane are sent in the order they were queued, even when they
+///   are queued from different tasks. A later message on a lane is never written before an
+///   earlier one.
+/// - Messages on different lanes have no ordering relative to each other.
+///
+/// Notifications that describe the same thing go through the same lane: resource updates are
+/// keyed by resource URI, progress by progress token, and session-wide notifications such as
+/// `list_changed` or log messages by [`LaneKey::Session`]. With [`DeliveryOrdering::Sequential`]
+/// every queued message shares one lane instead, trading concurrency for a total order.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::protocol::{JsonRpcMessage, ProtocolError};
+use crate::transport::Transport;
+
+/// Identifies an ordered delivery lane.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LaneKey {
+    /// Session-wide notifications (list changes, log messages)
+    Session,
+    /// Notifications about a single resource, keyed by URI
+    Resource(String),
+    /// Notifications tied to a progress token or request
+    Progress(String),
+    /// Application-defined lanes
+    Custom(String),
+}
+
+/// How queued messages are ordered relative to each other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeliveryOrdering {
+    /// Messages are ordered within their lane; lanes are independent.
+    #[default]
+    PerKey,
+    /// All queued messages share a single lane and are sent in queue order.
+    Sequential,
+}
+
+type Delivery = (JsonRpcMessage, oneshot::Sender<Result<(), ProtocolError>>);
+
+/// The outbound half of a connection.
+pub struct Outbound {
+    transport: Arc<dyn Transport>,
+    ordering: DeliveryOrdering,
+    lanes: Arc<Mutex<HashMap<LaneKey, mpsc::UnboundedSender<Delivery>>>>,
+}
+
+impl Outbound {
+    pub fn new(transport: Arc<dyn Transport>) -> Self {
+        Self::with_ordering(transport, DeliveryOrdering::default())
+    }
+
+    pub fn with_ordering(transport: Arc<dyn Transport>, ordering: DeliveryOrdering) -> Self {
+        Outbound {
+            transport,
+            ordering,
+            lanes: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Sends a message right away, without any ordering relative to other messages.
+    pub async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        self.transport.send(message).await
+    }
+
+    /// Queues a message on the lane for `key`.
+    ///
+    /// The message is queued when this function is called, not when the returned future is
+    /// first polled, so calls made in sequence are delivered in sequence. The future resolves
+    /// once the message has been handed to the transport.
+    pub fn send_ordered(
+        &self,
+        key: LaneKey,
+        message: JsonRpcMessage,
+    ) -> impl Future<Output = Result<(), ProtocolError>> + Send + 'static {
+        let key = match self.ordering {
+            DeliveryOrdering::PerKey => key,
+            DeliveryOrdering::Sequential => LaneKey::Session,
+        };
+        let (sender, receiver) = oneshot::channel();
+
+        let mut lanes = self.lanes.lock().unwrap_or_else(|e| e.into_inner());
+        let lane = lanes.entry(key.clone()).or_insert_with(|| {
+            let (lane, queue) = mpsc::unbounded_channel();
+            tokio::spawn(run_lane(
+                key,
+                queue,
+                self.transport.clone(),
+                self.lanes.clone(),
+            ));
+            lane
+        });
+        // The worker only exits after removing its lane while holding this lock, so the
+        // lane in the map always has a live receiver.
+        let _ = lane.send((message, sender));
+        drop(lanes);
+
+        async move {
+            receiver.await.unwrap_or_else(|_| {
+                Err(ProtocolError::TransportError(
+                    "Delivery lane closed".to_string(),
+                ))
+            })
+        }
+    }
+
+    /// Returns the number of lanes that currently have queued or in-flight messages.
+    pub fn active_lanes(&self) -> usize {
+        self.lanes.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+async fn run_lane(
+    key: LaneKey,
+    mut queue: mpsc::UnboundedReceiver<Delivery>,
+    transport: Arc<dyn Transport>,
+    lanes: Arc<Mutex<HashMap<LaneKey, mpsc::UnboundedSender<Delivery>>>>,
+) {
+    loop {
+        while let Ok((message, done)) = queue.try_recv() {
+            let _ = done.send(transport.send(message).await);
+        }
+
+        // Retire the lane once it is drained. Senders queue under the same lock, so nothing
+        // can slip in between the final emptiness check and the removal.
+        {
+            let mut lanes = lanes.lock().unwrap_or_else(|e| e.into_inner());
+            if queue.is_empty() {
+                lanes.remove(&key);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{JSONRPC_VERSION, JsonRpcNotification};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::time::Duration;
+
+    /// Records sent messages, taking longer for earlier messages to provoke reordering.
+    #[derive(Default)]
+    struct SlowTransport {
+        sent: Mutex<Vec<(String, u64)>>,
+    }
+
+    #[async_trait]
+    impl Transport for SlowTransport {
+        async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+            let JsonRpcMessage::Notification(notification) = message else {
+                return Ok(());
+            };
+            let params = notification.params.unwrap();
+            let seq = params["seq"].as_u64().unwrap();
+            tokio::time::sleep(Duration::from_micros(200 - seq * 2)).await;
+            self.sent.lock().unwrap().push((notification.method, seq));
+            Ok(())
+        }
+
+        async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+            Ok(None)
+        }
+
+        async fn close(&self) -> Result<(), ProtocolError> {
+            Ok(())
+        }
+    }
+
+    fn notification(uri: &str, seq: u64) -> JsonRpcMessage {
+        JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: uri.to_string(),
+            params: Some(json!({ "seq": seq })),
+        })
+    }
+
+    fn sent_for(transport: &SlowTransport, uri: &str) -> Vec<u64> {
+        transport
+            .sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(method, _)| method == uri)
+            .map(|(_, seq)| *seq)
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_same_lane_is_delivered_in_order() {
+        let transport = Arc::new(SlowTransport::default());
+        let outbound = Arc::new(Outbound::new(transport.clone()));
+
+        let mut deliveries = Vec::new();
+        for seq in 0..60 {
+            let uri = if seq % 2 == 0 {
+                "file:///a"
+            } else {
+                "file:///b"
+            };
+            let delivery =
+                outbound.send_ordered(LaneKey::Resource(uri.to_string()), notification(uri, seq));
+            deliveries.push(tokio::spawn(delivery));
+        }
+        for delivery in deliveries {
+            delivery.await.unwrap().unwrap();
+        }
+
+        let a = sent_for(&transport, "file:///a");
+        let b = sent_for(&transport, "file:///b");
+        assert_eq!(a, (0..60).filter(|seq| seq % 2 == 0).collect::<Vec<_>>());
+        assert_eq!(b, (0..60).filter(|seq| seq % 2 == 1).collect::<Vec<_>>());
+
+        // Drained lanes retire shortly after their last delivery completes.
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while outbound.active_lanes() > 0 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_sequential_ordering_is_total() {
+        let transport = Arc::new(SlowTransport::default());
+        let outbound = Outbound::with_ordering(transport.clone(), DeliveryOrdering::Sequential);
+
+        let deliveries: Vec<_> = (0..40)
+            .map(|seq| {
+                let uri = format!("file:///{}", seq % 3);
+                outbound.send_ordered(LaneKey::Resource(uri.clone()), notification(&uri, seq))
+            })
+            .collect();
+        for result in futures::future::join_all(deliveries).await {
+            result.unwrap();
+        }
+
+        let sent: Vec<u64> = transport
+            .sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, seq)| *seq)
+            .collect();
+        assert_eq!(sent, (0..40).collect::<Vec<_>>());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_producers_keep_queue_order() {
+        let transport = Arc::new(SlowTransport::default());
+        let outbound = Arc::new(Outbound::new(transport.clone()));
+
+        // Each producer queues its own lane from its own task.
+        let producers: Vec<_> = (0..4)
+            .map(|producer| {
+                let outbound = outbound.clone();
+                tokio::spawn(async move {
+                    let uri = format!("file:///{}", producer);
+                    let deliveries: Vec<_> = (0..20)
+                        .map(|seq| {
+                            outbound.send_ordered(
+                                LaneKey::Resource(uri.clone()),
+                                notification(&uri, seq),
+                            )
+                        })
+                        .collect();
+                    futures::future::join_all(deliveries).await
+                })
+            })
+            .collect();
+        for producer in producers {
+            for result in producer.await.unwrap() {
+                result.unwrap();
+            }
+        }
+
+        for producer in 0..4 {
+            let sent = sent_for(&transport, &format!("file:///{}", producer));
+            assert_eq!(sent, (0..20).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+/// Cursor-based pagination of list operations.
+///
+/// `tools/list`, `resources/list`, `resources/templates/list` and `prompts/list` return their
+/// items in pages. A request carries the `cursor` of the page it wants, and every result
+/// carries the `nextCursor` to request after it, or none on the last page. [`Paginated`]
+/// exposes both, so code can page through any list without knowing which one it is.
+///
+/// Servers split their items with [`paginate`], and clients fetch every page with
+/// [`Client::request_all_pages`](crate::client::Client::request_all_pages).
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+
+use crate::prompt::ListPromptsResult;
+use crate::protocol::{Cursor, PaginatedRequestParams, ProtocolError};
+use crate::resource::{ListResourceTemplatesResult, ListResourcesResult};
+use crate::tool::ListToolsResult;
+
+/// The number of items per page when a server does not choose its own, small enough to keep
+/// list results well below typical message size limits.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// A list request or result that takes part in pagination.
+pub trait Paginated {
+    /// The cursor a request asks for; `None` for results.
+    fn cursor(&self) -> Option<&Cursor>;
+
+    /// The cursor of the page after a result; `None` for requests and last pages.
+    fn next_cursor(&self) -> Option<&Cursor>;
+}
+
+impl Paginated for PaginatedRequestParams {
+    fn cursor(&self) -> Option<&Cursor> {
+        self.cursor.as_ref()
+    }
+
+    fn next_cursor(&self) -> Option<&Cursor> {
+        None
+    }
+}
+
+macro_rules! paginated_result {
+    ($($result:ty),*) => {
+        $(
+            impl Paginated for $result {
+                fn cursor(&self) -> Option<&Cursor> {
+                    None
+                }
+
+                fn next_cursor(&self) -> Option<&Cursor> {
+                    self.next_cursor.as_ref()
+                }
+            }
+        )*
+    };
+}
+
+paginated_result!(
+    ListToolsResult,
+    ListResourcesResult,
+    ListResourceTemplatesResult,
+    ListPromptsResult
+);
+
+/// Returns the page of `items` that `request` asks for, at most `page_size` long, and the
+/// cursor of the page after it.
+///
+/// Cursors encode the offset into `items`, so the list must not be reordered between pages. A
+/// cursor that was not produced here is rejected as invalid params.
+pub fn paginate<T: Clone>(
+    items: &[T],
+    request: &impl Paginated,
+    page_size: usize,
+) -> Result<(Vec<T>, Option<Cursor>), ProtocolError> {
+    let start = match request.cursor() {
+        Some(cursor) => decode_offset(cursor)
+            .filter(|&offset| offset <= items.len())
+            .ok_or_else(|| ProtocolError::InvalidParams(format!("Invalid cursor: {}", cursor.0)))?,
+        None => 0,
+    };
+    let end = start.saturating_add(page_size.max(1)).min(items.len());
+    let next_cursor = (end < items.len()).then(|| encode_offset(end));
+    Ok((items[start..end].to_vec(), next_cursor))
+}
+
+fn encode_offset(offset: usize) -> Cursor {
+    Cursor(BASE64_URL_SAFE_NO_PAD.encode(offset.to_string()))
+}
+
+fn decode_offset(cursor: &Cursor) -> Option<usize> {
+    let bytes = BASE64_URL_SAFE_NO_PAD.decode(&cursor.0).ok()?;
+    std::str::from_utf8(&bytes).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate_walks_every_page() {
+        let items: Vec<u32> = (0..5).collect();
+        let mut request = PaginatedRequestParams::default();
+        let mut pages = Vec::new();
+        loop {
+            let (page, next_cursor) = paginate(&items, &request, 2).unwrap();
+            pages.push(page);
+            let result = ListToolsResult {
+                next_cursor,
+                ..Default::default()
+            };
+            match result.next_cursor() {
+                Some(cursor) => request.cursor = Some(cursor.clone()),
+                None => break,
+            }
+        }
+        assert_eq!(pages, vec![vec![0, 1], vec![2, 3], vec![4]]);
+
+        request.cursor = Some(Cursor::from("not-a-cursor"));
+        assert!(matches!(
+            paginate(&items, &request, 2),
+            Err(ProtocolError::InvalidParams(_))
+        ));
+    }
+}
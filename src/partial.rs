@@ -0,0 +1,198 @@
+/// Streaming partial tool results, a non-standard extension.
+///
+/// Tools wrapping slow processes, such as a build or a test run, can hand their output to the
+/// client while it is produced rather than all at once at the end. A server offering the
+/// extension advertises [`PARTIAL_RESULTS_CAPABILITY`] under `experimental`, see
+/// [`Server::with_partial_results`]. A client opts in per call by setting the same name to
+/// `true` in the request's `_meta`, next to the `progressToken` the chunks are tied to:
+///
+/// ```text
+/// -> tools/call                              {"name": "build", "_meta": {"progressToken": "7", "x-mcp-ox/partialResults": true}}
+/// <- notifications/x-mcp-ox/partial_result   {"progressToken": "7", "content": [{"type": "text", "text": "Compiling\n"}]}
+/// <- notifications/x-mcp-ox/partial_result   {"progressToken": "7", "content": [{"type": "text", "text": "Finished\n"}]}
+/// <- result                                  {"content": [{"type": "text", "text": "Compiling\nFinished\n"}]}
+/// ```
+///
+/// Handlers emit chunks with [`RequestContext::partial_results`]; for calls that did not opt
+/// in, emitting is a no-op. Chunks leave in the order they were emitted, on the same lane as
+/// the request's progress notifications. The final `CallToolResult` marks the end of the call
+/// and is complete on its own, so clients that ignore the chunks lose nothing. Clients of this
+/// crate receive them with [`Client::call_tool_with_partial_results`].
+///
+/// [`Server::with_partial_results`]: crate::server::Server::with_partial_results
+/// [`RequestContext::partial_results`]: crate::server::RequestContext::partial_results
+/// [`Client::call_tool_with_partial_results`]: crate::client::Client::call_tool_with_partial_results
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::outbound::{LaneKey, Outbound};
+use crate::progress::ProgressToken;
+use crate::prompt::PromptMessageContent;
+use crate::protocol::{
+    JSONRPC_VERSION, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, ProtocolError,
+};
+
+/// The name the extension is advertised under, and the `_meta` flag a call opts in with.
+pub const PARTIAL_RESULTS_CAPABILITY: &str = "x-mcp-ox/partialResults";
+
+/// Method name of the notifications carrying chunks.
+pub const PARTIAL_RESULT_NOTIFICATION: &str = "notifications/x-mcp-ox/partial_result";
+
+/// Parameters of a partial result notification.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialResultNotificationParams {
+    /// The token given in the original request
+    pub progress_token: ProgressToken,
+    /// The content produced since the previous chunk
+    pub content: Vec<PromptMessageContent>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl PartialResultNotificationParams {
+    pub fn into_notification(self) -> Result<JsonRpcNotification, ProtocolError> {
+        Ok(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: PARTIAL_RESULT_NOTIFICATION.to_string(),
+            params: Some(
+                serde_json::to_value(self)
+                    .map_err(|e| ProtocolError::InternalError(e.to_string()))?,
+            ),
+        })
+    }
+}
+
+/// Whether `request` opted into partial results.
+pub fn requested(request: &JsonRpcRequest) -> bool {
+    request
+        .params
+        .as_ref()
+        .and_then(|params| params.get("_meta"))
+        .and_then(|meta| meta.get(PARTIAL_RESULTS_CAPABILITY))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Opts a call into partial results, by setting the flag in its `_meta`.
+pub fn request_in(meta: &mut Option<Map<String, Value>>) {
+    meta.get_or_insert_with(Map::new)
+        .insert(PARTIAL_RESULTS_CAPABILITY.to_string(), Value::Bool(true));
+}
+
+/// Emits chunks of a tool's result from the side handling the call.
+///
+/// Emitting is a no-op when the call did not opt in or carried no progress token.
+#[derive(Clone)]
+pub struct PartialResults {
+    token: Option<ProgressToken>,
+    outbound: Arc<Outbound>,
+}
+
+impl PartialResults {
+    pub fn new(token: Option<ProgressToken>, outbound: Arc<Outbound>) -> Self {
+        PartialResults { token, outbound }
+    }
+
+    /// Creates the emitter for `request`, enabled if it opted in and carries a progress token.
+    pub fn for_request(request: &JsonRpcRequest, outbound: Arc<Outbound>) -> Self {
+        let token = ProgressToken::from_request(request).filter(|_| requested(request));
+        Self::new(token, outbound)
+    }
+
+    /// Returns `true` if the requester receives the chunks.
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Sends `content` as the next chunk.
+    pub async fn send(&self, content: Vec<PromptMessageContent>) -> Result<(), ProtocolError> {
+        let Some(token) = self.token.clone() else {
+            return Ok(());
+        };
+        let lane = LaneKey::Progress(token.to_string());
+        let notification = PartialResultNotificationParams {
+            progress_token: token,
+            content,
+            meta: None,
+        }
+        .into_notification()?;
+        self.outbound
+            .send_ordered(lane, JsonRpcMessage::Notification(notification))
+            .await
+    }
+
+    /// Sends `text` as the next chunk.
+    pub async fn text(&self, text: impl Into<String>) -> Result<(), ProtocolError> {
+        self.send(vec![PromptMessageContent::text(text)]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::protocol::{ClientCapabilities, Implementation};
+    use crate::server::{RequestContext, Server};
+    use crate::tool::{CallToolRequestParams, CallToolResult, Tool};
+    use crate::transport::MemoryTransport;
+
+    #[tokio::test]
+    async fn test_chunks_stream_before_the_result() {
+        let server =
+            Server::new(Implementation::new("test-server", "1.0.0")).with_partial_results();
+        server.add_tool(
+            Tool::builder().name("build").build(),
+            |_: Option<Map<String, Value>>, context: RequestContext| async move {
+                let chunks = context.partial_results();
+                chunks.text("Compiling\n").await.ok();
+                chunks.text("Finished\n").await.ok();
+                Ok(CallToolResult::text("Compiling\nFinished\n"))
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let client = Client::connect(
+            client,
+            Implementation::new("test-client", "1.0.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let experimental = client.session_info().capabilities.experimental.as_ref();
+        assert!(
+            experimental
+                .unwrap()
+                .contains_key(PARTIAL_RESULTS_CAPABILITY)
+        );
+
+        let call = || CallToolRequestParams {
+            name: "build".to_string(),
+            arguments: None,
+            meta: None,
+        };
+        let mut chunks = Vec::new();
+        let result = client
+            .call_tool_with_partial_results(call(), |content| chunks.extend(content))
+            .await
+            .unwrap();
+        assert_eq!(
+            chunks,
+            [
+                CallToolResult::text("Compiling\n").content[0].clone(),
+                CallToolResult::text("Finished\n").content[0].clone(),
+            ]
+        );
+        assert_eq!(result, CallToolResult::text("Compiling\nFinished\n"));
+
+        // Calls that do not opt in only get the result.
+        let result: CallToolResult = client
+            .request(crate::protocol::ClientRequest::CallTool(call()))
+            .await
+            .unwrap();
+        assert_eq!(result, CallToolResult::text("Compiling\nFinished\n"));
+    }
+}
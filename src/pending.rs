@@ -0,0 +1,222 @@
+/// Correlation of responses with the requests they answer.
+///
+/// A peer that has several requests outstanding at once registers each outgoing request id in
+/// [`PendingRequests`] and awaits the returned [`PendingResponse`]. The receive loop hands every
+/// response and error response to [`PendingRequests::resolve`], which wakes the matching caller.
+/// Responses nobody is waiting for are reported back as orphans instead of being dropped
+/// silently, and [`PendingRequests::close`] fails every outstanding request when the transport
+/// goes away.
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+use crate::error::McpError;
+use crate::protocol::{JsonRpcMessage, RequestId};
+
+type Responder = oneshot::Sender<Result<Value, McpError>>;
+
+/// What [`PendingRequests::resolve`] did with a message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Correlation {
+    /// The message answered a pending request and was delivered to its caller
+    Matched(RequestId),
+    /// The message is a response, but no request with its id is pending
+    Orphan(JsonRpcMessage),
+    /// The message is not a response and is handed back untouched
+    Unrelated(JsonRpcMessage),
+}
+
+/// The outgoing requests that are still waiting for a response.
+#[derive(Debug, Clone, Default)]
+pub struct PendingRequests {
+    responders: Arc<Mutex<HashMap<RequestId, Responder>>>,
+}
+
+impl PendingRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an outgoing request. Register before sending, so a fast response cannot be
+    /// mistaken for an orphan.
+    ///
+    /// Dropping the returned future unregisters the request.
+    pub fn register(&self, id: RequestId) -> PendingResponse {
+        let (sender, receiver) = oneshot::channel();
+        self.lock().insert(id.clone(), sender);
+        PendingResponse {
+            id,
+            receiver,
+            requests: self.clone(),
+        }
+    }
+
+    /// Delivers a response or error response to the caller waiting for it.
+    pub fn resolve(&self, message: JsonRpcMessage) -> Correlation {
+        let (id, result) = match &message {
+            JsonRpcMessage::Response(response) => (
+                Some(response.id.clone()),
+                match &response.error {
+                    Some(error) => Err(McpError::from(error.clone())),
+                    None => Ok(response.result.clone().unwrap_or(Value::Null)),
+                },
+            ),
+            JsonRpcMessage::Error(error) => {
+                (error.id.clone(), Err(McpError::from(error.error.clone())))
+            }
+            _ => return Correlation::Unrelated(message),
+        };
+
+        let responder = id.as_ref().and_then(|id| self.lock().remove(id));
+        match (id, responder) {
+            (Some(id), Some(responder)) => {
+                // The caller may have stopped waiting in the meantime.
+                let _ = responder.send(result);
+                Correlation::Matched(id)
+            }
+            _ => Correlation::Orphan(message),
+        }
+    }
+
+    /// Fails every pending request with a connection-closed error, e.g. when the transport
+    /// closed.
+    pub fn close(&self, reason: &str) {
+        for (_, responder) in self.lock().drain() {
+            let _ = responder.send(Err(McpError::connection_closed(reason)));
+        }
+    }
+
+    /// Returns `true` if a request with the given id is waiting for a response.
+    pub fn contains(&self, id: &RequestId) -> bool {
+        self.lock().contains_key(id)
+    }
+
+    /// Returns the number of requests waiting for a response.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<RequestId, Responder>> {
+        self.responders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Resolves with the result of one pending request.
+#[derive(Debug)]
+pub struct PendingResponse {
+    id: RequestId,
+    receiver: oneshot::Receiver<Result<Value, McpError>>,
+    requests: PendingRequests,
+}
+
+impl PendingResponse {
+    pub fn id(&self) -> &RequestId {
+        &self.id
+    }
+}
+
+impl Future for PendingResponse {
+    type Output = Result<Value, McpError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(|result| {
+            result.unwrap_or_else(|_| {
+                Err(McpError::connection_closed(
+                    "Request was dropped without a response",
+                ))
+            })
+        })
+    }
+}
+
+impl Drop for PendingResponse {
+    fn drop(&mut self) {
+        self.requests.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{
+        CONNECTION_CLOSED, ErrorData, JSONRPC_VERSION, JsonRpcError, JsonRpcNotification,
+        JsonRpcResponse, METHOD_NOT_FOUND,
+    };
+    use serde_json::json;
+
+    fn response(id: i64, result: Value) -> JsonRpcMessage {
+        JsonRpcMessage::Response(JsonRpcResponse {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(id),
+            result: Some(result),
+            error: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_responses_reach_their_callers() {
+        let pending = PendingRequests::new();
+        let first = pending.register(RequestId::Number(1));
+        let second = pending.register(RequestId::Number(2));
+        assert_eq!(pending.len(), 2);
+
+        // Responses may arrive in any order.
+        let error = JsonRpcMessage::Error(JsonRpcError {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(RequestId::Number(2)),
+            error: ErrorData {
+                code: METHOD_NOT_FOUND,
+                message: "Unknown method".to_string(),
+                data: None,
+            },
+        });
+        assert_eq!(
+            pending.resolve(error),
+            Correlation::Matched(RequestId::Number(2))
+        );
+        assert_eq!(
+            pending.resolve(response(1, json!({ "ok": true }))),
+            Correlation::Matched(RequestId::Number(1))
+        );
+
+        assert_eq!(first.await.unwrap(), json!({ "ok": true }));
+        assert_eq!(second.await.unwrap_err().code(), METHOD_NOT_FOUND);
+        assert!(pending.is_empty());
+
+        assert!(matches!(
+            pending.resolve(response(1, json!({}))),
+            Correlation::Orphan(_)
+        ));
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: "notifications/initialized".to_string(),
+            params: None,
+        });
+        assert!(matches!(
+            pending.resolve(notification),
+            Correlation::Unrelated(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_close_fails_pending_requests() {
+        let pending = PendingRequests::new();
+        let waiting = pending.register(RequestId::from("a"));
+        let abandoned = pending.register(RequestId::from("b"));
+        drop(abandoned);
+        assert!(!pending.contains(&RequestId::from("b")));
+
+        pending.close("Transport closed");
+        assert_eq!(waiting.await.unwrap_err().code(), CONNECTION_CLOSED);
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+/// Liveness checks with the `ping` request.
+///
+/// Either side may send a ping at any time, even before initialization, and the receiver must
+/// answer promptly with an empty result. [`ping_response`] builds that answer so receive loops
+/// can reply directly instead of routing pings to a handler.
+use serde_json::json;
+
+use crate::protocol::{JSONRPC_VERSION, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse};
+
+/// Method name of the ping request.
+pub const PING_METHOD: &str = "ping";
+
+/// Returns the response to `request` if it is a ping, or `None` for any other request.
+pub fn ping_response(request: &JsonRpcRequest) -> Option<JsonRpcMessage> {
+    if request.method != PING_METHOD {
+        return None;
+    }
+    Some(JsonRpcMessage::Response(JsonRpcResponse {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        id: request.id.clone(),
+        result: Some(json!({})),
+        error: None,
+    }))
+}
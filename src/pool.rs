@@ -0,0 +1,254 @@
+/// Connections to many MCP servers at once, the core of an agent host.
+///
+/// A [`ClientPool`] holds one [`Client`] per server, keyed by a name the host chooses. It
+/// connects to its servers concurrently, offers their tools as one list, and routes calls to
+/// the server a tool came from:
+///
+/// ```text
+/// let (pool, failed) = ClientPool::connect(info, capabilities, [
+///     ("fs".to_string(), Box::new(fs_transport) as Box<dyn Transport>),
+///     ("git".to_string(), Box::new(git_transport)),
+/// ]).await;
+/// let tools = pool.list_tools().await?;
+/// let result = pool.call_tool("fs__read_file", json!({ "path": "README.md" })).await?;
+/// ```
+///
+/// Tools are offered as `<server>__<name>`, like the tools of mounted servers, see
+/// [`crate::mount`], so tools of the same name on different servers stay apart.
+use futures::future::join_all;
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::mount::{MOUNT_SEPARATOR, prefixed};
+use crate::protocol::{ClientCapabilities, Implementation, ProtocolError};
+use crate::tool::{CallToolResult, Tool};
+use crate::transport::Transport;
+
+/// A client in a pool, over any kind of transport.
+pub type PooledClient = Client<Box<dyn Transport>>;
+
+/// Why a pool could not do what was asked of it.
+#[derive(Debug, thiserror::Error)]
+pub enum PoolError {
+    #[error("No server named {0}")]
+    UnknownServer(String),
+    #[error("Server {server}: {source}")]
+    Server {
+        server: String,
+        #[source]
+        source: ProtocolError,
+    },
+}
+
+impl PoolError {
+    fn server(server: &str) -> impl FnOnce(ProtocolError) -> Self {
+        let server = server.to_string();
+        move |source| PoolError::Server { server, source }
+    }
+}
+
+/// A tool, and the server of the pool that offers it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PooledTool {
+    pub server: String,
+    pub tool: Tool,
+}
+
+impl PooledTool {
+    /// The name the pool offers the tool under, `<server>__<name>`.
+    pub fn qualified_name(&self) -> String {
+        prefixed(&self.server, &self.tool.name)
+    }
+}
+
+/// Clients of many servers, keyed by name, in the order they were added.
+#[derive(Default)]
+pub struct ClientPool {
+    clients: Vec<(String, PooledClient)>,
+}
+
+impl ClientPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connects to all `servers` at once. Returns the pool of the servers that could be
+    /// connected to, in the given order, and the errors of those that could not.
+    pub async fn connect(
+        client_info: Implementation,
+        capabilities: ClientCapabilities,
+        servers: impl IntoIterator<Item = (String, Box<dyn Transport>)>,
+    ) -> (Self, Vec<PoolError>) {
+        let connections = servers.into_iter().map(|(name, transport)| {
+            let client_info = client_info.clone();
+            let capabilities = capabilities.clone();
+            async move {
+                let connected = Client::connect(transport, client_info, capabilities).await;
+                (name, connected)
+            }
+        });
+        let mut pool = ClientPool::new();
+        let mut failed = Vec::new();
+        for (name, connected) in join_all(connections).await {
+            match connected {
+                Ok(client) => {
+                    pool.insert(name, client);
+                }
+                Err(source) => failed.push(PoolError::Server {
+                    server: name,
+                    source,
+                }),
+            }
+        }
+        (pool, failed)
+    }
+
+    /// Adds `client` under `name`, returning the client it replaces, if any.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        client: PooledClient,
+    ) -> Option<PooledClient> {
+        let name = name.into();
+        match self
+            .clients
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, existing)) => Some(std::mem::replace(existing, client)),
+            None => {
+                self.clients.push((name, client));
+                None
+            }
+        }
+    }
+
+    /// Removes the client named `name` from the pool and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<PooledClient> {
+        let index = self
+            .clients
+            .iter()
+            .position(|(existing, _)| existing == name)?;
+        Some(self.clients.remove(index).1)
+    }
+
+    /// Returns the client named `name`.
+    pub fn get(&self, name: &str) -> Option<&PooledClient> {
+        self.clients
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, client)| client)
+    }
+
+    /// Returns the names of the servers in the pool.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.clients.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Lists the tools of all servers that offer tools, asking them all at once.
+    pub async fn list_tools(&self) -> Result<Vec<PooledTool>, PoolError> {
+        let listings = self
+            .clients
+            .iter()
+            .filter(|(_, client)| client.server_capabilities().tools.is_some())
+            .map(|(name, client)| async move {
+                let tools = client.list_tools().await.map_err(PoolError::server(name))?;
+                Ok::<_, PoolError>(tools.into_iter().map(|tool| PooledTool {
+                    server: name.clone(),
+                    tool,
+                }))
+            });
+        let mut tools = Vec::new();
+        for listed in join_all(listings).await {
+            tools.extend(listed?);
+        }
+        Ok(tools)
+    }
+
+    /// Calls the tool offered as `name`, i.e. `<server>__<tool>`, on its server.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: impl Serialize,
+    ) -> Result<CallToolResult, PoolError> {
+        let (server, client, tool) = self
+            .clients
+            .iter()
+            .filter_map(|(server, client)| {
+                let tool = name
+                    .strip_prefix(server.as_str())?
+                    .strip_prefix(MOUNT_SEPARATOR)?;
+                Some((server, client, tool))
+            })
+            // Of servers named `a` and `a__b`, the tool `a__b__c` is `c` of `a__b`.
+            .max_by_key(|(server, _, _)| server.len())
+            .ok_or_else(|| PoolError::UnknownServer(name.to_string()))?;
+        client
+            .call_tool(tool, arguments)
+            .await
+            .map_err(PoolError::server(server))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use crate::transport::MemoryTransport;
+    use serde_json::{Map, Value};
+
+    fn server(name: &str, tools: &[&'static str]) -> Box<dyn Transport> {
+        let server = Server::new(Implementation::new(name, "1.0.0"));
+        for &tool in tools {
+            server.add_tool(
+                Tool::builder().name(tool).build(),
+                move |_: Option<Map<String, Value>>, _| async move {
+                    Ok(CallToolResult::text(tool))
+                },
+            );
+        }
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        Box::new(client)
+    }
+
+    #[tokio::test]
+    async fn test_tools_of_all_servers_are_offered() {
+        let (unreachable, server_side) = MemoryTransport::pair();
+        drop(server_side);
+        let (pool, failed) = ClientPool::connect(
+            Implementation::new("test-host", "1.0.0"),
+            ClientCapabilities::default(),
+            [
+                ("fs".to_string(), server("fs", &["read", "write"])),
+                (
+                    "down".to_string(),
+                    Box::new(unreachable) as Box<dyn Transport>,
+                ),
+                ("git".to_string(), server("git", &["read"])),
+            ],
+        )
+        .await;
+        assert_eq!(pool.names().collect::<Vec<_>>(), ["fs", "git"]);
+        assert!(matches!(&failed[..], [PoolError::Server { server, .. }] if server == "down"));
+
+        let tools = pool.list_tools().await.unwrap();
+        let names: Vec<_> = tools.iter().map(PooledTool::qualified_name).collect();
+        assert_eq!(names, ["fs__read", "fs__write", "git__read"]);
+
+        let result = pool.call_tool("git__read", ()).await.unwrap();
+        assert_eq!(result, CallToolResult::text("read"));
+        assert!(matches!(
+            pool.call_tool("svn__read", ()).await,
+            Err(PoolError::UnknownServer(_))
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+/// Progress notifications for long-running requests.
+///
+/// A requester opts into progress by putting a `progressToken` into the request's `_meta`.
+/// The handling side reports against that token with [`ProgressReporter`], and the requester
+/// receives `notifications/progress` messages carrying the same token.
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::outbound::{LaneKey, Outbound};
+use crate::protocol::{
+    JSONRPC_VERSION, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, ProtocolError,
+};
+
+/// Method name of progress notifications.
+pub const PROGRESS_NOTIFICATION: &str = "notifications/progress";
+
+/// A token used to associate progress notifications with the original request.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum ProgressToken {
+    Number(i64),
+    /// A number above `i64::MAX`
+    Unsigned(u64),
+    String(String),
+}
+
+impl fmt::Display for ProgressToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgressToken::Number(token) => write!(f, "{}", token),
+            ProgressToken::Unsigned(token) => write!(f, "{}", token),
+            ProgressToken::String(token) => write!(f, "{}", token),
+        }
+    }
+}
+
+impl ProgressToken {
+    /// Reads the progress token from a request's `_meta.progressToken`, if present.
+    pub fn from_request(request: &JsonRpcRequest) -> Option<Self> {
+        let token = request
+            .params
+            .as_ref()?
+            .get("_meta")?
+            .get("progressToken")?;
+        serde_json::from_value(token.clone()).ok()
+    }
+
+    /// Stores this token in a request's `_meta.progressToken`.
+    pub fn attach_to(&self, request: &mut JsonRpcRequest) {
+        let params = request
+            .params
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        if !params.is_object() {
+            return;
+        }
+        let meta = params
+            .as_object_mut()
+            .map(|params| params.entry("_meta").or_insert_with(|| json!({})));
+        if let Some(Value::Object(meta)) = meta {
+            meta.insert("progressToken".to_string(), json!(self));
+        }
+    }
+}
+
+/// Parameters of a `notifications/progress` notification.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressNotificationParams {
+    /// The token given in the original request
+    pub progress_token: ProgressToken,
+    /// The progress so far. Increases every time progress is reported
+    pub progress: f64,
+    /// Total number of items to process, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    /// An optional message describing the current progress
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl ProgressNotificationParams {
+    pub fn into_notification(self) -> Result<JsonRpcNotification, ProtocolError> {
+        Ok(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: PROGRESS_NOTIFICATION.to_string(),
+            params: Some(
+                serde_json::to_value(self)
+                    .map_err(|e| ProtocolError::InternalError(e.to_string()))?,
+            ),
+        })
+    }
+}
+
+/// Reports progress for one request from the side handling it.
+///
+/// Reporting is a no-op when the request did not carry a progress token.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    token: Option<ProgressToken>,
+    outbound: Arc<Outbound>,
+    last: Arc<Mutex<Option<f64>>>,
+}
+
+impl ProgressReporter {
+    pub fn new(token: Option<ProgressToken>, outbound: Arc<Outbound>) -> Self {
+        ProgressReporter {
+            token,
+            outbound,
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Creates a reporter for the progress token carried by `request`, if any.
+    pub fn for_request(request: &JsonRpcRequest, outbound: Arc<Outbound>) -> Self {
+        Self::new(ProgressToken::from_request(request), outbound)
+    }
+
+    /// Returns the progress token, or `None` if the requester did not ask for progress.
+    pub fn token(&self) -> Option<&ProgressToken> {
+        self.token.as_ref()
+    }
+
+    /// Sends a progress notification. `progress` must increase with every call.
+    pub async fn report(
+        &self,
+        progress: f64,
+        total: Option<f64>,
+        message: Option<String>,
+    ) -> Result<(), ProtocolError> {
+        let Some(token) = self.token.clone() else {
+            return Ok(());
+        };
+
+        let delivery = {
+            let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+            if last.is_some_and(|last| progress <= last) {
+                return Err(ProtocolError::InvalidParams(
+                    "Progress must increase with each notification".to_string(),
+                ));
+            }
+            *last = Some(progress);
+
+            let notification = ProgressNotificationParams {
+                progress_token: token.clone(),
+                progress,
+                total,
+                message,
+                meta: None,
+            }
+            .into_notification()?;
+            // Queue while holding the lock so notifications leave in the order they were checked.
+            self.outbound.send_ordered(
+                LaneKey::Progress(token.to_string()),
+                JsonRpcMessage::Notification(notification),
+            )
+        };
+        delivery.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RequestId;
+    use crate::transport::{MemoryTransport, Transport};
+
+    fn request(params: Option<Value>) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(1),
+            method: "tools/call".to_string(),
+            params,
+        }
+    }
+
+    #[test]
+    fn test_attach_and_read_token() {
+        let mut with_params = request(Some(json!({ "name": "slow" })));
+        assert_eq!(ProgressToken::from_request(&with_params), None);
+
+        ProgressToken::String("abc".to_string()).attach_to(&mut with_params);
+        assert_eq!(
+            with_params.params,
+            Some(json!({ "name": "slow", "_meta": { "progressToken": "abc" } }))
+        );
+        assert_eq!(
+            ProgressToken::from_request(&with_params),
+            Some(ProgressToken::String("abc".to_string()))
+        );
+
+        let mut without_params = request(None);
+        ProgressToken::Number(5).attach_to(&mut without_params);
+        assert_eq!(
+            ProgressToken::from_request(&without_params),
+            Some(ProgressToken::Number(5))
+        );
+    }
+
+    #[test]
+    fn test_progress_numbers_are_not_truncated() {
+        let params: ProgressNotificationParams = serde_json::from_value(json!({
+            "progressToken": u64::MAX,
+            "progress": 0.25,
+            "total": 9007199254740991u64
+        }))
+        .unwrap();
+        assert_eq!(params.progress_token, ProgressToken::Unsigned(u64::MAX));
+        assert_eq!(params.progress, 0.25);
+        assert_eq!(params.total, Some(9007199254740991.0));
+        assert_eq!(
+            serde_json::to_value(&params).unwrap()["progressToken"],
+            json!(u64::MAX)
+        );
+
+        let params: ProgressNotificationParams =
+            serde_json::from_value(json!({ "progressToken": "t-1", "progress": 3 })).unwrap();
+        assert_eq!(params.progress, 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_reporter_sends_notifications() {
+        let (local, remote) = MemoryTransport::pair();
+        let outbound = Arc::new(Outbound::new(Arc::new(local)));
+
+        let mut with_token = request(None);
+        ProgressToken::Number(9).attach_to(&mut with_token);
+        let reporter = ProgressReporter::for_request(&with_token, outbound.clone());
+
+        reporter
+            .report(1.0, Some(2.0), Some("halfway".to_string()))
+            .await
+            .unwrap();
+        assert!(reporter.report(1.0, Some(2.0), None).await.is_err());
+
+        let Some(JsonRpcMessage::Notification(notification)) = remote.receive().await.unwrap()
+        else {
+            panic!("Expected a progress notification");
+        };
+        assert_eq!(notification.method, PROGRESS_NOTIFICATION);
+        assert_eq!(
+            notification.params,
+            Some(
+                json!({ "progressToken": 9, "progress": 1.0, "total": 2.0, "message": "halfway" })
+            )
+        );
+
+        // Without a token nothing is sent.
+        let silent = ProgressReporter::for_request(&request(None), outbound);
+        silent.report(1.0, None, None).await.unwrap();
+    }
+}
@@ -1,18 +1,28 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
 use bon::Builder;
 use mime::Mime;
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use thiserror::Error;
 use url::Url;
 
+use crate::protocol::{Cursor, ProtocolError};
+
 /// Error types for prompt operations
 #[derive(Debug, Error)]
 pub enum PromptError {
     #[error("Invalid parameters: {0}")]
     InvalidParameters(String),
+    #[error("Prompt not found: {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -24,7 +34,7 @@ pub struct Prompt {
     /// A list of arguments to use for templating the prompt
     #[serde(skip_serializing_if = "Option::is_none")]
     #[builder(field)]
-    pub arguments: Option<Vec<Value>>,
+    pub arguments: Option<Vec<PromptArgument>>,
     /// The name of the prompt or prompt template
     #[builder(into)]
     pub name: String,
@@ -33,19 +43,74 @@ pub struct Prompt {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[builder(into)]
     pub description: Option<String>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
 impl<S: prompt_builder::State> PromptBuilder<S> {
-    pub fn argument<T: JsonSchema>(mut self) -> PromptBuilder<S> {
-        if let Some(args) = &mut self.arguments {
-            args.push(schema_for!(T).to_value());
-        } else {
-            self.arguments = Some(vec![schema_for!(T).to_value()]);
-        }
+    pub fn argument(mut self, argument: PromptArgument) -> PromptBuilder<S> {
+        self.arguments.get_or_insert_with(Vec::new).push(argument);
+        self
+    }
+
+    /// Adds an argument for each field of `T`, see [`PromptArgument::from_schema`].
+    pub fn arguments_from_schema<T: JsonSchema>(mut self) -> PromptBuilder<S> {
+        self.arguments
+            .get_or_insert_with(Vec::new)
+            .extend(PromptArgument::from_schema::<T>());
         self
     }
 }
 
+/// Describes an argument that a prompt template accepts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptArgument {
+    /// The name of the argument
+    #[builder(into)]
+    pub name: String,
+
+    /// A human-readable description of the argument
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub description: Option<String>,
+
+    /// Whether this argument must be provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+}
+
+impl PromptArgument {
+    /// One argument per property of the JSON Schema of `T`, described by the property's
+    /// description and required if the schema requires it.
+    pub fn from_schema<T: JsonSchema>() -> Vec<Self> {
+        let schema = schema_for!(T).to_value();
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(name, property)| PromptArgument {
+                name: name.clone(),
+                description: property
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                required: Some(required.contains(&name.as_str())),
+            })
+            .collect()
+    }
+}
+
 /// Represents the role of a message sender in a prompt conversation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -60,6 +125,9 @@ pub enum PromptMessageRole {
 pub struct TextContent {
     /// The text content of the message
     pub text: String,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
 /// An image provided to or from an LLM
@@ -73,6 +141,10 @@ pub struct ImageContent {
     /// The MIME type of the image
     #[builder(field)]
     pub mime_type: String,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
 impl<S: image_content_builder::State> ImageContentBuilder<S> {
@@ -103,6 +175,51 @@ impl<S: image_content_builder::State> ImageContentBuilder<S> {
     }
 }
 
+/// Audio provided to or from an LLM
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioContent {
+    /// The base64-encoded audio data
+    #[builder(field)]
+    pub data: String,
+
+    /// The MIME type of the audio
+    #[builder(field)]
+    pub mime_type: String,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl<S: audio_content_builder::State> AudioContentBuilder<S> {
+    pub fn data(mut self, data: impl Into<String>) -> Result<Self, PromptError> {
+        let data_str = data.into();
+
+        // Validate base64 data
+        BASE64_STANDARD.decode(&data_str).map_err(|_| {
+            PromptError::InvalidParameters("Audio data must be valid base64".to_string())
+        })?;
+
+        self.data = data_str;
+        Ok(self)
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Result<Self, PromptError> {
+        let mime_type_str = mime_type.into();
+
+        // Validate mime type
+        if !mime_type_str.starts_with("audio/") {
+            return Err(PromptError::InvalidParameters(
+                "MIME type must be a valid audio type (e.g. audio/wav)".to_string(),
+            ));
+        }
+
+        self.mime_type = mime_type_str;
+        Ok(self)
+    }
+}
+
 /// The contents of a specific resource or sub-resource
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
 #[serde(rename_all = "camelCase")]
@@ -115,6 +232,10 @@ pub struct ResourceContents {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[builder(field)]
     pub mime_type: Option<String>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
 impl<S: resource_contents_builder::State> ResourceContentsBuilder<S> {
@@ -149,6 +270,10 @@ pub struct TextResourceContents {
     /// The text content of the resource
     #[builder(into)]
     pub text: String,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
 impl<S: text_resource_contents_builder::State> TextResourceContentsBuilder<S> {
@@ -173,6 +298,34 @@ impl<S: text_resource_contents_builder::State> TextResourceContentsBuilder<S> {
 pub struct EmbeddedResource {
     /// The resource content
     pub resource: TextResourceContents,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// A link to a resource the client can read or subscribe to, in a prompt or tool call result
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLink {
+    /// The URI of the linked resource
+    #[builder(into)]
+    pub uri: String,
+    /// Name of the linked resource
+    #[builder(into)]
+    pub name: String,
+    /// A human-readable name for display
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub mime_type: Option<String>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
 /// Content types that can be included in prompt messages
@@ -185,8 +338,69 @@ pub enum PromptMessageContent {
     /// Image content with base64-encoded data
     Image(ImageContent),
 
+    /// Audio content with base64-encoded data
+    Audio(AudioContent),
+
     /// Embedded server-side resource
     Resource { resource: EmbeddedResource },
+
+    /// A link to a server-side resource, which is not embedded
+    #[serde(rename = "resource_link")]
+    ResourceLink(ResourceLink),
+}
+
+/// The content of prompt messages and tool call results.
+pub type Content = PromptMessageContent;
+
+impl PromptMessageContent {
+    /// Plain text content.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(TextContent {
+            text: text.into(),
+            meta: None,
+        })
+    }
+
+    /// Image content holding `data` encoded as base64. `mime_type` should be an image type,
+    /// e.g. `image/png`.
+    pub fn image(data: impl AsRef<[u8]>, mime_type: impl Into<String>) -> Self {
+        Self::Image(ImageContent {
+            data: BASE64_STANDARD.encode(data),
+            mime_type: mime_type.into(),
+            meta: None,
+        })
+    }
+
+    /// Audio content holding `data` encoded as base64. `mime_type` should be an audio type,
+    /// e.g. `audio/wav`.
+    pub fn audio(data: impl AsRef<[u8]>, mime_type: impl Into<String>) -> Self {
+        Self::Audio(AudioContent {
+            data: BASE64_STANDARD.encode(data),
+            mime_type: mime_type.into(),
+            meta: None,
+        })
+    }
+
+    /// The contents of a resource, embedded.
+    pub fn embedded_resource(resource: TextResourceContents) -> Self {
+        Self::Resource {
+            resource: EmbeddedResource {
+                resource,
+                meta: None,
+            },
+        }
+    }
+
+    /// A link to the resource at `uri`, named after its URI. Use [`ResourceLink::builder`]
+    /// to name or describe it.
+    pub fn resource_link(uri: impl Into<String>) -> Self {
+        let uri = uri.into();
+        Self::ResourceLink(ResourceLink {
+            name: uri.clone(),
+            uri,
+            ..Default::default()
+        })
+    }
 }
 
 impl Default for PromptMessageContent {
@@ -195,6 +409,24 @@ impl Default for PromptMessageContent {
     }
 }
 
+impl From<String> for PromptMessageContent {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<&str> for PromptMessageContent {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<ResourceLink> for PromptMessageContent {
+    fn from(link: ResourceLink) -> Self {
+        Self::ResourceLink(link)
+    }
+}
+
 /// Describes a message returned as part of a prompt
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
 pub struct PromptMessage {
@@ -226,6 +458,18 @@ impl<S: prompt_message_builder::State> PromptMessageBuilder<S> {
             }
         }
 
+        if let PromptMessageContent::Audio(audio) = &content {
+            BASE64_STANDARD.decode(&audio.data).map_err(|_| {
+                PromptError::InvalidParameters("Audio data must be valid base64".to_string())
+            })?;
+
+            if !audio.mime_type.starts_with("audio/") {
+                return Err(PromptError::InvalidParameters(
+                    "MIME type must be a valid audio type (e.g. audio/wav)".to_string(),
+                ));
+            }
+        }
+
         self.content = content;
         Ok(self)
     }
@@ -255,7 +499,27 @@ impl PromptMessage {
 
         Ok(Self {
             role,
-            content: PromptMessageContent::Image(ImageContent { data, mime_type }),
+            content: PromptMessageContent::Image(ImageContent {
+                data,
+                mime_type,
+                meta: None,
+            }),
+        })
+    }
+
+    /// Create a new audio message with the given role, data and mime type
+    pub fn new_audio<S: Into<String>>(
+        role: PromptMessageRole,
+        data: S,
+        mime_type: S,
+    ) -> Result<Self, PromptError> {
+        let content = AudioContent::builder()
+            .data(data)?
+            .mime_type(mime_type)?
+            .build();
+        Ok(Self {
+            role,
+            content: PromptMessageContent::Audio(content),
         })
     }
 
@@ -270,6 +534,7 @@ impl PromptMessage {
             uri,
             mime_type,
             text,
+            meta: None,
         };
 
         Self {
@@ -277,8 +542,192 @@ impl PromptMessage {
             content: PromptMessageContent::Resource {
                 resource: EmbeddedResource {
                     resource: resource_contents,
+                    meta: None,
                 },
             },
         }
     }
 }
+
+/// The server's response to a prompts/list request from the client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPromptsResult {
+    /// The prompts offered by the server
+    pub prompts: Vec<Prompt>,
+
+    /// An opaque token representing the pagination position after the last returned result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<Cursor>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Parameters of a prompts/get request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPromptRequestParams {
+    /// The name of the prompt or prompt template
+    pub name: String,
+
+    /// Arguments to use for templating the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<HashMap<String, String>>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The server's response to a prompts/get request from the client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPromptResult {
+    /// An optional description for the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The messages that make up the prompt
+    pub messages: Vec<PromptMessage>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The prompts a server offers, listed in pages and rendered on request.
+///
+/// A server answers `prompts/list` and `prompts/get` with the provider given to
+/// [`crate::server::Server::with_prompts`].
+#[async_trait]
+pub trait PromptProvider: Send + Sync {
+    /// Lists the page of prompts starting at `cursor`.
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListPromptsResult, PromptError>;
+
+    /// Renders the prompt named `name` with `arguments`. Unknown prompts are
+    /// [`PromptError::NotFound`], and missing or invalid arguments
+    /// [`PromptError::InvalidParameters`].
+    async fn get(
+        &self,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> Result<GetPromptResult, PromptError>;
+}
+
+#[async_trait]
+impl<P: PromptProvider + ?Sized> PromptProvider for Arc<P> {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListPromptsResult, PromptError> {
+        (**self).list(cursor).await
+    }
+
+    async fn get(
+        &self,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> Result<GetPromptResult, PromptError> {
+        (**self).get(name, arguments).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_content_constructors() {
+        let content: Vec<Content> = vec![
+            "done".into(),
+            Content::image([0x89, 0x50, 0x4e, 0x47], "image/png"),
+            Content::embedded_resource(TextResourceContents {
+                uri: "file:///notes.txt".to_string(),
+                mime_type: None,
+                text: "Water the plants".to_string(),
+                meta: None,
+            }),
+            Content::resource_link("file:///build.log"),
+            ResourceLink::builder()
+                .uri("file:///a.txt")
+                .name("a")
+                .mime_type("text/plain")
+                .build()
+                .into(),
+        ];
+        assert_eq!(
+            serde_json::to_value(&content).unwrap(),
+            json!([
+                { "type": "text", "text": "done" },
+                { "type": "image", "data": "iVBORw==", "mimeType": "image/png" },
+                {
+                    "type": "resource",
+                    "resource": {
+                        "resource": { "uri": "file:///notes.txt", "text": "Water the plants" }
+                    }
+                },
+                { "type": "resource_link", "uri": "file:///build.log", "name": "file:///build.log" },
+                { "type": "resource_link", "uri": "file:///a.txt", "name": "a", "mimeType": "text/plain" }
+            ])
+        );
+        let parsed: Vec<Content> =
+            serde_json::from_value(serde_json::to_value(&content).unwrap()).unwrap();
+        assert_eq!(parsed, content);
+        assert_eq!(Content::from(String::from("done")), content[0]);
+    }
+
+    /// Arguments of a code review prompt
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Review {
+        /// The code to review
+        code: String,
+        /// The language the code is written in
+        language: Option<String>,
+    }
+
+    #[test]
+    fn test_audio_content_is_validated() {
+        let message = PromptMessage::new_audio(PromptMessageRole::User, "UklGRg==", "audio/wav")
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&message.content).unwrap(),
+            json!({ "type": "audio", "data": "UklGRg==", "mimeType": "audio/wav" })
+        );
+        assert_eq!(message.content, Content::audio(b"RIFF", "audio/wav"));
+
+        assert!(PromptMessage::new_audio(PromptMessageRole::User, "not base64!", "audio/wav").is_err());
+        assert!(PromptMessage::new_audio(PromptMessageRole::User, "UklGRg==", "image/png").is_err());
+        let video = Content::audio(b"RIFF", "video/mp4");
+        assert!(
+            PromptMessage::builder()
+                .role(PromptMessageRole::Assistant)
+                .content(video)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_arguments_from_schema() {
+        let prompt = Prompt::builder()
+            .name("review")
+            .arguments_from_schema::<Review>()
+            .argument(PromptArgument::builder().name("tone").build())
+            .build();
+        assert_eq!(
+            serde_json::to_value(&prompt).unwrap(),
+            json!({
+                "name": "review",
+                "arguments": [
+                    { "name": "code", "description": "The code to review", "required": true },
+                    {
+                        "name": "language",
+                        "description": "The language the code is written in",
+                        "required": false
+                    },
+                    { "name": "tone" }
+                ]
+            })
+        );
+    }
+}
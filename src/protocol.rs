@@ -2,18 +2,165 @@
 ///
 /// This module provides a type-safe implementation of MCP over JSON-RPC 2.0,
 /// enabling communication between clients and servers for AI model interactions.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use bon::Builder;
 use thiserror::Error;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use crate::cancellation::CancelledNotificationParams;
+use crate::completion::CompleteRequestParams;
+use crate::elicitation::ElicitRequestParams;
+use crate::logging::{LoggingMessageNotificationParams, SetLevelRequestParams};
+use crate::progress::ProgressNotificationParams;
+use crate::prompt::GetPromptRequestParams;
+use crate::resource::{
+    ReadResourceRequestParams, ResourceUpdatedNotificationParams, SubscribeRequestParams,
+    UnsubscribeRequestParams,
+};
+use crate::sampling::CreateMessageRequestParams;
+use crate::tool::CallToolRequestParams;
+
+/// The latest MCP protocol version this crate implements.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// A revision of the MCP specification.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    #[serde(rename = "2024-11-05")]
+    V2024_11_05,
+    #[serde(rename = "2025-03-26")]
+    V2025_03_26,
+    #[serde(rename = "2025-06-18")]
+    V2025_06_18,
+}
+
+impl ProtocolVersion {
+    /// The newest supported version, which clients request by default.
+    pub const LATEST: ProtocolVersion = ProtocolVersion::V2025_06_18;
+
+    /// Every supported version, oldest first.
+    pub const SUPPORTED: &[ProtocolVersion] = &[
+        ProtocolVersion::V2024_11_05,
+        ProtocolVersion::V2025_03_26,
+        ProtocolVersion::V2025_06_18,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProtocolVersion::V2024_11_05 => "2024-11-05",
+            ProtocolVersion::V2025_03_26 => "2025-03-26",
+            ProtocolVersion::V2025_06_18 => "2025-06-18",
+        }
+    }
 
-/// Protocol version for MCP.
-pub const PROTOCOL_VERSION: &str = "0.2.0";
+    /// Picks the version a server answers an initialize request with.
+    ///
+    /// The requested version is accepted if it is supported. Otherwise the server proposes
+    /// its latest version and leaves it to the client to disconnect if it cannot use it.
+    pub fn negotiate(requested: &str) -> ProtocolVersion {
+        requested.parse().unwrap_or(ProtocolVersion::LATEST)
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ProtocolVersion {
+    type Err = ProtocolError;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        ProtocolVersion::SUPPORTED
+            .iter()
+            .find(|supported| supported.as_str() == version)
+            .copied()
+            .ok_or_else(|| {
+                ProtocolError::ProtocolError(format!("Unsupported protocol version: {}", version))
+            })
+    }
+}
+
+/// JSON-RPC version used by all MCP messages.
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Identifier of a JSON-RPC request, either a number or a string.
+///
+/// Numbers are echoed back exactly as received. Ids that fit in an `i64` always parse as
+/// [`RequestId::Number`], so the same id never compares unequal to itself.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(i64),
+    /// A number above `i64::MAX`
+    Unsigned(u64),
+    String(String),
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestId::Number(id) => write!(f, "{}", id),
+            RequestId::Unsigned(id) => write!(f, "{}", id),
+            RequestId::String(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl From<i64> for RequestId {
+    fn from(id: i64) -> Self {
+        RequestId::Number(id)
+    }
+}
+
+impl From<String> for RequestId {
+    fn from(id: String) -> Self {
+        RequestId::String(id)
+    }
+}
+
+impl From<&str> for RequestId {
+    fn from(id: &str) -> Self {
+        RequestId::String(id.to_string())
+    }
+}
+
+/// Generates unique, monotonically increasing numeric request ids.
+#[derive(Debug)]
+pub struct RequestIdGenerator {
+    next: AtomicI64,
+}
+
+impl RequestIdGenerator {
+    /// Creates a generator whose first id is `start`.
+    pub fn starting_at(start: i64) -> Self {
+        RequestIdGenerator {
+            next: AtomicI64::new(start),
+        }
+    }
+
+    /// Returns the next id.
+    pub fn next_id(&self) -> RequestId {
+        RequestId::Number(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for RequestIdGenerator {
+    fn default() -> Self {
+        RequestIdGenerator::starting_at(1)
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JsonRpcRequest {
     pub jsonrpc: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<Value>,
+    pub id: RequestId,
     pub method: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub params: Option<Value>,
@@ -22,8 +169,7 @@ pub struct JsonRpcRequest {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JsonRpcResponse {
     pub jsonrpc: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<Value>,
+    pub id: RequestId,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -41,19 +187,64 @@ pub struct JsonRpcNotification {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JsonRpcError {
     pub jsonrpc: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<Value>,
+    /// The id of the failed request, or `None` if it could not be determined.
+    pub id: Option<RequestId>,
     pub error: ErrorData,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// A message exchanged between MCP peers.
+///
+/// Notifications are never answered. Code that used to answer them with the former `Nil`
+/// variant should return `Option<JsonRpcMessage>` and produce `None` instead; transports skip
+/// the `null` messages older peers may still send.
+#[derive(Debug, Serialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum JsonRpcMessage {
     Request(JsonRpcRequest),
     Response(JsonRpcResponse),
     Notification(JsonRpcNotification),
     Error(JsonRpcError),
-    Nil, // used to respond to notifications
+}
+
+impl<'de> Deserialize<'de> for JsonRpcMessage {
+    /// Classifies messages by their members instead of trying each variant in turn: a message
+    /// with a `method` is a request when it has an `id` and a notification otherwise, a message
+    /// with an `error` is an error response, and a message with a `result` is a response.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let Value::Object(object) = &value else {
+            return Err(de::Error::custom("JSON-RPC message must be an object"));
+        };
+
+        if object.contains_key("method") {
+            if object.contains_key("id") {
+                serde_json::from_value(value)
+                    .map(JsonRpcMessage::Request)
+                    .map_err(de::Error::custom)
+            } else {
+                serde_json::from_value(value)
+                    .map(JsonRpcMessage::Notification)
+                    .map_err(de::Error::custom)
+            }
+        } else if object.contains_key("error") {
+            if object.contains_key("result") {
+                return Err(de::Error::custom(
+                    "JSON-RPC response must not contain both result and error",
+                ));
+            }
+            serde_json::from_value(value)
+                .map(JsonRpcMessage::Error)
+                .map_err(de::Error::custom)
+        } else if object.contains_key("result") {
+            serde_json::from_value(value)
+                .map(JsonRpcMessage::Response)
+                .map_err(de::Error::custom)
+        } else {
+            Err(de::Error::custom(
+                "JSON-RPC message must contain a method, result or error",
+            ))
+        }
+    }
 }
 
 // Standard JSON-RPC error codes
@@ -63,6 +254,17 @@ pub const METHOD_NOT_FOUND: i32 = -32601;
 pub const INVALID_PARAMS: i32 = -32602;
 pub const INTERNAL_ERROR: i32 = -32603;
 
+// MCP-specific error codes
+pub const CONNECTION_CLOSED: i32 = -32000;
+pub const REQUEST_TIMEOUT: i32 = -32001;
+pub const RESOURCE_NOT_FOUND: i32 = -32002;
+pub const SERVER_BUSY: i32 = -32003;
+pub const FORBIDDEN: i32 = -32004;
+/// A request needs a capability the peer did not advertise
+pub const CAPABILITY_NOT_SUPPORTED: i32 = -32005;
+/// The user declined the request, e.g. a sampling request they did not approve
+pub const USER_REJECTED: i32 = -1;
+
 /// Error information for JSON-RPC error responses.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ErrorData {
@@ -86,43 +288,537 @@ pub struct InitializeResult {
     pub server_info: Implementation,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub instructions: Option<String>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
+/// Parameters of the initialize request sent by the client.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeRequestParams {
+    /// The latest protocol version the client supports.
+    pub protocol_version: String,
+    pub capabilities: ClientCapabilities,
+    pub client_info: Implementation,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The name and version of a client or server, and how host UIs present it.
+///
+/// ```text
+/// let info = Implementation::builder()
+///     .name("notes-server")
+///     .version(env!("CARGO_PKG_VERSION"))
+///     .title("Notes")
+///     .website_url("https://example.com/notes")
+///     .icons(vec![Icon::builder().src("https://example.com/notes.png").build()])
+///     .build();
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Builder)]
+#[serde(rename_all = "camelCase")]
 pub struct Implementation {
+    /// The programmatic name, used as the display name when there is no title
+    #[builder(into)]
     pub name: String,
+    #[builder(into)]
     pub version: String,
+    /// A human-readable name for display, e.g. "Notes" for `notes-server`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub title: Option<String>,
+    /// The URL of the implementation's website
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub website_url: Option<String>,
+    /// Icons host UIs may show next to the implementation's name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icons: Option<Vec<Icon>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+impl Implementation {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Implementation {
+            name: name.into(),
+            version: version.into(),
+            ..Default::default()
+        }
+    }
+
+    /// The name to display: the title, or the name if there is none.
+    pub fn display_name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// An icon a host UI may display.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct Icon {
+    /// An `https:` or `data:` URI of the image
+    #[builder(into)]
+    pub src: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub mime_type: Option<String>,
+    /// The sizes the image is available in, e.g. `48x48`, or `any` for scalable formats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sizes: Option<Vec<String>>,
+    /// The theme the icon is designed for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<IconTheme>,
+}
+
+/// The background an [`Icon`] is meant to be shown on.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IconTheme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Builder)]
 pub struct ServerCapabilities {
+    /// Experimental, non-standard capabilities that the server supports
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(field)]
+    pub experimental: Option<HashMap<String, Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prompts: Option<PromptsCapability>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resources: Option<ResourcesCapability>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<ToolsCapability>,
+    /// Present if the server can send log messages to the client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingCapability>,
+    /// Present if the server offers argument autocompletion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completions: Option<CompletionsCapability>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+impl<S: server_capabilities_builder::State> ServerCapabilitiesBuilder<S> {
+    /// Advertises an experimental capability under `name`.
+    pub fn experimental(mut self, name: impl Into<String>, capability: Value) -> Self {
+        self.experimental
+            .get_or_insert_with(HashMap::new)
+            .insert(name.into(), capability);
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PromptsCapability {
     pub list_changed: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourcesCapability {
     pub subscribe: Option<bool>,
     pub list_changed: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolsCapability {
     pub list_changed: Option<bool>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LoggingCapability {}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CompletionsCapability {}
+
+/// Capabilities a client may support, sent with the initialize request.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientCapabilities {
+    /// Present if the client can list roots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<RootsCapability>,
+    /// Present if the client supports sampling from an LLM
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingCapability>,
+    /// Present if the client supports elicitation from the server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elicitation: Option<ElicitationCapability>,
+    /// Experimental, non-standard capabilities that the client supports
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<HashMap<String, Value>>,
+}
+
+impl ClientCapabilities {
+    /// Declares that the client lists roots, and notifies the server when they change.
+    pub fn with_roots(mut self) -> Self {
+        self.roots = Some(RootsCapability {
+            list_changed: Some(true),
+        });
+        self
+    }
+
+    /// Declares that the client asks the user for the input servers elicit.
+    pub fn with_elicitation(mut self) -> Self {
+        self.elicitation = Some(ElicitationCapability {});
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RootsCapability {
+    /// Whether the client will notify the server when the list of roots changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SamplingCapability {}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ElicitationCapability {}
+
+/// An opaque token used to represent a cursor for pagination.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct Cursor(pub String);
+
+impl From<String> for Cursor {
+    fn from(cursor: String) -> Self {
+        Cursor(cursor)
+    }
+}
+
+impl From<&str> for Cursor {
+    fn from(cursor: &str) -> Self {
+        Cursor(cursor.to_string())
+    }
+}
+
+/// Parameters shared by all list requests that support pagination.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginatedRequestParams {
+    /// An opaque token representing the current pagination position.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Cursor>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// A response that indicates success but carries no data.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct EmptyResult {}
+
+/// Requests sent from the client to the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientRequest {
+    Initialize(InitializeRequestParams),
+    Ping,
+    ListTools(PaginatedRequestParams),
+    CallTool(CallToolRequestParams),
+    ListResources(PaginatedRequestParams),
+    ListResourceTemplates(PaginatedRequestParams),
+    ReadResource(ReadResourceRequestParams),
+    Subscribe(SubscribeRequestParams),
+    Unsubscribe(UnsubscribeRequestParams),
+    ListPrompts(PaginatedRequestParams),
+    GetPrompt(GetPromptRequestParams),
+    Complete(CompleteRequestParams),
+    SetLevel(SetLevelRequestParams),
+}
+
+impl ClientRequest {
+    /// Returns the JSON-RPC method name of this request.
+    pub fn method(&self) -> &'static str {
+        match self {
+            ClientRequest::Initialize(_) => "initialize",
+            ClientRequest::Ping => "ping",
+            ClientRequest::ListTools(_) => "tools/list",
+            ClientRequest::CallTool(_) => "tools/call",
+            ClientRequest::ListResources(_) => "resources/list",
+            ClientRequest::ListResourceTemplates(_) => "resources/templates/list",
+            ClientRequest::ReadResource(_) => "resources/read",
+            ClientRequest::Subscribe(_) => "resources/subscribe",
+            ClientRequest::Unsubscribe(_) => "resources/unsubscribe",
+            ClientRequest::ListPrompts(_) => "prompts/list",
+            ClientRequest::GetPrompt(_) => "prompts/get",
+            ClientRequest::Complete(_) => "completion/complete",
+            ClientRequest::SetLevel(_) => "logging/setLevel",
+        }
+    }
+
+    /// Serializes the parameters of this request.
+    pub fn params(&self) -> Result<Option<Value>, ProtocolError> {
+        match self {
+            ClientRequest::Initialize(params) => to_params(params),
+            ClientRequest::Ping => Ok(None),
+            ClientRequest::ListTools(params) => to_params(params),
+            ClientRequest::CallTool(params) => to_params(params),
+            ClientRequest::ListResources(params) => to_params(params),
+            ClientRequest::ListResourceTemplates(params) => to_params(params),
+            ClientRequest::ReadResource(params) => to_params(params),
+            ClientRequest::Subscribe(params) => to_params(params),
+            ClientRequest::Unsubscribe(params) => to_params(params),
+            ClientRequest::ListPrompts(params) => to_params(params),
+            ClientRequest::GetPrompt(params) => to_params(params),
+            ClientRequest::Complete(params) => to_params(params),
+            ClientRequest::SetLevel(params) => to_params(params),
+        }
+    }
+
+    /// Parses a request from its method name and raw parameters.
+    pub fn from_parts(method: &str, params: Option<Value>) -> Result<Self, ProtocolError> {
+        match method {
+            "initialize" => Ok(ClientRequest::Initialize(from_params(params)?)),
+            "ping" => Ok(ClientRequest::Ping),
+            "tools/list" => Ok(ClientRequest::ListTools(from_params(params)?)),
+            "tools/call" => Ok(ClientRequest::CallTool(from_params(params)?)),
+            "resources/list" => Ok(ClientRequest::ListResources(from_params(params)?)),
+            "resources/templates/list" => {
+                Ok(ClientRequest::ListResourceTemplates(from_params(params)?))
+            }
+            "resources/read" => Ok(ClientRequest::ReadResource(from_params(params)?)),
+            "resources/subscribe" => Ok(ClientRequest::Subscribe(from_params(params)?)),
+            "resources/unsubscribe" => Ok(ClientRequest::Unsubscribe(from_params(params)?)),
+            "prompts/list" => Ok(ClientRequest::ListPrompts(from_params(params)?)),
+            "prompts/get" => Ok(ClientRequest::GetPrompt(from_params(params)?)),
+            "completion/complete" => Ok(ClientRequest::Complete(from_params(params)?)),
+            "logging/setLevel" => Ok(ClientRequest::SetLevel(from_params(params)?)),
+            _ => Err(ProtocolError::MethodNotImplemented(method.to_string())),
+        }
+    }
+
+    /// Wraps this request into a JSON-RPC request with the given id.
+    pub fn into_request(self, id: RequestId) -> Result<JsonRpcRequest, ProtocolError> {
+        Ok(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method: self.method().to_string(),
+            params: self.params()?,
+        })
+    }
+}
+
+impl TryFrom<JsonRpcRequest> for ClientRequest {
+    type Error = ProtocolError;
+
+    fn try_from(request: JsonRpcRequest) -> Result<Self, Self::Error> {
+        ClientRequest::from_parts(&request.method, request.params)
+    }
+}
+
+/// Requests sent from the server to the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerRequest {
+    Ping,
+    CreateMessage(Box<CreateMessageRequestParams>),
+    ListRoots,
+    Elicit(ElicitRequestParams),
+}
+
+impl ServerRequest {
+    /// Returns the JSON-RPC method name of this request.
+    pub fn method(&self) -> &'static str {
+        match self {
+            ServerRequest::Ping => "ping",
+            ServerRequest::CreateMessage(_) => "sampling/createMessage",
+            ServerRequest::ListRoots => "roots/list",
+            ServerRequest::Elicit(_) => "elicitation/create",
+        }
+    }
+
+    /// Serializes the parameters of this request.
+    pub fn params(&self) -> Result<Option<Value>, ProtocolError> {
+        match self {
+            ServerRequest::Ping => Ok(None),
+            ServerRequest::CreateMessage(params) => to_params(params),
+            ServerRequest::ListRoots => Ok(None),
+            ServerRequest::Elicit(params) => to_params(params),
+        }
+    }
+
+    /// Parses a request from its method name and raw parameters.
+    pub fn from_parts(method: &str, params: Option<Value>) -> Result<Self, ProtocolError> {
+        match method {
+            "ping" => Ok(ServerRequest::Ping),
+            "sampling/createMessage" => Ok(ServerRequest::CreateMessage(from_params(params)?)),
+            "roots/list" => Ok(ServerRequest::ListRoots),
+            "elicitation/create" => Ok(ServerRequest::Elicit(from_params(params)?)),
+            _ => Err(ProtocolError::MethodNotImplemented(method.to_string())),
+        }
+    }
+
+    /// Wraps this request into a JSON-RPC request with the given id.
+    pub fn into_request(self, id: RequestId) -> Result<JsonRpcRequest, ProtocolError> {
+        Ok(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method: self.method().to_string(),
+            params: self.params()?,
+        })
+    }
+}
+
+impl TryFrom<JsonRpcRequest> for ServerRequest {
+    type Error = ProtocolError;
+
+    fn try_from(request: JsonRpcRequest) -> Result<Self, Self::Error> {
+        ServerRequest::from_parts(&request.method, request.params)
+    }
+}
+
+/// Notifications sent from the server to the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerNotification {
+    Cancelled(CancelledNotificationParams),
+    Progress(ProgressNotificationParams),
+    LoggingMessage(LoggingMessageNotificationParams),
+    ResourceUpdated(ResourceUpdatedNotificationParams),
+    ResourceListChanged,
+    ToolListChanged,
+    PromptListChanged,
+}
+
+impl ServerNotification {
+    /// Returns the JSON-RPC method name of this notification.
+    pub fn method(&self) -> &'static str {
+        match self {
+            ServerNotification::Cancelled(_) => "notifications/cancelled",
+            ServerNotification::Progress(_) => "notifications/progress",
+            ServerNotification::LoggingMessage(_) => "notifications/message",
+            ServerNotification::ResourceUpdated(_) => "notifications/resources/updated",
+            ServerNotification::ResourceListChanged => "notifications/resources/list_changed",
+            ServerNotification::ToolListChanged => "notifications/tools/list_changed",
+            ServerNotification::PromptListChanged => "notifications/prompts/list_changed",
+        }
+    }
+
+    /// Serializes the parameters of this notification.
+    pub fn params(&self) -> Result<Option<Value>, ProtocolError> {
+        match self {
+            ServerNotification::Cancelled(params) => to_params(params),
+            ServerNotification::Progress(params) => to_params(params),
+            ServerNotification::LoggingMessage(params) => to_params(params),
+            ServerNotification::ResourceUpdated(params) => to_params(params),
+            ServerNotification::ResourceListChanged
+            | ServerNotification::ToolListChanged
+            | ServerNotification::PromptListChanged => Ok(None),
+        }
+    }
+
+    /// Parses a notification from its method name and raw parameters.
+    pub fn from_parts(method: &str, params: Option<Value>) -> Result<Self, ProtocolError> {
+        match method {
+            "notifications/cancelled" => Ok(ServerNotification::Cancelled(from_params(params)?)),
+            "notifications/progress" => Ok(ServerNotification::Progress(from_params(params)?)),
+            "notifications/message" => Ok(ServerNotification::LoggingMessage(from_params(params)?)),
+            "notifications/resources/updated" => {
+                Ok(ServerNotification::ResourceUpdated(from_params(params)?))
+            }
+            "notifications/resources/list_changed" => Ok(ServerNotification::ResourceListChanged),
+            "notifications/tools/list_changed" => Ok(ServerNotification::ToolListChanged),
+            "notifications/prompts/list_changed" => Ok(ServerNotification::PromptListChanged),
+            _ => Err(ProtocolError::MethodNotImplemented(method.to_string())),
+        }
+    }
+
+    /// Wraps this notification into a JSON-RPC notification.
+    pub fn into_notification(self) -> Result<JsonRpcNotification, ProtocolError> {
+        Ok(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: self.method().to_string(),
+            params: self.params()?,
+        })
+    }
+}
+
+impl TryFrom<JsonRpcNotification> for ServerNotification {
+    type Error = ProtocolError;
+
+    fn try_from(notification: JsonRpcNotification) -> Result<Self, Self::Error> {
+        ServerNotification::from_parts(&notification.method, notification.params)
+    }
+}
+
+/// Notifications sent from the client to the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientNotification {
+    Cancelled(CancelledNotificationParams),
+    Progress(ProgressNotificationParams),
+    Initialized,
+    RootsListChanged,
+}
+
+impl ClientNotification {
+    /// Returns the JSON-RPC method name of this notification.
+    pub fn method(&self) -> &'static str {
+        match self {
+            ClientNotification::Cancelled(_) => "notifications/cancelled",
+            ClientNotification::Progress(_) => "notifications/progress",
+            ClientNotification::Initialized => "notifications/initialized",
+            ClientNotification::RootsListChanged => "notifications/roots/list_changed",
+        }
+    }
+
+    /// Serializes the parameters of this notification.
+    pub fn params(&self) -> Result<Option<Value>, ProtocolError> {
+        match self {
+            ClientNotification::Cancelled(params) => to_params(params),
+            ClientNotification::Progress(params) => to_params(params),
+            ClientNotification::Initialized | ClientNotification::RootsListChanged => Ok(None),
+        }
+    }
+
+    /// Parses a notification from its method name and raw parameters.
+    pub fn from_parts(method: &str, params: Option<Value>) -> Result<Self, ProtocolError> {
+        match method {
+            "notifications/cancelled" => Ok(ClientNotification::Cancelled(from_params(params)?)),
+            "notifications/progress" => Ok(ClientNotification::Progress(from_params(params)?)),
+            "notifications/initialized" => Ok(ClientNotification::Initialized),
+            "notifications/roots/list_changed" => Ok(ClientNotification::RootsListChanged),
+            _ => Err(ProtocolError::MethodNotImplemented(method.to_string())),
+        }
+    }
+
+    /// Wraps this notification into a JSON-RPC notification.
+    pub fn into_notification(self) -> Result<JsonRpcNotification, ProtocolError> {
+        Ok(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: self.method().to_string(),
+            params: self.params()?,
+        })
+    }
+}
+
+impl TryFrom<JsonRpcNotification> for ClientNotification {
+    type Error = ProtocolError;
+
+    fn try_from(notification: JsonRpcNotification) -> Result<Self, Self::Error> {
+        ClientNotification::from_parts(&notification.method, notification.params)
+    }
+}
+
+fn to_params<T: Serialize>(params: &T) -> Result<Option<Value>, ProtocolError> {
+    serde_json::to_value(params)
+        .map(Some)
+        .map_err(|e| ProtocolError::InternalError(e.to_string()))
+}
+
+/// Deserializes request parameters, treating missing params as an empty object.
+pub fn from_params<T: DeserializeOwned>(params: Option<Value>) -> Result<T, ProtocolError> {
+    let params = match params {
+        Some(Value::Null) | None => Value::Object(Map::new()),
+        Some(params) => params,
+    };
+    serde_json::from_value(params).map_err(|e| ProtocolError::InvalidParams(e.to_string()))
+}
+
 /// Error types that can occur in the MCP protocol.
 #[derive(Debug, Clone, Error)]
 pub enum ProtocolError {
@@ -138,6 +834,30 @@ pub enum ProtocolError {
     InvalidParams(String),
     #[error("Internal error: {0}")]
     InternalError(String),
+    #[error("Request cancelled: {0}")]
+    Cancelled(String),
+    #[error("Capability not negotiated: {capability}{}", required_by(.method))]
+    CapabilityNotSupported {
+        /// The path of the capability, e.g. `resources.subscribe`
+        capability: String,
+        /// The method that requires the capability, if known
+        method: Option<String>,
+    },
+    #[error("Request timed out: {0}")]
+    Timeout(String),
+    #[error("Denied by the user: {0}")]
+    Denied(String),
+    /// The peer answered with an error response, kept with its code and data
+    #[error("Error response: {} (code {})", .0.message, .0.code)]
+    ErrorResponse(ErrorData),
+}
+
+/// Names the method that needs a missing capability, for error messages.
+fn required_by(method: &Option<String>) -> String {
+    method
+        .as_ref()
+        .map(|method| format!(", which {} requires", method))
+        .unwrap_or_default()
 }
 
 impl From<ProtocolError> for ErrorData {
@@ -173,6 +893,464 @@ impl From<ProtocolError> for ErrorData {
                 message: msg,
                 data: None,
             },
+            ProtocolError::Cancelled(msg) => ErrorData {
+                code: INTERNAL_ERROR,
+                message: msg,
+                data: None,
+            },
+            ProtocolError::CapabilityNotSupported { capability, method } => {
+                let message = format!(
+                    "Capability not negotiated: {}{}",
+                    capability,
+                    required_by(&method)
+                );
+                let mut data = Map::new();
+                data.insert("capability".to_string(), Value::String(capability));
+                if let Some(method) = method {
+                    data.insert("method".to_string(), Value::String(method));
+                }
+                ErrorData {
+                    code: CAPABILITY_NOT_SUPPORTED,
+                    message,
+                    data: Some(Value::Object(data)),
+                }
+            }
+            ProtocolError::Timeout(msg) => ErrorData {
+                code: REQUEST_TIMEOUT,
+                message: msg,
+                data: None,
+            },
+            ProtocolError::Denied(msg) => ErrorData {
+                code: USER_REJECTED,
+                message: msg,
+                data: None,
+            },
+            ProtocolError::ErrorResponse(error) => error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_client_request_round_trip() {
+        let request = ClientRequest::CallTool(CallToolRequestParams {
+            name: "search".to_string(),
+            arguments: Some(json!({ "query": "rust" }).as_object().unwrap().clone()),
+            meta: None,
+        })
+        .into_request(RequestId::Number(1))
+        .unwrap();
+
+        assert_eq!(request.method, "tools/call");
+        assert_eq!(
+            request.params,
+            Some(json!({ "name": "search", "arguments": { "query": "rust" } }))
+        );
+
+        let parsed = ClientRequest::try_from(request).unwrap();
+        assert!(matches!(parsed, ClientRequest::CallTool(params) if params.name == "search"));
+    }
+
+    #[test]
+    fn test_request_id_serialization() {
+        assert_eq!(serde_json::to_value(RequestId::from(7)).unwrap(), json!(7));
+        assert_eq!(serde_json::to_value(RequestId::from("abc")).unwrap(), json!("abc"));
+        assert_eq!(
+            serde_json::from_value::<RequestId>(json!("7")).unwrap(),
+            RequestId::String("7".to_string())
+        );
+        assert!(serde_json::from_value::<RequestId>(json!(null)).is_err());
+        assert_eq!(RequestId::from(7).to_string(), "7");
+    }
+
+    #[test]
+    fn test_large_request_ids_round_trip() {
+        // The TypeScript SDK counts ids up from 0 and may reach Number.MAX_SAFE_INTEGER; other
+        // peers use the full u64 range.
+        for (id, expected) in [
+            (json!(0), RequestId::Number(0)),
+            (json!(2147483648u64), RequestId::Number(2147483648)),
+            (json!(9007199254740991u64), RequestId::Number(9007199254740991)),
+            (json!(i64::MAX), RequestId::Number(i64::MAX)),
+            (json!(i64::MAX as u64 + 1), RequestId::Unsigned(i64::MAX as u64 + 1)),
+            (json!(u64::MAX), RequestId::Unsigned(u64::MAX)),
+            (json!(-1), RequestId::Number(-1)),
+        ] {
+            let value = json!({ "jsonrpc": "2.0", "id": id, "result": {} });
+            let message: JsonRpcMessage = serde_json::from_str(&value.to_string()).unwrap();
+            let JsonRpcMessage::Response(response) = &message else {
+                panic!("Expected a response");
+            };
+            assert_eq!(response.id, expected);
+            assert_eq!(serde_json::to_value(&message).unwrap(), value);
+            let mut bytes = value.to_string().into_bytes();
+            assert_eq!(crate::transport::decode_message(&mut bytes).unwrap(), message);
+        }
+        assert_eq!(RequestId::Unsigned(u64::MAX).to_string(), "18446744073709551615");
+        assert!(serde_json::from_value::<RequestId>(json!(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_request_id_generator_is_ordered() {
+        let generator = RequestIdGenerator::default();
+        let first = generator.next_id();
+        let second = generator.next_id();
+        assert_eq!(first, RequestId::Number(1));
+        assert!(first < second);
+    }
+
+    fn parse(json: Value) -> Result<JsonRpcMessage, serde_json::Error> {
+        serde_json::from_value(json)
+    }
+
+    #[test]
+    fn test_classify_request_without_params() {
+        let message = parse(json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" })).unwrap();
+        assert!(matches!(message, JsonRpcMessage::Request(r) if r.id == RequestId::Number(1)));
+    }
+
+    #[test]
+    fn test_classify_notification() {
+        let message = parse(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized"
+        }))
+        .unwrap();
+        assert!(matches!(message, JsonRpcMessage::Notification(n) if n.params.is_none()));
+
+        let message = parse(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": { "progressToken": 1, "progress": 5 }
+        }))
+        .unwrap();
+        assert!(matches!(message, JsonRpcMessage::Notification(_)));
+    }
+
+    #[test]
+    fn test_classify_request_with_string_id() {
+        let message = parse(json!({
+            "jsonrpc": "2.0",
+            "id": "abc",
+            "method": "tools/list",
+            "params": {}
+        }))
+        .unwrap();
+        assert!(matches!(message, JsonRpcMessage::Request(r) if r.id == RequestId::from("abc")));
+    }
+
+    #[test]
+    fn test_classify_response() {
+        let message = parse(json!({ "jsonrpc": "2.0", "id": 2, "result": {} })).unwrap();
+        assert!(matches!(message, JsonRpcMessage::Response(r) if r.result == Some(json!({}))));
+    }
+
+    #[test]
+    fn test_classify_error_only_response() {
+        let message = parse(json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "error": { "code": METHOD_NOT_FOUND, "message": "Unknown method" }
+        }))
+        .unwrap();
+        assert!(
+            matches!(message, JsonRpcMessage::Error(e) if e.id == Some(RequestId::Number(3)))
+        );
+    }
+
+    #[test]
+    fn test_classify_error_without_id() {
+        let message = parse(json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": PARSE_ERROR, "message": "Parse error" }
+        }))
+        .unwrap();
+        assert!(matches!(message, JsonRpcMessage::Error(e) if e.id.is_none()));
+
+        let message = parse(json!({
+            "jsonrpc": "2.0",
+            "error": { "code": PARSE_ERROR, "message": "Parse error" }
+        }))
+        .unwrap();
+        assert!(matches!(message, JsonRpcMessage::Error(e) if e.id.is_none()));
+    }
+
+    #[test]
+    fn test_classify_invalid_shapes() {
+        assert!(parse(json!({ "jsonrpc": "2.0", "id": 1 })).is_err());
+        assert!(parse(json!({ "jsonrpc": "2.0", "result": {} })).is_err());
+        assert!(parse(json!({ "jsonrpc": "2.0", "id": null, "method": "ping" })).is_err());
+        assert!(parse(json!([])).is_err());
+        assert!(parse(json!(null)).is_err());
+        assert!(
+            parse(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {},
+                "error": { "code": INTERNAL_ERROR, "message": "Both" }
+            }))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let messages = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": "a", "result": { "tools": [] } }),
+            json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": "x" } }),
+        ];
+        for json in messages {
+            let message = parse(json.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&message).unwrap(), json);
         }
     }
+
+    #[test]
+    fn test_client_request_missing_params() {
+        let parsed = ClientRequest::from_parts("tools/list", None).unwrap();
+        assert_eq!(
+            parsed,
+            ClientRequest::ListTools(PaginatedRequestParams::default())
+        );
+
+        let parsed = ClientRequest::from_parts("ping", None).unwrap();
+        assert_eq!(parsed, ClientRequest::Ping);
+    }
+
+    #[test]
+    fn test_client_request_invalid_params() {
+        let result = ClientRequest::from_parts("resources/read", Some(json!({ "url": "x" })));
+        assert!(matches!(result, Err(ProtocolError::InvalidParams(_))));
+    }
+
+    #[test]
+    fn test_unknown_method() {
+        let result = ServerRequest::from_parts("tools/call", None);
+        assert!(matches!(
+            result,
+            Err(ProtocolError::MethodNotImplemented(_))
+        ));
+    }
+
+    #[test]
+    fn test_server_request_create_message() {
+        let params = json!({
+            "messages": [{ "role": "user", "content": { "type": "text", "text": "hi" } }],
+            "maxTokens": 100
+        });
+        let parsed =
+            ServerRequest::from_parts("sampling/createMessage", Some(params.clone())).unwrap();
+        assert_eq!(parsed.params().unwrap(), Some(params));
+    }
+
+    #[test]
+    fn test_protocol_version_negotiation() {
+        assert_eq!(
+            ProtocolVersion::negotiate("2024-11-05"),
+            ProtocolVersion::V2024_11_05
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate("2099-01-01"),
+            ProtocolVersion::LATEST
+        );
+        assert_eq!(ProtocolVersion::negotiate("0.2.0"), ProtocolVersion::LATEST);
+        assert_eq!(ProtocolVersion::LATEST.as_str(), PROTOCOL_VERSION);
+        assert!(ProtocolVersion::V2024_11_05 < ProtocolVersion::V2025_03_26);
+
+        assert_eq!(
+            serde_json::to_value(ProtocolVersion::V2025_03_26).unwrap(),
+            json!("2025-03-26")
+        );
+        assert!(serde_json::from_value::<ProtocolVersion>(json!("1.0")).is_err());
+    }
+
+    #[test]
+    fn test_meta_is_preserved() {
+        let params: CallToolRequestParams = from_params(Some(json!({
+            "name": "search",
+            "_meta": { "progressToken": 1, "com.example/trace": "abc" }
+        })))
+        .unwrap();
+        let meta = params.meta.clone().unwrap();
+        assert_eq!(meta["com.example/trace"], json!("abc"));
+        assert_eq!(
+            serde_json::to_value(&params).unwrap()["_meta"]["progressToken"],
+            json!(1)
+        );
+
+        let tool = crate::tool::Tool::builder()
+            .name("search")
+            .meta(json!({ "version": 2 }).as_object().unwrap().clone())
+            .build();
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap()["_meta"],
+            json!({ "version": 2 })
+        );
+        let without_meta = crate::tool::Tool::builder().name("search").build();
+        assert!(
+            serde_json::to_value(&without_meta)
+                .unwrap()
+                .get("_meta")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_initialize_params_with_client_capabilities() {
+        let request = ClientRequest::from_parts(
+            "initialize",
+            Some(json!({
+                "protocolVersion": "2025-06-18",
+                "capabilities": {
+                    "roots": { "listChanged": true },
+                    "sampling": {},
+                    "experimental": { "com.example/feature": { "enabled": true } }
+                },
+                "clientInfo": { "name": "client", "version": "1.0.0" }
+            })),
+        )
+        .unwrap();
+        let ClientRequest::Initialize(params) = request else {
+            panic!("Expected initialize request");
+        };
+
+        let capabilities = params.capabilities;
+        assert_eq!(capabilities.roots.unwrap().list_changed, Some(true));
+        assert_eq!(capabilities.sampling, Some(SamplingCapability {}));
+        assert_eq!(capabilities.elicitation, None);
+        assert_eq!(
+            capabilities.experimental.unwrap()["com.example/feature"],
+            json!({ "enabled": true })
+        );
+        assert_eq!(
+            serde_json::to_value(ClientCapabilities::default()).unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn test_implementation_metadata() {
+        let info = Implementation::builder()
+            .name("notes-server")
+            .version("1.2.0")
+            .title("Notes")
+            .website_url("https://example.com/notes")
+            .icons(vec![
+                Icon::builder()
+                    .src("https://example.com/notes.svg")
+                    .mime_type("image/svg+xml")
+                    .sizes(vec!["any".to_string()])
+                    .theme(IconTheme::Dark)
+                    .build(),
+            ])
+            .build();
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "name": "notes-server",
+                "version": "1.2.0",
+                "title": "Notes",
+                "websiteUrl": "https://example.com/notes",
+                "icons": [{
+                    "src": "https://example.com/notes.svg",
+                    "mimeType": "image/svg+xml",
+                    "sizes": ["any"],
+                    "theme": "dark"
+                }]
+            })
+        );
+        assert_eq!(serde_json::from_value::<Implementation>(value).unwrap(), info);
+        assert_eq!(info.display_name(), "Notes");
+
+        let plain = Implementation::new("notes-server", "1.2.0");
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap(),
+            json!({ "name": "notes-server", "version": "1.2.0" })
+        );
+        assert_eq!(plain.display_name(), "notes-server");
+    }
+
+    #[test]
+    fn test_server_capabilities_builder() {
+        let capabilities = ServerCapabilities::builder()
+            .tools(ToolsCapability {
+                list_changed: Some(true),
+            })
+            .logging(LoggingCapability {})
+            .completions(CompletionsCapability {})
+            .experimental("com.example/feature", json!({ "enabled": true }))
+            .build();
+        let value = serde_json::to_value(&capabilities).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "experimental": { "com.example/feature": { "enabled": true } },
+                "tools": { "listChanged": true },
+                "logging": {},
+                "completions": {}
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<ServerCapabilities>(value).unwrap(),
+            capabilities
+        );
+    }
+
+    #[test]
+    fn test_notification_round_trip() {
+        let notification = ServerNotification::ResourceUpdated(ResourceUpdatedNotificationParams {
+            uri: "file:///notes.txt".to_string(),
+            meta: None,
+        })
+        .into_notification()
+        .unwrap();
+        assert_eq!(notification.method, "notifications/resources/updated");
+        assert_eq!(
+            notification.params,
+            Some(json!({ "uri": "file:///notes.txt" }))
+        );
+        assert!(matches!(
+            ServerNotification::try_from(notification).unwrap(),
+            ServerNotification::ResourceUpdated(params) if params.uri == "file:///notes.txt"
+        ));
+
+        let notification = ServerNotification::ToolListChanged.into_notification().unwrap();
+        assert_eq!(notification.params, None);
+        assert_eq!(
+            ServerNotification::try_from(notification).unwrap(),
+            ServerNotification::ToolListChanged
+        );
+
+        let message = ServerNotification::from_parts(
+            "notifications/message",
+            Some(json!({ "level": "warning", "logger": "db", "data": { "rows": 0 } })),
+        )
+        .unwrap();
+        let ServerNotification::LoggingMessage(params) = message else {
+            panic!("Expected a logging message");
+        };
+        assert_eq!(params.level, crate::logging::LoggingLevel::Warning);
+        assert_eq!(params.data, json!({ "rows": 0 }));
+
+        assert_eq!(
+            ClientNotification::from_parts("notifications/roots/list_changed", None).unwrap(),
+            ClientNotification::RootsListChanged
+        );
+        assert!(matches!(
+            ClientNotification::from_parts("notifications/tools/list_changed", None),
+            Err(ProtocolError::MethodNotImplemented(_))
+        ));
+        assert!(matches!(
+            ClientNotification::from_parts("notifications/cancelled", Some(json!({}))),
+            Err(ProtocolError::InvalidParams(_))
+        ));
+    }
 }
@@ -0,0 +1,308 @@
+/// Zero-copy access to received messages.
+///
+/// Deserializing a [`JsonRpcMessage`] materializes every parameter and result as a
+/// [`Value`], which allocates for each nested member. Code that only routes messages, or
+/// forwards large tool results unchanged, does not need that. A [`RawFrame`] keeps the text a
+/// transport received, and [`RawFrame::message`] parses it into a [`BorrowedMessage`] whose
+/// method names borrow from the text and whose parameters and results stay unparsed
+/// [`RawValue`]s. They can be deserialized into a typed value directly, or serialized again
+/// verbatim.
+use std::borrow::Cow;
+
+use serde::Serialize;
+use serde::de::{Deserialize, DeserializeOwned, Deserializer};
+use serde_json::Value;
+use serde_json::value::RawValue;
+
+use crate::protocol::{
+    ErrorData, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    ProtocolError, RequestId,
+};
+
+/// The text of one received message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame {
+    text: String,
+}
+
+impl RawFrame {
+    pub fn new(text: impl Into<String>) -> Self {
+        RawFrame { text: text.into() }
+    }
+
+    /// Serializes an already parsed message into a frame.
+    pub fn from_message(message: &JsonRpcMessage) -> Result<Self, ProtocolError> {
+        serde_json::to_string(message)
+            .map(RawFrame::new)
+            .map_err(|e| ProtocolError::InternalError(e.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// Parses the frame without copying method names, parameters or results.
+    pub fn message(&self) -> Result<BorrowedMessage<'_>, ProtocolError> {
+        BorrowedMessage::parse(&self.text)
+    }
+
+    /// Parses the frame into an owned message.
+    pub fn into_message(self) -> Result<JsonRpcMessage, ProtocolError> {
+        serde_json::from_str(&self.text).map_err(|e| ProtocolError::ParseError(e.to_string()))
+    }
+}
+
+/// A message that borrows from the text it was parsed from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum BorrowedMessage<'a> {
+    Request(BorrowedRequest<'a>),
+    Response(BorrowedResponse<'a>),
+    Notification(BorrowedNotification<'a>),
+    Error(JsonRpcError),
+}
+
+/// A request whose parameters are left unparsed.
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowedRequest<'a> {
+    pub jsonrpc: Cow<'a, str>,
+    pub id: RequestId,
+    pub method: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<&'a RawValue>,
+}
+
+/// A notification whose parameters are left unparsed.
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowedNotification<'a> {
+    pub jsonrpc: Cow<'a, str>,
+    pub method: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<&'a RawValue>,
+}
+
+/// A successful response whose result is left unparsed.
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowedResponse<'a> {
+    pub jsonrpc: Cow<'a, str>,
+    pub id: RequestId,
+    pub result: &'a RawValue,
+}
+
+/// All members a message may have, read in a single pass.
+#[derive(serde::Deserialize)]
+struct Envelope<'a> {
+    #[serde(borrow)]
+    jsonrpc: Cow<'a, str>,
+    /// `Some` whenever the member is present, even if it is `null`
+    #[serde(borrow, default, deserialize_with = "present")]
+    id: Option<&'a RawValue>,
+    #[serde(borrow, default)]
+    method: Option<Text<'a>>,
+    #[serde(borrow, default)]
+    params: Option<&'a RawValue>,
+    #[serde(borrow, default, deserialize_with = "present")]
+    result: Option<&'a RawValue>,
+    #[serde(default)]
+    error: Option<ErrorData>,
+}
+
+/// A string that borrows from the input unless it contains escapes. Serde only borrows
+/// `Cow` fields directly, not inside an `Option`.
+#[derive(serde::Deserialize)]
+struct Text<'a>(#[serde(borrow)] Cow<'a, str>);
+
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<&'de RawValue>, D::Error> {
+    <&RawValue>::deserialize(deserializer).map(Some)
+}
+
+impl<'a> BorrowedMessage<'a> {
+    /// Parses `text`, classifying the message the same way [`JsonRpcMessage`] does.
+    pub fn parse(text: &'a str) -> Result<Self, ProtocolError> {
+        let envelope: Envelope<'a> =
+            serde_json::from_str(text).map_err(|e| ProtocolError::ParseError(e.to_string()))?;
+        let id = envelope
+            .id
+            .map(parse_raw::<Option<RequestId>>)
+            .transpose()?;
+
+        match (envelope.method, envelope.error, envelope.result) {
+            (Some(Text(method)), _, _) => match id {
+                Some(Some(id)) => Ok(BorrowedMessage::Request(BorrowedRequest {
+                    jsonrpc: envelope.jsonrpc,
+                    id,
+                    method,
+                    params: envelope.params,
+                })),
+                Some(None) => Err(ProtocolError::ParseError(
+                    "Request id must not be null".to_string(),
+                )),
+                None => Ok(BorrowedMessage::Notification(BorrowedNotification {
+                    jsonrpc: envelope.jsonrpc,
+                    method,
+                    params: envelope.params,
+                })),
+            },
+            (None, Some(_), Some(_)) => Err(ProtocolError::ParseError(
+                "JSON-RPC response must not contain both result and error".to_string(),
+            )),
+            (None, Some(error), None) => Ok(BorrowedMessage::Error(JsonRpcError {
+                jsonrpc: envelope.jsonrpc.into_owned(),
+                id: id.flatten(),
+                error,
+            })),
+            (None, None, Some(result)) => match id {
+                Some(Some(id)) => Ok(BorrowedMessage::Response(BorrowedResponse {
+                    jsonrpc: envelope.jsonrpc,
+                    id,
+                    result,
+                })),
+                _ => Err(ProtocolError::ParseError(
+                    "Response id must not be missing or null".to_string(),
+                )),
+            },
+            (None, None, None) => Err(ProtocolError::ParseError(
+                "JSON-RPC message must contain a method, result or error".to_string(),
+            )),
+        }
+    }
+
+    /// Copies the message into an owned [`JsonRpcMessage`].
+    pub fn into_owned(self) -> Result<JsonRpcMessage, ProtocolError> {
+        Ok(match self {
+            BorrowedMessage::Request(request) => JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: request.jsonrpc.into_owned(),
+                id: request.id,
+                method: request.method.into_owned(),
+                params: request.params.map(parse_raw).transpose()?,
+            }),
+            BorrowedMessage::Notification(notification) => {
+                JsonRpcMessage::Notification(JsonRpcNotification {
+                    jsonrpc: notification.jsonrpc.into_owned(),
+                    method: notification.method.into_owned(),
+                    params: notification.params.map(parse_raw).transpose()?,
+                })
+            }
+            BorrowedMessage::Response(response) => JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: response.jsonrpc.into_owned(),
+                id: response.id,
+                result: parse_raw(response.result)?,
+                error: None,
+            }),
+            BorrowedMessage::Error(error) => JsonRpcMessage::Error(error),
+        })
+    }
+}
+
+impl BorrowedRequest<'_> {
+    /// Deserializes the parameters, treating missing params as an empty object.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T, ProtocolError> {
+        typed_params(self.params)
+    }
+}
+
+impl BorrowedNotification<'_> {
+    /// Deserializes the parameters, treating missing params as an empty object.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T, ProtocolError> {
+        typed_params(self.params)
+    }
+}
+
+impl BorrowedResponse<'_> {
+    /// Deserializes the result.
+    pub fn result<T: DeserializeOwned>(&self) -> Result<T, ProtocolError> {
+        parse_raw(self.result)
+    }
+}
+
+fn typed_params<T: DeserializeOwned>(params: Option<&RawValue>) -> Result<T, ProtocolError> {
+    match params.map(RawValue::get) {
+        Some(text) if text != "null" => {
+            serde_json::from_str(text).map_err(|e| ProtocolError::InvalidParams(e.to_string()))
+        }
+        _ => serde_json::from_value(Value::Object(Default::default()))
+            .map_err(|e| ProtocolError::InvalidParams(e.to_string())),
+    }
+}
+
+fn parse_raw<T: DeserializeOwned>(raw: &RawValue) -> Result<T, ProtocolError> {
+    serde_json::from_str(raw.get()).map_err(|e| ProtocolError::ParseError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::CallToolRequestParams;
+    use serde_json::json;
+
+    #[test]
+    fn test_borrowed_message_points_into_frame() {
+        let frame = RawFrame::new(
+            r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"search","arguments":{"q":"rust"}}}"#,
+        );
+        let BorrowedMessage::Request(request) = frame.message().unwrap() else {
+            panic!("Expected a request");
+        };
+        assert!(matches!(request.method, Cow::Borrowed("tools/call")));
+        let params = request.params.unwrap().get();
+        assert!(frame.as_str().contains(params));
+        assert!(std::ptr::eq(
+            params.as_ptr(),
+            frame.as_str()[frame.as_str().find(r#"{"name""#).unwrap()..].as_ptr()
+        ));
+        let typed: CallToolRequestParams = request.params().unwrap();
+        assert_eq!(typed.name, "search");
+
+        // Forwarding under a new id keeps the parameters byte for byte.
+        let forwarded = serde_json::to_string(&BorrowedRequest {
+            id: RequestId::Number(9),
+            ..request
+        })
+        .unwrap();
+        assert!(forwarded.contains(r#""params":{"name":"search","arguments":{"q":"rust"}}"#));
+    }
+
+    #[test]
+    fn test_classification_matches_owned_messages() {
+        for value in [
+            json!({ "jsonrpc": "2.0", "id": "a", "method": "ping" }),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": { "tools": [] } }),
+            json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": "x" } }),
+        ] {
+            let text = value.to_string();
+            let owned: JsonRpcMessage = serde_json::from_str(&text).unwrap();
+            assert_eq!(
+                BorrowedMessage::parse(&text).unwrap().into_owned().unwrap(),
+                owned
+            );
+        }
+
+        for value in [
+            json!({ "jsonrpc": "2.0", "id": null, "method": "ping" }),
+            json!({ "jsonrpc": "2.0", "id": 1 }),
+            json!({ "jsonrpc": "2.0", "result": {} }),
+        ] {
+            assert!(BorrowedMessage::parse(&value.to_string()).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_transport_returns_received_text() {
+        use crate::transport::{MemoryTransport, Transport};
+
+        let (client, server) = MemoryTransport::pair();
+        let message: JsonRpcMessage =
+            serde_json::from_value(json!({ "jsonrpc": "2.0", "id": 1, "result": { "a": 1 } }))
+                .unwrap();
+        client.send(message.clone()).await.unwrap();
+
+        let frame = server.receive_frame().await.unwrap().unwrap();
+        assert_eq!(frame.as_str(), serde_json::to_string(&message).unwrap());
+        assert_eq!(frame.into_message().unwrap(), message);
+    }
+}
@@ -0,0 +1,224 @@
+/// Clients that connect again on their own when their connection drops.
+///
+/// A [`ReconnectingClient`] wraps a [`Client`] together with a way to open new transports.
+/// When an operation fails because the connection dropped, it opens a new transport, repeats
+/// the handshake, tells the server about the roots again and renews the resource
+/// subscriptions, then runs the operation once more:
+///
+/// ```text
+/// let client = ReconnectingClient::new(client, || async {
+///     TcpTransport::connect("127.0.0.1:4000").await
+/// })
+/// .on_session_restored(|restored| cache.clear());
+/// let tools = client.run(async |client| client.list_tools().await).await?;
+/// ```
+///
+/// The server of the restored session may offer other tools, resources and prompts than the
+/// one before, so lists cached by the host should be fetched again on [`SessionRestored`].
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::future::BoxFuture;
+use serde::Serialize;
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::client::{Client, SessionInfo};
+use crate::protocol::ProtocolError;
+use crate::retry::RetryPolicy;
+use crate::transport::Transport;
+
+/// Tells that a client connected again after its connection dropped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRestored {
+    /// The session id of the connection that dropped, for transports that have one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_session_id: Option<String>,
+    /// What was negotiated for the new connection
+    pub session: SessionInfo,
+    /// The URIs of the resources that were subscribed to again
+    pub subscriptions: Vec<String>,
+}
+
+type Connector<T> = Box<dyn Fn() -> BoxFuture<'static, Result<T, ProtocolError>> + Send + Sync>;
+
+/// A [`Client`] that restores its session over a new transport when its connection drops.
+pub struct ReconnectingClient<T> {
+    client: RwLock<Client<T>>,
+    connect: Connector<T>,
+    policy: RetryPolicy,
+    on_session_restored: Option<Box<dyn Fn(SessionRestored) + Send + Sync>>,
+    /// How often the session was restored, so that concurrent failures restore it only once
+    generation: AtomicU64,
+}
+
+impl<T: Transport + 'static> ReconnectingClient<T> {
+    /// Wraps `client`, opening new transports with `connect` when its connection drops.
+    pub fn new<F>(client: Client<T>, connect: impl Fn() -> F + Send + Sync + 'static) -> Self
+    where
+        F: Future<Output = Result<T, ProtocolError>> + Send + 'static,
+    {
+        Self {
+            client: RwLock::new(client),
+            connect: Box::new(move || Box::pin(connect())),
+            policy: RetryPolicy::default(),
+            on_session_restored: None,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets how often, and how far apart, connecting again is attempted. Only
+    /// [`RetryPolicy::max_attempts`] and the backoff settings of `policy` are used.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Calls `handler` every time the session was restored.
+    pub fn on_session_restored(
+        mut self,
+        handler: impl Fn(SessionRestored) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_session_restored = Some(Box::new(handler));
+        self
+    }
+
+    /// Returns the client, for operations that must not be run again on a new connection.
+    ///
+    /// The session is not restored while the returned guard is held.
+    pub async fn client(&self) -> RwLockReadGuard<'_, Client<T>> {
+        self.client.read().await
+    }
+
+    /// Runs `operation` on the client. When it fails because the connection dropped, the
+    /// session is restored and `operation` runs once more on the new connection.
+    ///
+    /// The server may have acted on a request before the connection dropped, so operations
+    /// with side effects, such as tool calls, should be run on [`ReconnectingClient::client`]
+    /// and the session restored with [`ReconnectingClient::restore`] instead.
+    pub async fn run<R>(
+        &self,
+        operation: impl AsyncFn(&Client<T>) -> Result<R, ProtocolError>,
+    ) -> Result<R, ProtocolError> {
+        let generation = self.generation.load(Ordering::Acquire);
+        let result = operation(&*self.client.read().await).await;
+        match result {
+            Err(error) if is_disconnect(&error) => {
+                self.restore_after(generation).await?;
+                operation(&*self.client.read().await).await
+            }
+            result => result,
+        }
+    }
+
+    /// Waits for the connection to close as [`Client::listen`] does, restoring the session
+    /// whenever it drops. Returns when the session cannot be restored.
+    pub async fn listen(&self) -> Result<(), ProtocolError> {
+        loop {
+            let generation = self.generation.load(Ordering::Acquire);
+            match self.client.read().await.listen().await {
+                Ok(()) => {}
+                Err(error) if is_disconnect(&error) => {}
+                Err(error) => return Err(error),
+            }
+            self.restore_after(generation).await?;
+        }
+    }
+
+    /// Opens a new transport and restores the session over it.
+    pub async fn restore(&self) -> Result<(), ProtocolError> {
+        self.restore_after(self.generation.load(Ordering::Acquire))
+            .await
+    }
+
+    /// Restores the session, unless it was restored since `generation` was read.
+    async fn restore_after(&self, generation: u64) -> Result<(), ProtocolError> {
+        let mut client = self.client.write().await;
+        if self.generation.load(Ordering::Acquire) != generation {
+            return Ok(());
+        }
+        let mut attempt = 1;
+        let restored = loop {
+            let restored = match (self.connect)().await {
+                Ok(transport) => client.restore(transport).await,
+                Err(error) => Err(error),
+            };
+            match restored {
+                Err(error) if attempt < self.policy.max_attempts && is_disconnect(&error) => {
+                    tokio::time::sleep(self.policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                restored => break restored?,
+            }
+        };
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        drop(client);
+        if let Some(handler) = &self.on_session_restored {
+            handler(restored);
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` for the errors that mean the connection dropped.
+fn is_disconnect(error: &ProtocolError) -> bool {
+    matches!(error, ProtocolError::TransportError(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{ClientCapabilities, Implementation};
+    use crate::server::Server;
+    use crate::tool::{CallToolResult, Tool};
+    use crate::transport::MemoryTransport;
+    use serde_json::{Map, Value};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::task::JoinHandle;
+
+    fn serve(tool: &'static str) -> (MemoryTransport, JoinHandle<Result<(), ProtocolError>>) {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"));
+        server.add_tool(
+            Tool::builder().name(tool).build(),
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("")) },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        (client, tokio::spawn(server.serve(server_side)))
+    }
+
+    #[tokio::test]
+    async fn test_session_is_restored_when_the_connection_drops() {
+        let (dropped, serving) = serve("echo");
+        let client = Client::connect(
+            dropped,
+            Implementation::new("test-client", "1.0.0"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        serving.abort();
+        let _ = serving.await;
+
+        let restored = Arc::new(Mutex::new(Vec::new()));
+        let events = restored.clone();
+        let client = ReconnectingClient::new(client, || async { Ok(serve("echo").0) })
+            .with_retry_policy(
+                RetryPolicy::builder()
+                    .initial_backoff(Duration::from_millis(1))
+                    .build(),
+            )
+            .on_session_restored(move |event| events.lock().unwrap().push(event));
+
+        let tools = client
+            .run(async |client| client.list_tools().await)
+            .await
+            .unwrap();
+        assert_eq!(tools[0].name, "echo");
+        let restored = restored.lock().unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].session.server_info.name, "test-server");
+        assert!(restored[0].session.capabilities.tools.is_some());
+        assert!(restored[0].subscriptions.is_empty());
+    }
+}
@@ -0,0 +1,564 @@
+/// Reloading what a server offers while it runs.
+///
+/// A [`ConfigSource`] loads the tools, prompts and resources a server offers, e.g. from a
+/// configuration file. [`Server::with_config_source`] serves what it loads and returns a
+/// [`Reloader`], which loads them again whenever asked to, e.g. from an admin endpoint or
+/// when the process receives `SIGHUP`:
+///
+/// ```text
+/// let (server, reloader) = Server::new(info)
+///     .with_config_source(|| async { load_config("server.toml").await })
+///     .await?;
+/// tokio::spawn(reloader.clone().reload_on_sighup());
+/// ```
+///
+/// A reload compares what was offered before with what is offered after, and notifies the
+/// connected clients with `notifications/tools/list_changed`,
+/// `notifications/prompts/list_changed` or `notifications/resources/list_changed` for each
+/// kind of item that changed. Sessions stay open, and requests already running finish with
+/// the handlers they started with. A reload that fails leaves everything as it was.
+///
+/// [`Server::with_config_source`]: crate::server::Server::with_config_source
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::prompt::{GetPromptResult, ListPromptsResult, PromptError, PromptProvider};
+use crate::protocol::Cursor;
+use crate::resource::{
+    ListResourceTemplatesResult, ListResourcesResult, ResourceContent, ResourceError,
+    ResourceProvider,
+};
+use crate::server::{ServerHandle, SubscriptionHandle};
+use crate::tool::ToolRegistry;
+
+/// The tools, prompts and resources a [`ConfigSource`] loads.
+#[derive(Clone, Default)]
+pub struct ServerConfig {
+    pub tools: ToolRegistry,
+    pub prompts: Option<Arc<dyn PromptProvider>>,
+    pub resources: Option<Arc<dyn ResourceProvider>>,
+}
+
+impl ServerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_prompts(mut self, provider: impl PromptProvider + 'static) -> Self {
+        self.prompts = Some(Arc::new(provider));
+        self
+    }
+
+    pub fn with_resources(mut self, provider: impl ResourceProvider + 'static) -> Self {
+        self.resources = Some(Arc::new(provider));
+        self
+    }
+}
+
+/// Loads what a server offers.
+#[async_trait]
+pub trait ConfigSource: Send + Sync {
+    async fn load(&self) -> Result<ServerConfig, ReloadError>;
+}
+
+#[async_trait]
+impl<F, Fut> ConfigSource for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<ServerConfig, ReloadError>> + Send,
+{
+    async fn load(&self) -> Result<ServerConfig, ReloadError> {
+        self().await
+    }
+}
+
+/// Why a configuration could not be loaded.
+#[derive(Debug, thiserror::Error)]
+pub enum ReloadError {
+    #[error("Failed to load configuration: {0}")]
+    Source(String),
+    #[error(transparent)]
+    Prompt(#[from] PromptError),
+    #[error(transparent)]
+    Resource(#[from] ResourceError),
+}
+
+/// The names of the items of one kind a reload added, removed or changed. Resources and
+/// resource templates are named by their URI and URI template.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The changes from the definitions `before` to those `after`, both keyed by name.
+    fn between(before: &[(String, Value)], after: &[(String, Value)]) -> Self {
+        let previous: HashMap<&str, &Value> = before
+            .iter()
+            .map(|(name, definition)| (name.as_str(), definition))
+            .collect();
+        let mut changes = Changes::default();
+        for (name, definition) in after {
+            match previous.get(name.as_str()) {
+                None => changes.added.push(name.clone()),
+                Some(&old) if old != definition => changes.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed = before
+            .iter()
+            .filter(|(name, _)| !after.iter().any(|(kept, _)| kept == name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        changes
+    }
+}
+
+/// What a reload changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    pub tools: Changes,
+    pub prompts: Changes,
+    pub resources: Changes,
+}
+
+impl ReloadReport {
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty() && self.prompts.is_empty() && self.resources.is_empty()
+    }
+}
+
+/// Loads the configuration of a server again, see [`crate::reload`].
+///
+/// Clones reload the same server.
+#[derive(Clone)]
+pub struct Reloader {
+    source: Arc<dyn ConfigSource>,
+    tools: ToolRegistry,
+    prompts: ReloadablePrompts,
+    resources: ReloadableResources,
+    handle: ServerHandle,
+    /// Held while reloading, so reloads do not interleave
+    reloading: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Reloader {
+    /// Loads the first configuration from `source`, for a server notifying its clients
+    /// through `handle`.
+    pub(crate) async fn load(
+        source: impl ConfigSource + 'static,
+        handle: ServerHandle,
+    ) -> Result<Self, ReloadError> {
+        let config = source.load().await?;
+        let resources = ReloadableResources::default();
+        resources.swap(config.resources);
+        Ok(Reloader {
+            source: Arc::new(source),
+            tools: config.tools,
+            prompts: ReloadablePrompts(Arc::new(RwLock::new(config.prompts))),
+            resources,
+            handle,
+            reloading: Arc::default(),
+        })
+    }
+
+    pub(crate) fn tools(&self) -> ToolRegistry {
+        self.tools.clone()
+    }
+
+    pub(crate) fn prompts(&self) -> impl PromptProvider + 'static {
+        self.prompts.clone()
+    }
+
+    pub(crate) fn resources(&self) -> impl ResourceProvider + 'static {
+        self.resources.clone()
+    }
+
+    /// Loads the configuration again, and notifies the clients of what changed.
+    pub async fn reload(&self) -> Result<ReloadReport, ReloadError> {
+        let _reloading = self.reloading.lock().await;
+        let config = self.source.load().await?;
+        let prompts = self.prompts.current();
+        let resources = self.resources.current();
+        let report = ReloadReport {
+            tools: Changes::between(
+                &definitions(self.tools.definitions(), |tool| &tool.name),
+                &definitions(config.tools.definitions(), |tool| &tool.name),
+            ),
+            prompts: Changes::between(
+                &prompt_definitions(prompts.as_deref()).await?,
+                &prompt_definitions(config.prompts.as_deref()).await?,
+            ),
+            resources: Changes::between(
+                &resource_definitions(resources.as_deref()).await?,
+                &resource_definitions(config.resources.as_deref()).await?,
+            ),
+        };
+
+        // Tools announce their own changes.
+        self.tools.replace_all(&config.tools);
+        *self.prompts.0.write().unwrap_or_else(|e| e.into_inner()) = config.prompts;
+        self.resources.swap(config.resources);
+        if !report.prompts.is_empty() {
+            self.handle.notify_prompts_list_changed().await;
+        }
+        if !report.resources.is_empty() {
+            self.handle.notify_resources_list_changed().await;
+        }
+        Ok(report)
+    }
+
+    /// Reloads whenever the process receives `SIGHUP`. Reloads that fail keep what was
+    /// loaded before. Returns an error only if the signal cannot be listened for.
+    #[cfg(unix)]
+    pub async fn reload_on_sighup(self) -> std::io::Result<()> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangups = signal(SignalKind::hangup())?;
+        while hangups.recv().await.is_some() {
+            let result = self.reload().await;
+            #[cfg(feature = "tracing")]
+            match &result {
+                Ok(report) => tracing::info!(?report, "Reloaded the server configuration"),
+                Err(error) => {
+                    tracing::warn!(%error, "Failed to reload the server configuration")
+                }
+            }
+            drop(result);
+        }
+        Ok(())
+    }
+}
+
+/// `items` as definitions keyed by the name `name` gives them.
+fn definitions<T: Serialize>(items: Vec<T>, name: impl Fn(&T) -> &String) -> Vec<(String, Value)> {
+    items
+        .iter()
+        .map(|item| {
+            let definition = serde_json::to_value(item).unwrap_or_default();
+            (name(item).clone(), definition)
+        })
+        .collect()
+}
+
+async fn prompt_definitions(
+    provider: Option<&dyn PromptProvider>,
+) -> Result<Vec<(String, Value)>, ReloadError> {
+    let Some(provider) = provider else {
+        return Ok(Vec::new());
+    };
+    let mut prompts = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = provider.list(cursor).await?;
+        prompts.extend(definitions(page.prompts, |prompt| &prompt.name));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            return Ok(prompts);
+        }
+    }
+}
+
+async fn resource_definitions(
+    provider: Option<&dyn ResourceProvider>,
+) -> Result<Vec<(String, Value)>, ReloadError> {
+    let Some(provider) = provider else {
+        return Ok(Vec::new());
+    };
+    let mut resources = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = provider.list(cursor).await?;
+        resources.extend(definitions(page.resources, |resource| &resource.uri));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    loop {
+        let page = provider.list_templates(cursor).await?;
+        resources.extend(definitions(page.resource_templates, |template| {
+            &template.uri_template
+        }));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            return Ok(resources);
+        }
+    }
+}
+
+/// The prompts loaded last.
+#[derive(Clone, Default)]
+struct ReloadablePrompts(Arc<RwLock<Option<Arc<dyn PromptProvider>>>>);
+
+impl ReloadablePrompts {
+    fn current(&self) -> Option<Arc<dyn PromptProvider>> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[async_trait]
+impl PromptProvider for ReloadablePrompts {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListPromptsResult, PromptError> {
+        match self.current() {
+            Some(provider) => provider.list(cursor).await,
+            None => Ok(ListPromptsResult::default()),
+        }
+    }
+
+    async fn get(
+        &self,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> Result<GetPromptResult, PromptError> {
+        match self.current() {
+            Some(provider) => provider.get(name, arguments).await,
+            None => Err(PromptError::NotFound(name.to_string())),
+        }
+    }
+}
+
+/// The resources loaded last.
+#[derive(Clone, Default)]
+struct ReloadableResources {
+    current: Arc<RwLock<Option<Arc<dyn ResourceProvider>>>>,
+    /// The handle the server attached, passed on to every provider loaded
+    subscriptions: Arc<Mutex<Option<SubscriptionHandle>>>,
+}
+
+impl ReloadableResources {
+    fn current(&self) -> Option<Arc<dyn ResourceProvider>> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn swap(&self, provider: Option<Arc<dyn ResourceProvider>>) {
+        let subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if let (Some(provider), Some(subscriptions)) = (&provider, subscriptions) {
+            attach(provider.as_ref(), subscriptions);
+        }
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = provider;
+    }
+}
+
+fn attach(provider: &dyn ResourceProvider, subscriptions: SubscriptionHandle) {
+    if provider.supports_subscriptions() || provider.supports_list_changed() {
+        provider.attach(subscriptions);
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for ReloadableResources {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+        match self.current() {
+            Some(provider) => provider.list(cursor).await,
+            None => Ok(ListResourcesResult::default()),
+        }
+    }
+
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+        match self.current() {
+            Some(provider) => provider.read(uri).await,
+            None => Err(ResourceError::NotFound),
+        }
+    }
+
+    async fn list_templates(
+        &self,
+        cursor: Option<Cursor>,
+    ) -> Result<ListResourceTemplatesResult, ResourceError> {
+        match self.current() {
+            Some(provider) => provider.list_templates(cursor).await,
+            None => Ok(ListResourceTemplatesResult::default()),
+        }
+    }
+
+    /// Whether the resources loaded first support subscriptions, which decides whether the
+    /// server offers them.
+    fn supports_subscriptions(&self) -> bool {
+        self.current()
+            .is_some_and(|provider| provider.supports_subscriptions())
+    }
+
+    /// Reloads can always change the list.
+    fn supports_list_changed(&self) -> bool {
+        true
+    }
+
+    fn attach(&self, subscriptions: SubscriptionHandle) {
+        if let Some(provider) = self.current() {
+            attach(provider.as_ref(), subscriptions.clone());
+        }
+        *self.subscriptions.lock().unwrap_or_else(|e| e.into_inner()) = Some(subscriptions);
+    }
+
+    async fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        match self.current() {
+            Some(provider) => provider.subscribe(uri).await,
+            None => Err(ResourceError::NotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::{InitializeRequest, client_handshake};
+    use crate::prompt::Prompt;
+    use crate::protocol::{
+        ClientCapabilities, Implementation, InitializeRequestParams, JSONRPC_VERSION,
+        JsonRpcMessage, JsonRpcRequest, RequestId,
+    };
+    use crate::server::Server;
+    use crate::tool::{CallToolResult, Tool};
+    use crate::transport::{MemoryTransport, Transport};
+    use serde_json::Map;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    /// Offers prompts with the given names.
+    struct Prompts(Vec<&'static str>);
+
+    #[async_trait]
+    impl PromptProvider for Prompts {
+        async fn list(&self, _: Option<Cursor>) -> Result<ListPromptsResult, PromptError> {
+            Ok(ListPromptsResult {
+                prompts: self
+                    .0
+                    .iter()
+                    .map(|name| Prompt::builder().name(*name).build())
+                    .collect(),
+                ..Default::default()
+            })
+        }
+
+        async fn get(
+            &self,
+            name: &str,
+            _: HashMap<String, String>,
+        ) -> Result<GetPromptResult, PromptError> {
+            Err(PromptError::NotFound(name.to_string()))
+        }
+    }
+
+    fn config(reloaded: bool) -> ServerConfig {
+        let tools = ToolRegistry::new();
+        let handler =
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("ok")) };
+        let search = match reloaded {
+            false => Tool::builder().name("search").build(),
+            true => Tool::builder()
+                .name("search")
+                .description("Searches the notes")
+                .build(),
+        };
+        tools.register(search, handler);
+        if reloaded {
+            tools.register(Tool::builder().name("fetch").build(), handler);
+        }
+        let prompts = match reloaded {
+            false => Prompts(vec!["greet"]),
+            true => Prompts(vec!["greet", "summarize"]),
+        };
+        ServerConfig::new().with_tools(tools).with_prompts(prompts)
+    }
+
+    #[tokio::test]
+    async fn test_reloads_announce_what_changed() {
+        let reloaded = Arc::new(AtomicBool::new(false));
+        let source = {
+            let reloaded = reloaded.clone();
+            move || {
+                let config = config(reloaded.load(Ordering::SeqCst));
+                async move { Ok(config) }
+            }
+        };
+        let (server, reloader) = Server::new(Implementation::new("test-server", "1.0.0"))
+            .with_config_source(source)
+            .await
+            .unwrap();
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let handshake = client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation::new("test-client", "1.0.0"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            handshake.peer_capabilities.prompts.unwrap().list_changed,
+            Some(true)
+        );
+
+        // Once the ping is answered, the session is attached and hears of changes.
+        client
+            .send(JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: RequestId::Number(1),
+                method: "ping".to_string(),
+                params: None,
+            }))
+            .await
+            .unwrap();
+        client.receive().await.unwrap();
+
+        reloaded.store(true, Ordering::SeqCst);
+        let report = reloader.reload().await.unwrap();
+        assert_eq!(report.tools.added, ["fetch"]);
+        assert_eq!(report.tools.changed, ["search"]);
+        assert_eq!(report.prompts.added, ["summarize"]);
+        assert!(report.resources.is_empty());
+
+        let mut notified = Vec::new();
+        for _ in 0..2 {
+            let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+            else {
+                panic!("Expected a notification");
+            };
+            notified.push(notification.method);
+        }
+        notified.sort();
+        assert_eq!(
+            notified,
+            [
+                "notifications/prompts/list_changed",
+                "notifications/tools/list_changed"
+            ]
+        );
+
+        // Nothing changed, so nothing is announced.
+        assert!(reloader.reload().await.unwrap().is_empty());
+        let next = tokio::time::timeout(Duration::from_millis(50), client.receive()).await;
+        assert!(next.is_err());
+    }
+}
@@ -0,0 +1,636 @@
+/// Resources fetched over HTTP, with the `http` feature.
+///
+/// [`HttpResourceProvider`] exposes remote documents as resources: it lists the resources it
+/// was given, and reads them, and any other URI under an allowed prefix, with a `GET`. The
+/// response's `Content-Type` becomes the content's MIME type, and textual responses are read
+/// as text, all others as base64 blobs.
+///
+/// Requests carry the [`Credentials`](crate::credentials::Credentials) given with
+/// [`HttpResourceProvider::with_credentials`], for documents behind a token.
+///
+/// Only `https://` URIs are read unless plain HTTP is allowed. Redirects are followed only to
+/// URIs that may be read themselves. Responses larger than the size limit are refused,
+/// requests that take longer than the timeout fail, and successful reads are cached for a
+/// while, up to a total size, so clients reading the same document repeatedly do not hit the
+/// remote server every time:
+///
+/// ```text
+/// let docs = HttpResourceProvider::new()
+///     .with_resource(Resource::builder().uri(spec_url).name("spec").build())
+///     .with_max_size(1024 * 1024)
+///     .with_cache_ttl(Duration::from_secs(300));
+/// ```
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use url::Url;
+
+use crate::credentials::CredentialsHandle;
+use crate::pagination::{DEFAULT_PAGE_SIZE, paginate};
+use crate::protocol::{Cursor, PaginatedRequestParams};
+use crate::resource::{
+    ListResourcesResult, Resource, ResourceContent, ResourceError, ResourceProvider,
+};
+
+/// The largest response read unless chosen with [`HttpResourceProvider::with_max_size`].
+pub const DEFAULT_MAX_SIZE: usize = 10 * 1024 * 1024;
+
+/// How long a request may take unless chosen with [`HttpResourceProvider::with_timeout`].
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long reads are cached unless chosen with [`HttpResourceProvider::with_cache_ttl`].
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How many bytes of content are cached at most unless chosen with
+/// [`HttpResourceProvider::with_max_cache_size`].
+pub const DEFAULT_MAX_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// How many redirects a read follows at most.
+pub const MAX_REDIRECTS: usize = 10;
+
+/// Reads resources from the web.
+#[derive(Debug)]
+pub struct HttpResourceProvider {
+    client: reqwest::Client,
+    resources: Vec<Resource>,
+    prefixes: Vec<String>,
+    allow_http: bool,
+    max_size: usize,
+    timeout: Duration,
+    cache_ttl: Duration,
+    max_cache_size: usize,
+    credentials: Option<CredentialsHandle>,
+    cache: Mutex<HashMap<String, (Instant, ResourceContent)>>,
+}
+
+impl Default for HttpResourceProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpResourceProvider {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to build the HTTP client");
+        Self::with_client(client)
+    }
+
+    /// Sends requests with `client`, e.g. one with its own headers or proxy.
+    ///
+    /// The provider follows redirects itself, checking every hop, so `client` should be
+    /// built with [`reqwest::redirect::Policy::none`]. Redirects a client follows on its own
+    /// are not checked.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        HttpResourceProvider {
+            client,
+            resources: Vec::new(),
+            prefixes: Vec::new(),
+            allow_http: false,
+            max_size: DEFAULT_MAX_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            max_cache_size: DEFAULT_MAX_CACHE_SIZE,
+            credentials: None,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lists `resource`, and allows reading it.
+    pub fn with_resource(mut self, resource: Resource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    /// Allows reading every URI that starts with `prefix`, e.g. `https://docs.example.com/`,
+    /// without listing them.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Reads `http://` URIs too.
+    pub fn allow_http(mut self) -> Self {
+        self.allow_http = true;
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Caches reads for `cache_ttl`; `Duration::ZERO` turns caching off.
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Caches at most `max_cache_size` bytes of content, evicting the oldest reads first.
+    pub fn with_max_cache_size(mut self, max_cache_size: usize) -> Self {
+        self.max_cache_size = max_cache_size;
+        self
+    }
+
+    /// Sends `credentials` with every request. They can be rotated through the handle
+    /// returned by [`HttpResourceProvider::credentials`].
+    pub fn with_credentials(mut self, credentials: impl Into<CredentialsHandle>) -> Self {
+        self.credentials = Some(credentials.into());
+        self
+    }
+
+    /// Returns the handle of the credentials requests are sent with, if any.
+    pub fn credentials(&self) -> Option<CredentialsHandle> {
+        self.credentials.clone()
+    }
+
+    /// Checks that `uri` may be read, and parses it.
+    fn url(&self, uri: &str) -> Result<Url, ResourceError> {
+        let url = Url::parse(uri)?;
+        let scheme_allowed = match url.scheme() {
+            "https" => true,
+            "http" => self.allow_http,
+            _ => false,
+        };
+        let listed = self.resources.iter().any(|resource| resource.uri == uri)
+            || self
+                .prefixes
+                .iter()
+                .any(|prefix| under_prefix(&url, prefix));
+        if scheme_allowed && listed {
+            Ok(url)
+        } else {
+            Err(ResourceError::NotFound)
+        }
+    }
+
+    fn cached(&self, uri: &str) -> Option<ResourceContent> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        match cache.get(uri) {
+            Some((fetched, content)) if fetched.elapsed() < self.cache_ttl => Some(content.clone()),
+            Some(_) => {
+                cache.remove(uri);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches `content` read from `uri`, evicting expired and then the oldest reads to stay
+    /// within the cache size.
+    fn cache(&self, uri: &str, content: &ResourceContent) {
+        let size = content_size(content);
+        if self.cache_ttl.is_zero() || size > self.max_cache_size {
+            return;
+        }
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|cached, (fetched, _)| cached != uri && fetched.elapsed() < self.cache_ttl);
+        let mut total: usize = cache
+            .values()
+            .map(|(_, content)| content_size(content))
+            .sum();
+        while total + size > self.max_cache_size {
+            let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, (fetched, _))| *fetched)
+                .map(|(uri, _)| uri.clone())
+            else {
+                break;
+            };
+            if let Some((_, evicted)) = cache.remove(&oldest) {
+                total -= content_size(&evicted);
+            }
+        }
+        cache.insert(uri.to_string(), (Instant::now(), content.clone()));
+    }
+
+    async fn fetch(&self, uri: &str, mut url: Url) -> Result<ResourceContent, ResourceError> {
+        let failed = |error: reqwest::Error| ResourceError::Other(format!("{}: {}", uri, error));
+        let origin = url.origin();
+        let mut redirects = 0;
+        let mut response = loop {
+            let mut request = self.client.get(url.clone()).timeout(self.timeout);
+            // Credentials are meant for the origin that was asked for, not for redirects away.
+            if url.origin() == origin {
+                for (name, value) in self.credentials.iter().flat_map(CredentialsHandle::headers) {
+                    request = request.header(name, value);
+                }
+            }
+            let response = request.send().await.map_err(failed)?;
+            if !response.status().is_redirection() {
+                break response;
+            }
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| {
+                    ResourceError::Other(format!("{}: redirect without a location", uri))
+                })?;
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(ResourceError::Other(format!("{}: too many redirects", uri)));
+            }
+            let next = url.join(location)?;
+            url = self.url(next.as_str()).map_err(|_| {
+                ResourceError::Other(format!("{}: redirect to {} is not allowed", uri, next))
+            })?;
+        };
+        match response.status() {
+            status if status.is_success() => {}
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE => {
+                return Err(ResourceError::NotFound);
+            }
+            status => return Err(ResourceError::Other(format!("{}: HTTP {}", uri, status))),
+        }
+        let too_large =
+            || ResourceError::Other(format!("{}: response exceeds {} bytes", uri, self.max_size));
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_size as u64)
+        {
+            return Err(too_large());
+        }
+        let mime_type: Option<mime::Mime> = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(failed)? {
+            if body.len() + chunk.len() > self.max_size {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        let textual = mime_type.as_ref().is_none_or(is_textual);
+        let mime_type = mime_type.map(|mime_type| mime_type.to_string());
+        Ok(match String::from_utf8(body) {
+            Ok(text) if textual => ResourceContent::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type,
+                text,
+                meta: None,
+            },
+            Ok(text) => ResourceContent::BlobResourceContent {
+                uri: uri.to_string(),
+                mime_type,
+                blob: BASE64_STANDARD.encode(text),
+                meta: None,
+            },
+            Err(error) => ResourceContent::BlobResourceContent {
+                uri: uri.to_string(),
+                mime_type,
+                blob: BASE64_STANDARD.encode(error.into_bytes()),
+                meta: None,
+            },
+        })
+    }
+}
+
+/// Whether `url` is under `prefix`: of the same origin, and with a path that is the prefix's
+/// or lies below it. A prefix without a trailing `/` covers the path it names and the paths
+/// below, not paths that merely start with the same characters.
+fn under_prefix(url: &Url, prefix: &str) -> bool {
+    let Ok(prefix) = Url::parse(prefix) else {
+        return false;
+    };
+    if url.origin() != prefix.origin() {
+        return false;
+    }
+    let (path, prefix) = (url.path(), prefix.path());
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The bytes `content` takes up in the cache.
+fn content_size(content: &ResourceContent) -> usize {
+    match content {
+        ResourceContent::TextResourceContents { text, .. } => text.len(),
+        ResourceContent::BlobResourceContent { blob, .. } => blob.len(),
+    }
+}
+
+/// Whether content of this type is text rather than binary data.
+fn is_textual(mime_type: &mime::Mime) -> bool {
+    mime_type.type_() == mime::TEXT
+        || mime_type.get_param(mime::CHARSET).is_some()
+        || [mime::JSON, mime::XML].contains(&mime_type.subtype())
+        || mime_type
+            .suffix()
+            .is_some_and(|suffix| suffix == mime::JSON || suffix == mime::XML)
+}
+
+#[async_trait]
+impl ResourceProvider for HttpResourceProvider {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+        let request = PaginatedRequestParams { cursor, meta: None };
+        let (resources, next_cursor) = paginate(&self.resources, &request, DEFAULT_PAGE_SIZE)?;
+        Ok(ListResourcesResult {
+            resources,
+            next_cursor,
+            meta: None,
+        })
+    }
+
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+        let url = self.url(uri)?;
+        if let Some(content) = self.cached(uri) {
+            return Ok(vec![content]);
+        }
+        let content = self.fetch(uri, url).await?;
+        self.cache(uri, &content);
+        Ok(vec![content])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credentials::Credentials;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves a fixed response for every path, counting the requests.
+    async fn serve(
+        routes: &'static [(&'static str, &'static str, &'static [u8])],
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                counted.fetch_add(1, Ordering::SeqCst);
+                let mut request = vec![0; 4096];
+                let read = stream.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..read]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let response = match routes.iter().find(|(route, _, _)| *route == path) {
+                    Some((_, content_type, body)) => {
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                            content_type,
+                            body.len()
+                        )
+                        .into_bytes();
+                        response.extend_from_slice(body);
+                        response
+                    }
+                    None => {
+                        b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                            .to_vec()
+                    }
+                };
+                let _ = stream.write_all(&response).await;
+            }
+        });
+        (base, requests)
+    }
+
+    #[tokio::test]
+    async fn test_remote_documents_are_read_and_cached() {
+        let (base, requests) = serve(&[
+            ("/guide.md", "text/markdown; charset=utf-8", b"# Guide"),
+            ("/data.json", "application/json", b"{\"ok\":true}"),
+            ("/logo.png", "image/png", &[0x89, 0x50, 0xff]),
+            ("/large.txt", "text/plain", &[b'a'; 64]),
+        ])
+        .await;
+        let guide = format!("{}/guide.md", base);
+        let provider = HttpResourceProvider::new()
+            .with_resource(
+                Resource::builder()
+                    .uri(Url::parse(&guide).unwrap())
+                    .name("guide")
+                    .build(),
+            )
+            .with_prefix(format!("{}/", base))
+            .allow_http()
+            .with_max_size(32);
+        assert_eq!(provider.list(None).await.unwrap().resources.len(), 1);
+
+        let read = async |path: &str| provider.read(&format!("{}{}", base, path)).await;
+        let contents = read("/guide.md").await.unwrap();
+        let [
+            ResourceContent::TextResourceContents {
+                text, mime_type, ..
+            },
+        ] = contents.as_slice()
+        else {
+            panic!("Expected text");
+        };
+        assert_eq!(text, "# Guide");
+        assert_eq!(mime_type.as_deref(), Some("text/markdown; charset=utf-8"));
+        read("/guide.md").await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let contents = read("/data.json").await.unwrap();
+        assert!(matches!(
+            contents.as_slice(),
+            [ResourceContent::TextResourceContents { .. }]
+        ));
+        let contents = read("/logo.png").await.unwrap();
+        let [ResourceContent::BlobResourceContent { blob, .. }] = contents.as_slice() else {
+            panic!("Expected a blob");
+        };
+        assert_eq!(blob, "iVD/");
+
+        assert!(matches!(
+            read("/large.txt").await,
+            Err(ResourceError::Other(message)) if message.contains("exceeds 32 bytes")
+        ));
+        assert!(matches!(
+            read("/missing").await,
+            Err(ResourceError::NotFound)
+        ));
+        assert!(matches!(
+            provider.read("https://example.com/elsewhere").await,
+            Err(ResourceError::NotFound)
+        ));
+        let https_only = HttpResourceProvider::new().with_prefix(format!("{}/", base));
+        assert!(matches!(
+            https_only.read(&guide).await,
+            Err(ResourceError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_the_current_credentials() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/", listener.local_addr().unwrap());
+        // Answers every request with its own authorization header.
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let read = stream.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let body = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("authorization: "))
+                    .unwrap_or("")
+                    .to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        let provider = HttpResourceProvider::new()
+            .with_prefix(base.clone())
+            .allow_http()
+            .with_cache_ttl(Duration::ZERO)
+            .with_credentials(Credentials::bearer("first"));
+        let read = async || {
+            let contents = provider.read(&format!("{}token", base)).await.unwrap();
+            let [ResourceContent::TextResourceContents { text, .. }] = contents.as_slice() else {
+                panic!("Expected text");
+            };
+            text.clone()
+        };
+        assert_eq!(read().await, "Bearer first");
+        provider
+            .credentials()
+            .unwrap()
+            .rotate(Credentials::bearer("second"));
+        assert_eq!(read().await, "Bearer second");
+    }
+
+    /// Answers requests for the paths of `routes` with the raw responses given for them.
+    async fn serve_raw(routes: Vec<(&'static str, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let read = stream.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let response = routes
+                    .iter()
+                    .find(|(route, _)| *route == path)
+                    .map(|(_, response)| response.clone())
+                    .unwrap_or_else(|| {
+                        "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n".to_string()
+                    });
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        base
+    }
+
+    fn redirect(location: &str) -> String {
+        format!(
+            "HTTP/1.1 302 Found\r\nlocation: {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            location
+        )
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_checked_at_every_hop() {
+        let elsewhere = serve_raw(vec![(
+            "/secret",
+            "HTTP/1.1 200 OK\r\ncontent-length: 6\r\nconnection: close\r\n\r\nsecret".to_string(),
+        )])
+        .await;
+        let base = serve_raw(vec![
+            ("/moved", redirect("/docs/guide.md")),
+            ("/away", redirect(&format!("{}/secret", elsewhere))),
+            (
+                "/docs/guide.md",
+                "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: 7\r\nconnection: close\r\n\r\n# Guide"
+                    .to_string(),
+            ),
+        ])
+        .await;
+        let provider = HttpResourceProvider::new()
+            .with_prefix(format!("{}/", base))
+            .allow_http();
+
+        let contents = provider.read(&format!("{}/moved", base)).await.unwrap();
+        assert!(matches!(
+            contents.as_slice(),
+            [ResourceContent::TextResourceContents { text, .. }] if text == "# Guide"
+        ));
+        assert!(matches!(
+            provider.read(&format!("{}/away", base)).await,
+            Err(ResourceError::Other(message)) if message.contains("is not allowed")
+        ));
+    }
+
+    #[test]
+    fn test_prefixes_match_whole_origins_and_path_segments() {
+        let allowed = |uri: &str, prefix: &str| under_prefix(&Url::parse(uri).unwrap(), prefix);
+        assert!(allowed(
+            "https://docs.example.com/guide",
+            "https://docs.example.com"
+        ));
+        assert!(!allowed(
+            "https://docs.example.com.evil.net/",
+            "https://docs.example.com"
+        ));
+        assert!(!allowed(
+            "http://docs.example.com/guide",
+            "https://docs.example.com/"
+        ));
+        assert!(allowed(
+            "https://example.com/docs/guide",
+            "https://example.com/docs"
+        ));
+        assert!(allowed(
+            "https://example.com/docs",
+            "https://example.com/docs"
+        ));
+        assert!(!allowed(
+            "https://example.com/docs-private",
+            "https://example.com/docs"
+        ));
+        assert!(!allowed(
+            "https://example.com/docs-private",
+            "https://example.com/docs/"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_bounded() {
+        let (base, requests) = serve(&[
+            ("/guide.md", "text/plain", b"# Guide"),
+            ("/data.json", "application/json", b"{\"ok\":true}"),
+        ])
+        .await;
+        let provider = HttpResourceProvider::new()
+            .with_prefix(format!("{}/", base))
+            .allow_http()
+            .with_max_cache_size(12);
+        let read = async |path: &str| provider.read(&format!("{}{}", base, path)).await.unwrap();
+
+        read("/guide.md").await;
+        read("/data.json").await;
+        // Both do not fit, so the guide was evicted for the data.
+        read("/guide.md").await;
+        read("/guide.md").await;
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(provider.cache.lock().unwrap().len(), 1);
+    }
+}
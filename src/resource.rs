@@ -1,11 +1,19 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
 use bon::Builder;
 /// Resources that servers provide to clients
-use chrono::{DateTime, Utc};
 use mime::Mime;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use thiserror::Error;
 use url::Url;
 
+use crate::protocol::{Cursor, ProtocolError};
+use crate::server::SubscriptionHandle;
+use crate::uri_template::UriTemplate;
+
 #[derive(Error, Debug)]
 pub enum ResourceError {
     #[error("Invalid URI: {0}")]
@@ -14,6 +22,10 @@ pub enum ResourceError {
     InvalidFilePath,
     #[error("Resource not found")]
     NotFound,
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    #[error("{0}")]
+    Other(String),
 }
 
 /// Represents a resource in the extension with metadata
@@ -23,8 +35,8 @@ pub struct Resource {
     /// URI representing the resource location (e.g., "file:///path/to/file" or "str:///content")
     #[builder(field)]
     pub uri: String,
-    /// MIME type of the resource content ("text" or "blob")
-    #[builder(field = "text/plain".to_string())]
+    /// MIME type of the resource content, `text/plain` unless set
+    #[builder(field = mime::TEXT_PLAIN.to_string())]
     pub mime_type: String,
     /// Name of the resource
     #[builder(field = "unnamed".to_string())]
@@ -33,6 +45,9 @@ pub struct Resource {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[builder(into)]
     pub description: Option<String>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
 }
 
 impl<S: resource_builder::State> ResourceBuilder<S> {
@@ -51,7 +66,7 @@ impl<S: resource_builder::State> ResourceBuilder<S> {
     pub fn name_from_uri(mut self, uri: Url) -> Self {
         let name = uri
             .path_segments()
-            .and_then(|segments| segments.last())
+            .and_then(|mut segments| segments.next_back())
             .unwrap_or("unnamed")
             .to_string();
         self.name = name;
@@ -68,6 +83,8 @@ pub enum ResourceContent {
         #[serde(rename = "mimeType")]
         mime_type: Option<String>,
         text: String,
+        #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+        meta: Option<Map<String, Value>>,
     },
     BlobResourceContent {
         uri: String,
@@ -75,9 +92,198 @@ pub enum ResourceContent {
         #[serde(rename = "mimeType")]
         mime_type: Option<String>,
         blob: String,
+        #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+        meta: Option<Map<String, Value>>,
     },
 }
 
+/// A template description for resources available on the server
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    /// A URI template (according to RFC 6570) that can be used to construct resource URIs
+    pub uri_template: String,
+    /// Name of the resources matching this template
+    pub name: String,
+    /// Optional description of what this template is for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// MIME type of all resources matching this template, if they share one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The server's response to a resources/list request from the client
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourcesResult {
+    pub resources: Vec<Resource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<Cursor>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The server's response to a resources/templates/list request from the client
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourceTemplatesResult {
+    pub resource_templates: Vec<ResourceTemplate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<Cursor>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Parameters of a resources/read request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadResourceRequestParams {
+    /// The URI of the resource to read
+    pub uri: String,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The server's response to a resources/read request from the client
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadResourceResult {
+    pub contents: Vec<ResourceContent>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Parameters of a resources/subscribe request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscribeRequestParams {
+    /// The URI of the resource to subscribe to
+    pub uri: String,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Parameters of a resources/unsubscribe request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsubscribeRequestParams {
+    /// The URI of the resource to unsubscribe from
+    pub uri: String,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Parameters of a notifications/resources/updated notification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUpdatedNotificationParams {
+    /// The URI of the resource that has been updated
+    pub uri: String,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The source of the resources a server offers.
+///
+/// [`Server::with_resources`](crate::server::Server::with_resources) answers
+/// `resources/list`, `resources/templates/list` and `resources/read` with a provider. A read
+/// failing with [`ResourceError::NotFound`] is sent to the client as the spec's
+/// `RESOURCE_NOT_FOUND` error, with the URI in its data.
+///
+/// Providers whose resources change can let clients subscribe to them: they return `true`
+/// from [`ResourceProvider::supports_subscriptions`], keep the [`SubscriptionHandle`] passed
+/// to [`ResourceProvider::attach`], and notify it when a resource changes.
+#[async_trait]
+pub trait ResourceProvider: Send + Sync {
+    /// Lists the page of resources starting at `cursor`.
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError>;
+
+    /// Reads the contents of the resource at `uri`.
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError>;
+
+    /// Lists the page of resource templates starting at `cursor`. There are none by default.
+    async fn list_templates(
+        &self,
+        _cursor: Option<Cursor>,
+    ) -> Result<ListResourceTemplatesResult, ResourceError> {
+        Ok(ListResourceTemplatesResult::default())
+    }
+
+    /// Returns `true` if clients may subscribe to updates of these resources.
+    fn supports_subscriptions(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if the provider announces changes to its list of resources through the
+    /// [`SubscriptionHandle`].
+    fn supports_list_changed(&self) -> bool {
+        false
+    }
+
+    /// Receives the handle that notifies sessions of changes, once, when a server takes the
+    /// provider. Only called if the provider supports subscriptions or list changes.
+    fn attach(&self, _subscriptions: SubscriptionHandle) {}
+
+    /// Checks a subscription to the resource at `uri` before it is recorded, e.g. that the
+    /// resource exists. Every subscription is accepted by default.
+    async fn subscribe(&self, _uri: &str) -> Result<(), ResourceError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<P: ResourceProvider + ?Sized> ResourceProvider for Arc<P> {
+    async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+        (**self).list(cursor).await
+    }
+
+    async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+        (**self).read(uri).await
+    }
+
+    async fn list_templates(
+        &self,
+        cursor: Option<Cursor>,
+    ) -> Result<ListResourceTemplatesResult, ResourceError> {
+        (**self).list_templates(cursor).await
+    }
+
+    fn supports_subscriptions(&self) -> bool {
+        (**self).supports_subscriptions()
+    }
+
+    fn supports_list_changed(&self) -> bool {
+        (**self).supports_list_changed()
+    }
+
+    fn attach(&self, subscriptions: SubscriptionHandle) {
+        (**self).attach(subscriptions)
+    }
+
+    async fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        (**self).subscribe(uri).await
+    }
+}
+
+impl ResourceTemplate {
+    /// Returns the values of the template's variables if `uri` matches its URI template, or
+    /// `None` if it does not or the template is malformed. See [`UriTemplate::matches`].
+    pub fn matches(&self, uri: &str) -> Option<HashMap<String, String>> {
+        UriTemplate::parse(&self.uri_template).ok()?.matches(uri)
+    }
+}
+
 impl Resource {
     /// Returns the scheme of the URI
     pub fn scheme(&self) -> Result<String, ResourceError> {
@@ -102,7 +308,7 @@ mod tests {
 
         let resource = Resource::builder().uri(uri).name("test").build();
         assert!(resource.uri.starts_with("file:///"));
-        assert_eq!(resource.mime_type, "text");
+        assert_eq!(resource.mime_type, "text/plain");
         assert_eq!(resource.scheme()?, "file");
 
         Ok(())
@@ -120,7 +326,7 @@ mod tests {
 
         assert_eq!(resource.uri, uri);
         assert_eq!(resource.name, "test.txt");
-        assert_eq!(resource.mime_type, "text");
+        assert_eq!(resource.mime_type, "text/plain");
         assert_eq!(resource.scheme()?, "str");
 
         Ok(())
@@ -145,7 +351,7 @@ mod tests {
         let resource = Resource::builder()
             .uri(Url::parse("file:///test.txt").unwrap())
             .build();
-        assert_eq!(resource.mime_type, "text");
+        assert_eq!(resource.mime_type, "text/plain");
 
         Ok(())
     }
@@ -223,6 +429,7 @@ mod tests {
             uri: "str:///content".to_string(),
             mime_type: Some("text/plain".to_string()),
             text: "Hello world".to_string(),
+            meta: None,
         };
 
         let serialized = serde_json::to_string(&content).unwrap();
@@ -238,6 +445,7 @@ mod tests {
             uri: "blob:///data".to_string(),
             mime_type: Some("application/octet-stream".to_string()),
             blob: "base64encodedcontent".to_string(),
+            meta: None,
         };
 
         let serialized = serde_json::to_string(&content).unwrap();
@@ -273,6 +481,7 @@ mod tests {
                 uri,
                 mime_type,
                 text,
+                ..
             } => {
                 assert_eq!(uri, "str:///content");
                 assert_eq!(mime_type, Some("text/plain".to_string()));
@@ -286,6 +495,7 @@ mod tests {
                 uri,
                 mime_type,
                 blob,
+                ..
             } => {
                 assert_eq!(uri, "blob:///data");
                 assert_eq!(mime_type, Some("application/octet-stream".to_string()));
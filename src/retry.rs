@@ -0,0 +1,119 @@
+/// Retrying client requests that failed for a transient reason.
+///
+/// With a [`RetryPolicy`], a request that only reads, such as `tools/list` or
+/// `resources/read`, is sent again when it fails with an error the policy deems transient,
+/// after a backoff that grows with every attempt:
+///
+/// ```text
+/// let client = client.with_retry_policy(RetryPolicy::builder().max_attempts(4).build());
+/// ```
+///
+/// Requests with side effects, such as `tools/call`, are never retried, as the server may
+/// have acted on them before the failure. Every attempt is sent with a new request id.
+use std::time::Duration;
+
+use bon::Builder;
+
+use crate::protocol::{ProtocolError, REQUEST_TIMEOUT, SERVER_BUSY};
+
+/// The methods that are safe to send again, as they only read.
+pub const IDEMPOTENT_METHODS: &[&str] = &[
+    "ping",
+    "tools/list",
+    "resources/list",
+    "resources/templates/list",
+    "resources/read",
+    "prompts/list",
+    "prompts/get",
+    "completion/complete",
+];
+
+/// Returns `true` if requests of `method` are safe to send again.
+pub fn is_idempotent(method: &str) -> bool {
+    IDEMPOTENT_METHODS.contains(&method)
+}
+
+/// Returns `true` for the errors that may go away on their own: transport failures,
+/// timeouts, and error responses of servers that were busy or timed out themselves. Other
+/// errors the server answered with are not, as it would answer the same again.
+pub fn is_transient(error: &ProtocolError) -> bool {
+    match error {
+        ProtocolError::TransportError(_) | ProtocolError::Timeout(_) => true,
+        ProtocolError::ErrorResponse(error) => {
+            matches!(error.code, SERVER_BUSY | REQUEST_TIMEOUT)
+        }
+        _ => false,
+    }
+}
+
+/// How often, and after which errors, idempotent requests are retried.
+#[derive(Debug, Clone, Builder)]
+pub struct RetryPolicy {
+    /// How often a request is sent at most, the first attempt included
+    #[builder(default = 3)]
+    pub max_attempts: u32,
+    /// The wait before the first retry
+    #[builder(default = Duration::from_millis(100))]
+    pub initial_backoff: Duration,
+    /// The factor the wait grows by with every further retry
+    #[builder(default = 2.0)]
+    pub multiplier: f64,
+    /// The longest wait between two attempts
+    #[builder(default = Duration::from_secs(5))]
+    pub max_backoff: Duration,
+    /// Decides which errors are retried, [`is_transient`] by default
+    #[builder(default = is_transient)]
+    pub retry_on: fn(&ProtocolError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl RetryPolicy {
+    /// Returns `true` if a request of `method` that failed with `error` on attempt number
+    /// `attempt`, counted from 1, is sent again.
+    pub fn should_retry(&self, method: &str, attempt: u32, error: &ProtocolError) -> bool {
+        attempt < self.max_attempts && is_idempotent(method) && (self.retry_on)(error)
+    }
+
+    /// The wait after attempt number `attempt`, counted from 1, before the next one.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{ErrorData, INVALID_PARAMS};
+
+    #[test]
+    fn test_only_transient_failures_of_reads_are_retried() {
+        let policy = RetryPolicy::default();
+        let dropped = ProtocolError::TransportError("Connection reset".to_string());
+        assert!(policy.should_retry("tools/list", 1, &dropped));
+        assert!(policy.should_retry("tools/list", 2, &dropped));
+        assert!(!policy.should_retry("tools/list", 3, &dropped));
+        assert!(!policy.should_retry("tools/call", 1, &dropped));
+        let rejected = ProtocolError::InvalidParams("Unknown cursor".to_string());
+        assert!(!policy.should_retry("tools/list", 1, &rejected));
+        let answered = |code| {
+            ProtocolError::ErrorResponse(ErrorData {
+                code,
+                message: "Try again".to_string(),
+                data: None,
+            })
+        };
+        assert!(policy.should_retry("tools/list", 1, &answered(SERVER_BUSY)));
+        assert!(policy.should_retry("tools/list", 1, &answered(REQUEST_TIMEOUT)));
+        assert!(!policy.should_retry("tools/list", 1, &answered(INVALID_PARAMS)));
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(20), Duration::from_secs(5));
+    }
+}
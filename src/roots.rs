@@ -0,0 +1,140 @@
+/// Filesystem roots that clients expose to servers
+///
+/// A server lists the roots of every client that has the `roots` capability once the session
+/// starts, and again whenever the client sends `notifications/roots/list_changed`. The last
+/// list is kept per session in [`SessionRoots`]. Handlers reach it through their
+/// [`RequestContext`](crate::server::RequestContext), and code they call, such as resource
+/// providers, through [`SessionRoots::current`], e.g. to refuse files outside the roots:
+///
+/// ```text
+/// if SessionRoots::current().is_some_and(|roots| !roots.contains(uri)) {
+///     return Err(ResourceError::NotFound);
+/// }
+/// ```
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use url::Url;
+
+tokio::task_local! {
+    static CURRENT: SessionRoots;
+}
+
+/// A root directory or file that the server can operate on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Root {
+    /// The URI identifying the root. Must start with file:// for now
+    pub uri: String,
+
+    /// An optional name for the root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl Root {
+    /// Returns `true` if `uri` is the root itself or lies beneath it.
+    pub fn contains(&self, uri: &str) -> bool {
+        let (Ok(root), Ok(uri)) = (Url::parse(&self.uri), Url::parse(uri)) else {
+            return false;
+        };
+        if root.scheme() != uri.scheme() || root.host() != uri.host() {
+            return false;
+        }
+        let directory = root.path().trim_end_matches('/');
+        uri.path()
+            .strip_prefix(directory)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// The client's response to a roots/list request from the server
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRootsResult {
+    /// The roots the client exposes
+    pub roots: Vec<Root>,
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The roots a client last listed for its session.
+///
+/// Clones share the list, which the server replaces whenever the client's roots change.
+#[derive(Debug, Clone, Default)]
+pub struct SessionRoots {
+    roots: Arc<Mutex<Option<Vec<Root>>>>,
+}
+
+impl SessionRoots {
+    /// The roots of the session whose request is being handled, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` with these roots as [`SessionRoots::current`].
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// The roots, or `None` if the client has not listed any, e.g. because it lacks the
+    /// `roots` capability.
+    pub fn get(&self) -> Option<Vec<Root>> {
+        self.lock().clone()
+    }
+
+    /// Returns `true` if `uri` lies within one of the roots. Nothing does before the client
+    /// has listed its roots.
+    pub fn contains(&self, uri: &str) -> bool {
+        self.lock().iter().flatten().any(|root| root.contains(uri))
+    }
+
+    pub(crate) fn set(&self, roots: Vec<Root>) {
+        *self.lock() = Some(roots);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Vec<Root>>> {
+        self.roots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(uri: &str) -> Root {
+        Root {
+            uri: uri.to_string(),
+            name: None,
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roots_contain_their_descendants() {
+        let roots = SessionRoots::default();
+        assert!(!roots.contains("file:///home/ada/notes.txt"));
+        roots.set(vec![root("file:///home/ada/"), root("file:///srv/site")]);
+
+        assert!(roots.contains("file:///home/ada"));
+        assert!(roots.contains("file:///home/ada/notes.txt"));
+        assert!(roots.contains("file:///srv/site/index.html"));
+        assert!(!roots.contains("file:///srv/site-old/index.html"));
+        assert!(!roots.contains("file:///etc/passwd"));
+        assert!(!roots.contains("https://home/ada/notes.txt"));
+
+        assert!(SessionRoots::current().is_none());
+        let current = roots
+            .clone()
+            .scope(async { SessionRoots::current().and_then(|roots| roots.get()) })
+            .await;
+        assert_eq!(current.map(|roots| roots.len()), Some(2));
+    }
+}
@@ -0,0 +1,248 @@
+/// LLM sampling requested by servers through clients
+use std::future::Future;
+
+use async_trait::async_trait;
+use bon::Builder;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::McpError;
+use crate::prompt::{PromptMessageContent, PromptMessageRole};
+
+/// Describes a message issued to or received from an LLM API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingMessage {
+    /// The role of the message sender
+    pub role: PromptMessageRole,
+
+    /// The content of the message
+    pub content: PromptMessageContent,
+}
+
+/// Hints to use for model selection
+///
+/// Keys not declared here are currently left unspecified by the spec and are up to the client
+/// to interpret.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelHint {
+    /// A hint for a model name, matched as a substring, e.g. `sonnet` or `claude`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub name: Option<String>,
+}
+
+impl ModelHint {
+    pub fn name(name: impl Into<String>) -> Self {
+        ModelHint {
+            name: Some(name.into()),
+        }
+    }
+}
+
+/// The server's preferences for model selection, requested of the client during sampling
+///
+/// Priorities range from 0 to 1, where 1 means the property matters most. Clients are free to
+/// ignore all of them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreferences {
+    /// Hints for model selection, evaluated in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(field)]
+    pub hints: Option<Vec<ModelHint>>,
+
+    /// How much to prioritize cost when selecting a model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_priority: Option<f64>,
+
+    /// How much to prioritize sampling speed (latency) when selecting a model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_priority: Option<f64>,
+
+    /// How much to prioritize intelligence and capabilities when selecting a model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intelligence_priority: Option<f64>,
+}
+
+impl<S: model_preferences_builder::State> ModelPreferencesBuilder<S> {
+    /// Adds a hint for a model name.
+    pub fn hint(mut self, name: impl Into<String>) -> Self {
+        self.hints
+            .get_or_insert_with(Vec::new)
+            .push(ModelHint::name(name));
+        self
+    }
+}
+
+/// Which MCP servers' context the client should include in the prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IncludeContext {
+    None,
+    ThisServer,
+    AllServers,
+}
+
+/// The reason sampling stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StopReason {
+    EndTurn,
+    StopSequence,
+    MaxTokens,
+    /// A reason specific to the client or model provider
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// Parameters of a sampling/createMessage request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageRequestParams {
+    /// The conversation to sample from
+    pub messages: Vec<SamplingMessage>,
+
+    /// The server's preferences for which model to select
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<ModelPreferences>,
+
+    /// An optional system prompt the server wants to use for sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub system_prompt: Option<String>,
+
+    /// A request to include context from one or more MCP servers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<IncludeContext>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+
+    /// The maximum number of tokens to sample
+    pub max_tokens: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+
+    /// Optional metadata to pass through to the LLM provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The client's response to a sampling/createMessage request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    /// The role of the generated message
+    pub role: PromptMessageRole,
+
+    /// The generated content
+    pub content: PromptMessageContent,
+
+    /// The name of the model that generated the message
+    #[builder(into)]
+    pub model: String,
+
+    /// The reason why sampling stopped, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Answers the `sampling/createMessage` requests of servers on the client, by sampling from
+/// the client's LLM.
+#[async_trait]
+pub trait SamplingHandler: Send + Sync {
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParams,
+    ) -> Result<CreateMessageResult, McpError>;
+}
+
+#[async_trait]
+impl<F, Fut> SamplingHandler for F
+where
+    F: Fn(CreateMessageRequestParams) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<CreateMessageResult, McpError>> + Send,
+{
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParams,
+    ) -> Result<CreateMessageResult, McpError> {
+        self(params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::TextContent;
+    use serde_json::json;
+
+    #[test]
+    fn test_create_message_request_without_raw_json() {
+        let params = CreateMessageRequestParams::builder()
+            .messages(vec![SamplingMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::Text(TextContent {
+                    text: "Hello".to_string(),
+                    meta: None,
+                }),
+            }])
+            .model_preferences(
+                ModelPreferences::builder()
+                    .hint("claude-3-sonnet")
+                    .hint("claude")
+                    .intelligence_priority(0.8)
+                    .speed_priority(0.5)
+                    .build(),
+            )
+            .include_context(IncludeContext::ThisServer)
+            .max_tokens(100)
+            .build();
+
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "messages": [{ "role": "user", "content": { "type": "text", "text": "Hello" } }],
+                "modelPreferences": {
+                    "hints": [{ "name": "claude-3-sonnet" }, { "name": "claude" }],
+                    "speedPriority": 0.5,
+                    "intelligencePriority": 0.8
+                },
+                "includeContext": "thisServer",
+                "maxTokens": 100
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<CreateMessageRequestParams>(value).unwrap(),
+            params
+        );
+    }
+
+    #[test]
+    fn test_stop_reason_keeps_unknown_values() {
+        for (value, reason) in [
+            (json!("endTurn"), StopReason::EndTurn),
+            (json!("stopSequence"), StopReason::StopSequence),
+            (json!("maxTokens"), StopReason::MaxTokens),
+            (json!("refusal"), StopReason::Other("refusal".to_string())),
+        ] {
+            assert_eq!(
+                serde_json::from_value::<StopReason>(value.clone()).unwrap(),
+                reason
+            );
+            assert_eq!(serde_json::to_value(&reason).unwrap(), value);
+        }
+    }
+}
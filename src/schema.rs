@@ -0,0 +1,315 @@
+/// Validation of JSON values against the JSON Schemas tools declare.
+///
+/// Tools describe their output with a JSON Schema, and servers may check results against it
+/// before sending them. This is the subset of JSON Schema that schemas generated by
+/// `schemars` and written by hand for MCP tools use:
+///
+/// - `type`, `enum` and `const`
+/// - `properties`, `required` and `additionalProperties` for objects
+/// - `items`, `minItems` and `maxItems` for arrays
+/// - `minLength` and `maxLength` for strings, `minimum` and `maximum` for numbers
+/// - `allOf`, `anyOf`, `oneOf` and `not`
+/// - `$ref` to definitions in the same document, under `$defs` or `definitions`
+///
+/// Other keywords, such as `format` or `pattern`, are ignored.
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+/// A place where a value does not match its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// The dotted path to the value, empty for the root, e.g. `items.0.name`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.as_str() {
+            "" => write!(f, "{}", self.message),
+            path => write!(f, "{}: {}", path, self.message),
+        }
+    }
+}
+
+/// Checks `value` against `schema` and returns every mismatch found.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), Vec<SchemaError>> {
+    let mut errors = Vec::new();
+    Validator { root: schema }.check(schema, value, "", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    fn check(&self, schema: &'a Value, value: &Value, path: &str, errors: &mut Vec<SchemaError>) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return errors.push(error(path, "no value is allowed here")),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, value, path, errors),
+                None => errors.push(error(path, format!("unresolved $ref {}", reference))),
+            }
+        }
+
+        if let Some(types) = schema.get("type")
+            && !matches_type(types, value)
+        {
+            return errors.push(error(
+                path,
+                format!("expected {}, found {}", describe(types), type_name(value)),
+            ));
+        }
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+            && !allowed.contains(value)
+        {
+            errors.push(error(
+                path,
+                format!("{} is not one of {}", value, Value::from(allowed.clone())),
+            ));
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != value
+        {
+            errors.push(error(path, format!("expected {}", expected)));
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path, errors),
+            Value::Array(items) => self.check_array(schema, items, path, errors),
+            Value::String(text) => {
+                let length = text.chars().count() as f64;
+                check_bound(schema, "minLength", length, |l, b| l >= b, path, errors);
+                check_bound(schema, "maxLength", length, |l, b| l <= b, path, errors);
+            }
+            Value::Number(number) => {
+                let number = number.as_f64().unwrap_or_default();
+                check_bound(schema, "minimum", number, |n, b| n >= b, path, errors);
+                check_bound(schema, "maximum", number, |n, b| n <= b, path, errors);
+            }
+            _ => {}
+        }
+
+        for schema in subschemas(schema, "allOf") {
+            self.check(schema, value, path, errors);
+        }
+        let matching = |key| {
+            subschemas(schema, key)
+                .filter(|schema| self.matches(schema, value))
+                .count()
+        };
+        if schema.contains_key("anyOf") && matching("anyOf") == 0 {
+            errors.push(error(path, "does not match any schema in anyOf"));
+        }
+        if schema.contains_key("oneOf") && matching("oneOf") != 1 {
+            errors.push(error(path, "does not match exactly one schema in oneOf"));
+        }
+        if let Some(not) = schema.get("not")
+            && self.matches(not, value)
+        {
+            errors.push(error(path, "matches the schema in not"));
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &'a Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for required in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(name) = required.as_str()
+                && !object.contains_key(name)
+            {
+                errors.push(error(path, format!("missing required property {}", name)));
+            }
+        }
+        for (name, value) in object {
+            let path = join(path, name);
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property) => self.check(property, value, &path, errors),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(error(&path, "additional properties are not allowed"))
+                    }
+                    Some(additional) => self.check(additional, value, &path, errors),
+                    None => {}
+                },
+            }
+        }
+    }
+
+    fn check_array(
+        &self,
+        schema: &'a Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let length = items.len() as f64;
+        check_bound(schema, "minItems", length, |l, b| l >= b, path, errors);
+        check_bound(schema, "maxItems", length, |l, b| l <= b, path, errors);
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                self.check(item_schema, item, &join(path, &index.to_string()), errors);
+            }
+        }
+    }
+
+    fn matches(&self, schema: &'a Value, value: &Value) -> bool {
+        let mut errors = Vec::new();
+        self.check(schema, value, "", &mut errors);
+        errors.is_empty()
+    }
+
+    /// Resolves a local reference such as `#/$defs/Item`.
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+fn subschemas<'a>(
+    schema: &'a Map<String, Value>,
+    key: &str,
+) -> impl Iterator<Item = &'a Value> + use<'a> {
+    schema
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn check_bound(
+    schema: &Map<String, Value>,
+    keyword: &str,
+    actual: f64,
+    within: impl Fn(f64, f64) -> bool,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if let Some(bound) = schema.get(keyword).and_then(Value::as_f64)
+        && !within(actual, bound)
+    {
+        errors.push(error(path, format!("violates {} of {}", keyword, bound)));
+    }
+}
+
+fn matches_type(types: &Value, value: &Value) -> bool {
+    match types {
+        Value::String(name) => is_type(name, value),
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|name| is_type(name, value)),
+        _ => true,
+    }
+}
+
+fn is_type(name: &str, value: &Value) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        name => type_name(value) == name,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn describe(types: &Value) -> String {
+    match types {
+        Value::String(name) => name.clone(),
+        types => types.to_string(),
+    }
+}
+
+fn join(path: &str, segment: &str) -> String {
+    match path {
+        "" => segment.to_string(),
+        path => format!("{}.{}", path, segment),
+    }
+}
+
+fn error(path: &str, message: impl Into<String>) -> SchemaError {
+    SchemaError {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "status": { "enum": ["ok", "degraded"] },
+                "items": { "type": "array", "items": { "$ref": "#/$defs/Item" }, "maxItems": 2 },
+                "note": { "type": ["string", "null"] }
+            },
+            "required": ["status"],
+            "additionalProperties": false,
+            "$defs": {
+                "Item": {
+                    "type": "object",
+                    "properties": { "id": { "type": "integer", "minimum": 1 } },
+                    "required": ["id"]
+                }
+            }
+        });
+        validate(
+            &schema,
+            &json!({ "status": "ok", "items": [{ "id": 1 }], "note": null }),
+        )
+        .unwrap();
+
+        let errors = validate(
+            &schema,
+            &json!({ "status": "down", "items": [{ "id": 0 }, {}, { "id": 2.5 }], "extra": 1 }),
+        )
+        .unwrap_err();
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "extra: additional properties are not allowed",
+                "items: violates maxItems of 2",
+                "items.0.id: violates minimum of 1",
+                "items.1: missing required property id",
+                "items.2.id: expected integer, found number",
+                r#"status: "down" is not one of ["ok","degraded"]"#,
+            ]
+        );
+    }
+}
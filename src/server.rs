@@ -0,0 +1,3253 @@
+/// MCP servers that route client requests to handlers.
+///
+/// A [`Server`] maps method names to handlers registered with [`Server::on_request`], each
+/// taking the request's typed parameters and returning its typed result. [`Server::serve`]
+/// takes over a transport: it answers the `initialize` handshake and every `ping` itself,
+/// then dispatches each request to its handler and sends the result, or the error, back.
+///
+/// Handlers run in their own tasks, so a slow handler does not hold up the others. Each gets
+/// a [`RequestContext`] with the request's cancellation token, tripped by
+/// `notifications/cancelled` or when the connection closes, and a progress reporter. A
+/// cancelled handler is dropped once it finishes winding down, or after
+/// [`CANCELLATION_GRACE_PERIOD`], and its response is never sent. A handler that panics is
+/// answered with `INTERNAL_ERROR` and reported, see [`Server::with_crash_reporter`].
+/// Requests no handler is registered for are answered with `METHOD_NOT_FOUND`.
+///
+/// Code outside of handlers, such as background tasks watching for changes, notifies the
+/// connected clients through a [`ServerHandle`].
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use bon::bon;
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use schemars::{JsonSchema, schema_for};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+#[cfg(feature = "tower")]
+use tower::{BoxError, Layer, Service};
+
+use crate::auth::{self, AuthContext, AuthorizationRequest, Authorizer};
+use crate::cancellation::{
+    CANCELLATION_GRACE_PERIOD, CancelledNotificationParams, InFlightRequests,
+};
+use crate::compat::SerializationContext;
+use crate::completion::{CompleteRequestParams, CompletionRegistry};
+use crate::crash::CrashReporter;
+use crate::debounce::ListChanges;
+use crate::elicitation::{ElicitAction, ElicitRequestParams, ElicitResult, Elicitation};
+use crate::error::McpError;
+use crate::extension::ExtensionRegistry;
+use crate::filter::SurfaceFilter;
+use crate::handshake::{Handshake, INITIALIZE_METHOD, server_handshake};
+use crate::limit::{ConcurrencyLimits, Limiter, SessionLimiter};
+use crate::logging::{LoggingLevel, LoggingMessageNotificationParams, SetLevelRequestParams};
+use crate::mount::{MountedPrompts, MountedResources};
+use crate::outbound::{LaneKey, Outbound};
+use crate::partial::{PARTIAL_RESULTS_CAPABILITY, PartialResults};
+use crate::pending::{PendingRequests, PendingResponse};
+use crate::ping::ping_response;
+use crate::progress::ProgressReporter;
+use crate::prompt::{GetPromptRequestParams, PromptProvider};
+use crate::protocol::{
+    ClientCapabilities, CompletionsCapability, EmptyResult, Implementation, InitializeResult,
+    JSONRPC_VERSION, JsonRpcError, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse,
+    LoggingCapability, PaginatedRequestParams, PromptsCapability, ProtocolError, ProtocolVersion,
+    RequestId, RequestIdGenerator, ResourcesCapability, ServerCapabilities, ServerNotification,
+    ServerRequest, ToolsCapability, from_params,
+};
+use crate::reload::{ConfigSource, ReloadError, Reloader};
+use crate::resource::{
+    ReadResourceRequestParams, ReadResourceResult, ResourceError, ResourceProvider,
+    ResourceUpdatedNotificationParams, SubscribeRequestParams, UnsubscribeRequestParams,
+};
+use crate::roots::{ListRootsResult, SessionRoots};
+use crate::sampling::{CreateMessageRequestParams, CreateMessageResult};
+#[cfg(feature = "tower")]
+use crate::service::{HandlerService, HandlerStack, Middleware, TypedRequest};
+use crate::state::SessionState;
+use crate::tool::{CallToolRequestParams, Tool, ToolHandler, ToolRegistry};
+use crate::trace::RequestScope;
+use crate::transport::Transport;
+
+pub(crate) type Handler = Arc<
+    dyn Fn(Option<Value>, RequestContext) -> BoxFuture<'static, Result<Value, McpError>>
+        + Send
+        + Sync,
+>;
+
+/// What a handler knows about the request it handles and the session it belongs to.
+#[derive(Clone)]
+pub struct RequestContext {
+    id: RequestId,
+    cancellation: CancellationToken,
+    progress: ProgressReporter,
+    partial_results: PartialResults,
+    outbound: Arc<Outbound>,
+    session: Arc<Handshake<ClientCapabilities>>,
+    handle: ServerHandle,
+    session_id: u64,
+    state: SessionState,
+    auth: Arc<AuthContext>,
+    peer: Peer,
+}
+
+/// The client's side of a session: the requests sent to it, the responses they wait for, and
+/// the roots it listed.
+#[derive(Clone, Default)]
+struct Peer {
+    pending: PendingRequests,
+    ids: Arc<RequestIdGenerator>,
+    roots: SessionRoots,
+}
+
+impl Peer {
+    /// Sends `request` to the client, returning its id and the response to wait for.
+    async fn send(
+        &self,
+        outbound: &Outbound,
+        request: ServerRequest,
+    ) -> Result<(RequestId, PendingResponse), McpError> {
+        let id = self.ids.next_id();
+        let request = request.into_request(id.clone())?;
+        let response = self.pending.register(id.clone());
+        outbound.send(JsonRpcMessage::Request(request)).await?;
+        Ok((id, response))
+    }
+
+    /// Lists the client's roots again and keeps them. The previous roots are kept if the
+    /// client fails to answer.
+    async fn refresh_roots(self, outbound: Arc<Outbound>) {
+        let Ok((_, response)) = self.send(&outbound, ServerRequest::ListRoots).await else {
+            return;
+        };
+        let result = response.await.and_then(|result| {
+            serde_json::from_value::<ListRootsResult>(result)
+                .map_err(|e| McpError::internal_error(e.to_string()))
+        });
+        if let Ok(result) = result {
+            self.roots.set(result.roots);
+        }
+    }
+}
+
+impl RequestContext {
+    pub fn id(&self) -> &RequestId {
+        &self.id
+    }
+
+    /// The token tripped when the client cancels the request or the connection closes.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Reports progress to the client with `notifications/progress`, if it asked for progress.
+    ///
+    /// Reports are dropped when the request carried no progress token, so long-running
+    /// handlers can report unconditionally.
+    pub fn progress(&self) -> &ProgressReporter {
+        &self.progress
+    }
+
+    /// Sends chunks of a tool's result to the client before the result itself, if the call
+    /// opted into them, see [`crate::partial`].
+    pub fn partial_results(&self) -> &PartialResults {
+        &self.partial_results
+    }
+
+    /// Sends notifications and requests of its own to the client.
+    pub fn outbound(&self) -> &Arc<Outbound> {
+        &self.outbound
+    }
+
+    /// What the handshake established about the client.
+    pub fn session(&self) -> &Handshake<ClientCapabilities> {
+        &self.session
+    }
+
+    /// Identifies the session among those the server has served, in the order they started.
+    pub fn session_id(&self) -> u64 {
+        self.session_id
+    }
+
+    /// The protocol version negotiated for the session.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.session.protocol_version
+    }
+
+    /// The name and version of the client's implementation.
+    pub fn client_info(&self) -> &Implementation {
+        &self.session.peer_info
+    }
+
+    /// The capabilities the client declared.
+    pub fn client_capabilities(&self) -> &ClientCapabilities {
+        &self.session.peer_capabilities
+    }
+
+    /// State kept for the length of the session, shared by all of its requests.
+    pub fn state(&self) -> &SessionState {
+        &self.state
+    }
+
+    /// Who the session acts for, see [`Server::serve_with_auth`].
+    pub fn auth(&self) -> &AuthContext {
+        &self.auth
+    }
+
+    /// Sends a log message to this request's client, unless it asked for messages above
+    /// `level` with `logging/setLevel`. `logger` names the part of the server logging it.
+    pub async fn log(&self, level: LoggingLevel, logger: Option<&str>, data: impl Into<Value>) {
+        let wanted = self
+            .handle
+            .lock()
+            .get(&self.session_id)
+            .is_some_and(|session| Audience::Logging(level).includes(session));
+        if !wanted {
+            return;
+        }
+        let notification = ServerNotification::LoggingMessage(LoggingMessageNotificationParams {
+            level,
+            logger: logger.map(str::to_string),
+            data: data.into(),
+            meta: None,
+        });
+        let Ok(notification) = notification.into_notification() else {
+            return;
+        };
+        // A client that has gone away cannot read the message; the request ends anyway.
+        let _ = self
+            .outbound
+            .send_ordered(LaneKey::Session, JsonRpcMessage::Notification(notification))
+            .await;
+    }
+
+    /// Sends `request` to the client and waits for its result.
+    ///
+    /// If the request being handled is cancelled first, the client is sent a
+    /// `notifications/cancelled` for `request` and a cancellation error is returned.
+    pub async fn request<R: DeserializeOwned>(
+        &self,
+        request: ServerRequest,
+    ) -> Result<R, McpError> {
+        let (id, response) = self.peer.send(&self.outbound, request).await?;
+        let result = tokio::select! {
+            result = response => result?,
+            _ = self.cancellation.cancelled() => {
+                let reason = Some("The request that sent it was cancelled".to_string());
+                let notification = CancelledNotificationParams::new(id.clone(), reason);
+                let _ = self
+                    .outbound
+                    .send(JsonRpcMessage::Notification(notification.into_notification()?))
+                    .await;
+                let message = format!("Request {} was cancelled", id);
+                return Err(ProtocolError::Cancelled(message).into());
+            }
+        };
+        serde_json::from_value(result).map_err(|e| McpError::internal_error(e.to_string()))
+    }
+
+    /// Asks the client to sample a message from its LLM, with `sampling/createMessage`.
+    ///
+    /// Fails without sending anything if the client did not declare the sampling capability.
+    pub async fn create_message(
+        &self,
+        params: CreateMessageRequestParams,
+    ) -> Result<CreateMessageResult, McpError> {
+        if self.session.peer_capabilities.sampling.is_none() {
+            return Err(McpError::invalid_request(
+                "The client does not support sampling",
+            ));
+        }
+        self.request(ServerRequest::CreateMessage(Box::new(params)))
+            .await
+    }
+
+    /// Asks the user, through the client, for input of type `T` with `elicitation/create`.
+    ///
+    /// The requested schema is generated from `T`, which should be a flat struct of strings,
+    /// numbers, booleans and enums, as the protocol allows no more. Fails without sending
+    /// anything if the client did not declare the elicitation capability.
+    pub async fn elicit<T: JsonSchema + DeserializeOwned>(
+        &self,
+        message: impl Into<String>,
+    ) -> Result<Elicitation<T>, McpError> {
+        if self.session.peer_capabilities.elicitation.is_none() {
+            return Err(McpError::invalid_request(
+                "The client does not support elicitation",
+            ));
+        }
+        let params = ElicitRequestParams {
+            message: message.into(),
+            requested_schema: schema_for!(T).to_value(),
+            meta: None,
+        };
+        let result: ElicitResult = self.request(ServerRequest::Elicit(params)).await?;
+        match result.action {
+            ElicitAction::Accept => {
+                let content = Value::Object(result.content.unwrap_or_default());
+                serde_json::from_value(content)
+                    .map(Elicitation::Accepted)
+                    .map_err(|e| McpError::invalid_params(format!("Invalid input: {}", e)))
+            }
+            ElicitAction::Decline => Ok(Elicitation::Declined),
+            ElicitAction::Cancel => Ok(Elicitation::Cancelled),
+        }
+    }
+
+    /// The roots the client last listed, also available to the code the handler calls as
+    /// [`SessionRoots::current`].
+    pub fn roots(&self) -> &SessionRoots {
+        &self.peer.roots
+    }
+
+    /// Subscribes this session to updates of the resource at `uri`.
+    fn subscribe(&self, uri: String) {
+        if let Some(session) = self.handle.lock().get_mut(&self.session_id) {
+            session.subscriptions.insert(uri);
+        }
+    }
+
+    /// Unsubscribes this session from updates of the resource at `uri`.
+    fn unsubscribe(&self, uri: &str) {
+        if let Some(session) = self.handle.lock().get_mut(&self.session_id) {
+            session.subscriptions.remove(uri);
+        }
+    }
+}
+
+/// An MCP server: its identity, capabilities and request handlers.
+#[derive(Clone)]
+pub struct Server {
+    info: Implementation,
+    capabilities: ServerCapabilities,
+    instructions: Option<String>,
+    handlers: HashMap<String, Handler>,
+    extensions: ExtensionRegistry,
+    tools: ToolRegistry,
+    resources: Option<Arc<dyn ResourceProvider>>,
+    prompts: Option<Arc<dyn PromptProvider>>,
+    limiter: Limiter,
+    crashes: CrashReporter,
+    timeouts: HashMap<String, Duration>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    filter: Option<SessionFilter>,
+    handle: ServerHandle,
+    list_changed_window: Duration,
+    #[cfg(feature = "tower")]
+    middleware: Vec<Middleware>,
+    /// The handlers wrapped in the middleware, built when serving starts
+    #[cfg(feature = "tower")]
+    stack: Option<HandlerStack>,
+}
+
+impl fmt::Debug for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Server")
+            .field("info", &self.info)
+            .field("capabilities", &self.capabilities)
+            .field("methods", &self.handlers.keys().collect::<Vec<_>>())
+            .field("extensions", &self.extensions)
+            .field("tools", &self.tools)
+            .field("resources", &self.resources.is_some())
+            .field("prompts", &self.prompts.is_some())
+            .field("timeouts", &self.timeouts)
+            .field("authorizer", &self.authorizer.is_some())
+            .field("filter", &self.filter.is_some())
+            .field("list_changed_window", &self.list_changed_window)
+            .finish()
+    }
+}
+
+#[bon]
+impl Server {
+    /// Builds a server from what it offers.
+    ///
+    /// ```text
+    /// let server = Server::builder()
+    ///     .info(implementation)
+    ///     .tools(tools)
+    ///     .instructions("Search before you ask")
+    ///     .build();
+    /// ```
+    ///
+    /// Capabilities follow from what the server is given: `tools` is advertised with
+    /// `listChanged` once it has tools, `resources` once it has a resource provider,
+    /// `prompts` once it has a prompt provider, `completions` once it has completion
+    /// providers, and extensions add theirs under `experimental`.
+    /// Capabilities passed explicitly take precedence over the derived ones.
+    #[builder(finish_fn = build)]
+    pub fn builder(
+        info: Implementation,
+        #[builder(into)] instructions: Option<String>,
+        capabilities: Option<ServerCapabilities>,
+        tools: Option<ToolRegistry>,
+        resources: Option<Arc<dyn ResourceProvider>>,
+        prompts: Option<Arc<dyn PromptProvider>>,
+        completions: Option<CompletionRegistry>,
+        extensions: Option<ExtensionRegistry>,
+        concurrency_limits: Option<ConcurrencyLimits>,
+        crash_reporter: Option<CrashReporter>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+    ) -> Self {
+        let mut server = Server::new(info);
+        if let Some(capabilities) = capabilities {
+            server = server.with_capabilities(capabilities);
+        }
+        server.instructions = instructions;
+        if let Some(extensions) = extensions {
+            server = server.with_extensions(extensions);
+        }
+        if let Some(resources) = resources {
+            server = server.with_resources(resources);
+        }
+        if let Some(prompts) = prompts {
+            server = server.with_prompts(prompts);
+        }
+        if let Some(completions) = completions {
+            server = server.with_completions(completions);
+        }
+        if let Some(limits) = concurrency_limits {
+            server = server.with_concurrency_limits(limits);
+        }
+        if let Some(crashes) = crash_reporter {
+            server = server.with_crash_reporter(crashes);
+        }
+        server.authorizer = authorizer;
+        match tools {
+            Some(tools) => server.with_tools(tools),
+            None => server,
+        }
+    }
+
+    pub fn new(info: Implementation) -> Self {
+        Server {
+            info,
+            capabilities: ServerCapabilities::default(),
+            instructions: None,
+            handlers: HashMap::new(),
+            extensions: ExtensionRegistry::new(),
+            tools: ToolRegistry::new(),
+            resources: None,
+            prompts: None,
+            limiter: Limiter::default(),
+            crashes: CrashReporter::new(),
+            timeouts: HashMap::new(),
+            authorizer: None,
+            filter: None,
+            handle: ServerHandle::default(),
+            list_changed_window: Duration::ZERO,
+            #[cfg(feature = "tower")]
+            middleware: Vec::new(),
+            #[cfg(feature = "tower")]
+            stack: None,
+        }
+    }
+
+    /// A handle that notifies the clients this server, or any clone of it, is serving.
+    pub fn handle(&self) -> ServerHandle {
+        self.handle.clone()
+    }
+
+    /// Sets the capabilities to advertise. Capabilities left unset are inferred from the
+    /// registered handlers, see [`Server::capabilities`].
+    pub fn with_capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Sets the instructions sent to the client with the initialize result.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Holds back `list_changed` notifications for `window` after a list first changes, then
+    /// sends one for every list that changed, see [`crate::debounce`].
+    pub fn with_list_changed_window(mut self, window: Duration) -> Self {
+        self.list_changed_window = window;
+        self
+    }
+
+    /// Advertises the partial results extension, so tools can stream chunks of their results
+    /// to clients that opt in, see [`crate::partial`].
+    pub fn with_partial_results(mut self) -> Self {
+        self.extensions
+            .advertise(PARTIAL_RESULTS_CAPABILITY, Value::Object(Map::new()));
+        self
+    }
+
+    /// Limits how many handlers run at once, see [`ConcurrencyLimits`]. The global limit is
+    /// shared by the clones of the server made after this call.
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.limiter = Limiter::new(limits);
+        self
+    }
+
+    /// Asks `authorizer` about every request before its handler runs, see [`crate::auth`].
+    ///
+    /// `ping` and `logging/setLevel` are answered without asking it, as they are answered
+    /// before dispatch, outside the concurrency limits too. A server that registers its own
+    /// `logging/setLevel` handler has it authorized like any other.
+    pub fn with_authorizer(mut self, authorizer: impl Authorizer + 'static) -> Self {
+        self.authorizer = Some(Arc::new(authorizer));
+        self
+    }
+
+    /// Shows every session only what passes `filter`, see [`crate::filter`].
+    pub fn with_filter(self, filter: SurfaceFilter) -> Self {
+        self.with_session_filter(move |_: &AuthContext| filter.clone())
+    }
+
+    /// Shows each session only what passes the filter `filter` makes for it from the
+    /// session's [`AuthContext`], see [`crate::filter`].
+    pub fn with_session_filter(
+        mut self,
+        filter: impl Fn(&AuthContext) -> SurfaceFilter + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Gives the handler of `method` at most `timeout` to answer a request. A handler that
+    /// takes longer is dropped and the request answered with a `REQUEST_TIMEOUT` error, see
+    /// [`McpError::timed_out`]. Tools can be given timeouts of their own with
+    /// [`ToolRegistry::with_timeout`].
+    pub fn with_method_timeout(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.timeouts.insert(method.into(), timeout);
+        self
+    }
+
+    /// Reports handlers that panic to the sinks of `crashes`. A panicking handler is always
+    /// answered with an `INTERNAL_ERROR` naming the incident; without sinks the panic is only
+    /// printed by the panic hook.
+    pub fn with_crash_reporter(mut self, crashes: CrashReporter) -> Self {
+        self.crashes = crashes;
+        self
+    }
+
+    /// Answers extension methods with `extensions` and advertises their capabilities.
+    pub fn with_extensions(mut self, extensions: ExtensionRegistry) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Answers `tools/list` and `tools/call` with the tools in `tools`, replacing the tools
+    /// added with [`Server::add_tool`].
+    ///
+    /// Unless the tools capability is set explicitly, it is advertised with `listChanged`,
+    /// and clients are notified whenever a tool is added or removed during the session.
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.capabilities.tools.get_or_insert(ToolsCapability {
+            list_changed: Some(true),
+        });
+        self.tools = tools.clone();
+        let listed = tools.clone();
+        self.on_request("tools/list", move |params: PaginatedRequestParams, _| {
+            let result = listed.list(&params);
+            async move { result }
+        })
+        .on_request(
+            "tools/call",
+            move |params: CallToolRequestParams, context| {
+                let tools = tools.clone();
+                async move { tools.call(params, context).await }
+            },
+        )
+    }
+
+    /// Adds a tool, replacing any tool with the same name.
+    ///
+    /// The tools are shared by all clones of the server, so a tool added to a clone of a
+    /// running server is offered to its clients right away. A server serves its tools if it
+    /// has any when [`Server::serve`] is called, or was given them with
+    /// [`Server::with_tools`].
+    pub fn add_tool(&self, tool: Tool, handler: impl ToolHandler + 'static) -> &Self {
+        self.tools.register(tool, handler);
+        self
+    }
+
+    /// Removes the tool named `name`. Returns `false` if there is none.
+    pub fn remove_tool(&self, name: &str) -> bool {
+        self.tools.remove(name)
+    }
+
+    /// The tools this server offers.
+    pub fn tools(&self) -> &ToolRegistry {
+        &self.tools
+    }
+
+    /// Answers `resources/list`, `resources/templates/list` and `resources/read` with
+    /// `provider`.
+    ///
+    /// If the provider supports subscriptions, `resources/subscribe` and
+    /// `resources/unsubscribe` are answered too, and the provider is given the
+    /// [`SubscriptionHandle`] that notifies the subscribed sessions.
+    pub fn with_resources(mut self, provider: impl ResourceProvider + 'static) -> Self {
+        let provider: Arc<dyn ResourceProvider> = Arc::new(provider);
+        self.resources = Some(provider.clone());
+        if provider.supports_subscriptions() || provider.supports_list_changed() {
+            provider.attach(SubscriptionHandle {
+                handle: self.handle.clone(),
+            });
+        }
+        if provider.supports_subscriptions() {
+            let subscribing = provider.clone();
+            self = self
+                .on_request(
+                    "resources/subscribe",
+                    move |params: SubscribeRequestParams, context| {
+                        let provider = subscribing.clone();
+                        async move {
+                            provider
+                                .subscribe(&params.uri)
+                                .await
+                                .map_err(|error| resource_error(&params.uri, error))?;
+                            context.subscribe(params.uri);
+                            Ok(EmptyResult {})
+                        }
+                    },
+                )
+                .on_request(
+                    "resources/unsubscribe",
+                    |params: UnsubscribeRequestParams, context| async move {
+                        context.unsubscribe(&params.uri);
+                        Ok(EmptyResult {})
+                    },
+                );
+        }
+        let listed = provider.clone();
+        let templates = provider.clone();
+        self.on_request(
+            "resources/list",
+            move |params: PaginatedRequestParams, _| {
+                let provider = listed.clone();
+                async move { Ok(provider.list(params.cursor).await?) }
+            },
+        )
+        .on_request(
+            "resources/templates/list",
+            move |params: PaginatedRequestParams, _| {
+                let provider = templates.clone();
+                async move { Ok(provider.list_templates(params.cursor).await?) }
+            },
+        )
+        .on_request(
+            "resources/read",
+            move |params: ReadResourceRequestParams, _| {
+                let provider = provider.clone();
+                async move {
+                    let contents = provider
+                        .read(&params.uri)
+                        .await
+                        .map_err(|error| resource_error(&params.uri, error))?;
+                    Ok(ReadResourceResult {
+                        contents,
+                        meta: None,
+                    })
+                }
+            },
+        )
+    }
+
+    /// Answers `prompts/list` and `prompts/get` with `provider`.
+    pub fn with_prompts(mut self, provider: impl PromptProvider + 'static) -> Self {
+        let provider: Arc<dyn PromptProvider> = Arc::new(provider);
+        self.prompts = Some(provider.clone());
+        let listed = provider.clone();
+        self.on_request("prompts/list", move |params: PaginatedRequestParams, _| {
+            let provider = listed.clone();
+            async move { Ok(provider.list(params.cursor).await?) }
+        })
+        .on_request("prompts/get", move |params: GetPromptRequestParams, _| {
+            let provider = provider.clone();
+            async move {
+                let arguments = params.arguments.unwrap_or_default();
+                Ok(provider.get(&params.name, arguments).await?)
+            }
+        })
+    }
+
+    /// Offers the tools, prompts and resources of `server` under `prefix`, e.g. its tool
+    /// `read_file` as `fs__read_file`, see [`crate::mount`].
+    ///
+    /// Tools added to or removed from `server` later are offered or withdrawn right away, see
+    /// [`ToolRegistry::mount`]. The handlers it registered with [`Server::on_request`] are
+    /// not offered.
+    pub fn mount(mut self, prefix: &str, server: Server) -> Self {
+        self.tools.mount(prefix, &server.tools);
+        if let Some(prompts) = server.prompts {
+            let mounted = MountedPrompts::new(self.prompts.clone()).mount(prefix, prompts);
+            self = self.with_prompts(mounted);
+        }
+        if let Some(resources) = server.resources {
+            let mounted = MountedResources::new(self.resources.clone()).mount(prefix, resources);
+            self = self.with_resources(mounted);
+        }
+        self
+    }
+
+    /// Serves the tools, prompts and resources `source` loads, replacing those given before,
+    /// and returns the [`Reloader`] that loads them again, see [`crate::reload`].
+    ///
+    /// Prompts and resources are advertised with `listChanged`, since a reload can change
+    /// them. Resource subscriptions are offered if the resources loaded first support them.
+    pub async fn with_config_source(
+        mut self,
+        source: impl ConfigSource + 'static,
+    ) -> Result<(Self, Reloader), ReloadError> {
+        let reloader = Reloader::load(source, self.handle()).await?;
+        self.capabilities.prompts.get_or_insert(PromptsCapability {
+            list_changed: Some(true),
+        });
+        let server = self
+            .with_tools(reloader.tools())
+            .with_prompts(reloader.prompts())
+            .with_resources(reloader.resources());
+        Ok((server, reloader))
+    }
+
+    /// Answers `completion/complete` with the providers in `completions`.
+    pub fn with_completions(self, completions: CompletionRegistry) -> Self {
+        self.on_request(
+            "completion/complete",
+            move |params: CompleteRequestParams, _| {
+                let completions = completions.clone();
+                async move { completions.complete(params).await }
+            },
+        )
+    }
+
+    /// Registers the handler for `method`, replacing any handler registered before.
+    ///
+    /// Parameters that do not deserialize into `P` are answered with `INVALID_PARAMS`
+    /// without calling the handler.
+    pub fn on_request<P, R, F, Fut>(mut self, method: impl Into<String>, handler: F) -> Self
+    where
+        P: DeserializeOwned + Send + 'static,
+        R: Serialize + Send + 'static,
+        F: Fn(P, RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, McpError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let erased: Handler = Arc::new(move |params, context| {
+            let handler = handler.clone();
+            async move {
+                let params: P = from_params(params)?;
+                let result = handler(params, context).await?;
+                serde_json::to_value(result).map_err(|e| McpError::internal_error(e.to_string()))
+            }
+            .boxed()
+        });
+        self.handlers.insert(method.into(), erased);
+        self
+    }
+
+    /// Wraps the handlers in `layer`, e.g. a `tower` timeout or concurrency limit, see
+    /// [`crate::service`].
+    #[cfg(feature = "tower")]
+    pub fn with_layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<HandlerStack> + Send + Sync + 'static,
+        L::Service: Service<TypedRequest, Response = Value, Error = BoxError>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<TypedRequest>>::Future: Send + 'static,
+    {
+        self.middleware
+            .push(Arc::new(move |inner| HandlerStack::new(layer.layer(inner))));
+        self
+    }
+
+    /// The handlers registered so far, wrapped in the layers added with
+    /// [`Server::with_layer`], as a `tower` service.
+    #[cfg(feature = "tower")]
+    pub fn service(&self) -> HandlerStack {
+        let handlers = HandlerStack::new(HandlerService::new(self.handlers.clone()));
+        self.middleware
+            .iter()
+            .fold(handlers, |stack, middleware| middleware(stack))
+    }
+
+    /// The handler for `method`, wrapped in the middleware while serving.
+    fn handler(&self, method: &str) -> Option<Handler> {
+        let handler = self.handlers.get(method).cloned()?;
+        #[cfg(feature = "tower")]
+        if let Some(stack) = &self.stack {
+            return Some(crate::service::through(stack.clone(), method.to_string()));
+        }
+        Some(handler)
+    }
+
+    /// Returns `true` if a handler or extension is registered for `method`.
+    pub fn handles(&self, method: &str) -> bool {
+        self.handlers.contains_key(method) || self.extensions.handles(method)
+    }
+
+    /// The capabilities advertised to clients.
+    ///
+    /// Capabilities that were not set explicitly are advertised when a handler is registered
+    /// for their methods, e.g. `tools` once `tools/list` or `tools/call` is handled.
+    /// `logging` is always advertised, since any server can log through its
+    /// [`ServerHandle`]. Extension capabilities are added under `experimental`.
+    pub fn capabilities(&self) -> ServerCapabilities {
+        let mut capabilities = self.capabilities.clone();
+        let handles_any = |prefix: &str| self.handlers.keys().any(|m| m.starts_with(prefix));
+        if capabilities.tools.is_none() && handles_any("tools/") {
+            capabilities.tools = Some(ToolsCapability::default());
+        }
+        if capabilities.resources.is_none() && handles_any("resources/") {
+            capabilities.resources = Some(ResourcesCapability {
+                subscribe: self
+                    .handlers
+                    .contains_key("resources/subscribe")
+                    .then_some(true),
+                list_changed: self
+                    .resources
+                    .as_ref()
+                    .is_some_and(|provider| provider.supports_list_changed())
+                    .then_some(true),
+            });
+        }
+        if capabilities.prompts.is_none() && handles_any("prompts/") {
+            capabilities.prompts = Some(PromptsCapability::default());
+        }
+        capabilities.logging.get_or_insert(LoggingCapability {});
+        if capabilities.completions.is_none() && handles_any("completion/") {
+            capabilities.completions = Some(CompletionsCapability {});
+        }
+        self.extensions.advertise_in(&mut capabilities);
+        capabilities
+    }
+
+    /// Serves one client over `transport` until the connection closes.
+    ///
+    /// Returns an error if the handshake fails or the transport breaks; a client closing
+    /// the connection is a normal end of the session. Handlers still running when the
+    /// connection closes are cancelled.
+    pub async fn serve<T: Transport + 'static>(self, transport: T) -> Result<(), ProtocolError> {
+        self.serve_with_auth(transport, AuthContext::anonymous())
+            .await
+    }
+
+    /// Serves one client over `transport` like [`Server::serve`], on behalf of `auth`.
+    ///
+    /// `auth` is whatever authenticated the connection established, such as the claims of a
+    /// bearer token. Handlers find it in [`RequestContext::auth`], and the authorizer is
+    /// asked about every request with it, see [`Server::with_authorizer`].
+    pub async fn serve_with_auth<T: Transport + 'static>(
+        mut self,
+        transport: T,
+        auth: AuthContext,
+    ) -> Result<(), ProtocolError> {
+        if !self.tools.is_empty() && !self.handles("tools/list") {
+            let tools = self.tools.clone();
+            self = self.with_tools(tools);
+        }
+        #[cfg(feature = "tower")]
+        if !self.middleware.is_empty() {
+            self.stack = Some(self.service());
+        }
+        // Subscribe before the handshake, so changes made during it are announced after it.
+        let tool_changes = self.tools.subscribe();
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        let session = server_handshake(
+            transport.as_ref(),
+            InitializeResult {
+                protocol_version: String::new(),
+                capabilities: self.capabilities(),
+                server_info: self.info.clone(),
+                instructions: self.instructions.clone(),
+                meta: None,
+            },
+        )
+        .await?;
+        let session = Arc::new(session);
+        let outbound = Arc::new(Outbound::new(transport.clone()));
+        let in_flight = InFlightRequests::new();
+        let closed = CancellationToken::new();
+        let _closed = closed.clone().drop_guard();
+        let filter = self.filter.as_ref().map(|filter| Arc::new(filter(&auth)));
+        let list_changes = Arc::new(ListChanges::new(outbound.clone(), self.list_changed_window));
+        let attached = self.handle.attach(
+            outbound.clone(),
+            list_changes.clone(),
+            self.limiter.for_session(),
+            auth,
+            filter,
+        );
+        let peer = Peer::default();
+        let lists_roots = session.peer_capabilities.roots.is_some();
+        if lists_roots {
+            tokio::spawn(peer.clone().refresh_roots(outbound.clone()));
+        }
+        if self
+            .capabilities()
+            .tools
+            .and_then(|tools| tools.list_changed)
+            == Some(true)
+        {
+            tokio::spawn(announce_tool_changes(tool_changes, list_changes, closed));
+        }
+
+        let result = loop {
+            let message = match transport.receive().await {
+                Ok(Some(message)) => message,
+                Ok(None) => break Ok(()),
+                Err(error @ ProtocolError::TransportError(_)) => break Err(error),
+                // A line that is not a message is answered without an id, and reading goes on.
+                Err(error) => {
+                    let rejection = JsonRpcMessage::Error(JsonRpcError {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        id: None,
+                        error: error.into(),
+                    });
+                    if let Err(error) = outbound.send(rejection).await {
+                        break Err(error);
+                    }
+                    continue;
+                }
+            };
+            match message {
+                JsonRpcMessage::Request(request) => {
+                    // `ping` and `logging/setLevel` are answered right here, but sent like every
+                    // other response, so that a failure to send ends the session below.
+                    let answer = if let Some(pong) = ping_response(&request) {
+                        pong
+                    } else if request.method == SET_LEVEL_METHOD && !self.handles(SET_LEVEL_METHOD)
+                    {
+                        attached.set_level(request)
+                    } else {
+                        self.dispatch(request, &session, &attached, &outbound, &peer, &in_flight);
+                        continue;
+                    };
+                    if let Err(error) = outbound.send(answer).await {
+                        break Err(error);
+                    }
+                }
+                JsonRpcMessage::Notification(notification) => {
+                    if notification.method == ROOTS_LIST_CHANGED && lists_roots {
+                        tokio::spawn(peer.clone().refresh_roots(outbound.clone()));
+                    }
+                    in_flight.handle_notification(&notification);
+                }
+                // Responses to requests no handler waits for anymore are dropped.
+                message @ (JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_)) => {
+                    peer.pending.resolve(message);
+                }
+            }
+        };
+        in_flight.cancel_all();
+        peer.pending.close("Connection closed");
+        result
+    }
+
+    /// Spawns the task that handles `request` and sends its response.
+    fn dispatch(
+        &self,
+        request: JsonRpcRequest,
+        session: &Arc<Handshake<ClientCapabilities>>,
+        attached: &AttachedSession,
+        outbound: &Arc<Outbound>,
+        peer: &Peer,
+        in_flight: &InFlightRequests,
+    ) {
+        let handler = self.handler(&request.method);
+        let extensions = self.extensions.clone();
+        let guard = in_flight.register(request.id.clone());
+        let context = RequestContext {
+            id: request.id.clone(),
+            cancellation: guard.token().clone(),
+            progress: ProgressReporter::for_request(&request, outbound.clone()),
+            partial_results: PartialResults::for_request(&request, outbound.clone()),
+            outbound: outbound.clone(),
+            session: session.clone(),
+            handle: attached.handle.clone(),
+            session_id: attached.id,
+            state: attached.state.clone(),
+            auth: attached.auth.clone(),
+            peer: peer.clone(),
+        };
+        let roots = peer.roots.clone();
+        let serialization = SerializationContext::new(session.protocol_version);
+        let outbound = outbound.clone();
+        let scope = RequestScope::new(&request);
+        let limiter = attached.limiter.clone();
+        let crashes = self.crashes.clone();
+        let timeout = self.timeouts.get(&request.method).copied();
+        let filter = attached.filter.clone();
+        let auth = attached.auth.clone();
+        let hidden = filter
+            .as_ref()
+            .and_then(|filter| filter.check(&request.method, request.params.as_ref()));
+        let authorization = self.authorizer.clone().map(|authorizer| {
+            let request = AuthorizationRequest::new(
+                attached.auth.clone(),
+                &request.method,
+                request.params.as_ref(),
+            );
+            (authorizer, request)
+        });
+
+        tokio::spawn(scope.run(async move {
+            let id = request.id.clone();
+            // Filtered items are refused before the authorizer can reveal that they exist.
+            let refused = match (hidden, authorization) {
+                (Some(error), _) => Some(error),
+                (None, Some((authorizer, authorization))) => authorizer
+                    .authorize(authorization)
+                    .await
+                    .err()
+                    .map(McpError::from),
+                (None, None) => None,
+            };
+            if let Some(error) = refused {
+                drop(guard);
+                let _ = outbound.send(error.into_response(id)).await;
+                return;
+            }
+            let permits = tokio::select! {
+                biased;
+                permits = limiter.acquire() => permits,
+                // Nobody waits for the response to a request cancelled while it waited.
+                _ = guard.token().cancelled() => return,
+            };
+            let _permits = match permits {
+                Ok(permits) => permits,
+                Err(error) => {
+                    drop(guard);
+                    let _ = outbound.send(error.into_response(id)).await;
+                    return;
+                }
+            };
+            let token = guard.token().clone();
+            let method = request.method.clone();
+            let crashed = request.id.clone();
+            let timed_out = request.id.clone();
+            let handling = async move {
+                match handler {
+                    Some(handler) => match roots
+                        .scope(auth::scope(auth, handler(request.params, context)))
+                        .await
+                    {
+                        Ok(mut result) => {
+                            if let Some(filter) = &filter {
+                                filter.hide(&request.method, &mut result);
+                            }
+                            serialization.adapt_result(&request.method, &mut result);
+                            JsonRpcMessage::Response(JsonRpcResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                id,
+                                result: Some(result),
+                                error: None,
+                            })
+                        }
+                        Err(error) => error.into_response(id),
+                    },
+                    None if request.method == INITIALIZE_METHOD => {
+                        McpError::invalid_request("Session is already initialized")
+                            .into_response(id)
+                    }
+                    None => {
+                        let method = request.method.clone();
+                        match extensions.handle(request).await {
+                            Some(response) => response,
+                            None => McpError::method_not_found(&method).into_response(id),
+                        }
+                    }
+                }
+            };
+            let bounded = async move {
+                match timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, handling)
+                        .await
+                        .unwrap_or_else(|_| McpError::timed_out(timeout).into_response(timed_out)),
+                    None => handling.await,
+                }
+            };
+            let mut respond = async move {
+                crashes
+                    .catch(method, bounded)
+                    .await
+                    .unwrap_or_else(|report| McpError::from(report).into_response(crashed))
+            }
+            .boxed();
+            let response = tokio::select! {
+                biased;
+                response = &mut respond => response,
+                _ = token.cancelled() => {
+                    // The handler gets a moment to wind down, such as cancelling the requests it
+                    // sent to the client, before it is dropped. Its response is not sent.
+                    let _ = tokio::time::timeout(CANCELLATION_GRACE_PERIOD, respond).await;
+                    return;
+                }
+            };
+            // Nor is the response of a handler that finished as it was cancelled.
+            if token.is_cancelled() {
+                return;
+            }
+            drop(guard);
+            let _ = outbound.send(response).await;
+        }));
+    }
+}
+
+/// Makes the [`SurfaceFilter`] of a session from its [`AuthContext`].
+type SessionFilter = Arc<dyn Fn(&AuthContext) -> SurfaceFilter + Send + Sync>;
+
+const SET_LEVEL_METHOD: &str = "logging/setLevel";
+const ROOTS_LIST_CHANGED: &str = "notifications/roots/list_changed";
+
+/// Sends notifications to every client a server is serving, from anywhere in the program.
+///
+/// Handles are cheap to clone and stay valid across sessions: a notification reaches the
+/// sessions that are open when it is sent, and none if there are none. Resource updates only
+/// reach the sessions subscribed to the resource. Log messages respect the level each client
+/// chose with `logging/setLevel`; clients that chose none get all of them. Delivery to a
+/// client whose connection breaks is skipped without affecting the others.
+#[derive(Clone, Default)]
+pub struct ServerHandle {
+    sessions: Arc<Mutex<HashMap<u64, Session>>>,
+    next_session: Arc<AtomicU64>,
+}
+
+struct Session {
+    outbound: Arc<Outbound>,
+    list_changes: Arc<ListChanges>,
+    level: Option<LoggingLevel>,
+    subscriptions: HashSet<String>,
+}
+
+/// Which sessions a notification goes to.
+enum Audience<'a> {
+    /// Sessions that want log messages at this level
+    Logging(LoggingLevel),
+    /// Sessions subscribed to the resource at this URI
+    Subscribers(&'a str),
+}
+
+impl Audience<'_> {
+    fn includes(&self, session: &Session) -> bool {
+        match self {
+            Audience::Logging(level) => session.level.is_none_or(|minimum| *level >= minimum),
+            Audience::Subscribers(uri) => session.subscriptions.contains(*uri),
+        }
+    }
+}
+
+impl fmt::Debug for ServerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerHandle")
+            .field("sessions", &self.sessions())
+            .finish()
+    }
+}
+
+impl ServerHandle {
+    /// Sends `notifications/resources/updated` for the resource at `uri` to the sessions
+    /// subscribed to it.
+    pub async fn notify_resources_updated(&self, uri: impl Into<String>) {
+        let uri = uri.into();
+        let notification = ServerNotification::ResourceUpdated(ResourceUpdatedNotificationParams {
+            uri: uri.clone(),
+            meta: None,
+        });
+        self.broadcast(
+            LaneKey::Resource(uri.clone()),
+            notification,
+            Audience::Subscribers(&uri),
+        )
+        .await;
+    }
+
+    /// Sends `notifications/resources/list_changed`.
+    pub async fn notify_resources_list_changed(&self) {
+        self.broadcast_list_changed(ServerNotification::ResourceListChanged)
+            .await;
+    }
+
+    /// Sends `notifications/tools/list_changed`.
+    ///
+    /// Servers announce changes made through [`Server::add_tool`] and
+    /// [`Server::remove_tool`] on their own; this is for tools that change in other ways.
+    pub async fn notify_tools_list_changed(&self) {
+        self.broadcast_list_changed(ServerNotification::ToolListChanged)
+            .await;
+    }
+
+    /// Sends `notifications/prompts/list_changed`.
+    pub async fn notify_prompts_list_changed(&self) {
+        self.broadcast_list_changed(ServerNotification::PromptListChanged)
+            .await;
+    }
+
+    /// Sends a log message to every client that asked for messages at `level`.
+    pub async fn log(&self, level: LoggingLevel, data: impl Into<Value>) {
+        self.log_from(level, None, data.into()).await;
+    }
+
+    /// Sends a log message issued by `logger` to every client that asked for messages at
+    /// `level`.
+    pub(crate) async fn log_from(&self, level: LoggingLevel, logger: Option<String>, data: Value) {
+        let notification = ServerNotification::LoggingMessage(LoggingMessageNotificationParams {
+            level,
+            logger,
+            data,
+            meta: None,
+        });
+        self.broadcast(LaneKey::Session, notification, Audience::Logging(level))
+            .await;
+    }
+
+    /// The number of sessions currently open.
+    pub fn sessions(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Sends `notification` to the sessions in `audience`.
+    async fn broadcast(
+        &self,
+        key: LaneKey,
+        notification: ServerNotification,
+        audience: Audience<'_>,
+    ) {
+        let Ok(notification) = notification.into_notification() else {
+            return;
+        };
+        let deliveries: Vec<_> = self
+            .lock()
+            .values()
+            .filter(|session| audience.includes(session))
+            .map(|session| {
+                session.outbound.send_ordered(
+                    key.clone(),
+                    JsonRpcMessage::Notification(notification.clone()),
+                )
+            })
+            .collect();
+        futures::future::join_all(deliveries).await;
+    }
+
+    /// Sends a `list_changed` notification to every session, or holds it back until the
+    /// session's list-changed window closes.
+    async fn broadcast_list_changed(&self, notification: ServerNotification) {
+        let Ok(notification) = notification.into_notification() else {
+            return;
+        };
+        let deliveries: Vec<_> = self
+            .lock()
+            .values()
+            .map(|session| session.list_changes.notify(notification.clone()))
+            .collect();
+        futures::future::join_all(deliveries).await;
+    }
+
+    /// Adds a session, until the returned guard is dropped.
+    fn attach(
+        &self,
+        outbound: Arc<Outbound>,
+        list_changes: Arc<ListChanges>,
+        limiter: SessionLimiter,
+        auth: AuthContext,
+        filter: Option<Arc<SurfaceFilter>>,
+    ) -> AttachedSession {
+        let id = self.next_session.fetch_add(1, Ordering::Relaxed);
+        self.lock().insert(
+            id,
+            Session {
+                outbound,
+                list_changes,
+                level: None,
+                subscriptions: HashSet::new(),
+            },
+        );
+        AttachedSession {
+            handle: self.clone(),
+            id,
+            state: SessionState::new(),
+            limiter,
+            auth: Arc::new(auth),
+            filter,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, Session>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Notifies sessions of changes to a resource provider's resources.
+///
+/// Providers that support subscriptions or list changes are given one by
+/// [`Server::with_resources`].
+#[derive(Debug, Clone)]
+pub struct SubscriptionHandle {
+    handle: ServerHandle,
+}
+
+impl SubscriptionHandle {
+    /// Sends `notifications/resources/updated` to the sessions subscribed to `uri`.
+    pub async fn notify_updated(&self, uri: impl Into<String>) {
+        self.handle.notify_resources_updated(uri).await;
+    }
+
+    /// Sends `notifications/resources/list_changed` to every session.
+    pub async fn notify_list_changed(&self) {
+        self.handle.notify_resources_list_changed().await;
+    }
+
+    /// Returns `true` if any session is subscribed to `uri`.
+    pub fn is_subscribed(&self, uri: &str) -> bool {
+        self.handle
+            .lock()
+            .values()
+            .any(|session| session.subscriptions.contains(uri))
+    }
+}
+
+/// A session's place in its [`ServerHandle`], removed when the session ends.
+struct AttachedSession {
+    handle: ServerHandle,
+    id: u64,
+    state: SessionState,
+    limiter: SessionLimiter,
+    auth: Arc<AuthContext>,
+    filter: Option<Arc<SurfaceFilter>>,
+}
+
+impl AttachedSession {
+    /// Answers `logging/setLevel` by recording the level the client asked for.
+    fn set_level(&self, request: JsonRpcRequest) -> JsonRpcMessage {
+        match from_params::<SetLevelRequestParams>(request.params) {
+            Ok(params) => {
+                if let Some(session) = self.handle.lock().get_mut(&self.id) {
+                    session.level = Some(params.level);
+                }
+                JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: request.id,
+                    result: Some(Value::Object(Default::default())),
+                    error: None,
+                })
+            }
+            Err(error) => McpError::from(error).into_response(request.id),
+        }
+    }
+}
+
+impl Drop for AttachedSession {
+    fn drop(&mut self) {
+        self.handle.lock().remove(&self.id);
+    }
+}
+
+/// Reports a resource provider's error about the resource at `uri`, with the URI in the data
+/// of `RESOURCE_NOT_FOUND` errors.
+fn resource_error(uri: &str, error: ResourceError) -> McpError {
+    match error {
+        ResourceError::NotFound => McpError::resource_not_found(uri),
+        error => error.into(),
+    }
+}
+
+/// Sends `notifications/tools/list_changed` whenever the tools change, until the session is
+/// closed.
+async fn announce_tool_changes(
+    mut changes: watch::Receiver<()>,
+    list_changes: Arc<ListChanges>,
+    closed: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            _ = closed.cancelled() => return,
+            changed = changes.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+        let Ok(notification) = ServerNotification::ToolListChanged.into_notification() else {
+            return;
+        };
+        list_changes.notify(notification).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Forbidden;
+    use crate::cancellation::CancelledNotificationParams;
+    use crate::client::Client;
+    use crate::handshake::{InitializeRequest, client_handshake};
+    use crate::prompt::{
+        GetPromptResult, ListPromptsResult, Prompt, PromptError, PromptMessage,
+        PromptMessageContent, PromptMessageRole, TextContent,
+    };
+    use crate::protocol::{
+        ClientRequest, Cursor, ElicitationCapability, FORBIDDEN, INTERNAL_ERROR, INVALID_PARAMS,
+        INVALID_REQUEST, InitializeRequestParams, JsonRpcNotification, METHOD_NOT_FOUND,
+        PARSE_ERROR, PaginatedRequestParams, REQUEST_TIMEOUT, RESOURCE_NOT_FOUND, RootsCapability,
+        SERVER_BUSY, SamplingCapability,
+    };
+    use crate::resource::{ListResourcesResult, Resource, ResourceContent};
+    use crate::sampling::SamplingMessage;
+    use crate::tool::{CallToolResult, ListToolsResult, Tool};
+    use crate::transport::{MemoryTransport, StreamTransport};
+    use serde_json::{Map, json};
+    use std::time::Duration;
+
+    fn implementation(name: &str) -> Implementation {
+        Implementation::new(name, "1.0.0")
+    }
+
+    fn server() -> Server {
+        Server::new(implementation("test-server"))
+            .with_instructions("Be nice")
+            .on_request("tools/list", |_: PaginatedRequestParams, _| async move {
+                Ok(ListToolsResult {
+                    tools: vec![Tool::builder().name("search").build()],
+                    ..Default::default()
+                })
+            })
+    }
+
+    fn request(id: i64, method: &str, params: Option<Value>) -> JsonRpcMessage {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(id),
+            method: method.to_string(),
+            params,
+        })
+    }
+
+    async fn initialize(client: &MemoryTransport) -> Handshake<ServerCapabilities> {
+        client_handshake(
+            client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: implementation("test-client"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn test_builder_derives_capabilities() {
+        let tools = ToolRegistry::new();
+        tools.register(Tool::builder().name("search").build(), |_, _| async move {
+            Ok(CallToolResult::text("found"))
+        });
+        let server = Server::builder()
+            .info(implementation("test-server"))
+            .instructions("Be nice")
+            .tools(tools)
+            .resources(Arc::new(Notes))
+            .build();
+        assert!(server.handles("tools/call"));
+        assert!(server.handles("resources/read"));
+        let capabilities = server.capabilities();
+        assert_eq!(capabilities.tools.unwrap().list_changed, Some(true));
+        assert_eq!(capabilities.resources, Some(ResourcesCapability::default()));
+        assert!(capabilities.prompts.is_none());
+        assert!(capabilities.completions.is_none());
+
+        let server = Server::builder()
+            .info(implementation("test-server"))
+            .completions(CompletionRegistry::new())
+            .build();
+        assert!(server.handles("completion/complete"));
+        assert!(server.capabilities().completions.is_some());
+
+        let server = Server::builder()
+            .info(implementation("test-server"))
+            .capabilities(ServerCapabilities {
+                tools: Some(ToolsCapability::default()),
+                ..Default::default()
+            })
+            .tools(ToolRegistry::new())
+            .build();
+        assert_eq!(server.capabilities().tools.unwrap().list_changed, None);
+    }
+
+    #[tokio::test]
+    async fn test_client_talks_to_server() {
+        let (client, server_side) = MemoryTransport::pair();
+        let serving = tokio::spawn(server().serve(server_side));
+
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let session = client.session_info();
+        assert_eq!(session.instructions.as_deref(), Some("Be nice"));
+        assert!(session.capabilities.tools.is_some());
+        assert!(session.capabilities.resources.is_none());
+
+        client.ping().await.unwrap();
+        let result: ListToolsResult = client
+            .request(ClientRequest::ListTools(PaginatedRequestParams::default()))
+            .await
+            .unwrap();
+        assert_eq!(result.tools[0].name, "search");
+
+        client.close().await.unwrap();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_malformed_lines_are_answered_and_reading_goes_on() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (ours, theirs) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(theirs);
+        let serving = tokio::spawn(server().serve(StreamTransport::new(reader, writer)));
+        let (reader, mut writer) = tokio::io::split(ours);
+        let mut lines = BufReader::new(reader).lines();
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": {"name": "test-client", "version": "1.0.0"},
+            },
+        });
+        writer
+            .write_all(format!("{}\n", initialize).as_bytes())
+            .await
+            .unwrap();
+        lines.next_line().await.unwrap().unwrap();
+        writer
+            .write_all(
+                concat!(
+                    "{\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}\n",
+                    "{oops\n",
+                    "{\"jsonrpc\":\"2.0\",\"id\":1}\n",
+                    "{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"ping\"}\n",
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        let mut answers = Vec::new();
+        for _ in 0..3 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            answers.push(serde_json::from_str::<JsonRpcMessage>(&line).unwrap());
+        }
+        let JsonRpcMessage::Error(error) = &answers[0] else {
+            panic!("Expected a parse error, got {:?}", answers[0]);
+        };
+        assert_eq!((&error.id, error.error.code), (&None, PARSE_ERROR));
+        let JsonRpcMessage::Error(error) = &answers[1] else {
+            panic!("Expected an invalid request error, got {:?}", answers[1]);
+        };
+        assert_eq!((&error.id, error.error.code), (&None, INVALID_REQUEST));
+        let JsonRpcMessage::Response(pong) = &answers[2] else {
+            panic!("Expected the ping response, got {:?}", answers[2]);
+        };
+        assert_eq!(pong.id, RequestId::Number(2));
+
+        drop((lines, writer));
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_registered_tools_are_called() {
+        let tools = ToolRegistry::new();
+        tools
+            .register(
+                Tool::builder().name("echo").build(),
+                |arguments: Option<Map<String, Value>>, _| async move {
+                    match arguments.unwrap_or_default().get("text") {
+                        Some(Value::String(text)) => Ok(CallToolResult::text(text)),
+                        _ => Err(McpError::invalid_params("text is required")),
+                    }
+                },
+            )
+            .register(
+                Tool::builder().name("fetch").build(),
+                |_: Option<Map<String, Value>>, _| async move {
+                    Err::<CallToolResult, _>(McpError::internal_error("Upstream is down"))
+                },
+            );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        assert!(client.session_info().capabilities.tools.is_some());
+        let call = |name: &str, arguments: Value| {
+            ClientRequest::CallTool(CallToolRequestParams {
+                name: name.to_string(),
+                arguments: arguments.as_object().cloned(),
+                meta: None,
+            })
+        };
+
+        let result: CallToolResult = client
+            .request(call("echo", json!({ "text": "hi" })))
+            .await
+            .unwrap();
+        assert_eq!(result, CallToolResult::text("hi"));
+
+        // A failing tool is a result the model can see, not a protocol error.
+        let result: CallToolResult = client.request(call("fetch", json!({}))).await.unwrap();
+        assert_eq!(result, CallToolResult::error("Upstream is down"));
+        assert!(result.is_error());
+
+        for (name, message) in [
+            ("echo", "text is required"),
+            ("missing", "Unknown tool: missing"),
+        ] {
+            let error = client
+                .request::<CallToolResult>(call(name, json!({})))
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tools_report_progress() {
+        let tools = ToolRegistry::new();
+        tools.register(
+            Tool::builder().name("index").build(),
+            |_: Option<Map<String, Value>>, context: RequestContext| async move {
+                for file in 1..=2 {
+                    let message = format!("Indexed file {}", file);
+                    context
+                        .progress()
+                        .report(file as f64, Some(2.0), Some(message))
+                        .await?;
+                }
+                Ok(CallToolResult::text("done"))
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let call = || {
+            ClientRequest::CallTool(CallToolRequestParams {
+                name: "index".to_string(),
+                arguments: None,
+                meta: None,
+            })
+        };
+
+        let mut updates = Vec::new();
+        let result: CallToolResult = client
+            .request_with_progress(call(), |update| {
+                updates.push((update.progress, update.total, update.message))
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, CallToolResult::text("done"));
+        assert_eq!(
+            updates,
+            [
+                (1.0, Some(2.0), Some("Indexed file 1".to_string())),
+                (2.0, Some(2.0), Some("Indexed file 2".to_string())),
+            ]
+        );
+
+        // Without a progress token the reports are dropped.
+        let result: CallToolResult = client.request(call()).await.unwrap();
+        assert_eq!(result, CallToolResult::text("done"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_tools_and_methods_time_out() {
+        let slow = |_: Option<Map<String, Value>>, _| async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(CallToolResult::text("done"))
+        };
+        let tools = ToolRegistry::new().with_timeout("crawl", Duration::from_millis(20));
+        tools
+            .register(Tool::builder().name("crawl").build(), slow)
+            .register(
+                Tool::builder().name("echo").build(),
+                |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("hi")) },
+            );
+        let server = Server::new(implementation("test-server"))
+            .with_tools(tools)
+            .on_request("x-test/wait", |_: Value, _| async move {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(json!({}))
+            })
+            .with_method_timeout("x-test/wait", Duration::from_millis(30));
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        for (id, method, params, elapsed) in [
+            (30, "tools/call", Some(json!({ "name": "crawl" })), 20),
+            (31, "x-test/wait", None, 30),
+        ] {
+            client.send(request(id, method, params)).await.unwrap();
+            let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+                panic!("Expected an error response");
+            };
+            assert_eq!(error.id, Some(RequestId::Number(id)));
+            assert_eq!(error.error.code, REQUEST_TIMEOUT);
+            assert_eq!(error.error.data, Some(json!({ "elapsedMs": elapsed })));
+        }
+
+        // Other tools have no timeout.
+        client
+            .send(request(32, "tools/call", Some(json!({ "name": "echo" }))))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.id, RequestId::Number(32));
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_is_validated() {
+        #[derive(Serialize, schemars::JsonSchema)]
+        struct Weather {
+            temperature: f64,
+        }
+
+        let tools = ToolRegistry::new().with_output_validation();
+        for (name, output) in [
+            ("weather", json!({ "temperature": 21.5 })),
+            ("broken", json!({ "temperature": "warm" })),
+        ] {
+            tools.register(
+                Tool::builder()
+                    .name(name)
+                    .output_schema::<Weather>()
+                    .build(),
+                move |_: Option<Map<String, Value>>, _| {
+                    let output = output.clone();
+                    async move { CallToolResult::structured(output) }
+                },
+            );
+        }
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let call = |name: &str| {
+            ClientRequest::CallTool(CallToolRequestParams {
+                name: name.to_string(),
+                arguments: None,
+                meta: None,
+            })
+        };
+
+        let listed: ListToolsResult = client
+            .request(ClientRequest::ListTools(PaginatedRequestParams::default()))
+            .await
+            .unwrap();
+        assert_eq!(
+            listed.tools[0].output_schema.as_ref().unwrap()["required"],
+            json!(["temperature"])
+        );
+
+        let result: CallToolResult = client.request(call("weather")).await.unwrap();
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "temperature": 21.5 }))
+        );
+        assert_eq!(
+            result,
+            CallToolResult::structured(Weather { temperature: 21.5 }).unwrap()
+        );
+
+        let error = client
+            .request::<CallToolResult>(call("broken"))
+            .await
+            .unwrap_err();
+        #[cfg(not(feature = "jsonschema"))]
+        let expected = "temperature: expected number, found string";
+        #[cfg(feature = "jsonschema")]
+        let expected = "temperature: \"warm\" is not of type \"number\"";
+        assert!(error.to_string().contains(expected), "{}", error);
+    }
+
+    #[cfg(feature = "jsonschema")]
+    #[tokio::test]
+    async fn test_output_is_validated_against_the_full_specification() {
+        let tools = ToolRegistry::new().with_output_validation();
+        for (name, code) in [("valid", "0042"), ("invalid", "42a")] {
+            let mut tool = Tool::builder().name(name).build();
+            // `pattern` is one of the keywords the built-in subset ignores.
+            tool.output_schema = Some(json!({
+                "type": "object",
+                "properties": { "code": { "type": "string", "pattern": "^[0-9]+$" } },
+                "required": ["code"]
+            }));
+            tools.register(tool, move |_: Option<Map<String, Value>>, _| async move {
+                CallToolResult::structured(json!({ "code": code }))
+            });
+        }
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let call = |name: &str| {
+            ClientRequest::CallTool(CallToolRequestParams {
+                name: name.to_string(),
+                arguments: None,
+                meta: None,
+            })
+        };
+
+        let result: CallToolResult = client.request(call("valid")).await.unwrap();
+        assert_eq!(result.structured_content, Some(json!({ "code": "0042" })));
+        let error = client
+            .request::<CallToolResult>(call("invalid"))
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("does not match its output schema"),
+            "{}",
+            error
+        );
+    }
+
+    #[cfg(feature = "jsonschema")]
+    #[tokio::test]
+    async fn test_arguments_are_validated() {
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        #[allow(dead_code)]
+        struct Forecast {
+            city: String,
+            days: u8,
+        }
+
+        let tools = ToolRegistry::new().with_input_validation();
+        tools.register(
+            Tool::builder()
+                .name("forecast")
+                .input_schema::<Forecast>()
+                .build(),
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("sunny")) },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let call = |arguments: Value| {
+            ClientRequest::CallTool(CallToolRequestParams {
+                name: "forecast".to_string(),
+                arguments: arguments.as_object().cloned(),
+                meta: None,
+            })
+        };
+
+        let result: CallToolResult = client
+            .request(call(json!({ "city": "Oslo", "days": 3 })))
+            .await
+            .unwrap();
+        assert_eq!(result, CallToolResult::text("sunny"));
+
+        let error = client
+            .request::<CallToolResult>(call(json!({ "city": 42, "days": -1 })))
+            .await
+            .unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("city: 42 is not of type"), "{}", message);
+        assert!(message.contains("days: -1 is less than"), "{}", message);
+
+        let error = client
+            .request::<CallToolResult>(call(json!({ "days": 3 })))
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("\"city\" is a required property"),
+            "{}",
+            error
+        );
+    }
+
+    #[cfg(feature = "macros")]
+    #[tokio::test]
+    async fn test_tool_attribute() {
+        use crate::tool::ToolDefinition;
+        use schemars::JsonSchema;
+        use serde::Deserialize;
+
+        #[derive(Deserialize, JsonSchema)]
+        struct GreetArgs {
+            name: String,
+        }
+
+        /// Greets someone by name.
+        #[crate::tool(title = "Greet")]
+        async fn greet(args: GreetArgs) -> Result<CallToolResult, McpError> {
+            Ok(CallToolResult::text(format!("Hello, {}!", args.name)))
+        }
+
+        let definition = GreetTool.definition();
+        assert_eq!(definition.name, "greet");
+        assert_eq!(definition.title.as_deref(), Some("Greet"));
+        assert_eq!(
+            definition.description.as_deref(),
+            Some("Greets someone by name.")
+        );
+        assert_eq!(definition.input_schema["required"], json!(["name"]));
+
+        let tools = ToolRegistry::new();
+        tools.add(GreetTool);
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+        let call = |arguments: Value| {
+            ClientRequest::CallTool(CallToolRequestParams {
+                name: "greet".to_string(),
+                arguments: arguments.as_object().cloned(),
+                meta: None,
+            })
+        };
+
+        let result: CallToolResult = client
+            .request(call(json!({ "name": "Ada" })))
+            .await
+            .unwrap();
+        assert_eq!(result, CallToolResult::text("Hello, Ada!"));
+        let error = client
+            .request::<CallToolResult>(call(json!({ "name": 1 })))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Invalid arguments"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_tools_are_listed_in_pages() {
+        let tools = ToolRegistry::new().with_page_size(2);
+        for name in ["a", "b", "c", "d", "e"] {
+            tools.register(
+                Tool::builder().name(name).build(),
+                |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::default()) },
+            );
+        }
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let pages: Vec<ListToolsResult> = client
+            .request_all_pages(ClientRequest::ListTools)
+            .await
+            .unwrap();
+        assert_eq!(pages.len(), 3);
+        let names: Vec<_> = pages
+            .iter()
+            .flat_map(|page| &page.tools)
+            .map(|tool| tool.name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b", "c", "d", "e"]);
+
+        let error = client
+            .request::<ListToolsResult>(ClientRequest::ListTools(PaginatedRequestParams {
+                cursor: Some("bogus".into()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Invalid cursor"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_tools_change_while_serving() {
+        let server = Server::new(implementation("test-server")).with_tools(ToolRegistry::new());
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.clone().serve(server_side));
+        let session = initialize(&client).await;
+        assert_eq!(
+            session.peer_capabilities.tools,
+            Some(ToolsCapability {
+                list_changed: Some(true)
+            })
+        );
+
+        let list_changed = || async {
+            let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+            else {
+                panic!("Expected a notification");
+            };
+            assert_eq!(notification.method, "notifications/tools/list_changed");
+        };
+        server.add_tool(
+            Tool::builder().name("echo").build(),
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("echo")) },
+        );
+        list_changed().await;
+
+        client.send(request(30, "tools/list", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.result.unwrap()["tools"][0]["name"], json!("echo"));
+
+        assert!(server.remove_tool("echo"));
+        list_changed().await;
+        assert!(!server.remove_tool("echo"));
+        assert!(server.tools().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handlers_log_to_their_session() {
+        let server = server().on_request(
+            "work",
+            |_: Option<Value>, context: RequestContext| async move {
+                context.log(LoggingLevel::Info, None, "starting").await;
+                context
+                    .log(LoggingLevel::Warning, Some("worker"), "running late")
+                    .await;
+                Ok(EmptyResult {})
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        client
+            .send(request(
+                45,
+                "logging/setLevel",
+                Some(json!({ "level": "warning" })),
+            ))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(_)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        client.send(request(46, "work", None)).await.unwrap();
+        let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+        else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "notifications/message");
+        assert_eq!(
+            notification.params,
+            Some(json!({ "level": "warning", "logger": "worker", "data": "running late" }))
+        );
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.id, RequestId::Number(46));
+    }
+
+    #[tokio::test]
+    async fn test_handlers_share_session_state() {
+        let server = server().on_request(
+            "count",
+            |_: Option<Value>, context: RequestContext| async move {
+                let calls = context.state().update(|calls: &mut u64| {
+                    *calls += 1;
+                    *calls
+                });
+                Ok(json!({
+                    "calls": calls,
+                    "client": context.client_info().name,
+                    "protocolVersion": context.protocol_version(),
+                }))
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        for (id, calls) in [(47, 1), (48, 2)] {
+            client.send(request(id, "count", None)).await.unwrap();
+            let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+                panic!("Expected a response");
+            };
+            assert_eq!(
+                response.result,
+                Some(json!({
+                    "calls": calls,
+                    "client": "test-client",
+                    "protocolVersion": "2025-06-18",
+                }))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handlers_sample_through_the_client() {
+        let server = server().on_request(
+            "summarize",
+            |_: Option<Value>, context: RequestContext| async move {
+                let params = CreateMessageRequestParams::builder()
+                    .messages(vec![SamplingMessage {
+                        role: PromptMessageRole::User,
+                        content: PromptMessageContent::Text(TextContent {
+                            text: "Summarize the report".to_string(),
+                            meta: None,
+                        }),
+                    }])
+                    .max_tokens(100)
+                    .build();
+                context.create_message(params).await
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.clone().serve(server_side));
+        client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities {
+                        sampling: Some(SamplingCapability {}),
+                        ..Default::default()
+                    },
+                    client_info: implementation("test-client"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        client.send(request(80, "summarize", None)).await.unwrap();
+        let Some(JsonRpcMessage::Request(sampling)) = client.receive().await.unwrap() else {
+            panic!("Expected a request");
+        };
+        assert_eq!(sampling.method, "sampling/createMessage");
+        assert_eq!(sampling.params.unwrap()["maxTokens"], json!(100));
+        let sampled = json!({
+            "role": "assistant",
+            "content": { "type": "text", "text": "All is well" },
+            "model": "test-model"
+        });
+        client
+            .send(JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: sampling.id,
+                result: Some(sampled.clone()),
+                error: None,
+            }))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.id, RequestId::Number(80));
+        assert_eq!(response.result, Some(sampled));
+
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+        client.send(request(81, "summarize", None)).await.unwrap();
+        let Some(JsonRpcMessage::Error(response)) = client.receive().await.unwrap() else {
+            panic!("Expected an error");
+        };
+        assert_eq!(response.error.code, INVALID_REQUEST);
+    }
+
+    #[derive(serde::Deserialize, JsonSchema)]
+    struct Contact {
+        email: String,
+    }
+
+    #[tokio::test]
+    async fn test_handlers_elicit_input_from_the_user() {
+        let server = server().on_request(
+            "invite",
+            |_: Option<Value>, context: RequestContext| async move {
+                match context.elicit::<Contact>("Who should be invited?").await? {
+                    Elicitation::Accepted(contact) => Ok(format!("Invited {}", contact.email)),
+                    Elicitation::Declined | Elicitation::Cancelled => {
+                        Ok("Nobody invited".to_string())
+                    }
+                }
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities {
+                        elicitation: Some(ElicitationCapability {}),
+                        ..Default::default()
+                    },
+                    client_info: implementation("test-client"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        for (id, answer, expected) in [
+            (
+                85,
+                json!({ "action": "accept", "content": { "email": "ada@example.com" } }),
+                "Invited ada@example.com",
+            ),
+            (86, json!({ "action": "decline" }), "Nobody invited"),
+        ] {
+            client.send(request(id, "invite", None)).await.unwrap();
+            let Some(JsonRpcMessage::Request(elicitation)) = client.receive().await.unwrap() else {
+                panic!("Expected a request");
+            };
+            assert_eq!(elicitation.method, "elicitation/create");
+            let params = elicitation.params.unwrap();
+            assert_eq!(params["message"], json!("Who should be invited?"));
+            assert_eq!(params["requestedSchema"]["required"], json!(["email"]));
+            client
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: elicitation.id,
+                    result: Some(answer),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+            let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+                panic!("Expected a response");
+            };
+            assert_eq!(response.result, Some(json!(expected)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roots_are_listed_and_refreshed() {
+        let server = server().on_request("roots", |_: Option<Value>, _| async move {
+            let roots = SessionRoots::current().and_then(|roots| roots.get());
+            Ok(roots.unwrap_or_default())
+        });
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities {
+                        roots: Some(RootsCapability {
+                            list_changed: Some(true),
+                        }),
+                        ..Default::default()
+                    },
+                    client_info: implementation("test-client"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        let answer_roots = async |uri: &str| {
+            let Some(JsonRpcMessage::Request(listing)) = client.receive().await.unwrap() else {
+                panic!("Expected a request");
+            };
+            assert_eq!(listing.method, "roots/list");
+            client
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: listing.id,
+                    result: Some(json!({ "roots": [{ "uri": uri }] })),
+                    error: None,
+                }))
+                .await
+                .unwrap();
+        };
+        let roots_seen = async |expected: &str| {
+            for id in 90.. {
+                client.send(request(id, "roots", None)).await.unwrap();
+                let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap()
+                else {
+                    panic!("Expected a response");
+                };
+                if response.result.unwrap()[0]["uri"] == json!(expected) {
+                    return;
+                }
+                assert!(id < 190, "Roots were not refreshed");
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        answer_roots("file:///home/ada/").await;
+        roots_seen("file:///home/ada/").await;
+
+        client
+            .send(JsonRpcMessage::Notification(JsonRpcNotification {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                method: "notifications/roots/list_changed".to_string(),
+                params: None,
+            }))
+            .await
+            .unwrap();
+        answer_roots("file:///srv/site/").await;
+        roots_seen("file:///srv/site/").await;
+    }
+
+    #[tokio::test]
+    async fn test_handle_notifies_sessions() {
+        let server = server();
+        let handle = server.handle();
+        let (client, server_side) = MemoryTransport::pair();
+        let serving = tokio::spawn(server.serve(server_side));
+        let session = initialize(&client).await;
+        assert!(session.peer_capabilities.logging.is_some());
+
+        client
+            .send(request(
+                40,
+                "logging/setLevel",
+                Some(json!({ "level": "warning" })),
+            ))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.result, Some(json!({})));
+        assert_eq!(handle.sessions(), 1);
+
+        handle.log(LoggingLevel::Debug, "ignored").await;
+        handle
+            .log(LoggingLevel::Error, json!({ "disk": "full" }))
+            .await;
+        handle.notify_resources_updated("file:///notes.txt").await;
+        handle.notify_resources_list_changed().await;
+        let mut notifications = Vec::new();
+        for _ in 0..2 {
+            let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+            else {
+                panic!("Expected a notification");
+            };
+            notifications.push((notification.method, notification.params.unwrap_or_default()));
+        }
+        assert_eq!(
+            notifications,
+            [
+                (
+                    "notifications/message".to_string(),
+                    json!({ "level": "error", "data": { "disk": "full" } })
+                ),
+                (
+                    "notifications/resources/list_changed".to_string(),
+                    json!(null)
+                ),
+            ]
+        );
+
+        client.close().await.unwrap();
+        serving.await.unwrap().unwrap();
+        assert_eq!(handle.sessions(), 0);
+        handle.notify_tools_list_changed().await;
+    }
+
+    struct Notes;
+
+    #[async_trait::async_trait]
+    impl ResourceProvider for Notes {
+        async fn list(&self, _: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+            Ok(ListResourcesResult {
+                resources: vec![
+                    Resource::builder()
+                        .uri("str:///todo".parse().unwrap())
+                        .name("todo")
+                        .build(),
+                ],
+                ..Default::default()
+            })
+        }
+
+        async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+            match uri {
+                "str:///todo" => Ok(vec![ResourceContent::TextResourceContents {
+                    uri: uri.to_string(),
+                    mime_type: Some("text/plain".to_string()),
+                    text: "Water the plants".to_string(),
+                    meta: None,
+                }]),
+                _ => Err(ResourceError::NotFound),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resources_are_read_from_the_provider() {
+        let server = Server::new(implementation("test-server")).with_resources(Notes);
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let session = initialize(&client).await;
+        assert_eq!(
+            session.peer_capabilities.resources,
+            Some(ResourcesCapability::default())
+        );
+
+        let call = async |id, method: &str, params| {
+            client.send(request(id, method, params)).await.unwrap();
+            client.receive().await.unwrap().unwrap()
+        };
+        let JsonRpcMessage::Response(response) = call(50, "resources/list", None).await else {
+            panic!("Expected a response");
+        };
+        assert_eq!(
+            response.result.unwrap()["resources"][0]["uri"],
+            json!("str:///todo")
+        );
+        let JsonRpcMessage::Response(response) = call(51, "resources/templates/list", None).await
+        else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.result, Some(json!({ "resourceTemplates": [] })));
+        let JsonRpcMessage::Response(response) =
+            call(52, "resources/read", Some(json!({ "uri": "str:///todo" }))).await
+        else {
+            panic!("Expected a response");
+        };
+        assert_eq!(
+            response.result.unwrap()["contents"][0]["text"],
+            json!("Water the plants")
+        );
+
+        let JsonRpcMessage::Error(response) = call(
+            53,
+            "resources/read",
+            Some(json!({ "uri": "str:///missing" })),
+        )
+        .await
+        else {
+            panic!("Expected an error");
+        };
+        assert_eq!(response.error.code, RESOURCE_NOT_FOUND);
+        assert_eq!(
+            response.error.data,
+            Some(json!({ "uri": "str:///missing" }))
+        );
+    }
+
+    struct Greetings;
+
+    #[async_trait::async_trait]
+    impl PromptProvider for Greetings {
+        async fn list(&self, cursor: Option<Cursor>) -> Result<ListPromptsResult, PromptError> {
+            let prompts = [
+                Prompt::builder().name("greet").build(),
+                Prompt::builder().name("farewell").build(),
+            ];
+            let request = PaginatedRequestParams { cursor, meta: None };
+            let (prompts, next_cursor) = crate::pagination::paginate(&prompts, &request, 1)?;
+            Ok(ListPromptsResult {
+                prompts,
+                next_cursor,
+                meta: None,
+            })
+        }
+
+        async fn get(
+            &self,
+            name: &str,
+            arguments: HashMap<String, String>,
+        ) -> Result<GetPromptResult, PromptError> {
+            if name != "greet" {
+                return Err(PromptError::NotFound(name.to_string()));
+            }
+            let who = arguments.get("name").ok_or_else(|| {
+                PromptError::InvalidParameters("Missing argument: name".to_string())
+            })?;
+            Ok(GetPromptResult {
+                description: Some("A greeting".to_string()),
+                messages: vec![PromptMessage {
+                    role: PromptMessageRole::User,
+                    content: PromptMessageContent::Text(TextContent {
+                        text: format!("Say hello to {who}"),
+                        meta: None,
+                    }),
+                }],
+                meta: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lists_are_streamed_across_pages() {
+        use futures::{StreamExt, TryStreamExt};
+
+        let tools = ToolRegistry::new().with_page_size(2);
+        for name in ["fetch", "search", "summarize"] {
+            tools.register(
+                Tool::builder().name(name).build(),
+                |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("ok")) },
+            );
+        }
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(
+            Server::new(implementation("test-server"))
+                .with_tools(tools)
+                .with_prompts(Greetings)
+                .serve(server_side),
+        );
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let names: Vec<String> = client
+            .list_tools_stream()
+            .map_ok(|tool| tool.name)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(names, ["fetch", "search", "summarize"]);
+        let mut tools = std::pin::pin!(client.list_tools_stream());
+        let first = tools.next().await.unwrap().unwrap();
+        assert_eq!(first.name, "fetch");
+
+        let prompts = client.list_prompts().await.unwrap();
+        let names: Vec<_> = prompts.iter().map(|prompt| prompt.name.as_str()).collect();
+        assert_eq!(names, ["greet", "farewell"]);
+        assert!(matches!(
+            client.list_resources().await,
+            Err(crate::protocol::ProtocolError::CapabilityNotSupported { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_typed_client_methods() {
+        let store = crate::memory::InMemoryResourceProvider::new();
+        let notes = store.insert("notes", "Buy milk").await;
+        let completions = crate::completion::CompletionRegistry::new().with_prompt_argument(
+            "greet",
+            "name",
+            |value: String, _| async move { Ok(vec![format!("{value}ice")]) },
+        );
+        let server = Server::new(implementation("test-server"))
+            .with_resources(store)
+            .with_prompts(Greetings)
+            .with_completions(completions);
+        server.add_tool(
+            Tool::builder().name("echo").build(),
+            |arguments: Option<Map<String, Value>>, _| async move {
+                Ok(CallToolResult::text(
+                    Value::Object(arguments.unwrap_or_default()).to_string(),
+                ))
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let client = Client::connect(
+            client,
+            implementation("test-client"),
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "echo");
+        let result = client
+            .call_tool("echo", json!({ "text": "hi" }))
+            .await
+            .unwrap();
+        assert_eq!(result, CallToolResult::text(r#"{"text":"hi"}"#));
+        let result = client.call_tool("echo", ()).await.unwrap();
+        assert_eq!(result, CallToolResult::text("{}"));
+        assert!(matches!(
+            client.call_tool("echo", "hi").await,
+            Err(ProtocolError::InvalidParams(_))
+        ));
+
+        let resources = client.list_resources().await.unwrap();
+        assert_eq!(resources[0].uri, notes);
+        let read = client.read_resource(&notes).await.unwrap();
+        assert_eq!(read.contents.len(), 1);
+
+        let prompt = client
+            .get_prompt("greet", [("name", "Alice")])
+            .await
+            .unwrap();
+        assert_eq!(
+            prompt.messages[0].content,
+            PromptMessageContent::text("Say hello to Alice")
+        );
+
+        let completion = client
+            .complete(
+                crate::completion::CompletionReference::Prompt(
+                    crate::completion::PromptReference {
+                        name: "greet".to_string(),
+                    },
+                ),
+                "name",
+                "Al",
+            )
+            .await
+            .unwrap();
+        assert_eq!(completion.values, ["Alice"]);
+
+        client
+            .set_log_level(crate::logging::LoggingLevel::Warning)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prompts_are_listed_and_rendered_by_the_provider() {
+        let server = Server::builder()
+            .info(implementation("test-server"))
+            .prompts(Arc::new(Greetings))
+            .build();
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        let session = initialize(&client).await;
+        assert_eq!(
+            session.peer_capabilities.prompts,
+            Some(PromptsCapability::default())
+        );
+
+        let call = async |id, method: &str, params| {
+            client.send(request(id, method, params)).await.unwrap();
+            client.receive().await.unwrap().unwrap()
+        };
+        let JsonRpcMessage::Response(response) = call(70, "prompts/list", None).await else {
+            panic!("Expected a response");
+        };
+        let page: ListPromptsResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(page.prompts[0].name, "greet");
+        let cursor = page.next_cursor.unwrap();
+        let JsonRpcMessage::Response(response) =
+            call(71, "prompts/list", Some(json!({ "cursor": cursor }))).await
+        else {
+            panic!("Expected a response");
+        };
+        let page: ListPromptsResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(page.prompts[0].name, "farewell");
+        assert!(page.next_cursor.is_none());
+
+        let JsonRpcMessage::Response(response) = call(
+            72,
+            "prompts/get",
+            Some(json!({ "name": "greet", "arguments": { "name": "Ada" } })),
+        )
+        .await
+        else {
+            panic!("Expected a response");
+        };
+        let result: GetPromptResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(
+            result.messages[0].content,
+            PromptMessageContent::Text(TextContent {
+                text: "Say hello to Ada".to_string(),
+                meta: None,
+            })
+        );
+
+        for (id, params) in [
+            (73, json!({ "name": "greet" })),
+            (74, json!({ "name": "shout" })),
+        ] {
+            let JsonRpcMessage::Error(response) = call(id, "prompts/get", Some(params)).await
+            else {
+                panic!("Expected an error");
+            };
+            assert_eq!(response.error.code, INVALID_PARAMS);
+        }
+    }
+
+    #[derive(Default)]
+    struct WatchedNotes {
+        subscriptions: std::sync::OnceLock<SubscriptionHandle>,
+    }
+
+    #[async_trait::async_trait]
+    impl ResourceProvider for WatchedNotes {
+        async fn list(&self, cursor: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+            Notes.list(cursor).await
+        }
+
+        async fn read(&self, uri: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+            Notes.read(uri).await
+        }
+
+        fn supports_subscriptions(&self) -> bool {
+            true
+        }
+
+        fn attach(&self, subscriptions: SubscriptionHandle) {
+            let _ = self.subscriptions.set(subscriptions);
+        }
+
+        async fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+            Notes.read(uri).await.map(|_| ())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_updates_reach_subscribed_sessions() {
+        let notes = Arc::new(WatchedNotes::default());
+        let server = Server::builder()
+            .info(implementation("test-server"))
+            .resources(notes.clone())
+            .build();
+        assert_eq!(
+            server.capabilities().resources.unwrap().subscribe,
+            Some(true)
+        );
+        let subscriptions = notes.subscriptions.get().unwrap();
+
+        let mut clients = Vec::new();
+        for _ in 0..2 {
+            let (client, server_side) = MemoryTransport::pair();
+            tokio::spawn(server.clone().serve(server_side));
+            initialize(&client).await;
+            clients.push(client);
+        }
+        let call = async |client: &MemoryTransport, id, method: &str, uri: &str| {
+            let params = Some(json!({ "uri": uri }));
+            client.send(request(id, method, params)).await.unwrap();
+            client.receive().await.unwrap().unwrap()
+        };
+        let JsonRpcMessage::Error(response) =
+            call(&clients[0], 60, "resources/subscribe", "str:///missing").await
+        else {
+            panic!("Expected an error");
+        };
+        assert_eq!(response.error.code, RESOURCE_NOT_FOUND);
+        let JsonRpcMessage::Response(_) =
+            call(&clients[0], 61, "resources/subscribe", "str:///todo").await
+        else {
+            panic!("Expected a response");
+        };
+        assert!(subscriptions.is_subscribed("str:///todo"));
+
+        subscriptions.notify_updated("str:///todo").await;
+        let Some(JsonRpcMessage::Notification(notification)) = clients[0].receive().await.unwrap()
+        else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "notifications/resources/updated");
+        assert_eq!(notification.params, Some(json!({ "uri": "str:///todo" })));
+        // The other session is not subscribed, so the next message it sees is its own pong.
+        clients[1].send(request(62, "ping", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(pong)) = clients[1].receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(pong.id, RequestId::Number(62));
+
+        let JsonRpcMessage::Response(_) =
+            call(&clients[0], 63, "resources/unsubscribe", "str:///todo").await
+        else {
+            panic!("Expected a response");
+        };
+        assert!(!subscriptions.is_subscribed("str:///todo"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_methods_and_bad_params_are_errors() {
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server().serve(server_side));
+        initialize(&client).await;
+
+        for (id, method, params, code) in [
+            (10, "resources/list", None, METHOD_NOT_FOUND),
+            (
+                11,
+                "tools/list",
+                Some(json!({ "cursor": 1 })),
+                INVALID_PARAMS,
+            ),
+            (12, "initialize", None, INVALID_REQUEST),
+        ] {
+            client.send(request(id, method, params)).await.unwrap();
+            let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+                panic!("Expected an error response to {}", method);
+            };
+            assert_eq!(error.id, Some(RequestId::Number(id)));
+            assert_eq!(error.error.code, code, "{}", method);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_handler_sees_its_token() {
+        let (cancelled, mut seen) = tokio::sync::mpsc::unbounded_channel();
+        let server = Server::new(implementation("test-server")).on_request(
+            "x-test/wait",
+            move |_: Value, context: RequestContext| {
+                let cancelled = cancelled.clone();
+                async move {
+                    tokio::select! {
+                        _ = context.cancellation().cancelled() => {
+                            let _ = cancelled.send(context.id().clone());
+                            Err(McpError::internal_error("cancelled"))
+                        }
+                        _ = tokio::time::sleep(Duration::from_secs(10)) => Ok(json!({})),
+                    }
+                }
+            },
+        );
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        client.send(request(20, "x-test/wait", None)).await.unwrap();
+        let cancel = CancelledNotificationParams::new(RequestId::Number(20), None)
+            .into_notification()
+            .unwrap();
+        client
+            .send(JsonRpcMessage::Notification(cancel))
+            .await
+            .unwrap();
+        assert_eq!(seen.recv().await, Some(RequestId::Number(20)));
+        // The client is not sent a response to the request it cancelled.
+        let response = tokio::time::timeout(Duration::from_millis(100), client.receive()).await;
+        assert!(response.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_handlers_ignoring_cancellation_are_dropped_after_the_grace_period() {
+        struct Dropped(Option<tokio::sync::oneshot::Sender<()>>);
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                let _ = self.0.take().map(|dropped| dropped.send(()));
+            }
+        }
+
+        let (started, mut running) = tokio::sync::mpsc::unbounded_channel();
+        let server = Server::new(implementation("test-server"))
+            .on_request("x-test/stubborn", move |_: Value, _| {
+                let started = started.clone();
+                async move {
+                    let (dropped, on_drop) = tokio::sync::oneshot::channel();
+                    let _guard = Dropped(Some(dropped));
+                    let _ = started.send(on_drop);
+                    tokio::time::sleep(Duration::from_secs(3600)).await;
+                    Ok(json!({}))
+                }
+            })
+            .on_request("x-test/now", |_: Value, _| async move { Ok(json!({})) })
+            .with_concurrency_limits(ConcurrencyLimits::builder().per_session(1).build());
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        client
+            .send(request(30, "x-test/stubborn", None))
+            .await
+            .unwrap();
+        let on_drop = running.recv().await.unwrap();
+        let cancelled_at = tokio::time::Instant::now();
+        let cancel = CancelledNotificationParams::new(RequestId::Number(30), None)
+            .into_notification()
+            .unwrap();
+        client
+            .send(JsonRpcMessage::Notification(cancel))
+            .await
+            .unwrap();
+        on_drop.await.unwrap();
+        assert_eq!(cancelled_at.elapsed(), CANCELLATION_GRACE_PERIOD);
+
+        // The permit of the dropped handler is free again, and no response was sent for it.
+        client.send(request(31, "x-test/now", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.id, RequestId::Number(31));
+    }
+
+    #[tokio::test]
+    async fn test_requests_over_the_session_limit_are_rejected() {
+        let server = Server::new(implementation("test-server"))
+            .on_request(
+                "x-test/wait",
+                |_: Value, context: RequestContext| async move {
+                    context.cancellation().cancelled().await;
+                    Err::<Value, _>(McpError::internal_error("cancelled"))
+                },
+            )
+            .on_request("x-test/now", |_: Value, _| async move { Ok(json!({})) })
+            .with_concurrency_limits(ConcurrencyLimits::builder().per_session(1).build());
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        client.send(request(21, "x-test/wait", None)).await.unwrap();
+        client.send(request(22, "x-test/wait", None)).await.unwrap();
+        let Some(JsonRpcMessage::Error(busy)) = client.receive().await.unwrap() else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(busy.id, Some(RequestId::Number(22)));
+        assert_eq!(busy.error.code, SERVER_BUSY);
+
+        let cancel = CancelledNotificationParams::new(RequestId::Number(21), None)
+            .into_notification()
+            .unwrap();
+        client
+            .send(JsonRpcMessage::Notification(cancel))
+            .await
+            .unwrap();
+        // Cancelling the running request makes room for the next.
+        client.send(request(23, "x-test/now", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.id, RequestId::Number(23));
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handlers_are_answered_with_an_internal_error() {
+        let server = Server::new(implementation("test-server"))
+            .on_request("x-test/panic", |_: Value, _| async move {
+                if true {
+                    panic!("boom");
+                }
+                Ok(json!({}))
+            })
+            .on_request("x-test/now", |_: Value, _| async move { Ok(json!({})) });
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        client
+            .send(request(24, "x-test/panic", None))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(error.id, Some(RequestId::Number(24)));
+        assert_eq!(error.error.code, INTERNAL_ERROR);
+        assert!(error.error.data.unwrap()["incidentId"].is_string());
+
+        // The session keeps serving.
+        client.send(request(25, "x-test/now", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.id, RequestId::Number(25));
+    }
+
+    #[tokio::test]
+    async fn test_authorizer_denies_requests() {
+        let server = Server::new(implementation("test-server"))
+            .on_request("x-test/whoami", |_: Value, context: RequestContext| async move {
+                Ok(json!(context.auth().subject))
+            })
+            .on_request("x-test/drop", |_: Value, _| async move { Ok(json!({})) })
+            .with_authorizer(|request: AuthorizationRequest| async move {
+                match request.method.as_str() {
+                    "x-test/drop" if !request.auth.has_scope("admin") => {
+                        Err(Forbidden::new("Dropping requires the admin scope"))
+                    }
+                    _ => Ok(()),
+                }
+            });
+        let (client, server_side) = MemoryTransport::pair();
+        let auth = AuthContext::builder().subject("ada").build();
+        tokio::spawn(server.serve_with_auth(server_side, auth));
+        initialize(&client).await;
+
+        client
+            .send(request(33, "x-test/whoami", None))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.result, Some(json!("ada")));
+
+        client.send(request(34, "x-test/drop", None)).await.unwrap();
+        let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(error.error.code, FORBIDDEN);
+        assert_eq!(error.error.message, "Dropping requires the admin scope");
+    }
+
+    #[tokio::test]
+    async fn test_sessions_see_what_passes_their_filter() {
+        let tools = ToolRegistry::new();
+        for name in ["search", "admin_drop"] {
+            tools.register(
+                Tool::builder().name(name).build(),
+                |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("ok")) },
+            );
+        }
+        let server = Server::new(implementation("test-server"))
+            .with_tools(tools)
+            .with_session_filter(|auth: &AuthContext| match auth.has_scope("admin") {
+                true => SurfaceFilter::new(),
+                false => SurfaceFilter::new().deny_tools(["admin_*"]),
+            });
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        client.send(request(35, "tools/list", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        let listed: ListToolsResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        let names: Vec<_> = listed.tools.iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, ["search"]);
+
+        client
+            .send(request(
+                36,
+                "tools/call",
+                Some(json!({ "name": "admin_drop" })),
+            ))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(error.error.code, INVALID_PARAMS);
+        assert_eq!(error.error.message, "Unknown tool: admin_drop");
+    }
+
+    /// Lists one resource per tenant, named after the tenant of the current session.
+    struct TenantNotes;
+
+    #[async_trait::async_trait]
+    impl ResourceProvider for TenantNotes {
+        async fn list(&self, _: Option<Cursor>) -> Result<ListResourcesResult, ResourceError> {
+            let tenant = AuthContext::current()
+                .and_then(|auth| auth.tenant.clone())
+                .ok_or(ResourceError::NotFound)?;
+            Ok(ListResourcesResult {
+                resources: vec![
+                    Resource::builder()
+                        .uri(format!("str:///{}/todo", tenant).parse().unwrap())
+                        .name("todo")
+                        .build(),
+                ],
+                ..Default::default()
+            })
+        }
+
+        async fn read(&self, _: &str) -> Result<Vec<ResourceContent>, ResourceError> {
+            Err(ResourceError::NotFound)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_providers_see_the_tenant_of_the_session() {
+        let server = Server::new(implementation("test-server")).with_resources(TenantNotes);
+        let mut listed = Vec::new();
+        for tenant in ["acme", "globex"] {
+            let (client, server_side) = MemoryTransport::pair();
+            let auth = AuthContext::builder().subject("ada").tenant(tenant).build();
+            tokio::spawn(server.clone().serve_with_auth(server_side, auth));
+            initialize(&client).await;
+            client
+                .send(request(37, "resources/list", None))
+                .await
+                .unwrap();
+            let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+                panic!("Expected a response");
+            };
+            let result: ListResourcesResult =
+                serde_json::from_value(response.result.unwrap()).unwrap();
+            listed.push(result.resources[0].uri.to_string());
+        }
+        assert_eq!(listed, ["str:///acme/todo", "str:///globex/todo"]);
+    }
+
+    #[tokio::test]
+    async fn test_mounted_servers_are_offered_under_their_prefix() {
+        let files = Server::new(implementation("files")).with_prompts(Greetings);
+        files.add_tool(
+            Tool::builder().name("read_file").build(),
+            |arguments: Option<Map<String, Value>>, _| async move {
+                Ok(CallToolResult::text(format!(
+                    "read {}",
+                    arguments.unwrap_or_default()["path"]
+                )))
+            },
+        );
+        let tools = ToolRegistry::new();
+        tools.register(
+            Tool::builder().name("search").build(),
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("found")) },
+        );
+        let server = Server::new(implementation("test-server"))
+            .with_tools(tools)
+            .mount("fs", files);
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        let call = async |id, method: &str, params| {
+            client.send(request(id, method, params)).await.unwrap();
+            match client.receive().await.unwrap() {
+                Some(JsonRpcMessage::Response(response)) => response.result.unwrap(),
+                message => panic!("Expected a response, got {:?}", message),
+            }
+        };
+        let listed: ListToolsResult =
+            serde_json::from_value(call(38, "tools/list", None).await).unwrap();
+        let names: Vec<_> = listed.tools.iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, ["search", "fs__read_file"]);
+        let arguments = json!({ "name": "fs__read_file", "arguments": { "path": "notes.md" } });
+        let result: CallToolResult =
+            serde_json::from_value(call(39, "tools/call", Some(arguments)).await).unwrap();
+        assert_eq!(result, CallToolResult::text("read \"notes.md\""));
+
+        let mut prompts = Vec::new();
+        let mut cursor = None;
+        loop {
+            let params = json!({ "cursor": cursor });
+            let page: ListPromptsResult =
+                serde_json::from_value(call(40, "prompts/list", Some(params)).await).unwrap();
+            prompts.extend(page.prompts.into_iter().map(|prompt| prompt.name));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(prompts, ["fs__greet", "fs__farewell"]);
+        let params = json!({ "name": "fs__greet", "arguments": { "name": "Ada" } });
+        let result: GetPromptResult =
+            serde_json::from_value(call(41, "prompts/get", Some(params)).await).unwrap();
+        assert_eq!(result.description.as_deref(), Some("A greeting"));
+    }
+
+    #[tokio::test]
+    async fn test_tools_of_mounted_servers_change_while_serving() {
+        let files = Server::new(implementation("files"));
+        let server = Server::new(implementation("test-server"))
+            .with_tools(ToolRegistry::new())
+            .mount("fs", files.clone());
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        initialize(&client).await;
+
+        files.add_tool(
+            Tool::builder().name("write_file").build(),
+            |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::text("written")) },
+        );
+        let Some(JsonRpcMessage::Notification(notification)) = client.receive().await.unwrap()
+        else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "notifications/tools/list_changed");
+
+        client.send(request(42, "tools/list", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        let listed: ListToolsResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(listed.tools[0].name, "fs__write_file");
+        let arguments = json!({ "name": "fs__write_file" });
+        client
+            .send(request(43, "tools/call", Some(arguments)))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        let result: CallToolResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(result, CallToolResult::text("written"));
+
+        assert!(files.remove_tool("write_file"));
+        let Some(JsonRpcMessage::Notification(_)) = client.receive().await.unwrap() else {
+            panic!("Expected a notification");
+        };
+        client.send(request(44, "tools/list", None)).await.unwrap();
+        let Some(JsonRpcMessage::Response(response)) = client.receive().await.unwrap() else {
+            panic!("Expected a response");
+        };
+        let listed: ListToolsResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(listed.tools.is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+/// Request handling as a `tower` service.
+///
+/// The handlers of a [`Server`] form a [`tower::Service`] taking a [`TypedRequest`], so the
+/// layers of the `tower` ecosystem, for timeouts, concurrency limits, authorization or
+/// tracing, can wrap them:
+///
+/// ```text
+/// let server = Server::new(info)
+///     .with_tools(tools)
+///     .with_layer(TimeoutLayer::new(Duration::from_secs(30)))
+///     .with_layer(ConcurrencyLimitLayer::new(8));
+/// ```
+///
+/// Layers wrap the handlers of every request that has one; `ping`, `initialize` and extension
+/// methods are answered as before. Each layer wraps the ones added before it. Errors from
+/// layers are reported to the client: an [`McpError`] as it is, a `tower` timeout as
+/// `REQUEST_TIMEOUT`, anything else as an internal error.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tower::util::BoxCloneSyncService;
+use tower::{BoxError, Service, ServiceExt};
+
+use crate::error::McpError;
+use crate::protocol::{ClientRequest, ProtocolError};
+use crate::server::{Handler, RequestContext};
+
+/// A request on its way to its handler.
+#[derive(Clone)]
+pub struct TypedRequest {
+    pub method: String,
+    pub params: Option<Value>,
+    /// The context the handler is given
+    pub context: RequestContext,
+}
+
+impl std::fmt::Debug for TypedRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedRequest")
+            .field("method", &self.method)
+            .field("params", &self.params)
+            .field("id", self.context.id())
+            .finish()
+    }
+}
+
+impl TypedRequest {
+    /// Parses the request as one of the requests the protocol defines.
+    pub fn client_request(&self) -> Result<ClientRequest, ProtocolError> {
+        ClientRequest::from_parts(&self.method, self.params.clone())
+    }
+}
+
+/// The handlers of a server, wrapped in its layers.
+pub type HandlerStack = BoxCloneSyncService<TypedRequest, Value, BoxError>;
+
+/// Wraps a [`HandlerStack`] in one more layer.
+pub(crate) type Middleware = Arc<dyn Fn(HandlerStack) -> HandlerStack + Send + Sync>;
+
+/// Calls the handler registered for the request's method.
+#[derive(Clone)]
+pub(crate) struct HandlerService {
+    handlers: Arc<HashMap<String, Handler>>,
+}
+
+impl HandlerService {
+    pub(crate) fn new(handlers: HashMap<String, Handler>) -> Self {
+        HandlerService {
+            handlers: Arc::new(handlers),
+        }
+    }
+}
+
+impl Service<TypedRequest> for HandlerService {
+    type Response = Value;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Value, BoxError>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: TypedRequest) -> Self::Future {
+        let Some(handler) = self.handlers.get(&request.method).cloned() else {
+            let error = McpError::method_not_found(&request.method);
+            return async move { Err(error.into()) }.boxed();
+        };
+        async move {
+            handler(request.params, request.context)
+                .await
+                .map_err(BoxError::from)
+        }
+        .boxed()
+    }
+}
+
+/// A handler that runs the request for `method` through `stack`.
+pub(crate) fn through(stack: HandlerStack, method: String) -> Handler {
+    Arc::new(move |params, context| {
+        let request = TypedRequest {
+            method: method.clone(),
+            params,
+            context,
+        };
+        stack
+            .clone()
+            .oneshot(request)
+            .map(|result| result.map_err(into_mcp_error))
+            .boxed()
+    })
+}
+
+fn into_mcp_error(error: BoxError) -> McpError {
+    match error.downcast::<McpError>() {
+        Ok(error) => *error,
+        Err(error) if error.is::<tower::timeout::error::Elapsed>() => {
+            McpError::request_timeout("Request timed out")
+        }
+        Err(error) => McpError::internal_error(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::{InitializeRequest, client_handshake};
+    use crate::protocol::{
+        ClientCapabilities, Implementation, InitializeRequestParams, JSONRPC_VERSION,
+        JsonRpcMessage, JsonRpcRequest, REQUEST_TIMEOUT, RequestId,
+    };
+    use crate::server::Server;
+    use crate::transport::{MemoryTransport, Transport};
+    use serde_json::json;
+    use std::time::Duration;
+    use tower::timeout::TimeoutLayer;
+
+    #[tokio::test]
+    async fn test_layers_wrap_the_handlers() {
+        let server = Server::new(Implementation::new("test-server", "1.0.0"))
+            .on_request("echo", |params: Value, _| async move { Ok(params) })
+            .on_request("sleep", |_: Option<Value>, _| async move {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(json!({}))
+            })
+            .with_layer(TimeoutLayer::new(Duration::from_millis(50)));
+        let (client, server_side) = MemoryTransport::pair();
+        tokio::spawn(server.serve(server_side));
+        client_handshake(
+            &client,
+            InitializeRequest {
+                id: RequestId::Number(0),
+                params: InitializeRequestParams {
+                    protocol_version: "2025-06-18".to_string(),
+                    capabilities: ClientCapabilities::default(),
+                    client_info: Implementation::new("test-client", "1.0.0"),
+                    meta: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        let call = async |id, method: &str, params| {
+            client
+                .send(JsonRpcMessage::Request(JsonRpcRequest {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: RequestId::Number(id),
+                    method: method.to_string(),
+                    params,
+                }))
+                .await
+                .unwrap();
+            client.receive().await.unwrap().unwrap()
+        };
+        let JsonRpcMessage::Response(response) =
+            call(1, "echo", Some(json!({ "text": "hello" }))).await
+        else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.result, Some(json!({ "text": "hello" })));
+        let JsonRpcMessage::Error(response) = call(2, "sleep", None).await else {
+            panic!("Expected an error");
+        };
+        assert_eq!(response.error.code, REQUEST_TIMEOUT);
+    }
+}
@@ -0,0 +1,421 @@
+/// Message signatures carried in `_meta`.
+///
+/// Deployments where MCP traffic crosses trust boundaries over plain transports can sign every
+/// message. A [`Signer`] computes a signature over the canonical form of the message (see
+/// [`crate::canonical`]) and stores it under [`SIGNATURE_META_KEY`] in the `_meta` of the
+/// request or notification parameters, or of the response result:
+///
+/// ```text
+/// {"jsonrpc":"2.0","id":1,"method":"tools/list",
+///  "params":{"_meta":{"mcp-ox/signature":{"alg":"HS256","sig":"..."}}}}
+/// ```
+///
+/// The signed form is the message without the signature member, and without `_meta` if that
+/// member was its only content. A [`Verifier`] recomputes it and checks the signature.
+/// HMAC-SHA256 with a shared secret and Ed25519 are supported.
+///
+/// Error responses have no `_meta`, so their signature is stored under the same key in the
+/// `data` of the error, which must be an object or absent for the error to be signed:
+///
+/// ```text
+/// {"jsonrpc":"2.0","id":1,
+///  "error":{"code":-32602,"message":"...","data":{"mcp-ox/signature":{"alg":"HS256","sig":"..."}}}}
+/// ```
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use ed25519_dalek::{Signer as _, Verifier as _};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+
+use crate::canonical::to_canonical_vec;
+use crate::protocol::{
+    ErrorData, INVALID_REQUEST, JSONRPC_VERSION, JsonRpcError, JsonRpcMessage, ProtocolError,
+};
+use crate::transport::Transport;
+
+/// The `_meta` member holding the signature.
+pub const SIGNATURE_META_KEY: &str = "mcp-ox/signature";
+
+/// The signature stored in `_meta`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Signature {
+    /// The algorithm, `HS256` or `Ed25519`
+    pub alg: String,
+    /// Identifies the key used, for receivers that accept several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    /// The base64-encoded signature
+    pub sig: String,
+}
+
+const HS256: &str = "HS256";
+const ED25519: &str = "Ed25519";
+
+#[derive(Clone)]
+enum SigningKey {
+    Hmac(Arc<[u8]>),
+    Ed25519(Box<ed25519_dalek::SigningKey>),
+}
+
+/// Signs outgoing messages.
+#[derive(Clone)]
+pub struct Signer {
+    key: SigningKey,
+    key_id: Option<String>,
+}
+
+impl Signer {
+    /// Signs with HMAC-SHA256 using a secret shared with the peer.
+    pub fn hmac(secret: impl AsRef<[u8]>) -> Self {
+        Signer {
+            key: SigningKey::Hmac(secret.as_ref().into()),
+            key_id: None,
+        }
+    }
+
+    /// Signs with an Ed25519 private key.
+    pub fn ed25519(key: ed25519_dalek::SigningKey) -> Self {
+        Signer {
+            key: SigningKey::Ed25519(Box::new(key)),
+            key_id: None,
+        }
+    }
+
+    /// Names the key in every signature.
+    pub fn with_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    /// Adds the signature to the `_meta` of `message`, or to the `data` of an error.
+    pub fn sign(&self, message: &mut JsonRpcMessage) -> Result<(), ProtocolError> {
+        meta_mut(message)?.remove(SIGNATURE_META_KEY);
+        let payload = signed_form(message)?;
+        let (alg, sig) = match &self.key {
+            SigningKey::Hmac(secret) => (
+                HS256,
+                hmac(secret)?
+                    .chain_update(&payload)
+                    .finalize()
+                    .into_bytes()
+                    .to_vec(),
+            ),
+            SigningKey::Ed25519(key) => (ED25519, key.sign(&payload).to_bytes().to_vec()),
+        };
+        let signature = Signature {
+            alg: alg.to_string(),
+            key_id: self.key_id.clone(),
+            sig: BASE64_STANDARD.encode(sig),
+        };
+        let signature = serde_json::to_value(signature)
+            .map_err(|e| ProtocolError::InternalError(e.to_string()))?;
+        meta_mut(message)?.insert(SIGNATURE_META_KEY.to_string(), signature);
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+enum VerifyingKey {
+    Hmac(Arc<[u8]>),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+/// Verifies the signatures of incoming messages.
+#[derive(Clone)]
+pub struct Verifier {
+    key: VerifyingKey,
+}
+
+impl Verifier {
+    /// Verifies HMAC-SHA256 signatures made with the shared `secret`.
+    pub fn hmac(secret: impl AsRef<[u8]>) -> Self {
+        Verifier {
+            key: VerifyingKey::Hmac(secret.as_ref().into()),
+        }
+    }
+
+    /// Verifies Ed25519 signatures made with the private half of `key`.
+    pub fn ed25519(key: ed25519_dalek::VerifyingKey) -> Self {
+        Verifier {
+            key: VerifyingKey::Ed25519(key),
+        }
+    }
+
+    /// Checks the signature in the `_meta` of `message`, or in the `data` of an error.
+    pub fn verify(&self, message: &JsonRpcMessage) -> Result<(), ProtocolError> {
+        let mut message = message.clone();
+        let signature = meta_mut(&mut message)?
+            .remove(SIGNATURE_META_KEY)
+            .ok_or_else(|| invalid("Message is not signed"))?;
+        let signature: Signature =
+            serde_json::from_value(signature).map_err(|_| invalid("Malformed signature"))?;
+        let sig = BASE64_STANDARD
+            .decode(&signature.sig)
+            .map_err(|_| invalid("Malformed signature"))?;
+        let payload = signed_form(&mut message)?;
+
+        let valid = match (&self.key, signature.alg.as_str()) {
+            (VerifyingKey::Hmac(secret), HS256) => hmac(secret)?
+                .chain_update(&payload)
+                .verify_slice(&sig)
+                .is_ok(),
+            (VerifyingKey::Ed25519(key), ED25519) => ed25519_dalek::Signature::from_slice(&sig)
+                .is_ok_and(|sig| key.verify(&payload, &sig).is_ok()),
+            (_, alg) => return Err(invalid(&format!("Unexpected signature algorithm: {}", alg))),
+        };
+        if !valid {
+            return Err(invalid("Signature does not match"));
+        }
+        Ok(())
+    }
+}
+
+fn hmac(secret: &[u8]) -> Result<Hmac<Sha256>, ProtocolError> {
+    Hmac::<Sha256>::new_from_slice(secret).map_err(|e| ProtocolError::InternalError(e.to_string()))
+}
+
+fn invalid(message: &str) -> ProtocolError {
+    ProtocolError::ProtocolError(format!("Invalid signature: {}", message))
+}
+
+/// Returns the object the signature of `message` is stored in, creating it if needed: the
+/// `_meta` of its params or result, or the `data` of an error.
+fn meta_mut(message: &mut JsonRpcMessage) -> Result<&mut Map<String, Value>, ProtocolError> {
+    let container = match message {
+        JsonRpcMessage::Request(request) => &mut request.params,
+        JsonRpcMessage::Notification(notification) => &mut notification.params,
+        JsonRpcMessage::Response(response) => &mut response.result,
+        JsonRpcMessage::Error(error) => {
+            return match error
+                .error
+                .data
+                .get_or_insert_with(|| Value::Object(Map::new()))
+            {
+                Value::Object(data) => Ok(data),
+                _ => Err(ProtocolError::InvalidParams(
+                    "Only errors with object data can be signed".to_string(),
+                )),
+            };
+        }
+    };
+    let Value::Object(object) = container.get_or_insert_with(|| Value::Object(Map::new())) else {
+        return Err(ProtocolError::InvalidParams(
+            "Only messages with object params or results can be signed".to_string(),
+        ));
+    };
+    match object
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(Map::new()))
+    {
+        Value::Object(meta) => Ok(meta),
+        _ => Err(ProtocolError::InvalidParams(
+            "_meta must be an object".to_string(),
+        )),
+    }
+}
+
+/// The canonical bytes that are signed: the message without its signature, and without empty
+/// `_meta`, params or error data objects that only existed to hold it.
+fn signed_form(message: &mut JsonRpcMessage) -> Result<Vec<u8>, ProtocolError> {
+    let mut value =
+        serde_json::to_value(&*message).map_err(|e| ProtocolError::InternalError(e.to_string()))?;
+    if let Some(Value::Object(error)) = value.get_mut("error")
+        && error
+            .get("data")
+            .is_some_and(|data| data == &Value::Object(Map::new()))
+    {
+        error.remove("data");
+    }
+    for container in ["params", "result"] {
+        let Some(Value::Object(object)) = value.get_mut(container) else {
+            continue;
+        };
+        if object
+            .get("_meta")
+            .is_some_and(|meta| meta == &Value::Object(Map::new()))
+        {
+            object.remove("_meta");
+        }
+        if object.is_empty() && container == "params" {
+            value
+                .as_object_mut()
+                .map(|message| message.remove("params"));
+        }
+    }
+    to_canonical_vec(&value)
+}
+
+/// A transport that signs every message it sends and verifies every message it receives.
+///
+/// Received requests with a missing or invalid signature are answered with an
+/// `INVALID_REQUEST` error and skipped; other unverified messages are dropped.
+pub struct SignedTransport<T> {
+    inner: T,
+    signer: Signer,
+    verifier: Verifier,
+}
+
+impl<T: Transport> SignedTransport<T> {
+    pub fn new(inner: T, signer: Signer, verifier: Verifier) -> Self {
+        SignedTransport {
+            inner,
+            signer,
+            verifier,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for SignedTransport<T> {
+    async fn send(&self, mut message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        self.signer.sign(&mut message)?;
+        self.inner.send(message).await
+    }
+
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+        loop {
+            let Some(message) = self.inner.receive().await? else {
+                return Ok(None);
+            };
+            let error = match self.verifier.verify(&message) {
+                Ok(()) => return Ok(Some(message)),
+                Err(error) => error,
+            };
+            if let JsonRpcMessage::Request(request) = message {
+                self.send(JsonRpcMessage::Error(JsonRpcError {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    id: Some(request.id),
+                    error: ErrorData {
+                        code: INVALID_REQUEST,
+                        message: error.to_string(),
+                        data: None,
+                    },
+                }))
+                .await?;
+            }
+        }
+    }
+
+    async fn close(&self) -> Result<(), ProtocolError> {
+        self.inner.close().await
+    }
+
+    fn session_id(&self) -> Option<String> {
+        self.inner.session_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{JsonRpcRequest, RequestId};
+    use crate::transport::MemoryTransport;
+    use serde_json::json;
+
+    fn request(params: Option<Value>) -> JsonRpcMessage {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(1),
+            method: "tools/call".to_string(),
+            params,
+        })
+    }
+
+    #[test]
+    fn test_hmac_signatures() {
+        let signer = Signer::hmac("secret").with_key_id("k1");
+        let verifier = Verifier::hmac("secret");
+
+        let mut message = request(Some(json!({ "name": "search", "_meta": { "trace": 1 } })));
+        signer.sign(&mut message).unwrap();
+        verifier.verify(&message).unwrap();
+
+        let JsonRpcMessage::Request(signed) = &message else {
+            unreachable!()
+        };
+        let meta = &signed.params.as_ref().unwrap()["_meta"];
+        assert_eq!(meta["trace"], json!(1));
+        assert_eq!(meta[SIGNATURE_META_KEY]["keyId"], json!("k1"));
+
+        // Tampering with the message breaks the signature.
+        let mut tampered = message.clone();
+        if let JsonRpcMessage::Request(request) = &mut tampered {
+            request.params.as_mut().unwrap()["name"] = json!("delete");
+        }
+        assert!(verifier.verify(&tampered).is_err());
+        assert!(Verifier::hmac("other").verify(&message).is_err());
+        assert!(verifier.verify(&request(None)).is_err());
+    }
+
+    #[test]
+    fn test_error_responses_are_signed() {
+        let signer = Signer::hmac("secret");
+        let verifier = Verifier::hmac("secret");
+        let error = |data: Option<Value>| {
+            JsonRpcMessage::Error(JsonRpcError {
+                jsonrpc: JSONRPC_VERSION.to_string(),
+                id: Some(RequestId::Number(1)),
+                error: ErrorData {
+                    code: INVALID_REQUEST,
+                    message: "Unknown tool".to_string(),
+                    data,
+                },
+            })
+        };
+
+        let mut message = error(None);
+        signer.sign(&mut message).unwrap();
+        verifier.verify(&message).unwrap();
+        let mut message = error(Some(json!({ "tool": "search" })));
+        signer.sign(&mut message).unwrap();
+        verifier.verify(&message).unwrap();
+
+        // A forged or altered error is rejected like any other message.
+        assert!(verifier.verify(&error(None)).is_err());
+        if let JsonRpcMessage::Error(error) = &mut message {
+            error.error.message = "Try again".to_string();
+        }
+        assert!(verifier.verify(&message).is_err());
+        assert!(signer.sign(&mut error(Some(json!("text")))).is_err());
+    }
+
+    #[test]
+    fn test_ed25519_signatures() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let verifier = Verifier::ed25519(key.verifying_key());
+
+        let mut message = request(None);
+        Signer::ed25519(key).sign(&mut message).unwrap();
+        verifier.verify(&message).unwrap();
+        assert!(Verifier::hmac("secret").verify(&message).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signed_transport_rejects_unsigned_requests() {
+        let (client, server) = MemoryTransport::pair();
+        let client = SignedTransport::new(client, Signer::hmac("secret"), Verifier::hmac("secret"));
+        let server = SignedTransport::new(server, Signer::hmac("secret"), Verifier::hmac("secret"));
+
+        client.inner.send(request(None)).await.unwrap();
+        client.send(request(None)).await.unwrap();
+        assert!(matches!(
+            server.receive().await.unwrap(),
+            Some(JsonRpcMessage::Request(_))
+        ));
+
+        let Some(JsonRpcMessage::Error(error)) = client.receive().await.unwrap() else {
+            panic!("Expected an error response");
+        };
+        assert_eq!(error.error.code, INVALID_REQUEST);
+    }
+}
@@ -0,0 +1,125 @@
+/// State that handlers keep for the length of a session.
+///
+/// Every session has a [`SessionState`], reached through
+/// [`RequestContext::state`](crate::server::RequestContext::state). It holds one value per
+/// type, so independent parts of a server can keep their own state without coordinating
+/// on keys:
+///
+/// ```text
+/// struct Authenticated(UserId);
+///
+/// context.state().insert(Authenticated(user));
+/// let user = context.state().get::<Authenticated>();
+/// ```
+///
+/// The state is dropped when the session ends.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Values = HashMap<TypeId, Box<dyn Any + Send + Sync>>;
+
+/// Values kept for one session, one per type.
+///
+/// Clones share the values.
+#[derive(Clone, Default)]
+pub struct SessionState {
+    values: Arc<Mutex<Values>>,
+}
+
+impl std::fmt::Debug for SessionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionState")
+            .field("values", &self.lock().len())
+            .finish()
+    }
+}
+
+impl SessionState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value`, returning the value of the same type stored before.
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        self.lock()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// A copy of the stored value of type `T`.
+    pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.lock()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Runs `update` on the stored value of type `T`, storing `T::default()` first if there
+    /// is none, and returns what `update` returns.
+    pub fn update<T: Default + Send + Sync + 'static, R>(
+        &self,
+        update: impl FnOnce(&mut T) -> R,
+    ) -> R {
+        let mut values = self.lock();
+        let value = values
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut::<T>()
+            .expect("values are stored under their own type");
+        update(value)
+    }
+
+    /// Removes and returns the stored value of type `T`.
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
+        self.lock()
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Values> {
+        self.values.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct User(String);
+
+    #[test]
+    fn test_values_are_kept_by_type() {
+        let state = SessionState::new();
+        assert_eq!(state.get::<User>(), None);
+        assert_eq!(state.insert(User("ada".to_string())), None);
+        assert_eq!(
+            state.insert(User("grace".to_string())),
+            Some(User("ada".to_string()))
+        );
+
+        let shared = state.clone();
+        assert_eq!(shared.get::<User>(), Some(User("grace".to_string())));
+        assert_eq!(
+            state.update(|calls: &mut u32| {
+                *calls += 1;
+                *calls
+            }),
+            1
+        );
+        assert_eq!(
+            shared.update(|calls: &mut u32| {
+                *calls += 1;
+                *calls
+            }),
+            2
+        );
+
+        assert_eq!(state.remove::<User>(), Some(User("grace".to_string())));
+        assert_eq!(shared.get::<User>(), None);
+        assert_eq!(shared.get::<u32>(), Some(2));
+    }
+}
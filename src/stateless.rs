@@ -0,0 +1,202 @@
+/// Answering MCP requests over HTTP without keeping sessions, for serverless platforms.
+///
+/// On AWS Lambda, Cloudflare Workers and the like no process outlives the request it
+/// handles, so there is nowhere to keep a session between requests. [`handle_single`]
+/// answers every POST on its own: it serves the request to a session that exists only
+/// while the request is handled, initializing it first on the client's behalf unless the
+/// request is the `initialize` itself.
+///
+/// ```text
+/// async fn lambda(event: Request) -> Response {
+///     let request = HttpRequest::builder()
+///         .method(HttpMethod::Post)
+///         .maybe_accept(event.header("accept"))
+///         .body(event.into_body())
+///         .build();
+///     to_lambda_response(handle_single(&server, request).await)
+/// }
+/// ```
+///
+/// No `Mcp-Session-Id` is issued, and whatever the server learns about its client, or keeps
+/// in [`SessionState`], is gone once the response is sent. In this mode:
+///
+/// - progress and log messages sent while a request is handled reach the client only if it
+///   accepts event streams; anything the server sends after the response is dropped;
+/// - `list_changed` notifications and resource updates are never delivered, so resource
+///   subscriptions have no effect;
+/// - handlers cannot send requests to the client, such as for sampling, elicitation or its
+///   roots, since the client declares no capabilities;
+/// - notifications and responses the client POSTs, including cancellations, are accepted
+///   and dropped;
+/// - GET and DELETE are answered with `405 Method Not Allowed`.
+///
+/// Requests are served as the [`AuthContext`] in [`HttpRequest::auth`], or anonymously, so
+/// callers authenticate them beforehand, e.g. with [`crate::auth::authenticate`].
+///
+/// [`SessionState`]: crate::state::SessionState
+use serde_json::json;
+
+use crate::auth::AuthContext;
+use crate::error::McpError;
+use crate::handshake::INITIALIZE_METHOD;
+use crate::protocol::{
+    JSONRPC_VERSION, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, ProtocolVersion,
+    RequestId,
+};
+use crate::server::Server;
+use crate::streamable::{
+    HttpMethod, HttpRequest, HttpResponse, HttpSession, accepts_events, parse,
+};
+
+/// The client a session initialized on demand is opened for.
+pub const STATELESS_CLIENT_NAME: &str = "stateless-client";
+
+/// Answers one HTTP request to the MCP endpoint without keeping a session.
+pub async fn handle_single(server: &Server, request: HttpRequest) -> HttpResponse {
+    if request.method != HttpMethod::Post {
+        return HttpResponse::empty(405).with_header("Allow", "POST");
+    }
+    let message = match parse(&request.body) {
+        Ok(message) => message,
+        Err(response) => return response,
+    };
+    let JsonRpcMessage::Request(call) = &message else {
+        return HttpResponse::empty(202);
+    };
+    let version = match request.protocol_version.as_deref().map(str::parse) {
+        None => ProtocolVersion::LATEST,
+        Some(Ok(version)) => version,
+        Some(Err(_)) => {
+            return HttpResponse::error(
+                400,
+                McpError::invalid_request(format!(
+                    "Unsupported protocol version: {}",
+                    request.protocol_version.unwrap_or_default()
+                )),
+            );
+        }
+    };
+    let initialize = call.method == INITIALIZE_METHOD;
+    let auth = request.auth.clone().unwrap_or_else(AuthContext::anonymous);
+    let session = HttpSession::open(server.clone(), auth, |_| {});
+    if !initialize {
+        session.post(initialize_request(version), false).await;
+        let _ = session.inbound.send(initialized_notification());
+    }
+    session
+        .post(message, accepts_events(&request))
+        .await
+        .keeping(session)
+}
+
+/// The `initialize` request sent on behalf of a client that did not send one.
+fn initialize_request(version: ProtocolVersion) -> JsonRpcMessage {
+    JsonRpcMessage::Request(JsonRpcRequest {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        id: RequestId::String(format!("{}-initialize", STATELESS_CLIENT_NAME)),
+        method: INITIALIZE_METHOD.to_string(),
+        params: Some(json!({
+            "protocolVersion": version,
+            "capabilities": {},
+            "clientInfo": { "name": STATELESS_CLIENT_NAME, "version": "0.0.0" }
+        })),
+    })
+}
+
+fn initialized_notification() -> JsonRpcMessage {
+    JsonRpcMessage::Notification(JsonRpcNotification {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        method: "notifications/initialized".to_string(),
+        params: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::PROGRESS_NOTIFICATION;
+    use crate::protocol::Implementation;
+    use crate::server::RequestContext;
+    use crate::streamable::{HttpBody, SESSION_ID_HEADER, parse_events};
+    use crate::tool::{CallToolResult, Tool, ToolRegistry};
+    use futures::StreamExt;
+    use serde_json::{Map, Value};
+
+    fn server() -> Server {
+        let tools = ToolRegistry::new();
+        tools.register(
+            Tool::builder().name("whoami").build(),
+            |_: Option<Map<String, Value>>, context: RequestContext| async move {
+                context.progress().report(1.0, None, None).await.ok();
+                Ok(CallToolResult::text(context.client_info().name.clone()))
+            },
+        );
+        Server::new(Implementation::new("test-server", "1.0.0")).with_tools(tools)
+    }
+
+    fn post(message: Value, accept: &str) -> HttpRequest {
+        HttpRequest::builder()
+            .method(HttpMethod::Post)
+            .accept(accept)
+            .body(message.to_string().into_bytes())
+            .build()
+    }
+
+    async fn body(response: HttpResponse) -> String {
+        match response.body {
+            HttpBody::Empty => String::new(),
+            HttpBody::Json(json) => json,
+            HttpBody::Events(events) => events.collect::<Vec<_>>().await.concat(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_answered_without_a_session() {
+        let server = server();
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "whoami", "_meta": { "progressToken": 7 } }
+        });
+        let response = handle_single(&server, post(call.clone(), "application/json")).await;
+        assert_eq!(response.status, 200);
+        assert!(
+            response
+                .headers
+                .iter()
+                .all(|(name, _)| *name != SESSION_ID_HEADER)
+        );
+        let result: Value = serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(result["id"], 1);
+        assert_eq!(
+            result["result"]["content"][0]["text"],
+            STATELESS_CLIENT_NAME
+        );
+
+        let events = handle_single(&server, post(call, "text/event-stream")).await;
+        let events = parse_events(&body(events).await);
+        assert_eq!(events[0]["method"], PROGRESS_NOTIFICATION);
+        assert_eq!(events[1]["id"], 1);
+
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            }
+        });
+        let response = handle_single(&server, post(initialize, "application/json")).await;
+        let result: Value = serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(result["result"]["serverInfo"]["name"], "test-server");
+
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = handle_single(&server, post(initialized, "application/json")).await;
+        assert_eq!(response.status, 202);
+        let get = HttpRequest::builder().method(HttpMethod::Get).build();
+        assert_eq!(handle_single(&server, get).await.status, 405);
+    }
+}
@@ -0,0 +1,861 @@
+/// The Streamable HTTP transport, independent of any HTTP framework.
+///
+/// A [`SessionManager`] serves a [`Server`] at a single MCP endpoint. The web framework
+/// integrations, [`crate::axum`] and [`crate::actix`], turn each HTTP request to the endpoint
+/// into an [`HttpRequest`], pass it to [`SessionManager::handle`] and send back the
+/// [`HttpResponse`]:
+///
+/// ```text
+/// let sessions = SessionManager::new(server).with_token_validator(validator);
+/// let response = sessions.handle(request).await;
+/// ```
+///
+/// An `initialize` POST opens a session, whose id the response carries in the
+/// `Mcp-Session-Id` header, and every later request names it. Every other request POSTed
+/// is answered with an event stream carrying what the server sends about it, such as
+/// progress, ending with its response, or with just the response as JSON if the client does
+/// not accept event streams. Messages about no request in particular go to the stream the
+/// client opens with GET, or, without one, to the stream of a request still in progress.
+/// DELETE ends the session. Streams cannot be resumed.
+///
+/// A request the client cancels is not answered by the server, so its POST is answered with
+/// `204 No Content`, or its event stream ends, once the cancellation arrives. At most
+/// [`DEFAULT_MAX_SESSIONS`] sessions are open at a time, and `initialize` is refused with
+/// `503 Service Unavailable` beyond that; sessions that saw no request for
+/// [`DEFAULT_SESSION_IDLE_TIMEOUT`] are ended to make room first.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bon::Builder;
+use futures::StreamExt;
+use futures::stream::{self, BoxStream};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::auth::{AuthContext, TokenValidator, authenticate};
+use crate::cancellation::{CANCELLED_NOTIFICATION, CancelledNotificationParams};
+use crate::error::McpError;
+use crate::handshake::INITIALIZE_METHOD;
+use crate::progress::{PROGRESS_NOTIFICATION, ProgressToken};
+use crate::protocol::{
+    JSONRPC_VERSION, JsonRpcError, JsonRpcMessage, ProtocolError, ProtocolVersion, RequestId,
+};
+use crate::server::Server;
+use crate::stateless::handle_single;
+use crate::transport::Transport;
+
+/// Header carrying the id of the session a request belongs to.
+pub const SESSION_ID_HEADER: &str = "Mcp-Session-Id";
+
+/// Header carrying the protocol version negotiated for the session.
+pub const PROTOCOL_VERSION_HEADER: &str = "MCP-Protocol-Version";
+
+/// How many sessions a [`SessionManager`] keeps open at most, unless set otherwise.
+pub const DEFAULT_MAX_SESSIONS: usize = 1024;
+
+/// How long a session may see no request before it ends, unless set otherwise.
+pub const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// The HTTP methods of the MCP endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    /// Opens the stream of messages about no request in particular
+    Get,
+    /// Sends a message to the server
+    Post,
+    /// Ends the session
+    Delete,
+    /// Any other method, which the endpoint does not allow
+    Other,
+}
+
+/// The parts of an HTTP request the transport looks at.
+#[derive(Debug, Clone, Builder)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    /// The `Mcp-Session-Id` header
+    #[builder(into)]
+    pub session_id: Option<String>,
+    /// The `MCP-Protocol-Version` header
+    #[builder(into)]
+    pub protocol_version: Option<String>,
+    /// The `Authorization` header
+    #[builder(into)]
+    pub authorization: Option<String>,
+    /// The `Accept` header
+    #[builder(into)]
+    pub accept: Option<String>,
+    /// Who sent the request, if middleware in front of the endpoint authenticated it. The
+    /// token validator is not asked about such requests.
+    pub auth: Option<AuthContext>,
+    #[builder(default)]
+    pub body: Vec<u8>,
+}
+
+/// The answer to an [`HttpRequest`].
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: HttpBody,
+}
+
+/// The body of an [`HttpResponse`].
+pub enum HttpBody {
+    Empty,
+    /// A JSON document
+    Json(String),
+    /// A stream of server-sent events, each one complete
+    Events(BoxStream<'static, String>),
+}
+
+impl std::fmt::Debug for HttpBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpBody::Empty => f.write_str("Empty"),
+            HttpBody::Json(json) => f.debug_tuple("Json").field(json).finish(),
+            HttpBody::Events(_) => f.write_str("Events"),
+        }
+    }
+}
+
+impl HttpResponse {
+    pub(crate) fn empty(status: u16) -> Self {
+        HttpResponse {
+            status,
+            headers: Vec::new(),
+            body: HttpBody::Empty,
+        }
+    }
+
+    pub(crate) fn message(status: u16, message: &JsonRpcMessage) -> Self {
+        HttpResponse {
+            status,
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: HttpBody::Json(serde_json::to_string(message).unwrap_or_default()),
+        }
+    }
+
+    /// An error about the HTTP request itself, which no JSON-RPC request id belongs to.
+    pub(crate) fn error(status: u16, error: McpError) -> Self {
+        let error = JsonRpcMessage::Error(JsonRpcError {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: None,
+            error: error.into(),
+        });
+        Self::message(status, &error)
+    }
+
+    fn events(receiver: mpsc::UnboundedReceiver<JsonRpcMessage>) -> Self {
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            let message = receiver.recv().await?;
+            Some((event(&message), receiver))
+        });
+        HttpResponse {
+            status: 200,
+            headers: vec![
+                ("Content-Type", "text/event-stream".to_string()),
+                ("Cache-Control", "no-cache".to_string()),
+            ],
+            body: HttpBody::Events(events.boxed()),
+        }
+    }
+
+    /// Keeps `guard` alive until the body is sent.
+    pub(crate) fn keeping<T: Send + 'static>(mut self, guard: T) -> Self {
+        if let HttpBody::Events(events) = self.body {
+            let events = events.map(move |event| {
+                let _ = &guard;
+                event
+            });
+            self.body = HttpBody::Events(events.boxed());
+        }
+        self
+    }
+
+    pub(crate) fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// `message` as a server-sent event.
+fn event(message: &JsonRpcMessage) -> String {
+    let data = serde_json::to_string(message).unwrap_or_default();
+    format!("event: message\ndata: {}\n\n", data)
+}
+
+/// The sessions of a server served over Streamable HTTP.
+///
+/// Clones share the sessions.
+#[derive(Clone)]
+pub struct SessionManager {
+    server: Server,
+    validator: Option<Arc<dyn TokenValidator>>,
+    sessions: Arc<Mutex<HashMap<String, Arc<HttpSession>>>>,
+    stateless: bool,
+    max_sessions: usize,
+    idle_timeout: Duration,
+}
+
+impl std::fmt::Debug for SessionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionManager")
+            .field("sessions", &self.lock().len())
+            .field("max_sessions", &self.max_sessions)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish()
+    }
+}
+
+impl SessionManager {
+    pub fn new(server: Server) -> Self {
+        SessionManager {
+            server,
+            validator: None,
+            sessions: Arc::default(),
+            stateless: false,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+        }
+    }
+
+    /// Authenticates requests by their bearer token with `validator`. Requests without a
+    /// valid token are answered with `401 Unauthorized`, and a session is only continued by
+    /// requests authenticated as the subject that opened it.
+    pub fn with_token_validator(mut self, validator: impl TokenValidator + 'static) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Keeps no sessions: no `Mcp-Session-Id` is issued, and every POST is answered on its
+    /// own, initializing a session for it on demand, as [`handle_single`] does. Any instance
+    /// of a horizontally scaled deployment can then answer any request, at the cost of the
+    /// capabilities [`crate::stateless`] lists.
+    pub fn with_stateless_mode(mut self) -> Self {
+        self.stateless = true;
+        self
+    }
+
+    /// Keeps at most `max_sessions` sessions open instead of [`DEFAULT_MAX_SESSIONS`].
+    /// Beyond that, `initialize` is answered with `503 Service Unavailable`.
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    /// Ends sessions that saw no request for `timeout` instead of
+    /// [`DEFAULT_SESSION_IDLE_TIMEOUT`], checked whenever a session is opened. Sessions with
+    /// a request in progress or an open GET stream are not idle.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// The number of open sessions.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Answers one HTTP request to the MCP endpoint.
+    pub async fn handle(&self, request: HttpRequest) -> HttpResponse {
+        if request.method == HttpMethod::Other {
+            return HttpResponse::empty(405).with_header("Allow", "GET, POST, DELETE");
+        }
+        let auth = match self.authenticate(&request).await {
+            Ok(auth) => auth,
+            Err(response) => return response,
+        };
+        if self.stateless {
+            let request = HttpRequest {
+                auth: Some(auth),
+                ..request
+            };
+            return handle_single(&self.server, request).await;
+        }
+        if request.method == HttpMethod::Post && request.session_id.is_none() {
+            return self.initialize(request, auth).await;
+        }
+        let session = match self.session(&request, &auth) {
+            Ok(session) => session,
+            Err(response) => return response,
+        };
+        session.touch();
+        match request.method {
+            HttpMethod::Get => {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                session.routes().standalone = Some(sender);
+                HttpResponse::events(receiver)
+            }
+            HttpMethod::Delete => {
+                self.lock().remove(&session.id);
+                HttpResponse::empty(200)
+            }
+            _ => match parse(&request.body) {
+                Ok(message) => session.post(message, accepts_events(&request)).await,
+                Err(response) => response,
+            },
+        }
+    }
+
+    async fn authenticate(&self, request: &HttpRequest) -> Result<AuthContext, HttpResponse> {
+        if let Some(auth) = &request.auth {
+            return Ok(auth.clone());
+        }
+        let Some(validator) = &self.validator else {
+            return Ok(AuthContext::anonymous());
+        };
+        authenticate(validator.as_ref(), request.authorization.as_deref())
+            .await
+            .map_err(|unauthorized| {
+                HttpResponse::error(401, McpError::invalid_request(unauthorized.0))
+                    .with_header("WWW-Authenticate", "Bearer")
+            })
+    }
+
+    /// The session `request` continues.
+    fn session(
+        &self,
+        request: &HttpRequest,
+        auth: &AuthContext,
+    ) -> Result<Arc<HttpSession>, HttpResponse> {
+        let Some(id) = &request.session_id else {
+            return Err(HttpResponse::error(
+                400,
+                McpError::invalid_request(format!("Missing {} header", SESSION_ID_HEADER)),
+            ));
+        };
+        if let Some(version) = &request.protocol_version
+            && version.parse::<ProtocolVersion>().is_err()
+        {
+            return Err(HttpResponse::error(
+                400,
+                McpError::invalid_request(format!("Unsupported protocol version: {}", version)),
+            ));
+        }
+        // Sessions of other subjects are as good as unknown.
+        self.lock()
+            .get(id)
+            .filter(|session| session.subject == auth.subject)
+            .cloned()
+            .ok_or_else(|| HttpResponse::error(404, McpError::invalid_request("Session not found")))
+    }
+
+    /// Opens a session with the `initialize` request in `request`.
+    async fn initialize(&self, request: HttpRequest, auth: AuthContext) -> HttpResponse {
+        let message = match parse(&request.body) {
+            Ok(message) => message,
+            Err(response) => return response,
+        };
+        if !matches!(&message, JsonRpcMessage::Request(request) if request.method == INITIALIZE_METHOD)
+        {
+            return HttpResponse::error(
+                400,
+                McpError::invalid_request(format!("Missing {} header", SESSION_ID_HEADER)),
+            );
+        }
+        self.end_idle_sessions();
+        if self.len() >= self.max_sessions {
+            return HttpResponse::error(503, McpError::server_busy("Too many open sessions"));
+        }
+        let sessions = self.sessions.clone();
+        let session = HttpSession::open(self.server.clone(), auth, move |id| {
+            sessions
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(id);
+        });
+        self.lock().insert(session.id.clone(), session.clone());
+        session
+            .post(message, accepts_events(&request))
+            .await
+            .with_header(SESSION_ID_HEADER, session.id.clone())
+    }
+
+    /// Ends the sessions idle for longer than the idle timeout.
+    fn end_idle_sessions(&self) {
+        let now = Instant::now();
+        self.lock().retain(|_, session| {
+            session
+                .idle_since()
+                .is_none_or(|since| now.duration_since(since) < self.idle_timeout)
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<HttpSession>>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub(crate) fn parse(body: &[u8]) -> Result<JsonRpcMessage, HttpResponse> {
+    serde_json::from_slice(body).map_err(|e| {
+        HttpResponse::error(
+            400,
+            McpError::parse_error(format!("Invalid JSON-RPC message: {}", e)),
+        )
+    })
+}
+
+/// Whether the client takes its responses as event streams.
+pub(crate) fn accepts_events(request: &HttpRequest) -> bool {
+    request
+        .accept
+        .as_deref()
+        .is_some_and(|accept| accept.contains("text/event-stream"))
+}
+
+/// One session open over HTTP.
+pub(crate) struct HttpSession {
+    pub(crate) id: String,
+    /// Who opened the session
+    subject: Option<String>,
+    /// Messages for the server, ending the session when dropped
+    pub(crate) inbound: mpsc::UnboundedSender<JsonRpcMessage>,
+    routes: Arc<Mutex<Routes>>,
+    /// When the session last saw a request
+    last_active: Mutex<Instant>,
+}
+
+impl HttpSession {
+    /// Starts serving `server` to a new session on behalf of `auth`. `ended` is called with
+    /// the session's id once it ends, which it does when the returned session is dropped.
+    pub(crate) fn open(
+        server: Server,
+        auth: AuthContext,
+        ended: impl FnOnce(&str) + Send + 'static,
+    ) -> Arc<Self> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let (inbound, receiver) = mpsc::unbounded_channel();
+        let routes = Arc::new(Mutex::new(Routes::default()));
+        let transport = SessionTransport {
+            id: id.clone(),
+            inbound: tokio::sync::Mutex::new(receiver),
+            routes: routes.clone(),
+        };
+        let session = Arc::new(HttpSession {
+            id: id.clone(),
+            subject: auth.subject.clone(),
+            inbound,
+            routes,
+            last_active: Mutex::new(Instant::now()),
+        });
+        tokio::spawn(async move {
+            let result = server.serve_with_auth(transport, auth).await;
+            #[cfg(feature = "tracing")]
+            if let Err(error) = &result {
+                tracing::warn!(session = %id, error = %error, "HTTP session failed");
+            }
+            drop(result);
+            ended(&id);
+        });
+        session
+    }
+
+    /// Hands `message` to the server and answers the POST that carried it.
+    pub(crate) async fn post(&self, message: JsonRpcMessage, events: bool) -> HttpResponse {
+        let JsonRpcMessage::Request(request) = &message else {
+            if let JsonRpcMessage::Notification(notification) = &message
+                && notification.method == CANCELLED_NOTIFICATION
+                && let Some(params) = notification.params.clone()
+                && let Ok(cancelled) = serde_json::from_value::<CancelledNotificationParams>(params)
+            {
+                self.routes().cancel(&cancelled.request_id);
+            }
+            return match self.inbound.send(message) {
+                Ok(()) => HttpResponse::empty(202),
+                Err(_) => HttpResponse::error(404, McpError::invalid_request("Session not found")),
+            };
+        };
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        {
+            let mut routes = self.routes();
+            if events && let Some(token) = ProgressToken::from_request(request) {
+                routes.progress.insert(token, request.id.clone());
+            }
+            routes
+                .requests
+                .insert(request.id.clone(), Stream { sender, events });
+        }
+        if self.inbound.send(message).is_err() {
+            return HttpResponse::error(404, McpError::invalid_request("Session not found"));
+        }
+        if events {
+            return HttpResponse::events(receiver);
+        }
+        match receiver.recv().await {
+            Some(response) => HttpResponse::message(200, &response),
+            None if self.routes().closed => {
+                HttpResponse::error(500, McpError::internal_error("The session ended"))
+            }
+            // The request was cancelled, so the server does not answer it.
+            None => HttpResponse::empty(204),
+        }
+    }
+
+    /// Marks the session as active now.
+    fn touch(&self) {
+        *self.last_active.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// When the session became idle, or `None` while a request is in progress or the GET
+    /// stream is open.
+    fn idle_since(&self) -> Option<Instant> {
+        let routes = self.routes();
+        let streaming = routes
+            .standalone
+            .as_ref()
+            .is_some_and(|standalone| !standalone.is_closed());
+        if streaming || !routes.requests.is_empty() {
+            return None;
+        }
+        Some(*self.last_active.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn routes(&self) -> MutexGuard<'_, Routes> {
+        self.routes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A response waiting for the messages about its request.
+struct Stream {
+    sender: mpsc::UnboundedSender<JsonRpcMessage>,
+    /// Whether it streams events, or only takes the response
+    events: bool,
+}
+
+/// Where the messages the server sends go.
+#[derive(Default)]
+struct Routes {
+    /// The responses to requests in progress, by request id
+    requests: HashMap<RequestId, Stream>,
+    /// The request each progress token was given with
+    progress: HashMap<ProgressToken, RequestId>,
+    /// The stream opened with GET
+    standalone: Option<mpsc::UnboundedSender<JsonRpcMessage>>,
+    /// Whether the session ended
+    closed: bool,
+}
+
+impl Routes {
+    /// Ends the response to the request `id`, which the server will not answer.
+    fn cancel(&mut self, id: &RequestId) {
+        self.progress.retain(|_, request| request != id);
+        self.requests.remove(id);
+    }
+
+    fn route(&mut self, message: JsonRpcMessage) {
+        let request = match &message {
+            JsonRpcMessage::Response(response) => Some(response.id.clone()),
+            JsonRpcMessage::Error(error) => error.id.clone(),
+            JsonRpcMessage::Notification(notification)
+                if notification.method == PROGRESS_NOTIFICATION =>
+            {
+                notification
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.get("progressToken"))
+                    .and_then(|token| serde_json::from_value::<ProgressToken>(token.clone()).ok())
+                    .and_then(|token| self.progress.get(&token).cloned())
+            }
+            _ => None,
+        };
+        if let Some(id) = request {
+            let finished = matches!(
+                message,
+                JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_)
+            );
+            if finished {
+                self.progress.retain(|_, request| *request != id);
+                if let Some(stream) = self.requests.remove(&id) {
+                    let _ = stream.sender.send(message);
+                    return;
+                }
+            } else if let Some(stream) = self.requests.get(&id) {
+                let _ = stream.sender.send(message);
+                return;
+            }
+        }
+        self.route_unrelated(message);
+    }
+
+    /// Sends a message about no request in progress to the GET stream, or to any open event
+    /// stream. Without one the message is dropped.
+    fn route_unrelated(&mut self, message: JsonRpcMessage) {
+        if let Some(standalone) = &self.standalone {
+            match standalone.send(message) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(message)) => {
+                    self.standalone = None;
+                    return self.route_unrelated(message);
+                }
+            }
+        }
+        if let Some(stream) = self.requests.values().find(|stream| stream.events) {
+            let _ = stream.sender.send(message);
+        }
+    }
+}
+
+/// The transport a session's server is served over.
+struct SessionTransport {
+    id: String,
+    inbound: tokio::sync::Mutex<mpsc::UnboundedReceiver<JsonRpcMessage>>,
+    routes: Arc<Mutex<Routes>>,
+}
+
+#[async_trait]
+impl Transport for SessionTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        self.routes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .route(message);
+        Ok(())
+    }
+
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+        Ok(self.inbound.lock().await.recv().await)
+    }
+
+    /// Ends the streams still open.
+    async fn close(&self) -> Result<(), ProtocolError> {
+        *self.routes.lock().unwrap_or_else(|e| e.into_inner()) = Routes {
+            closed: true,
+            ..Routes::default()
+        };
+        Ok(())
+    }
+
+    fn session_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
+/// The JSON-RPC messages of the events in `body`, a complete event stream.
+#[cfg(test)]
+pub(crate) fn parse_events(body: &str) -> Vec<serde_json::Value> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::{CallToolResult, Tool, ToolRegistry};
+    use serde_json::{Map, Value, json};
+
+    fn server() -> Server {
+        let tools = ToolRegistry::new();
+        tools.register(
+            Tool::builder().name("count").build(),
+            |_: Option<Map<String, Value>>, context: crate::server::RequestContext| async move {
+                context.progress().report(1.0, Some(2.0), None).await.ok();
+                Ok(CallToolResult::text("done"))
+            },
+        );
+        Server::new(crate::protocol::Implementation::new("test-server", "1.0.0")).with_tools(tools)
+    }
+
+    fn post(session_id: Option<&str>, message: Value) -> HttpRequest {
+        HttpRequest::builder()
+            .method(HttpMethod::Post)
+            .maybe_session_id(session_id)
+            .accept("application/json, text/event-stream")
+            .body(message.to_string().into_bytes())
+            .build()
+    }
+
+    async fn body(response: HttpResponse) -> String {
+        match response.body {
+            HttpBody::Empty => String::new(),
+            HttpBody::Json(json) => json,
+            HttpBody::Events(events) => events.collect::<Vec<_>>().await.concat(),
+        }
+    }
+
+    fn header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(header, _)| *header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[tokio::test]
+    async fn test_sessions_over_http() {
+        let sessions = SessionManager::new(server());
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            }
+        });
+        let response = sessions.handle(post(None, initialize)).await;
+        assert_eq!(response.status, 200);
+        let id = header(&response, SESSION_ID_HEADER).unwrap().to_string();
+        let events = parse_events(&body(response).await);
+        assert_eq!(events[0]["result"]["serverInfo"]["name"], "test-server");
+        assert_eq!(sessions.len(), 1);
+
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = sessions.handle(post(Some(&id), initialized)).await;
+        assert_eq!(response.status, 202);
+
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": { "name": "count", "_meta": { "progressToken": "p-1" } }
+        });
+        let events = parse_events(&body(sessions.handle(post(Some(&id), call)).await).await);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["method"], PROGRESS_NOTIFICATION);
+        assert_eq!(events[1]["result"]["content"][0]["text"], "done");
+
+        let list = json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/list" });
+        let mut request = post(Some(&id), list);
+        request.accept = Some("application/json".to_string());
+        let response = sessions.handle(request).await;
+        assert_eq!(header(&response, "Content-Type"), Some("application/json"));
+        let listed: Value = serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(listed["result"]["tools"][0]["name"], "count");
+
+        let unknown = sessions.handle(post(Some("nope"), json!({}))).await;
+        assert_eq!(unknown.status, 404);
+        let missing = json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/list" });
+        assert_eq!(sessions.handle(post(None, missing)).await.status, 400);
+
+        let delete = HttpRequest::builder()
+            .method(HttpMethod::Delete)
+            .session_id(id.clone())
+            .build();
+        assert_eq!(sessions.handle(delete).await.status, 200);
+        assert!(sessions.is_empty());
+        let list = json!({ "jsonrpc": "2.0", "id": 5, "method": "tools/list" });
+        assert_eq!(sessions.handle(post(Some(&id), list)).await.status, 404);
+    }
+
+    fn initialize(sessions: &SessionManager) -> impl Future<Output = HttpResponse> {
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": { "name": "test-client", "version": "1.0.0" }
+            }
+        });
+        sessions.handle(post(None, initialize))
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_requests_end_their_response() {
+        let tools = ToolRegistry::new();
+        tools.register(
+            Tool::builder().name("wait").build(),
+            |_: Option<Map<String, Value>>, _| async move {
+                std::future::pending::<()>().await;
+                Ok(CallToolResult::default())
+            },
+        );
+        let server = Server::new(crate::protocol::Implementation::new("test-server", "1.0.0"))
+            .with_tools(tools);
+        let sessions = SessionManager::new(server);
+        let response = initialize(&sessions).await;
+        let id = header(&response, SESSION_ID_HEADER).unwrap().to_string();
+        body(response).await;
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        sessions.handle(post(Some(&id), initialized)).await;
+        let session = sessions.lock().get(&id).cloned().unwrap();
+
+        let call = |request_id: i64| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": request_id,
+                "method": "tools/call",
+                "params": { "name": "wait", "_meta": { "progressToken": request_id } }
+            })
+        };
+        let cancel = |request_id: i64| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/cancelled",
+                "params": { "requestId": request_id }
+            })
+        };
+        let mut request = post(Some(&id), call(2));
+        request.accept = Some("application/json".to_string());
+        let waiting = tokio::spawn({
+            let sessions = sessions.clone();
+            async move { sessions.handle(request).await }
+        });
+        while session.routes().requests.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        let response = sessions.handle(post(Some(&id), cancel(2))).await;
+        assert_eq!(response.status, 202);
+        assert_eq!(waiting.await.unwrap().status, 204);
+
+        let streaming = sessions.handle(post(Some(&id), call(3))).await;
+        sessions.handle(post(Some(&id), cancel(3))).await;
+        assert_eq!(body(streaming).await, "");
+        assert!(session.routes().requests.is_empty());
+        assert!(session.routes().progress.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sessions_are_capped_and_end_when_idle() {
+        let sessions = SessionManager::new(server())
+            .with_max_sessions(2)
+            .with_idle_timeout(Duration::from_secs(60));
+        assert_eq!(initialize(&sessions).await.status, 200);
+        assert_eq!(initialize(&sessions).await.status, 200);
+        let refused = initialize(&sessions).await;
+        assert_eq!(refused.status, 503);
+        assert_eq!(sessions.len(), 2);
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert_eq!(initialize(&sessions).await.status, 200);
+        assert_eq!(sessions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stateless_mode_issues_no_sessions() {
+        let sessions = SessionManager::new(server()).with_stateless_mode();
+        let list = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" });
+        let response = sessions.handle(post(None, list)).await;
+        assert_eq!(response.status, 200);
+        assert_eq!(header(&response, SESSION_ID_HEADER), None);
+        let events = parse_events(&body(response).await);
+        assert_eq!(events[0]["result"]["tools"][0]["name"], "count");
+        assert!(sessions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_a_valid_token() {
+        let sessions =
+            SessionManager::new(server()).with_token_validator(|token: String| async move {
+                match token.as_str() {
+                    "t-1" => Ok(AuthContext::builder().subject("ada").build()),
+                    _ => Err(crate::auth::Unauthorized::new("Unknown token")),
+                }
+            });
+        let list = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" });
+        let response = sessions.handle(post(None, list)).await;
+        assert_eq!(response.status, 401);
+        assert_eq!(header(&response, "WWW-Authenticate"), Some("Bearer"));
+    }
+}
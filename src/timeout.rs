@@ -0,0 +1,154 @@
+/// Deadlines for outgoing requests.
+///
+/// Every request sent through a [`RequestTracker`] is registered with a deadline. When the
+/// response does not arrive in time, the waiting future resolves with
+/// [`ProtocolError::Timeout`] and the peer is sent a `notifications/cancelled` for the request,
+/// so it can stop working on a result nobody is waiting for anymore.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::cancellation::CancelledNotificationParams;
+use crate::protocol::{JsonRpcMessage, ProtocolError, RequestId};
+use crate::transport::Transport;
+
+/// How long a request may take when no timeout is given explicitly.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Deadlines of the requests currently waiting for a response.
+#[derive(Debug, Clone)]
+pub struct RequestTracker {
+    default_timeout: Duration,
+    deadlines: Arc<Mutex<HashMap<RequestId, Instant>>>,
+}
+
+impl Default for RequestTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_REQUEST_TIMEOUT)
+    }
+}
+
+impl RequestTracker {
+    pub fn new(default_timeout: Duration) -> Self {
+        RequestTracker {
+            default_timeout,
+            deadlines: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The timeout applied to requests tracked without an explicit one.
+    pub fn default_timeout(&self) -> Duration {
+        self.default_timeout
+    }
+
+    /// Waits for `response`, the future resolving with the result of request `id`, until the
+    /// request's deadline.
+    ///
+    /// On timeout `response` is dropped, a `notifications/cancelled` for `id` is sent over
+    /// `transport` and [`ProtocolError::Timeout`] is returned.
+    pub async fn track<T, R, F>(
+        &self,
+        transport: &T,
+        id: RequestId,
+        timeout: Option<Duration>,
+        response: F,
+    ) -> Result<R, ProtocolError>
+    where
+        T: Transport + ?Sized,
+        F: Future<Output = Result<R, ProtocolError>>,
+    {
+        let timeout = timeout.unwrap_or(self.default_timeout);
+        let deadline = Instant::now() + timeout;
+        self.lock().insert(id.clone(), deadline);
+        let _guard = DeadlineGuard {
+            id: id.clone(),
+            tracker: self,
+        };
+
+        match tokio::time::timeout_at(deadline, response).await {
+            Ok(result) => result,
+            Err(_) => {
+                let reason = format!("Request timed out after {:?}", timeout);
+                let notification =
+                    CancelledNotificationParams::new(id.clone(), Some(reason.clone()))
+                        .into_notification()?;
+                transport
+                    .send(JsonRpcMessage::Notification(notification))
+                    .await?;
+                Err(ProtocolError::Timeout(format!(
+                    "Request {}: {}",
+                    id, reason
+                )))
+            }
+        }
+    }
+
+    /// Returns the deadline of the request with the given id, if it is still pending.
+    pub fn deadline(&self, id: &RequestId) -> Option<Instant> {
+        self.lock().get(id).copied()
+    }
+
+    /// Returns the number of requests currently waiting for a response.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<RequestId, Instant>> {
+        self.deadlines.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Unregisters a request however its wait ends, including when the waiting future is dropped.
+struct DeadlineGuard<'a> {
+    id: RequestId,
+    tracker: &'a RequestTracker,
+}
+
+impl Drop for DeadlineGuard<'_> {
+    fn drop(&mut self) {
+        self.tracker.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MemoryTransport;
+
+    #[tokio::test]
+    async fn test_timeout_cancels_request() {
+        let (client, server) = MemoryTransport::pair();
+        let tracker = RequestTracker::new(Duration::from_millis(20));
+
+        let result: Result<(), ProtocolError> = tracker
+            .track(&client, RequestId::Number(9), None, std::future::pending())
+            .await;
+        assert!(matches!(result, Err(ProtocolError::Timeout(_))));
+        assert!(tracker.is_empty());
+
+        let Some(JsonRpcMessage::Notification(notification)) = server.receive().await.unwrap()
+        else {
+            panic!("Expected a cancelled notification");
+        };
+        let params: CancelledNotificationParams =
+            serde_json::from_value(notification.params.unwrap()).unwrap();
+        assert_eq!(params.request_id, RequestId::Number(9));
+
+        // A response within the deadline goes through untouched.
+        let result = tracker
+            .track(&client, RequestId::Number(10), None, async {
+                assert!(tracker.deadline(&RequestId::Number(10)).is_some());
+                Ok(42)
+            })
+            .await;
+        assert_eq!(result.unwrap(), 42);
+        assert!(tracker.is_empty());
+    }
+}
@@ -0,0 +1,825 @@
+/// Tools that servers expose to clients
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bon::Builder;
+use schemars::{JsonSchema, schema_for};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use tokio::sync::watch;
+
+use crate::error::McpError;
+use crate::mount::{prefixed, unprefixed};
+use crate::pagination::{DEFAULT_PAGE_SIZE, paginate};
+use crate::prompt::PromptMessageContent;
+use crate::protocol::{
+    Cursor, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR, PaginatedRequestParams,
+};
+#[cfg(feature = "jsonschema")]
+use crate::schema::SchemaError;
+#[cfg(not(feature = "jsonschema"))]
+use crate::schema::validate;
+use crate::server::RequestContext;
+
+/// Definition for a tool the client can call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct Tool {
+    /// A JSON Schema object defining the expected parameters for the tool
+    #[builder(field = json!({ "type": "object" }))]
+    pub input_schema: Value,
+
+    /// A JSON Schema object describing the `structuredContent` of the tool's results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(field)]
+    pub output_schema: Option<Value>,
+
+    /// The name of the tool
+    #[builder(into)]
+    pub name: String,
+
+    /// A human-readable name for display, in place of `name`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub title: Option<String>,
+
+    /// A human-readable description of the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub description: Option<String>,
+
+    /// Hints about the tool's behavior, for clients to present risks to the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl<S: tool_builder::State> ToolBuilder<S> {
+    pub fn input_schema<T: JsonSchema>(mut self) -> Self {
+        self.input_schema = schema_for!(T).to_value();
+        self
+    }
+
+    /// Declares that the tool's results carry structured content of type `T`.
+    pub fn output_schema<T: JsonSchema>(mut self) -> Self {
+        self.output_schema = Some(schema_for!(T).to_value());
+        self
+    }
+}
+
+/// Hints describing how a tool behaves.
+///
+/// The hints are not guaranteed to be accurate. Clients must not base decisions on them for
+/// tools from servers they do not trust.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    /// A human-readable title for the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub title: Option<String>,
+
+    /// The tool does not modify its environment. Defaults to `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+
+    /// The tool may perform destructive updates, rather than only additive ones. Only
+    /// meaningful when the tool is not read-only. Defaults to `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+
+    /// Calling the tool repeatedly with the same arguments has no additional effect. Only
+    /// meaningful when the tool is not read-only. Defaults to `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+
+    /// The tool interacts with an open world of external entities, such as the web, rather
+    /// than a closed domain such as a memory store. Defaults to `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+/// The server's response to a tools/list request from the client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResult {
+    /// The tools offered by the server
+    pub tools: Vec<Tool>,
+
+    /// An opaque token representing the pagination position after the last returned result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<Cursor>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// Parameters of a tools/call request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolRequestParams {
+    /// The name of the tool to call
+    pub name: String,
+
+    /// Arguments to pass to the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Map<String, Value>>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+/// The server's response to a tool call
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    /// The content produced by the tool
+    pub content: Vec<PromptMessageContent>,
+
+    /// The result as a JSON object, matching the tool's output schema if it declares one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+
+    /// Whether the tool call ended in an error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
+
+    /// Reserved by MCP for attaching additional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Value>>,
+}
+
+impl CallToolResult {
+    /// A successful result with `content`, e.g.
+    /// `vec![Content::text("Rendered"), Content::image(png, "image/png")]`.
+    pub fn new(content: Vec<PromptMessageContent>) -> Self {
+        CallToolResult {
+            content,
+            ..Default::default()
+        }
+    }
+
+    /// A successful result with a single text content.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new(vec![PromptMessageContent::text(text)])
+    }
+
+    /// A successful result with `value` as structured content.
+    ///
+    /// The serialized JSON is also included as text content, for clients that do not read
+    /// structured content.
+    pub fn structured(value: impl Serialize) -> Result<Self, McpError> {
+        let value =
+            serde_json::to_value(value).map_err(|e| McpError::internal_error(e.to_string()))?;
+        Ok(CallToolResult {
+            structured_content: Some(value.clone()),
+            ..Self::text(value.to_string())
+        })
+    }
+
+    /// A failed tool call, described to the model by `message`.
+    pub fn error(message: impl Into<String>) -> Self {
+        CallToolResult {
+            is_error: Some(true),
+            ..Self::text(message)
+        }
+    }
+
+    /// Returns `true` if the tool call ended in an error.
+    pub fn is_error(&self) -> bool {
+        self.is_error == Some(true)
+    }
+}
+
+/// Runs the calls of one tool.
+#[async_trait]
+pub trait ToolHandler: Send + Sync {
+    /// Calls the tool with the arguments sent by the client.
+    ///
+    /// The tool failing is not a protocol error: the model should see the failure and may
+    /// recover from it, so [`ToolRegistry::call`] turns errors into results with `is_error`
+    /// set, see [`CallToolResult::error`]. Only errors about the request itself, such as
+    /// invalid arguments, are reported as protocol errors.
+    async fn call(
+        &self,
+        arguments: Option<Map<String, Value>>,
+        context: RequestContext,
+    ) -> Result<CallToolResult, McpError>;
+}
+
+#[async_trait]
+impl<F, Fut> ToolHandler for F
+where
+    F: Fn(Option<Map<String, Value>>, RequestContext) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<CallToolResult, McpError>> + Send,
+{
+    async fn call(
+        &self,
+        arguments: Option<Map<String, Value>>,
+        context: RequestContext,
+    ) -> Result<CallToolResult, McpError> {
+        self(arguments, context).await
+    }
+}
+
+/// A tool that knows its own definition, such as those generated by the `#[tool]` attribute
+/// (with the `macros` feature).
+pub trait ToolDefinition {
+    /// The definition listed by `tools/list`.
+    fn definition(&self) -> Tool;
+
+    /// The handler that runs the tool's calls.
+    fn handler(self) -> impl ToolHandler + 'static;
+}
+
+/// Deserializes the arguments of a tool call. Missing arguments deserialize from an empty
+/// object.
+///
+/// Failures are invalid params whose data names the offending field, e.g.
+/// `{"field": "filters.limit", "error": "invalid type: string \"ten\", expected u32"}`.
+pub fn parse_arguments<A: DeserializeOwned>(
+    arguments: Option<Map<String, Value>>,
+) -> Result<A, McpError> {
+    serde_path_to_error::deserialize(Value::Object(arguments.unwrap_or_default())).map_err(|e| {
+        let field = e.path().to_string();
+        let error = e.into_inner().to_string();
+        McpError::invalid_params(format!("Invalid arguments: {}: {}", field, error))
+            .with_data(json!({ "field": field, "error": error }))
+    })
+}
+
+/// Returns `true` for errors caused by the request rather than by running the tool.
+fn is_request_error(error: &McpError) -> bool {
+    matches!(
+        error.code(),
+        INVALID_PARAMS | INVALID_REQUEST | METHOD_NOT_FOUND | PARSE_ERROR
+    )
+}
+
+type Entry = (Tool, Arc<dyn ToolHandler>);
+
+/// The tools a server offers, with their handlers, in registration order.
+///
+/// Backs the `tools/list` and `tools/call` methods, see
+/// [`Server::with_tools`](crate::server::Server::with_tools). `tools/list` is paginated:
+/// each response holds at most one page of tools and the cursor to request the next.
+///
+/// Clones share their tools, so tools can be added and removed while a server runs. Every
+/// change is announced to subscribers, see [`ToolRegistry::subscribe`].
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: Arc<Mutex<Vec<Entry>>>,
+    /// Registries whose tools are offered under a prefix, see [`ToolRegistry::mount`]
+    mounts: Arc<Mutex<Vec<(String, ToolRegistry)>>>,
+    changes: Arc<Changes>,
+    page_size: Option<usize>,
+    validate_output: bool,
+    #[cfg(feature = "jsonschema")]
+    input_validators: Option<Validators>,
+    #[cfg(feature = "jsonschema")]
+    output_validators: Validators,
+    timeouts: HashMap<String, Duration>,
+}
+
+/// Announces the changes of a registry to its subscribers and to those of the registries it
+/// is mounted on.
+struct Changes {
+    sender: watch::Sender<()>,
+    mounted_on: Mutex<Vec<Weak<Changes>>>,
+}
+
+impl Changes {
+    fn announce(&self) {
+        self.sender.send_replace(());
+        let mounted_on: Vec<_> = self
+            .mounted_on
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for changes in mounted_on {
+            changes.announce();
+        }
+    }
+}
+
+/// Compiled schemas by tool name, next to the schema each was compiled from
+#[cfg(feature = "jsonschema")]
+type Validators = Arc<Mutex<HashMap<String, (Value, Arc<jsonschema::Validator>)>>>;
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        ToolRegistry {
+            tools: Arc::default(),
+            mounts: Arc::default(),
+            changes: Arc::new(Changes {
+                sender: watch::Sender::new(()),
+                mounted_on: Mutex::default(),
+            }),
+            page_size: None,
+            validate_output: false,
+            #[cfg(feature = "jsonschema")]
+            input_validators: None,
+            #[cfg(feature = "jsonschema")]
+            output_validators: Validators::default(),
+            timeouts: HashMap::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field(
+                "tools",
+                &self
+                    .lock()
+                    .iter()
+                    .map(|(tool, _)| tool.name.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .field(
+                "mounts",
+                &self
+                    .lock_mounts()
+                    .iter()
+                    .map(|(prefix, _)| prefix.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .field("page_size", &self.page_size)
+            .field("validate_output", &self.validate_output)
+            .field("timeouts", &self.timeouts)
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lists at most `page_size` tools per `tools/list` response instead of
+    /// [`DEFAULT_PAGE_SIZE`].
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Checks the structured content of successful results against the output schema of
+    /// their tool before they are sent. A result that does not match is replaced by an
+    /// internal error, so clients never receive output that breaks the declared contract.
+    ///
+    /// With the `jsonschema` feature the full JSON Schema specification is checked, otherwise
+    /// the subset described in [`validate`](crate::schema::validate).
+    pub fn with_output_validation(mut self) -> Self {
+        self.validate_output = true;
+        self
+    }
+
+    /// Checks the arguments of calls against the input schema of their tool before the
+    /// handler runs (with the `jsonschema` feature). Calls whose arguments do not match are
+    /// answered with an `INVALID_PARAMS` error whose data lists every violation and its path.
+    #[cfg(feature = "jsonschema")]
+    pub fn with_input_validation(mut self) -> Self {
+        self.input_validators = Some(Validators::default());
+        self
+    }
+
+    /// Gives calls of the tool named `name` at most `timeout` to finish. A call that takes
+    /// longer is dropped and answered with a `REQUEST_TIMEOUT` error, see
+    /// [`McpError::timed_out`].
+    pub fn with_timeout(mut self, name: impl Into<String>, timeout: Duration) -> Self {
+        self.timeouts.insert(name.into(), timeout);
+        self
+    }
+
+    /// Registers `tool` with its handler, replacing any tool registered under the same name.
+    pub fn register(&self, tool: Tool, handler: impl ToolHandler + 'static) -> &Self {
+        let handler: Arc<dyn ToolHandler> = Arc::new(handler);
+        {
+            let mut tools = self.lock();
+            match tools.iter_mut().find(|(t, _)| t.name == tool.name) {
+                Some(entry) => *entry = (tool, handler),
+                None => tools.push((tool, handler)),
+            }
+        }
+        self.changes.announce();
+        self
+    }
+
+    /// Replaces the registered tools with those of `tools`, announcing the change only if a
+    /// definition differs. Returns whether one did.
+    pub fn replace_all(&self, tools: &ToolRegistry) -> bool {
+        let entries = tools.lock().clone();
+        let changed = {
+            let mut current = self.lock();
+            let changed = current.len() != entries.len()
+                || current
+                    .iter()
+                    .zip(&entries)
+                    .any(|((before, _), (after, _))| before != after);
+            *current = entries;
+            changed
+        };
+        if changed {
+            self.changes.announce();
+        }
+        changed
+    }
+
+    /// Offers the tools of `tools` as `<prefix>__<name>`, after the registry's own, see
+    /// [`crate::mount`].
+    ///
+    /// The tools are looked up in `tools` whenever they are listed or called, so tools
+    /// registered on it or removed from it later are offered or withdrawn right away, and
+    /// the change is announced to this registry's subscribers. Calls are answered by `tools`
+    /// under the original name, with its own validation and timeouts.
+    pub fn mount(&self, prefix: &str, tools: &ToolRegistry) -> &Self {
+        {
+            let mut mounted_on = tools
+                .changes
+                .mounted_on
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            mounted_on.retain(|changes| changes.strong_count() > 0);
+            mounted_on.push(Arc::downgrade(&self.changes));
+        }
+        self.lock_mounts().push((prefix.to_string(), tools.clone()));
+        self.changes.announce();
+        self
+    }
+
+    /// Registers a tool whose handler takes its arguments as `A`.
+    ///
+    /// The tool's input schema is generated from `A`, and arguments that do not deserialize
+    /// into it are rejected with the failing field before the handler runs, see
+    /// [`parse_arguments`].
+    pub fn register_typed<A, F, Fut>(&self, name: impl Into<String>, handler: F) -> &Self
+    where
+        A: DeserializeOwned + JsonSchema + Send + 'static,
+        F: Fn(A, RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CallToolResult, McpError>> + Send + 'static,
+    {
+        let tool = Tool::builder().name(name).input_schema::<A>().build();
+        let handler = Arc::new(handler);
+        self.register(
+            tool,
+            move |arguments: Option<Map<String, Value>>, context| {
+                let handler = handler.clone();
+                async move { handler(parse_arguments(arguments)?, context).await }
+            },
+        )
+    }
+
+    /// Registers a tool that carries its own definition.
+    pub fn add(&self, tool: impl ToolDefinition) -> &Self {
+        let definition = tool.definition();
+        self.register(definition, tool.handler())
+    }
+
+    /// Removes the tool registered under `name`. Returns `false` if there is none.
+    pub fn remove(&self, name: &str) -> bool {
+        let removed = {
+            let mut tools = self.lock();
+            let before = tools.len();
+            tools.retain(|(tool, _)| tool.name != name);
+            tools.len() != before
+        };
+        if removed {
+            self.changes.announce();
+        }
+        removed
+    }
+
+    /// Returns the tool registered under `name`, or offered under it by a mounted registry.
+    pub fn get(&self, name: &str) -> Option<Tool> {
+        let own = self
+            .lock()
+            .iter()
+            .map(|(tool, _)| tool)
+            .find(|tool| tool.name == name)
+            .cloned();
+        own.or_else(|| {
+            let (prefix, tools) = self.mounted(name)?;
+            let tool = tools.get(unprefixed(&prefix, name)?)?;
+            Some(Tool {
+                name: name.to_string(),
+                ..tool
+            })
+        })
+    }
+
+    /// The definitions of all registered tools, in the order they were registered, followed
+    /// by those of the mounted registries under their prefix.
+    pub fn definitions(&self) -> Vec<Tool> {
+        let mut definitions: Vec<Tool> = self.lock().iter().map(|(tool, _)| tool.clone()).collect();
+        let mounts = self.lock_mounts().clone();
+        for (prefix, tools) in mounts {
+            for tool in tools.definitions() {
+                let name = prefixed(&prefix, &tool.name);
+                if !definitions.iter().any(|defined| defined.name == name) {
+                    definitions.push(Tool { name, ..tool });
+                }
+            }
+        }
+        definitions
+    }
+
+    pub fn len(&self) -> usize {
+        self.definitions().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+            && self
+                .lock_mounts()
+                .clone()
+                .iter()
+                .all(|(_, tools)| tools.is_empty())
+    }
+
+    /// Returns a receiver that is marked changed whenever a tool is registered or removed,
+    /// here or in a mounted registry.
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.changes.sender.subscribe()
+    }
+
+    /// Answers a `tools/list` request.
+    pub fn list(&self, params: &PaginatedRequestParams) -> Result<ListToolsResult, McpError> {
+        let tools = self.definitions();
+        let (tools, next_cursor) =
+            paginate(&tools, params, self.page_size.unwrap_or(DEFAULT_PAGE_SIZE))?;
+        Ok(ListToolsResult {
+            tools,
+            next_cursor,
+            meta: None,
+        })
+    }
+
+    /// Answers a `tools/call` request.
+    ///
+    /// Unknown tools and handler errors with the invalid params, invalid request, method not
+    /// found or parse error codes are protocol errors. Other handler errors become results
+    /// with `is_error` set that carry the error message.
+    pub async fn call(
+        &self,
+        params: CallToolRequestParams,
+        context: RequestContext,
+    ) -> Result<CallToolResult, McpError> {
+        let own = self
+            .lock()
+            .iter()
+            .find(|(tool, _)| tool.name == params.name)
+            .cloned();
+        let Some((tool, handler)) = own else {
+            let unknown = McpError::invalid_params(format!("Unknown tool: {}", params.name));
+            let Some((prefix, tools)) = self.mounted(&params.name) else {
+                return Err(unknown);
+            };
+            let Some(name) = unprefixed(&prefix, &params.name) else {
+                return Err(unknown);
+            };
+            let params = CallToolRequestParams {
+                name: name.to_string(),
+                ..params
+            };
+            return Box::pin(tools.call(params, context)).await;
+        };
+        #[cfg(feature = "jsonschema")]
+        if let Some(validators) = &self.input_validators {
+            check_input(validators, &tool, params.arguments.as_ref())?;
+        }
+        let call = handler.call(params.arguments, context);
+        let result = match self.timeouts.get(&tool.name) {
+            Some(&timeout) => tokio::time::timeout(timeout, call)
+                .await
+                .map_err(|_| McpError::timed_out(timeout))?,
+            None => call.await,
+        };
+        let result = match result {
+            Err(error) if !is_request_error(&error) => CallToolResult::error(error.message()),
+            result => result?,
+        };
+        if let Some(schema) = &tool.output_schema
+            && self.validate_output
+            && !result.is_error()
+        {
+            self.check_output(&tool, schema, &result)?;
+        }
+        Ok(result)
+    }
+
+    /// The mounted registry whose prefix `name` has.
+    fn mounted(&self, name: &str) -> Option<(String, ToolRegistry)> {
+        self.lock_mounts()
+            .iter()
+            .find(|(prefix, _)| unprefixed(prefix, name).is_some())
+            .cloned()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Entry>> {
+        self.tools.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_mounts(&self) -> std::sync::MutexGuard<'_, Vec<(String, ToolRegistry)>> {
+        self.mounts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn check_output(
+        &self,
+        tool: &Tool,
+        schema: &Value,
+        result: &CallToolResult,
+    ) -> Result<(), McpError> {
+        let Some(content) = &result.structured_content else {
+            return Err(McpError::internal_error(format!(
+                "Tool {} declares an output schema but returned no structured content",
+                tool.name
+            )));
+        };
+        #[cfg(feature = "jsonschema")]
+        let errors = {
+            let validator = compiled(&self.output_validators, tool, schema, "Output")?;
+            schema_errors(&validator, content)
+        };
+        #[cfg(not(feature = "jsonschema"))]
+        let errors = validate(schema, content).err().unwrap_or_default();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        Err(McpError::internal_error(format!(
+            "Structured content of {} does not match its output schema: {}",
+            tool.name,
+            errors.join("; ")
+        ))
+        .with_data(json!({ "errors": errors })))
+    }
+}
+
+/// The validator for `schema` of `tool`, compiled on first use and again whenever the tool is
+/// replaced. `kind` names the schema in the error for a schema that does not compile.
+#[cfg(feature = "jsonschema")]
+fn compiled(
+    validators: &Validators,
+    tool: &Tool,
+    schema: &Value,
+    kind: &str,
+) -> Result<Arc<jsonschema::Validator>, McpError> {
+    let mut validators = validators.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((compiled_from, validator)) = validators.get(&tool.name)
+        && compiled_from == schema
+    {
+        return Ok(validator.clone());
+    }
+    let validator = jsonschema::validator_for(schema).map_err(|e| {
+        McpError::internal_error(format!(
+            "{} schema of {} is invalid: {}",
+            kind, tool.name, e
+        ))
+    })?;
+    let validator = Arc::new(validator);
+    validators.insert(tool.name.clone(), (schema.clone(), validator.clone()));
+    Ok(validator)
+}
+
+/// Every place where `value` does not match the compiled schema.
+#[cfg(feature = "jsonschema")]
+fn schema_errors(validator: &jsonschema::Validator, value: &Value) -> Vec<SchemaError> {
+    validator
+        .iter_errors(value)
+        .map(|error| SchemaError {
+            path: error
+                .instance_path()
+                .iter()
+                .map(|segment| segment.to_string())
+                .collect::<Vec<_>>()
+                .join("."),
+            message: error.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(feature = "jsonschema")]
+fn check_input(
+    validators: &Validators,
+    tool: &Tool,
+    arguments: Option<&Map<String, Value>>,
+) -> Result<(), McpError> {
+    let validator = compiled(validators, tool, &tool.input_schema, "Input")?;
+    let arguments = Value::Object(arguments.cloned().unwrap_or_default());
+    let errors = schema_errors(&validator, &arguments);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let listed: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    Err(McpError::invalid_params(format!(
+        "Arguments of {} do not match its input schema: {}",
+        tool.name,
+        listed.join("; ")
+    ))
+    .with_data(json!({
+        "errors": errors
+            .iter()
+            .map(|error| json!({ "path": error.path, "message": error.message }))
+            .collect::<Vec<_>>()
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_annotations() {
+        let tool = Tool::builder()
+            .name("delete_file")
+            .annotations(
+                ToolAnnotations::builder()
+                    .title("Delete file")
+                    .destructive_hint(true)
+                    .open_world_hint(false)
+                    .build(),
+            )
+            .build();
+        let value = serde_json::to_value(&tool).unwrap();
+        assert_eq!(
+            value["annotations"],
+            json!({ "title": "Delete file", "destructiveHint": true, "openWorldHint": false })
+        );
+        assert_eq!(serde_json::from_value::<Tool>(value).unwrap(), tool);
+    }
+
+    #[test]
+    fn test_registry_lists_tools_in_pages() {
+        let registry = ToolRegistry::new().with_page_size(2);
+        for name in ["a", "b", "c", "a"] {
+            registry.register(
+                Tool::builder()
+                    .name(name)
+                    .title(name.to_uppercase())
+                    .build(),
+                |_: Option<Map<String, Value>>, _| async move { Ok(CallToolResult::default()) },
+            );
+        }
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.get("a").unwrap().title.as_deref(), Some("A"));
+
+        let first = registry.list(&PaginatedRequestParams::default()).unwrap();
+        assert_eq!(first.tools.len(), 2);
+        let second = registry
+            .list(&PaginatedRequestParams {
+                cursor: first.next_cursor,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(second.tools[0].name, "c");
+        assert_eq!(second.next_cursor, None);
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct SearchArgs {
+        query: String,
+        filters: Option<Filters>,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Filters {
+        limit: u32,
+    }
+
+    #[test]
+    fn test_typed_arguments() {
+        let registry = ToolRegistry::new();
+        registry.register_typed("search", |_: SearchArgs, _| async move {
+            Ok(CallToolResult::default())
+        });
+        let schema = &registry.get("search").unwrap().input_schema;
+        assert_eq!(schema["type"], json!("object"));
+        assert_eq!(schema["required"], json!(["query"]));
+
+        let arguments = json!({ "query": "rust", "filters": { "limit": "ten" } });
+        let error = parse_arguments::<SearchArgs>(arguments.as_object().cloned()).unwrap_err();
+        assert_eq!(error.code(), crate::protocol::INVALID_PARAMS);
+        assert_eq!(error.data().unwrap()["field"], json!("filters.limit"));
+
+        let error = parse_arguments::<SearchArgs>(None).unwrap_err();
+        assert!(
+            error.message().contains("missing field `query`"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,287 @@
+/// W3C trace context carried in `_meta`.
+///
+/// Gateways forward a request they receive to upstream servers, so one tool call can cross
+/// several MCP hops. To keep those hops in one distributed trace, requests carry a W3C
+/// `traceparent` (and optionally `tracestate`) in their `_meta`:
+///
+/// ```text
+/// {"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"search",
+///  "_meta":{"traceparent":"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"}}}
+/// ```
+///
+/// A handler run through a [`RequestScope`] sees the context of the request it handles as
+/// [`TraceContext::current`]. Every request a [`Client`](crate::client::Client) sends while a
+/// context is current carries a child of it, so requests made by the handler continue the
+/// trace. With the `tracing` feature, each request also runs inside a `tracing` span that
+/// records the trace and span ids.
+use std::fmt::Write;
+use std::future::Future;
+
+use serde_json::{Map, Value, json};
+use uuid::Uuid;
+
+use crate::protocol::JsonRpcRequest;
+
+/// The `_meta` member holding the W3C `traceparent`.
+pub const TRACEPARENT_META_KEY: &str = "traceparent";
+
+/// The `_meta` member holding the W3C `tracestate`.
+pub const TRACESTATE_META_KEY: &str = "tracestate";
+
+const SAMPLED: u8 = 0x01;
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// The position of a request within a distributed trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    /// The id of the span that sent the request
+    pub span_id: [u8; 8],
+    /// The trace flags; bit 0 marks the trace as sampled
+    pub flags: u8,
+    /// Vendor-specific data, passed on unchanged
+    pub trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Starts a new, sampled trace.
+    pub fn new_root() -> Self {
+        TraceContext {
+            trace_id: *Uuid::new_v4().as_bytes(),
+            span_id: new_span_id(),
+            flags: SAMPLED,
+            trace_state: None,
+        }
+    }
+
+    /// Returns the context for a new span within the same trace.
+    pub fn child(&self) -> Self {
+        TraceContext {
+            span_id: new_span_id(),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.flags & SAMPLED != 0
+    }
+
+    /// Parses a `traceparent` header value and an optional `tracestate`.
+    ///
+    /// Returns `None` for malformed values, which W3C trace context says to ignore.
+    pub fn parse(traceparent: &str, trace_state: Option<&str>) -> Option<Self> {
+        let mut parts = traceparent.split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        // Later versions may append fields, version 00 may not.
+        if version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        decode_hex::<1>(version)?;
+
+        let trace_id = decode_hex::<16>(trace_id)?;
+        let span_id = decode_hex::<8>(span_id)?;
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+        Some(TraceContext {
+            trace_id,
+            span_id,
+            flags: decode_hex::<1>(flags)?[0],
+            trace_state: trace_state.map(str::to_string),
+        })
+    }
+
+    /// Formats the `traceparent` value for this context.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id_hex(),
+            self.span_id_hex(),
+            self.flags
+        )
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        encode_hex(&self.trace_id)
+    }
+
+    pub fn span_id_hex(&self) -> String {
+        encode_hex(&self.span_id)
+    }
+
+    /// Returns the context of the request being handled by the current task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` with this context as [`TraceContext::current`].
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// Reads the context from a request's `_meta`.
+    pub fn extract(request: &JsonRpcRequest) -> Option<Self> {
+        let meta = request.params.as_ref()?.get("_meta")?;
+        let traceparent = meta.get(TRACEPARENT_META_KEY)?.as_str()?;
+        let trace_state = meta.get(TRACESTATE_META_KEY).and_then(Value::as_str);
+        Self::parse(traceparent, trace_state)
+    }
+
+    /// Stores this context in a request's `_meta`.
+    pub fn attach_to(&self, request: &mut JsonRpcRequest) {
+        let params = request
+            .params
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        let meta = params
+            .as_object_mut()
+            .map(|params| params.entry("_meta").or_insert_with(|| json!({})));
+        if let Some(Value::Object(meta)) = meta {
+            meta.insert(TRACEPARENT_META_KEY.to_string(), json!(self.traceparent()));
+            if let Some(trace_state) = &self.trace_state {
+                meta.insert(TRACESTATE_META_KEY.to_string(), json!(trace_state));
+            }
+        }
+    }
+}
+
+/// The trace context and span of one request, captured before the request is consumed by
+/// the code that sends or handles it.
+#[derive(Debug)]
+pub struct RequestScope {
+    context: Option<TraceContext>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl RequestScope {
+    pub fn new(request: &JsonRpcRequest) -> Self {
+        RequestScope {
+            context: TraceContext::extract(request),
+            #[cfg(feature = "tracing")]
+            span: request_span(request),
+        }
+    }
+
+    /// Runs `future` with the request's trace context as [`TraceContext::current`] and, with
+    /// the `tracing` feature, inside the request's span.
+    pub async fn run<F: Future>(self, future: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        let future = tracing::Instrument::instrument(future, self.span);
+        match self.context {
+            Some(context) => context.scope(future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Creates the span for handling or sending `request`, recording the ids of its trace
+/// context.
+#[cfg(feature = "tracing")]
+pub fn request_span(request: &JsonRpcRequest) -> tracing::Span {
+    let span = tracing::info_span!(
+        "mcp.request",
+        rpc.method = %request.method,
+        rpc.id = %request.id,
+        trace_id = tracing::field::Empty,
+        span_id = tracing::field::Empty,
+    );
+    if let Some(context) = TraceContext::extract(request) {
+        span.record("trace_id", context.trace_id_hex());
+        span.record("span_id", context.span_id_hex());
+    }
+    span
+}
+
+fn new_span_id() -> [u8; 8] {
+    let mut span_id = [0; 8];
+    span_id.copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+    span_id
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(output, "{:02x}", byte);
+    }
+    output
+}
+
+/// Decodes exactly `N` bytes of lowercase hex, as trace context requires.
+fn decode_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != N * 2 || !text.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{JSONRPC_VERSION, RequestId};
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_traceparent_round_trip() {
+        let context = TraceContext::parse(TRACEPARENT, Some("vendor=abc")).unwrap();
+        assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id_hex(), "00f067aa0ba902b7");
+        assert!(context.is_sampled());
+        assert_eq!(context.traceparent(), TRACEPARENT);
+
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_ne!(child.span_id, context.span_id);
+        assert_eq!(child.trace_state.as_deref(), Some("vendor=abc"));
+
+        for invalid in [
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert_eq!(TraceContext::parse(invalid, None), None, "{}", invalid);
+        }
+        assert!(
+            TraceContext::parse(
+                "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+                None
+            )
+            .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_context_is_current_while_handling() {
+        let mut request = JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: RequestId::Number(1),
+            method: "tools/call".to_string(),
+            params: Some(json!({ "name": "search", "_meta": { "progressToken": 1 } })),
+        };
+        TraceContext::parse(TRACEPARENT, None)
+            .unwrap()
+            .attach_to(&mut request);
+        assert_eq!(
+            request.params.as_ref().unwrap()["_meta"],
+            json!({ "progressToken": 1, "traceparent": TRACEPARENT })
+        );
+
+        assert_eq!(TraceContext::current(), None);
+        let scope = RequestScope::new(&request);
+        let current = scope.run(async { TraceContext::current() }).await;
+        assert_eq!(current.unwrap().traceparent(), TRACEPARENT);
+    }
+}
@@ -1,90 +1,344 @@
+/// Transports that carry JSON-RPC messages between MCP peers.
+use std::process::Stdio;
+use std::time::Duration;
 
-// /// Implementation of a stdio transport for MCP.
-// pub struct StdioTransport {
-//     sender: tokio::sync::mpsc::Sender<JsonRpcMessage>,
-//     on_message: tokio::sync::Mutex<Option<Box<dyn Fn(JsonRpcMessage) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> + Send + Sync>>>,
-//     on_error: tokio::sync::Mutex<Option<Box<dyn Fn(ProtocolError) + Send + Sync>>>,
-//     on_close: tokio::sync::Mutex<Option<Box<dyn Fn() + Send + Sync>>>,
-// }
-
-// impl StdioTransport {
-//     /// Creates a new stdio transport.
-//     pub fn new() -> Self {
-//         let (tx, _) = tokio::sync::mpsc::channel(100);
-//         StdioTransport {
-//             sender: tx,
-//             on_message: tokio::sync::Mutex::new(None),
-//             on_error: tokio::sync::Mutex::new(None),
-//             on_close: tokio::sync::Mutex::new(None),
-//         }
-//     }
-// }
-
-// impl Transport for StdioTransport {
-//     fn start(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ProtocolError>> + Send>> {
-//         Box::pin(async {
-//             // Implementation for reading from stdin and writing to stdout
-//             Ok(())
-//         })
-//     }
-
-//     fn send(&self, message: JsonRpcMessage) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ProtocolError>> + Send>> {
-//         let sender = self.sender.clone();
-//         Box::pin(async move {
-//             sender
-//                 .send(message)
-//                 .await
-//                 .map_err(|e| ProtocolError::TransportError(format!("Failed to send message: {}", e)))
-//         })
-//     }
-
-//     fn close(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ProtocolError>> + Send>> {
-//         Box::pin(async { Ok(()) })
-//     }
-
-//     fn on_message(&self, handler: Box<dyn Fn(JsonRpcMessage) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> + Send + Sync>) {
-//         let on_message = self.on_message.clone();
-//         tokio::spawn(async move {
-//             let mut guard = on_message.lock().await;
-//             *guard = Some(handler);
-//         });
-//     }
-
-//     fn on_error(&self, handler: Box<dyn Fn(ProtocolError) + Send + Sync>) {
-//         let on_error = self.on_error.clone();
-//         tokio::spawn(async move {
-//             let mut guard = on_error.lock().await;
-//             *guard = Some(handler);
-//         });
-//     }
-
-//     fn on_close(&self, handler: Box<dyn Fn() + Send + Sync>) {
-//         let on_close = self.on_close.clone();
-//         tokio::spawn(async move {
-//             let mut guard = on_close.lock().await;
-//             *guard = Some(handler);
-//         });
-//     }
-// }
-
-// /// Trait for MCP transport implementations.
-// #[async_trait]
-// pub trait Transport: Send + Sync {
-//     /// Starts the transport and begins processing messages.
-//     async fn start(&self) -> Result<(), ProtocolError>;
-
-//     /// Sends a JSON-RPC message through the transport.
-//     async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError>;
-
-//     /// Closes the transport connection.
-//     async fn close(&self) -> Result<(), ProtocolError>;
-
-//     /// Sets a handler for incoming messages.
-//     fn on_message(&self, handler: Box<dyn Fn(JsonRpcMessage) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> + Send + Sync>);
-
-//     /// Sets a handler for transport errors.
-//     fn on_error(&self, handler: Box<dyn Fn(ProtocolError) + Send + Sync>);
-
-//     /// Sets a handler for transport close events.
-//     fn on_close(&self, handler: Box<dyn Fn() + Send + Sync>);
-// }
+use async_trait::async_trait;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, DuplexStream, ReadHalf,
+    Stdin, Stdout, WriteHalf,
+};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+use crate::protocol::{JsonRpcMessage, ProtocolError};
+use crate::raw::RawFrame;
+use crate::validation::{ValidationMode, parse_message};
+
+/// Trait for MCP transport implementations.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Sends a JSON-RPC message through the transport.
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError>;
+
+    /// Receives the next JSON-RPC message, or `None` once the peer closed the connection.
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError>;
+
+    /// Receives the text of the next message without parsing it into a [`JsonRpcMessage`].
+    ///
+    /// Transports that receive text return it as is. The default implementation serializes
+    /// the message returned by [`receive`](Transport::receive) again.
+    async fn receive_frame(&self) -> Result<Option<RawFrame>, ProtocolError> {
+        match self.receive().await? {
+            Some(message) => RawFrame::from_message(&message).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Closes the transport connection.
+    async fn close(&self) -> Result<(), ProtocolError>;
+
+    /// Returns the session id assigned by the server, for transports that have one.
+    fn session_id(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Lets transports of different kinds be used in the same place, e.g. by the clients of a
+/// [`ClientPool`](crate::pool::ClientPool).
+#[async_trait]
+impl Transport for Box<dyn Transport> {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        (**self).send(message).await
+    }
+
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+        (**self).receive().await
+    }
+
+    async fn receive_frame(&self) -> Result<Option<RawFrame>, ProtocolError> {
+        (**self).receive_frame().await
+    }
+
+    async fn close(&self) -> Result<(), ProtocolError> {
+        (**self).close().await
+    }
+
+    fn session_id(&self) -> Option<String> {
+        (**self).session_id()
+    }
+}
+
+/// Parses one serialized message.
+///
+/// With the `simd-json` feature the SIMD-accelerated parser is used, which uses `bytes` as
+/// scratch space and leaves it modified.
+pub fn decode_message(bytes: &mut [u8]) -> Result<JsonRpcMessage, ProtocolError> {
+    #[cfg(feature = "simd-json")]
+    let message = simd_json::serde::from_slice(bytes);
+    #[cfg(not(feature = "simd-json"))]
+    let message = serde_json::from_slice(bytes);
+    message.map_err(|e| ProtocolError::ParseError(e.to_string()))
+}
+
+/// Newline-delimited JSON transport over any pair of async byte streams.
+pub struct StreamTransport<R, W> {
+    /// The reader and the bytes of a line that has not been completely received yet, which
+    /// survive a cancelled `receive` call.
+    reader: Mutex<(BufReader<R>, Vec<u8>)>,
+    writer: Mutex<W>,
+    validation: ValidationMode,
+}
+
+impl<R, W> StreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    /// Creates a transport reading messages from `reader` and writing them to `writer`.
+    pub fn new(reader: R, writer: W) -> Self {
+        StreamTransport {
+            reader: Mutex::new((BufReader::new(reader), Vec::new())),
+            writer: Mutex::new(writer),
+            validation: ValidationMode::default(),
+        }
+    }
+
+    /// Reads the next line that holds a message, without the line break.
+    async fn read_line(&self) -> Result<Option<Vec<u8>>, ProtocolError> {
+        let mut guard = self.reader.lock().await;
+        let (reader, line) = &mut *guard;
+        loop {
+            // `read_until` keeps partially read bytes in `line` if this future is dropped.
+            let read = reader.read_until(b'\n', line).await.map_err(|e| {
+                ProtocolError::TransportError(format!("Failed to receive message: {}", e))
+            })?;
+            if read == 0 && line.is_empty() {
+                return Ok(None);
+            }
+
+            let mut complete = std::mem::take(line);
+            let text = complete.trim_ascii();
+            // Older peers answer notifications with `null`.
+            if text.is_empty() || text == b"null" {
+                continue;
+            }
+            let end = complete.trim_ascii_end().len();
+            complete.truncate(end);
+            return Ok(Some(complete));
+        }
+    }
+
+    /// Sets how incoming messages are validated.
+    ///
+    /// Messages rejected by the validation are answered with an error response and skipped.
+    pub fn with_validation(mut self, validation: ValidationMode) -> Self {
+        self.validation = validation;
+        self
+    }
+}
+
+#[async_trait]
+impl<R, W> Transport for StreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        let mut line = serde_json::to_string(&message)
+            .map_err(|e| ProtocolError::ParseError(e.to_string()))?;
+        line.push('\n');
+
+        let mut writer = self.writer.lock().await;
+        writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| ProtocolError::TransportError(format!("Failed to send message: {}", e)))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| ProtocolError::TransportError(format!("Failed to send message: {}", e)))
+    }
+
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+        loop {
+            let Some(mut line) = self.read_line().await? else {
+                return Ok(None);
+            };
+            if self.validation == ValidationMode::Standard
+                && let Ok(message) = decode_message(&mut line)
+            {
+                return Ok(Some(message));
+            }
+            // Parsing again classifies the line as a parse error or an invalid message.
+            match parse_message(&String::from_utf8_lossy(&line), self.validation) {
+                Ok(message) => return Ok(Some(message)),
+                Err(error) => self.send(JsonRpcMessage::Error(error)).await?,
+            }
+        }
+    }
+
+    async fn receive_frame(&self) -> Result<Option<RawFrame>, ProtocolError> {
+        if self.validation != ValidationMode::Standard {
+            return match self.receive().await? {
+                Some(message) => RawFrame::from_message(&message).map(Some),
+                None => Ok(None),
+            };
+        }
+        let Some(line) = self.read_line().await? else {
+            return Ok(None);
+        };
+        String::from_utf8(line)
+            .map(|text| Some(RawFrame::new(text)))
+            .map_err(|e| ProtocolError::ParseError(e.to_string()))
+    }
+
+    async fn close(&self) -> Result<(), ProtocolError> {
+        self.writer
+            .lock()
+            .await
+            .shutdown()
+            .await
+            .map_err(|e| ProtocolError::TransportError(format!("Failed to close transport: {}", e)))
+    }
+}
+
+/// Transport over the current process's stdin and stdout.
+pub type StdioTransport = StreamTransport<Stdin, Stdout>;
+
+impl StdioTransport {
+    /// Creates a transport that reads from stdin and writes to stdout.
+    pub fn stdio() -> Self {
+        StreamTransport::new(tokio::io::stdin(), tokio::io::stdout())
+    }
+}
+
+/// In-process transport backed by an in-memory pipe.
+pub type MemoryTransport = StreamTransport<ReadHalf<DuplexStream>, WriteHalf<DuplexStream>>;
+
+impl MemoryTransport {
+    /// Creates two connected transports; messages sent on one are received on the other.
+    pub fn pair() -> (Self, Self) {
+        let (left, right) = tokio::io::duplex(64 * 1024);
+        let (left_reader, left_writer) = tokio::io::split(left);
+        let (right_reader, right_writer) = tokio::io::split(right);
+        (
+            StreamTransport::new(left_reader, left_writer),
+            StreamTransport::new(right_reader, right_writer),
+        )
+    }
+}
+
+/// Transport over a Unix domain socket.
+#[cfg(unix)]
+pub type UnixSocketTransport =
+    StreamTransport<tokio::net::unix::OwnedReadHalf, tokio::net::unix::OwnedWriteHalf>;
+
+#[cfg(unix)]
+impl UnixSocketTransport {
+    /// Wraps an already connected Unix stream.
+    pub fn from_stream(stream: tokio::net::UnixStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        StreamTransport::new(reader, writer)
+    }
+
+    /// Connects to the Unix socket at `path`.
+    pub async fn connect(path: impl AsRef<std::path::Path>) -> Result<Self, ProtocolError> {
+        let stream = tokio::net::UnixStream::connect(path)
+            .await
+            .map_err(|e| ProtocolError::TransportError(format!("Failed to connect: {}", e)))?;
+        Ok(Self::from_stream(stream))
+    }
+}
+
+/// How long a child process gets to exit after its stdin is closed before it is killed.
+const CHILD_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Transport to an MCP server running as a child process, speaking over its stdio.
+pub struct ChildProcessTransport {
+    inner: StreamTransport<ChildStdout, ChildStdin>,
+    child: Mutex<Child>,
+}
+
+impl ChildProcessTransport {
+    /// Spawns `command` with piped stdin and stdout.
+    pub fn spawn(mut command: Command) -> Result<Self, ProtocolError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ProtocolError::TransportError(format!("Failed to spawn server: {}", e)))?;
+
+        let stdin = child.stdin.take().ok_or_else(|| {
+            ProtocolError::TransportError("Child process has no stdin".to_string())
+        })?;
+        let stdout = child.stdout.take().ok_or_else(|| {
+            ProtocolError::TransportError("Child process has no stdout".to_string())
+        })?;
+
+        Ok(ChildProcessTransport {
+            inner: StreamTransport::new(stdout, stdin),
+            child: Mutex::new(child),
+        })
+    }
+
+    /// Sets how messages from the server are validated, e.g. leniently for sloppy servers.
+    pub fn with_validation(mut self, validation: ValidationMode) -> Self {
+        self.inner = self.inner.with_validation(validation);
+        self
+    }
+}
+
+#[async_trait]
+impl Transport for ChildProcessTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), ProtocolError> {
+        self.inner.send(message).await
+    }
+
+    async fn receive(&self) -> Result<Option<JsonRpcMessage>, ProtocolError> {
+        self.inner.receive().await
+    }
+
+    async fn receive_frame(&self) -> Result<Option<RawFrame>, ProtocolError> {
+        self.inner.receive_frame().await
+    }
+
+    async fn close(&self) -> Result<(), ProtocolError> {
+        // Closing stdin is the polite way to ask a stdio server to exit.
+        let closed = self.inner.close().await;
+
+        let mut child = self.child.lock().await;
+        if tokio::time::timeout(CHILD_SHUTDOWN_TIMEOUT, child.wait())
+            .await
+            .is_err()
+        {
+            child.kill().await.map_err(|e| {
+                ProtocolError::TransportError(format!("Failed to kill server: {}", e))
+            })?;
+        }
+        closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_null_lines_are_skipped() {
+        let (peer, ours) = tokio::io::duplex(1024);
+        let (reader, writer) = tokio::io::split(ours);
+        let transport = StreamTransport::new(reader, writer);
+        let (_, mut peer) = tokio::io::split(peer);
+        peer.write_all(b"null\n  null \r\n{\"jsonrpc\":\"2.0\",\"method\":\"ping\",\"id\":1}\n")
+            .await
+            .unwrap();
+        drop(peer);
+
+        let Some(JsonRpcMessage::Request(request)) = transport.receive().await.unwrap() else {
+            panic!("Expected the ping request");
+        };
+        assert_eq!(request.method, "ping");
+        assert!(transport.receive().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,494 @@
+/// Matching of concrete URIs against the RFC 6570 URI templates of resource templates.
+///
+/// A server that offers resource templates such as `file:///{path}` or
+/// `weather://{city}/forecast{?days}` receives reads for concrete URIs and has to find the
+/// template each URI belongs to. [`UriTemplate::matches`] answers that, and extracts the
+/// values of the template's variables:
+///
+/// ```text
+/// let template: UriTemplate = "weather://{city}/forecast{?days}".parse()?;
+/// let variables = template.matches("weather://berlin/forecast?days=3").unwrap();
+/// assert_eq!(variables["city"], "berlin");
+/// assert_eq!(variables["days"], "3");
+/// ```
+///
+/// All expression operators of RFC 6570 are understood. Values are percent-decoded, and
+/// variables the URI leaves out, such as an omitted query parameter, are absent from the
+/// result. Prefix modifiers (`{var:3}`) are accepted but not enforced, and an exploded
+/// variable (`{/path*}`) takes all the remaining values of its expression, joined the way
+/// they appear in the URI.
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum UriTemplateError {
+    #[error("Unclosed expression at byte {0}")]
+    Unclosed(usize),
+    #[error("Unexpected '}}' at byte {0}")]
+    UnexpectedClose(usize),
+    #[error("Invalid variable name: {0:?}")]
+    InvalidVariable(String),
+}
+
+/// A parsed RFC 6570 URI template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UriTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expression {
+    operator: Operator,
+    variables: Vec<Variable>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Variable {
+    name: String,
+    explode: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    /// `{var}`
+    Simple,
+    /// `{+var}`
+    Reserved,
+    /// `{#var}`
+    Fragment,
+    /// `{.var}`
+    Label,
+    /// `{/var}`
+    Path,
+    /// `{;var}`
+    PathParameter,
+    /// `{?var}`
+    Query,
+    /// `{&var}`
+    QueryContinuation,
+}
+
+impl Operator {
+    fn parse(expression: &str) -> (Operator, &str) {
+        let operator = match expression.chars().next() {
+            Some('+') => Operator::Reserved,
+            Some('#') => Operator::Fragment,
+            Some('.') => Operator::Label,
+            Some('/') => Operator::Path,
+            Some(';') => Operator::PathParameter,
+            Some('?') => Operator::Query,
+            Some('&') => Operator::QueryContinuation,
+            _ => return (Operator::Simple, expression),
+        };
+        (operator, &expression[1..])
+    }
+
+    /// The character an expansion starts with, if any.
+    fn prefix(self) -> Option<char> {
+        match self {
+            Operator::Simple | Operator::Reserved => None,
+            Operator::Fragment => Some('#'),
+            Operator::Label => Some('.'),
+            Operator::Path => Some('/'),
+            Operator::PathParameter => Some(';'),
+            Operator::Query => Some('?'),
+            Operator::QueryContinuation => Some('&'),
+        }
+    }
+
+    /// The character between the values of an expansion.
+    fn separator(self) -> char {
+        match self {
+            Operator::Simple | Operator::Reserved | Operator::Fragment => ',',
+            Operator::Label => '.',
+            Operator::Path => '/',
+            Operator::PathParameter => ';',
+            Operator::Query | Operator::QueryContinuation => '&',
+        }
+    }
+
+    /// Whether values are expanded as `name=value` pairs.
+    fn named(self) -> bool {
+        matches!(
+            self,
+            Operator::PathParameter | Operator::Query | Operator::QueryContinuation
+        )
+    }
+
+    /// Whether reserved characters are expanded as they are rather than percent-encoded.
+    fn allows_reserved(self) -> bool {
+        matches!(self, Operator::Reserved | Operator::Fragment)
+    }
+}
+
+impl UriTemplate {
+    pub fn parse(template: &str) -> Result<Self, UriTemplateError> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        let mut offset = 0;
+        while !rest.is_empty() {
+            let open = rest.find('{');
+            if let Some(close) = rest.find('}')
+                && open.is_none_or(|open| close < open)
+            {
+                return Err(UriTemplateError::UnexpectedClose(offset + close));
+            }
+            let Some(open) = open else {
+                parts.push(Part::Literal(rest.to_string()));
+                break;
+            };
+            if open > 0 {
+                parts.push(Part::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .map(|close| open + close)
+                .ok_or(UriTemplateError::Unclosed(offset + open))?;
+            parts.push(Part::Expression(parse_expression(&rest[open + 1..close])?));
+            offset += close + 1;
+            rest = &rest[close + 1..];
+        }
+        Ok(UriTemplate {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// The names of the template's variables, in the order they appear.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Expression(expression) => Some(&expression.variables),
+                Part::Literal(_) => None,
+            })
+            .flatten()
+            .map(|variable| variable.name.as_str())
+    }
+
+    /// Returns the values of the template's variables if `uri` is an expansion of the
+    /// template, or `None` if it is not.
+    pub fn matches(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut variables = HashMap::new();
+        Matcher { uri }
+            .parts(&self.parts, 0, &mut variables)
+            .then_some(variables)
+    }
+}
+
+impl FromStr for UriTemplate {
+    type Err = UriTemplateError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        Self::parse(template)
+    }
+}
+
+impl fmt::Display for UriTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+fn parse_expression(expression: &str) -> Result<Expression, UriTemplateError> {
+    let (operator, list) = Operator::parse(expression);
+    let variables = list
+        .split(',')
+        .map(|spec| {
+            let (name, explode) = match spec.strip_suffix('*') {
+                Some(name) => (name, true),
+                None => (spec.split(':').next().unwrap_or(spec), false),
+            };
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '%'));
+            if valid {
+                Ok(Variable {
+                    name: name.to_string(),
+                    explode,
+                })
+            } else {
+                Err(UriTemplateError::InvalidVariable(spec.to_string()))
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Expression {
+        operator,
+        variables,
+    })
+}
+
+struct Matcher<'a> {
+    uri: &'a str,
+}
+
+impl Matcher<'_> {
+    /// Matches `parts` against the URI from byte `position` on, backtracking over the
+    /// possible lengths of each expansion.
+    fn parts(
+        &self,
+        parts: &[Part],
+        position: usize,
+        variables: &mut HashMap<String, String>,
+    ) -> bool {
+        let Some((part, rest)) = parts.split_first() else {
+            return position == self.uri.len();
+        };
+        match part {
+            Part::Literal(literal) => {
+                self.uri[position..].starts_with(literal.as_str())
+                    && self.parts(rest, position + literal.len(), variables)
+            }
+            Part::Expression(expression) => {
+                let ends = (position..=self.uri.len())
+                    .rev()
+                    .filter(|&end| self.uri.is_char_boundary(end));
+                for end in ends {
+                    let Some(values) = expansion(expression, &self.uri[position..end]) else {
+                        continue;
+                    };
+                    let names: Vec<String> = values.keys().cloned().collect();
+                    variables.extend(values);
+                    if self.parts(rest, end, variables) {
+                        return true;
+                    }
+                    for name in names {
+                        variables.remove(&name);
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+/// The values of `expression`'s variables if `text` is one of its expansions.
+fn expansion(expression: &Expression, text: &str) -> Option<HashMap<String, String>> {
+    let operator = expression.operator;
+    let mut values = HashMap::new();
+    if text.is_empty() {
+        return Some(values);
+    }
+    let text = match operator.prefix() {
+        Some(prefix) => text.strip_prefix(prefix)?,
+        None => text,
+    };
+    let allowed = |value: &str| {
+        value.chars().all(|c| {
+            is_unreserved(c) || c == '%' || (operator.allows_reserved() && !c.is_whitespace())
+        })
+    };
+
+    if operator.named() {
+        let names: Vec<&str> = expression
+            .variables
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        for pair in text.split(operator.separator()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            if !names.contains(&name) || !allowed(value) || values.contains_key(name) {
+                return None;
+            }
+            values.insert(name.to_string(), decode(value)?);
+        }
+        return Some(values);
+    }
+
+    let pieces: Vec<&str> = text.split(operator.separator()).collect();
+    let variables = &expression.variables;
+    // A single variable that may contain reserved characters takes the whole expansion.
+    if variables.len() == 1 && operator.allows_reserved() {
+        values.insert(variables[0].name.clone(), decode(text)?);
+        return Some(values);
+    }
+    let mut pieces = pieces.into_iter();
+    for variable in variables {
+        let value = if variable.explode {
+            let rest: Vec<&str> = pieces.by_ref().collect();
+            if rest.is_empty() {
+                break;
+            }
+            if !rest.iter().all(|piece| allowed(piece)) {
+                return None;
+            }
+            rest.iter()
+                .map(|piece| decode(piece))
+                .collect::<Option<Vec<_>>>()?
+                .join(&operator.separator().to_string())
+        } else {
+            match pieces.next() {
+                Some(piece) if allowed(piece) => decode(piece)?,
+                Some(_) => return None,
+                None => break,
+            }
+        };
+        values.insert(variable.name.clone(), value);
+    }
+    if pieces.next().is_some() {
+        return None;
+    }
+    Some(values)
+}
+
+fn is_unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')
+}
+
+/// Decodes `%XX` escapes, or returns `None` if an escape is malformed.
+fn decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = value.get(index + 1..index + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(template: &str, uri: &str) -> Option<Vec<(String, String)>> {
+        let template = UriTemplate::parse(template).unwrap();
+        let mut variables: Vec<_> = template.matches(uri)?.into_iter().collect();
+        variables.sort();
+        Some(variables)
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            (
+                "file:///{path}",
+                "file:///notes.txt",
+                vars(&[("path", "notes.txt")]),
+            ),
+            ("file:///{path}", "file:///notes/todo.txt", None),
+            (
+                "file:///{+path}",
+                "file:///notes/todo.txt",
+                vars(&[("path", "notes/todo.txt")]),
+            ),
+            (
+                "users://{id}/profile",
+                "users://42/profile",
+                vars(&[("id", "42")]),
+            ),
+            ("users://{id}/profile", "users://42/settings", None),
+            (
+                "str:///{name}",
+                "str:///hello%20world",
+                vars(&[("name", "hello world")]),
+            ),
+            (
+                "weather://{city}/forecast{?days,units}",
+                "weather://berlin/forecast?units=metric&days=3",
+                vars(&[("city", "berlin"), ("days", "3"), ("units", "metric")]),
+            ),
+            (
+                "weather://{city}/forecast{?days}",
+                "weather://berlin/forecast",
+                vars(&[("city", "berlin")]),
+            ),
+            (
+                "weather://{city}/forecast{?days}",
+                "weather://berlin/forecast?hours=3",
+                None,
+            ),
+            (
+                "repo://{owner}{/path*}",
+                "repo://ribelo/src/lib.rs",
+                vars(&[("owner", "ribelo"), ("path", "src/lib.rs")]),
+            ),
+            (
+                "dns://www{.domain,tld}",
+                "dns://www.example.com",
+                vars(&[("domain", "example"), ("tld", "com")]),
+            ),
+            (
+                "search://{index}{?q}{&page}",
+                "search://docs?q=mcp&page=2",
+                vars(&[("index", "docs"), ("page", "2"), ("q", "mcp")]),
+            ),
+            ("map://{x,y}", "map://1,2", vars(&[("x", "1"), ("y", "2")])),
+            (
+                "doc://{name}{#section}",
+                "doc://guide#install/linux",
+                vars(&[("name", "guide"), ("section", "install/linux")]),
+            ),
+            (
+                "grid://cell{;x,y}",
+                "grid://cell;x=1;y=2",
+                vars(&[("x", "1"), ("y", "2")]),
+            ),
+        ];
+        for (template, uri, expected) in cases {
+            assert_eq!(
+                matches(template, uri),
+                expected,
+                "{} against {}",
+                uri,
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let template: UriTemplate = "weather://{city}/forecast{?days,units}".parse().unwrap();
+        assert_eq!(
+            template.variables().collect::<Vec<_>>(),
+            ["city", "days", "units"]
+        );
+        assert_eq!(
+            template.to_string(),
+            "weather://{city}/forecast{?days,units}"
+        );
+
+        assert_eq!(
+            UriTemplate::parse("file:///{path"),
+            Err(UriTemplateError::Unclosed(8))
+        );
+        assert_eq!(
+            UriTemplate::parse("file:///path}"),
+            Err(UriTemplateError::UnexpectedClose(12))
+        );
+        assert_eq!(
+            UriTemplate::parse("file:///{pa th}"),
+            Err(UriTemplateError::InvalidVariable("pa th".to_string()))
+        );
+    }
+}
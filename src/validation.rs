@@ -0,0 +1,256 @@
+/// How strictly incoming messages are checked against the JSON-RPC and MCP schemas.
+///
+/// The default mode accepts anything that deserializes into a [`JsonRpcMessage`]. Strict mode
+/// is meant for reference and conformance servers and additionally rejects:
+///
+/// - a `jsonrpc` member other than `"2.0"`
+/// - top-level members that JSON-RPC does not define for the kind of message
+/// - requests with a `null` id
+/// - parameters of known methods that do not match their schema, e.g. invalid enum values
+///
+/// Lenient mode goes the other way for interoperability with sloppy peers. Before
+/// deserializing it repairs common violations seen in the wild:
+///
+/// - a missing or malformed `jsonrpc` member
+/// - numeric strings as response ids, e.g. `"7"` for a request sent with id `7`
+/// - `null` members such as `"params": null` or `"error": null` next to a result
+/// - requests with a `null` id, which are treated as notifications
+/// - responses with an id but neither `result` nor `error`, which are treated as empty results
+///
+/// Unknown members are ignored in both the default and the lenient mode.
+///
+/// A rejected message yields the [`JsonRpcError`] that should be sent back to the peer.
+use serde_json::{Map, Value};
+
+use crate::protocol::{
+    ClientRequest, ErrorData, INVALID_PARAMS, INVALID_REQUEST, JSONRPC_VERSION, JsonRpcError,
+    JsonRpcMessage, PARSE_ERROR, ProtocolError, RequestId, ServerRequest,
+};
+
+/// How incoming messages are validated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Accept every message that deserializes.
+    #[default]
+    Standard,
+    /// Reject every deviation from the specification.
+    Strict,
+    /// Repair common deviations before deserializing.
+    Lenient,
+}
+
+/// Parses one incoming message according to `mode`.
+pub fn parse_message(text: &str, mode: ValidationMode) -> Result<JsonRpcMessage, JsonRpcError> {
+    let mut value: Value = serde_json::from_str(text)
+        .map_err(|e| rejection(None, PARSE_ERROR, format!("Parse error: {}", e)))?;
+    match mode {
+        ValidationMode::Standard => {}
+        ValidationMode::Strict => validate_strict(&value)?,
+        ValidationMode::Lenient => repair(&mut value),
+    }
+    serde_json::from_value(value)
+        .map_err(|e| rejection(None, INVALID_REQUEST, format!("Invalid message: {}", e)))
+}
+
+fn validate_strict(value: &Value) -> Result<(), JsonRpcError> {
+    let Value::Object(object) = value else {
+        return Err(rejection(
+            None,
+            INVALID_REQUEST,
+            "Message must be a JSON object",
+        ));
+    };
+    let id = object
+        .get("id")
+        .and_then(|id| serde_json::from_value::<RequestId>(id.clone()).ok());
+
+    if object.get("jsonrpc").and_then(Value::as_str) != Some(JSONRPC_VERSION) {
+        return Err(rejection(
+            id,
+            INVALID_REQUEST,
+            "The jsonrpc member must be exactly \"2.0\"",
+        ));
+    }
+
+    let allowed: &[&str] = if object.contains_key("method") {
+        if object.get("id") == Some(&Value::Null) {
+            return Err(rejection(
+                None,
+                INVALID_REQUEST,
+                "Request id must not be null",
+            ));
+        }
+        if object.contains_key("id") {
+            &["jsonrpc", "id", "method", "params"]
+        } else {
+            &["jsonrpc", "method", "params"]
+        }
+    } else if object.contains_key("error") {
+        &["jsonrpc", "id", "error"]
+    } else {
+        &["jsonrpc", "id", "result"]
+    };
+    if let Some(unknown) = object.keys().find(|key| !allowed.contains(&key.as_str())) {
+        return Err(rejection(
+            id,
+            INVALID_REQUEST,
+            format!("Unknown member: {}", unknown),
+        ));
+    }
+
+    if let (Some(Value::String(method)), Some(id)) = (object.get("method"), id) {
+        validate_params(method, object).map_err(|e| rejection(Some(id), INVALID_PARAMS, e))?;
+    }
+    Ok(())
+}
+
+fn repair(value: &mut Value) {
+    let Value::Object(object) = value else {
+        return;
+    };
+    if !object.get("jsonrpc").is_some_and(Value::is_string) {
+        object.insert("jsonrpc".to_string(), Value::from(JSONRPC_VERSION));
+    }
+    for member in ["params", "error"] {
+        if object.get(member) == Some(&Value::Null) {
+            object.remove(member);
+        }
+    }
+
+    if object.contains_key("method") {
+        if object.get("id") == Some(&Value::Null) {
+            object.remove("id");
+        }
+        return;
+    }
+
+    // Responses: ids are matched against the numbers this side generated.
+    if let Some(Value::String(id)) = object.get("id")
+        && let Ok(number) = id.parse::<i64>()
+    {
+        object.insert("id".to_string(), Value::from(number));
+    }
+    if object.contains_key("error") {
+        object.remove("result");
+    } else if object.contains_key("id") && !object.contains_key("result") {
+        object.insert("result".to_string(), Value::Null);
+    }
+}
+
+/// Checks the parameters of requests whose method is known to either side.
+fn validate_params(method: &str, object: &Map<String, Value>) -> Result<(), String> {
+    let params = object.get("params").cloned();
+    let client = ClientRequest::from_parts(method, params.clone()).map(|_| ());
+    let server = ServerRequest::from_parts(method, params).map(|_| ());
+    match (client, server) {
+        (Ok(()), _) | (_, Ok(())) => Ok(()),
+        (Err(ProtocolError::InvalidParams(message)), _)
+        | (_, Err(ProtocolError::InvalidParams(message))) => Err(message),
+        // Unknown methods are for the dispatcher to reject.
+        _ => Ok(()),
+    }
+}
+
+fn rejection(id: Option<RequestId>, code: i32, message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        id,
+        error: ErrorData {
+            code,
+            message: message.into(),
+            data: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::JsonRpcResponse;
+
+    fn strict(text: &str) -> Result<JsonRpcMessage, JsonRpcError> {
+        parse_message(text, ValidationMode::Strict)
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_violations() {
+        let error = strict(r#"{"jsonrpc":"1.0","id":1,"method":"ping"}"#).unwrap_err();
+        assert_eq!(error.error.code, INVALID_REQUEST);
+        assert_eq!(error.id, Some(RequestId::Number(1)));
+
+        let error = strict(r#"{"jsonrpc":"2.0","id":null,"method":"ping"}"#).unwrap_err();
+        assert_eq!(error.error.code, INVALID_REQUEST);
+        assert_eq!(error.id, None);
+
+        let error = strict(r#"{"jsonrpc":"2.0","id":2,"method":"ping","extra":1}"#).unwrap_err();
+        assert_eq!(error.error.code, INVALID_REQUEST);
+
+        let error = strict(r#"{"jsonrpc":"2.0","id":3,"result":{},"error":null}"#).unwrap_err();
+        assert_eq!(error.error.code, INVALID_REQUEST);
+
+        let error = strict(
+            r#"{"jsonrpc":"2.0","id":4,"method":"logging/setLevel","params":{"level":"verbose"}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.error.code, INVALID_PARAMS);
+        assert_eq!(error.id, Some(RequestId::Number(4)));
+
+        let error = strict("not json").unwrap_err();
+        assert_eq!(error.error.code, PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_strict_mode_accepts_valid_messages() {
+        for text in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"logging/setLevel","params":{"level":"info"}}"#,
+            r#"{"jsonrpc":"2.0","id":"a","method":"vendor/custom","params":{"x":1}}"#,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#,
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"Parse error"}}"#,
+        ] {
+            assert!(strict(text).is_ok(), "{}", text);
+        }
+
+        // The default mode lets the same violations through.
+        assert!(
+            parse_message(
+                r#"{"jsonrpc":"1.0","id":2,"method":"ping","extra":1}"#,
+                ValidationMode::Standard
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_lenient_mode_repairs_common_violations() {
+        let lenient = |text| parse_message(text, ValidationMode::Lenient).unwrap();
+
+        let JsonRpcMessage::Response(response) = lenient(r#"{"id":"7","result":{},"error":null}"#)
+        else {
+            panic!("Expected a response");
+        };
+        assert_eq!(response.id, RequestId::Number(7));
+        assert_eq!(response.jsonrpc, JSONRPC_VERSION);
+
+        let JsonRpcMessage::Request(request) =
+            lenient(r#"{"jsonrpc":2,"id":"abc","method":"tools/list","params":null,"x":1}"#)
+        else {
+            panic!("Expected a request");
+        };
+        // Request ids are echoed back to the peer, so they are left alone.
+        assert_eq!(request.id, RequestId::from("abc"));
+        assert_eq!(request.params, None);
+
+        assert!(matches!(
+            lenient(r#"{"id":null,"method":"notifications/initialized"}"#),
+            JsonRpcMessage::Notification(_)
+        ));
+        assert!(matches!(
+            lenient(r#"{"jsonrpc":"2.0","id":3}"#),
+            JsonRpcMessage::Response(JsonRpcResponse { result: None, .. })
+        ));
+
+        // The same messages fail without repairs.
+        assert!(parse_message(r#"{"id":"7","result":{}}"#, ValidationMode::Standard).is_err());
+    }
+}